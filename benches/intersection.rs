@@ -0,0 +1,104 @@
+use std::{hint::black_box, rc::Rc};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use raytracing::{
+    boundingbox::BVHNode,
+    hittable::{Parallelogram, Sphere, Triangle},
+    material::Lambertian,
+    Color, Hittable, Interval, Material, Point3, Ray4, Vec3,
+};
+
+fn material() -> Rc<dyn Material> {
+    Lambertian::solid(Color::white()).into_mat()
+}
+
+/// Rays from in front of the unit square at z = 0, aimed at points scattered across it.
+/// Roughly a quarter of them miss the shapes being tested.
+fn rays(count: usize) -> Vec<Ray4> {
+    (0..count)
+        .map(|i| {
+            // low-discrepancy (Fibonacci lattice) targets, so runs are repeatable
+            let x = (i as f64 * 0.618_033_988_7).fract() * 2.0 - 1.0;
+            let y = (i as f64 + 0.5) / count as f64 * 2.0 - 1.0;
+            let origin = Point3::new(0.0, 0.0, 5.0);
+            Ray4::new(origin, Point3::new(x, y, 0.0) - origin, 0.0)
+        })
+        .collect()
+}
+
+fn hit_all(object: &dyn Hittable, rays: &[Ray4]) -> usize {
+    rays.iter()
+        .filter(|ray| black_box(object.hit(ray, Interval::new(0.001, f64::INFINITY))).is_some())
+        .count()
+}
+
+fn primitives(c: &mut Criterion) {
+    let rays = rays(1024);
+
+    let sphere = Sphere::stationary(Point3::origin(), 0.8, material());
+    c.bench_function("sphere hit", |b| b.iter(|| hit_all(&sphere, &rays)));
+
+    let triangle = Triangle::from_points(
+        Point3::new(-0.8, -0.8, 0.0),
+        Point3::new(0.8, -0.8, 0.0),
+        Point3::new(0.0, 0.8, 0.0),
+        material(),
+    );
+    c.bench_function("triangle hit", |b| b.iter(|| hit_all(&triangle, &rays)));
+
+    let parallelogram = Parallelogram::new(
+        Point3::new(-0.8, -0.8, 0.0),
+        Vec3::new(1.6, 0.0, 0.0),
+        Vec3::new(0.0, 1.6, 0.0),
+        material(),
+    );
+    c.bench_function("parallelogram hit", |b| {
+        b.iter(|| hit_all(&parallelogram, &rays))
+    });
+}
+
+/// A rippled heightfield over `-1.0..1.0` in x and y, made of `2 * n * n` triangles.
+fn heightfield(n: usize) -> Vec<Rc<dyn Hittable>> {
+    let mat = material();
+    let point = |i: usize, j: usize| {
+        let x = i as f64 / n as f64 * 2.0 - 1.0;
+        let y = j as f64 / n as f64 * 2.0 - 1.0;
+        Point3::new(x, y, 0.1 * (x * 10.0).sin() * (y * 10.0).cos())
+    };
+
+    let mut triangles: Vec<Rc<dyn Hittable>> = Vec::with_capacity(2 * n * n);
+    for i in 0..n {
+        for j in 0..n {
+            let (p00, p10, p01, p11) = (
+                point(i, j),
+                point(i + 1, j),
+                point(i, j + 1),
+                point(i + 1, j + 1),
+            );
+            triangles.push(Triangle::from_points(p00, p10, p11, Rc::clone(&mat)).hittable());
+            triangles.push(Triangle::from_points(p00, p11, p01, Rc::clone(&mat)).hittable());
+        }
+    }
+    triangles
+}
+
+fn bvh(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bvh");
+    group.sample_size(20);
+
+    // 2 * 224 * 224 ≈ 100k triangles
+    let triangles = heightfield(224);
+    group.bench_function("build 100k triangles", |b| {
+        b.iter(|| BVHNode::new(black_box(triangles.clone())))
+    });
+
+    let tree = BVHNode::new(triangles);
+    let rays = rays(4096);
+    group.bench_function("traverse 100k triangles", |b| {
+        b.iter(|| hit_all(&tree, &rays))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, primitives, bvh);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use raytracing::{
+    camera::AntialiasingType,
+    export::PpmWriter,
+    hittable::{HittableVec, Parallelogram, Sphere},
+    material::{Dielectric, DiffuseLight, Lambertian, Metal},
+    Angle, Background, CameraBuilder, Color, Hittable, Material, Point3, Vec3,
+};
+
+/// A small scene exercising diffuse, specular, and refractive materials under an area light.
+fn scene() -> (HittableVec, Rc<dyn Hittable>) {
+    let mut world = HittableVec::new();
+
+    let ground = Lambertian::solid(Color::new(0.5, 0.5, 0.5)).into_mat();
+    world.add(Sphere::stationary(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground).hittable());
+
+    let spheres: [(f64, Rc<dyn Material>); 3] = [
+        (
+            -2.2,
+            Lambertian::solid(Color::new(0.7, 0.2, 0.2)).into_mat(),
+        ),
+        (0.0, Dielectric::new(1.5).into_mat()),
+        (
+            2.2,
+            Metal::with_fuzz(Color::new(0.8, 0.8, 0.9), 0.1).into_mat(),
+        ),
+    ];
+    for (x, material) in spheres {
+        world.add(Sphere::stationary(Point3::new(x, 1.0, 0.0), 1.0, material).hittable());
+    }
+
+    let light = Parallelogram::new(
+        Point3::new(-1.5, 5.0, -1.5),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 3.0),
+        DiffuseLight::solid(Color::over_white(8.0)).into_mat(),
+    )
+    .hittable();
+    world.add(Rc::clone(&light));
+
+    (world, light)
+}
+
+fn frame(c: &mut Criterion) {
+    let (world, light) = scene();
+
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    group.bench_function("64x64 frame, 16 spp", |b| {
+        b.iter(|| {
+            let mut sink = std::io::sink();
+            let mut cam = CameraBuilder::new()
+                .dimensions(64, 64)
+                .antialias(AntialiasingType::Square, 16)
+                .max_depth(8)
+                .background(Background::Constant(Color::over_white(0.1)))
+                .camera_center(Point3::new(0.0, 2.0, 8.0))
+                .camera_target(Point3::new(0.0, 1.0, 0.0))
+                .vfov(Angle::degrees(40.0))
+                .writer(PpmWriter::new(&mut sink).into_box())
+                .build()
+                .unwrap();
+            cam.render(&world, Rc::clone(&light));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frame);
+criterion_main!(benches);
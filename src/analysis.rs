@@ -0,0 +1,195 @@
+//! Statistics of a rendered (linear) image, used to tune exposure and light brightness.
+
+use std::fmt::Display;
+
+use crate::Color;
+
+/// The luminance a well-exposed image averages to; "middle grey".
+const MIDDLE_GREY: f64 = 0.18;
+/// The range of the histogram, in stops relative to a luminance of 1.0.
+const MIN_STOP: i32 = -10;
+const MAX_STOP: i32 = 4;
+
+/// A luminance histogram and exposure statistics for an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExposureReport {
+    /// The number of pixels in each one-stop bin of luminance, from `2^MIN_STOP` to `2^MAX_STOP`.
+    /// Pixels outside of this range are counted in the first or last bin.
+    histogram: Vec<u32>,
+    /// The number of pixels which are completely black
+    black: u32,
+    /// The fraction of pixels with at least one channel above 1.0, which will be clipped
+    clipped: f64,
+    /// The log-average (geometric mean) luminance
+    log_average: f64,
+    pixels: u32,
+}
+
+impl ExposureReport {
+    pub fn from_buffer(colors: &[Color]) -> Self {
+        let mut histogram = vec![0; (MAX_STOP - MIN_STOP) as usize];
+        let mut black = 0;
+        let mut clipped = 0;
+        let mut log_sum = 0.0;
+
+        for color in colors {
+            if color.r() > 1.0 || color.g() > 1.0 || color.b() > 1.0 {
+                clipped += 1;
+            }
+
+            let luminance = color.luminance().max(0.0);
+            // the small offset keeps black pixels from dragging the average to zero
+            log_sum += (luminance + 1e-4).ln();
+            if luminance == 0.0 {
+                black += 1;
+                continue;
+            }
+
+            let stop = luminance.log2().floor() as i32;
+            let bin = stop.clamp(MIN_STOP, MAX_STOP - 1) - MIN_STOP;
+            histogram[bin as usize] += 1;
+        }
+
+        let pixels = colors.len() as u32;
+        Self {
+            histogram,
+            black,
+            clipped: f64::from(clipped) / f64::from(pixels.max(1)),
+            log_average: (log_sum / f64::from(pixels.max(1))).exp(),
+            pixels,
+        }
+    }
+
+    /// The fraction (`0.0..=1.0`) of pixels which are too bright to be displayed.
+    pub fn clipped(&self) -> f64 {
+        self.clipped
+    }
+
+    /// The log-average luminance of the image.
+    pub fn log_average(&self) -> f64 {
+        self.log_average
+    }
+
+    /// The change in exposure, in stops, which would bring the log-average luminance to
+    /// middle grey. Positive values mean the image is too dark; multiply the brightness of the
+    /// scene's lights by `2^stops` to correct it.
+    pub fn suggested_exposure(&self) -> f64 {
+        (MIDDLE_GREY / self.log_average).log2()
+    }
+}
+
+/// How the brightness of a scene is measured for automatic exposure; see
+/// [`CameraBuilder::auto_exposure`](crate::CameraBuilder::auto_exposure).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metering {
+    /// Every pixel counts equally.
+    Average,
+    /// Pixels near the centre of the image, where the subject usually is, count the most.
+    CenterWeighted,
+}
+
+impl Metering {
+    /// The change in exposure, in stops, which brings the metered log-average luminance of
+    /// `colors` (a `width` × `height` image in row-major order) to middle grey.
+    pub fn exposure(self, colors: &[Color], width: u32, height: u32) -> f64 {
+        assert_eq!(
+            colors.len(),
+            (width * height) as usize,
+            "Invalid image (expected width * height colors)"
+        );
+        let (mut log_sum, mut total) = (0.0, 0.0);
+        for (i, color) in colors.iter().enumerate() {
+            let weight = match self {
+                Metering::Average => 1.0,
+                Metering::CenterWeighted => {
+                    // a gaussian falloff, measured in half-heights from the centre
+                    let (x, y) = ((i as u32 % width) as f64, (i as u32 / width) as f64);
+                    let half = f64::from(height) / 2.0;
+                    let dx = (x + 0.5 - f64::from(width) / 2.0) / half;
+                    let dy = (y + 0.5 - half) / half;
+                    (-(dx * dx + dy * dy) / (2.0 * 0.4 * 0.4)).exp()
+                }
+            };
+            // as in `ExposureReport`, the offset keeps black pixels from dragging it to zero
+            log_sum += weight * (color.luminance().max(0.0) + 1e-4).ln();
+            total += weight;
+        }
+        if total == 0.0 {
+            return 0.0;
+        }
+        (MIDDLE_GREY / (log_sum / total).exp()).log2()
+    }
+}
+
+impl Display for ExposureReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const BAR_WIDTH: u32 = 40;
+
+        writeln!(f, "Luminance histogram (stops relative to 1.0):")?;
+        let max = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (i, count) in self.histogram.iter().enumerate().rev() {
+            let stop = MIN_STOP + i as i32;
+            let bar = "#".repeat((count * BAR_WIDTH).div_ceil(max) as usize);
+            writeln!(
+                f,
+                "  {stop:>+3} | {bar:<w$} {count}",
+                w = BAR_WIDTH as usize
+            )?;
+        }
+        writeln!(f, "  black: {}", self.black)?;
+        writeln!(
+            f,
+            "Clipped: {:.2}% of {} pixels",
+            self.clipped * 100.0,
+            self.pixels
+        )?;
+        writeln!(f, "Log-average luminance: {:.4}", self.log_average)?;
+        write!(
+            f,
+            "Suggested exposure: {:+.2} stops (scale lights by {:.3})",
+            self.suggested_exposure(),
+            self.suggested_exposure().exp2()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exposure() {
+        let grey = Color::over_white(MIDDLE_GREY);
+        let report = ExposureReport::from_buffer(&[grey; 16]);
+        assert!(report.suggested_exposure().abs() < 0.01);
+        assert_eq!(report.clipped(), 0.0);
+
+        let dark = Color::over_white(MIDDLE_GREY / 4.0);
+        let report = ExposureReport::from_buffer(&[dark; 16]);
+        assert!((report.suggested_exposure() - 2.0).abs() < 0.01);
+
+        let mut colors = [grey; 4];
+        colors[0] = Color::new(2.0, 0.0, 0.0);
+        let report = ExposureReport::from_buffer(&colors);
+        assert_eq!(report.clipped(), 0.25);
+    }
+
+    #[test]
+    fn metering() {
+        // a grey subject in the centre of a dark frame
+        let dark = Color::over_white(MIDDLE_GREY / 16.0);
+        let mut colors = vec![dark; 9 * 9];
+        for y in 3..6 {
+            for x in 3..6 {
+                colors[y * 9 + x] = Color::over_white(MIDDLE_GREY);
+            }
+        }
+        let average = Metering::Average.exposure(&colors, 9, 9);
+        let center = Metering::CenterWeighted.exposure(&colors, 9, 9);
+        assert!(average > 3.0 && average < 4.0);
+        assert!(center > 0.0 && center < average - 1.0);
+
+        let grey = [Color::over_white(MIDDLE_GREY); 4];
+        assert!(Metering::Average.exposure(&grey, 2, 2).abs() < 0.01);
+    }
+}
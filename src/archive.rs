@@ -0,0 +1,234 @@
+//! `.rtscene` archives, which bundle a scene with the files it refers to (such as image
+//! textures) so that it can be shared and rendered on another machine.
+//!
+//! An archive holds the scene's TOML exactly as written, and each referenced file under the
+//! path the scene refers to it by. When packing, files are found the same way as by
+//! [`ConfigModel::open`], so archives can be built from anywhere.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::config::{Assets, ConfigModel};
+
+/// Identifies an archive; the version is bumped whenever the layout changes.
+const MAGIC: &[u8; 8] = b"RTSCENE\0";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneArchive {
+    scene: String,
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl SceneArchive {
+    /// Creates an archive of `scene`, without any bundled files.
+    pub fn new(scene: impl Into<String>) -> Self {
+        Self {
+            scene: scene.into(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the scene at `path`, along with every file it refers to.
+    pub fn pack(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let scene = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display().green())))?;
+        let table = scene
+            .parse::<toml::Table>()
+            .map_err(|e| miette!("Invalid TOML: {}", e))?;
+
+        let mut references = Vec::new();
+        for value in table.values() {
+            find_references(value, &mut references);
+        }
+
+        let assets = Assets::relative_to(path).with_scene_search_paths(&table)?;
+        let mut archive = Self::new(scene);
+        for reference in references {
+            let file = assets.resolve(&reference);
+            let Some(data) = file.and_then(|file| std::fs::read(file).ok()) else {
+                bail!(
+                    "Failed to read file {}, referenced by the scene.",
+                    reference.display().green(),
+                );
+            };
+            archive.add_file(reference, data);
+        }
+        Ok(archive)
+    }
+
+    /// Bundles `data` as the file the scene refers to as `path`.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, data: Vec<u8>) {
+        self.files.insert(path.into(), data);
+    }
+
+    pub fn scene(&self) -> &str {
+        &self.scene
+    }
+
+    pub fn files(&self) -> &BTreeMap<PathBuf, Vec<u8>> {
+        &self.files
+    }
+
+    /// Parses the scene, reading the files it refers to from the archive.
+    pub fn config(&self) -> Result<ConfigModel> {
+        let table = self
+            .scene
+            .parse::<toml::Table>()
+            .map_err(|e| miette!("Invalid TOML: {}", e))?;
+        ConfigModel::from_table_with_assets(&table, Assets::Bundled(Rc::new(self.files.clone())))
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_bytes(writer, self.scene.as_bytes())?;
+
+        writer.write_all(&(self.files.len() as u64).to_le_bytes())?;
+        for (path, data) in &self.files {
+            let Some(path) = path.to_str() else {
+                return Err(invalid(format!("{} is not valid UTF-8", path.display())));
+            };
+            write_bytes(writer, path.as_bytes())?;
+            write_bytes(writer, data)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a scene archive"));
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "unsupported archive version {version} (expected {VERSION})"
+            )));
+        }
+
+        let scene = read_string(reader)?;
+        let mut archive = Self::new(scene);
+        for _ in 0..read_u64(reader)? {
+            let path = read_string(reader)?;
+            let data = read_bytes(reader)?;
+            archive.add_file(path, data);
+        }
+        Ok(archive)
+    }
+}
+
+/// Collects the paths of files referred to by image textures, wherever they are declared
+/// (including inline textures).
+fn find_references(value: &toml::Value, references: &mut Vec<PathBuf>) {
+    match value {
+        toml::Value::Table(table) => {
+            let is_image = match table.get("type") {
+                Some(toml::Value::String(t)) => t.eq_ignore_ascii_case("image"),
+                _ => false,
+            };
+            if let (true, Some(toml::Value::String(path))) = (is_image, table.get("path")) {
+                let path = PathBuf::from(path);
+                if !references.contains(&path) {
+                    references.push(path);
+                }
+            }
+            for value in table.values() {
+                find_references(value, references);
+            }
+        }
+        toml::Value::Array(values) => {
+            for value in values {
+                find_references(value, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| invalid("string is not valid UTF-8"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCENE: &str = r#"
+[textures.earth]
+type = "Image"
+path = "textures/earth.png"
+
+[materials.ground]
+type = "Lambertian"
+texture = "earth"
+
+[[objects]]
+type = "Sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "ground"
+"#;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("rtscene-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("textures")).unwrap();
+        std::fs::copy("assets/earth.png", dir.join("textures/earth.png")).unwrap();
+        std::fs::write(dir.join("scene.toml"), SCENE).unwrap();
+
+        // packing resolves paths against the scene, not the working directory
+        let archive = SceneArchive::pack(dir.join("scene.toml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(archive.scene(), SCENE);
+        assert!(archive
+            .files()
+            .contains_key(Path::new("textures/earth.png")));
+
+        let mut buf = Vec::new();
+        archive.write(&mut buf).unwrap();
+        let read = SceneArchive::read(&mut buf.as_slice()).unwrap();
+        assert_eq!(read, archive);
+        read.config().unwrap();
+
+        assert!(SceneArchive::read(&mut &buf[..buf.len() - 1]).is_err());
+        assert!(SceneArchive::new(SCENE).config().is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{vec::Normalized, Vec3};
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Axis {
     X,
@@ -9,6 +11,99 @@ impl Axis {
     pub fn iter() -> IterAxis {
         IterAxis(None)
     }
+
+    /// The two other axes, in cyclic order (X → Y → Z → X), so that their cross product
+    /// points along this axis.
+    pub fn others(self) -> (Axis, Axis) {
+        match self {
+            Axis::X => (Axis::Y, Axis::Z),
+            Axis::Y => (Axis::Z, Axis::X),
+            Axis::Z => (Axis::X, Axis::Y),
+        }
+    }
+
+    /// The unit vector pointing along the positive side of the axis.
+    pub fn unit(self) -> Vec3<Normalized> {
+        Direction::new(self, true).unit()
+    }
+}
+
+/// One of the six directions along the axes.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Direction {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::PositiveX,
+        Direction::NegativeX,
+        Direction::PositiveY,
+        Direction::NegativeY,
+        Direction::PositiveZ,
+        Direction::NegativeZ,
+    ];
+
+    /// The direction along `axis`, towards its positive side if `positive` is set.
+    pub fn new(axis: Axis, positive: bool) -> Self {
+        match (axis, positive) {
+            (Axis::X, true) => Direction::PositiveX,
+            (Axis::X, false) => Direction::NegativeX,
+            (Axis::Y, true) => Direction::PositiveY,
+            (Axis::Y, false) => Direction::NegativeY,
+            (Axis::Z, true) => Direction::PositiveZ,
+            (Axis::Z, false) => Direction::NegativeZ,
+        }
+    }
+
+    pub fn axis(self) -> Axis {
+        match self {
+            Direction::PositiveX | Direction::NegativeX => Axis::X,
+            Direction::PositiveY | Direction::NegativeY => Axis::Y,
+            Direction::PositiveZ | Direction::NegativeZ => Axis::Z,
+        }
+    }
+
+    pub fn is_positive(self) -> bool {
+        matches!(
+            self,
+            Direction::PositiveX | Direction::PositiveY | Direction::PositiveZ
+        )
+    }
+
+    /// `1.0` for positive directions, and `-1.0` for negative ones.
+    pub fn sign(self) -> f64 {
+        if self.is_positive() {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        Self::new(self.axis(), !self.is_positive())
+    }
+
+    pub fn unit(self) -> Vec3<Normalized> {
+        let s = self.sign();
+        let vec = match self.axis() {
+            Axis::X => Vec3::new(s, 0.0, 0.0),
+            Axis::Y => Vec3::new(0.0, s, 0.0),
+            Axis::Z => Vec3::new(0.0, 0.0, s),
+        };
+        vec.assert_is_normalized()
+    }
+}
+
+impl From<Direction> for Vec3<Normalized> {
+    fn from(value: Direction) -> Self {
+        value.unit()
+    }
 }
 
 pub struct IterAxis(Option<Axis>);
@@ -47,3 +142,24 @@ impl TryFrom<u8> for Axis {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn directions() {
+        for axis in Axis::iter() {
+            let (a, b) = axis.others();
+            assert_eq!(Vec3::<Normalized>::cross(&a.unit(), &b.unit()), axis.unit());
+        }
+
+        for direction in Direction::ALL {
+            assert_eq!(direction.opposite().opposite(), direction);
+            let unit: Vec3 = direction.unit().into();
+            let opposite: Vec3 = direction.opposite().unit().into();
+            assert_eq!(unit, -opposite);
+            assert_eq!(unit[direction.axis()], direction.sign());
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Serves renders over HTTP; see [`raytracing::server`].
+//!
+//! Usage: `render-server [ADDRESS]`, where `ADDRESS` defaults to `127.0.0.1:8080`.
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    eprintln!("Listening on http://{addr}");
+    if let Err(err) = raytracing::server::serve(addr.as_str()) {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
+}
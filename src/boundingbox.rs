@@ -1,7 +1,7 @@
-use crate::{Axis, HitRecord, Hittable, Interval, Point3, Ray3, Ray4, Vec3};
+use crate::{hittable::HittableVec, Axis, HitRecord, Hittable, Interval, Point3, Ray3, Ray4, Vec3};
 use std::{cmp::Ordering, rc::Rc};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoundingBox3 {
     x: Interval,
     y: Interval,
@@ -70,6 +70,17 @@ impl BoundingBox3 {
         }
     }
 
+    /// Interpolates between two boxes; `t = 0.0` gives `a` and `t = 1.0` gives `b`.
+    pub fn lerp(a: &BoundingBox3, b: &BoundingBox3, t: f64) -> Self {
+        let lerp = |a: &Interval, b: &Interval| {
+            Interval::new(
+                a.start() + (b.start() - a.start()) * t,
+                a.end() + (b.end() - a.end()) * t,
+            )
+        };
+        Self::new(lerp(&a.x, &b.x), lerp(&a.y, &b.y), lerp(&a.z, &b.z))
+    }
+
     /// Gets the longest axis of the bounding box.
     /// If this bounding box is empty, this function will arbitrarily return [`Axis::X`].
     pub fn longest_axis(&self) -> Axis {
@@ -93,7 +104,7 @@ impl BoundingBox3 {
         axis
     }
 
-    fn hit(&self, ray: &Ray3, ray_t: Interval) -> bool {
+    pub(crate) fn hit(&self, ray: &Ray3, ray_t: Interval) -> bool {
         let mut ray_t = ray_t;
         for axis in Axis::iter() {
             let ax = &self[axis];
@@ -125,6 +136,61 @@ impl BoundingBox3 {
     }
 }
 
+/// A sphere enclosing an object; coarser than a [`BoundingBox3`] for most shapes, but cheaper
+/// to test against, and independent of the object's orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    center: Point3,
+    radius: f64,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        assert!(radius >= 0.0, "Invalid radius (expected >= 0.0)");
+        Self { center, radius }
+    }
+
+    /// The sphere passing through the corners of `bbox`, or `None` if the box is unbounded.
+    pub fn from_box(bbox: &BoundingBox3) -> Option<Self> {
+        let min = Point3::new(*bbox.x.start(), *bbox.y.start(), *bbox.z.start());
+        let max = Point3::new(*bbox.x.end(), *bbox.y.end(), *bbox.z.end());
+        let diagonal: Vec3 = max - min;
+        let radius = diagonal.len() / 2.0;
+        if !radius.is_finite() {
+            return None;
+        }
+        Some(Self::new(min + diagonal / 2.0, radius))
+    }
+
+    pub fn center(&self) -> Point3 {
+        self.center
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn contains(&self, point: &Point3) -> bool {
+        (*point - self.center).len_squared() <= self.radius * self.radius
+    }
+
+    /// Whether the half-line from `origin` in `direction` passes through the sphere.
+    pub fn intersects(&self, origin: &Point3, direction: &Vec3) -> bool {
+        let oc: Vec3 = self.center - *origin;
+        let r2 = self.radius * self.radius;
+        if oc.len_squared() <= r2 {
+            return true;
+        }
+        let along = oc.dot(direction);
+        if along <= 0.0 {
+            // the sphere is behind the origin
+            return false;
+        }
+        // the squared distance from the center to the closest point on the line
+        oc.len_squared() - along * along / direction.len_squared() <= r2
+    }
+}
+
 impl std::ops::Index<Axis> for BoundingBox3 {
     type Output = Interval;
 
@@ -172,15 +238,50 @@ impl std::ops::Add<&BoundingBox3> for Vec3 {
 pub struct BVHNode {
     left: Rc<dyn Hittable>,
     right: Rc<dyn Hittable>,
-    bbox: BoundingBox3,
+    /// `None` if the node holds unbounded objects
+    bbox: Option<BoundingBox3>,
+    /// The bounds at times 0 and 1, if anything in the node moves
+    motion: Option<[BoundingBox3; 2]>,
 }
 
 impl BVHNode {
-    pub fn new(mut objects: Vec<Rc<dyn Hittable>>) -> Self {
+    pub fn new(objects: Vec<Rc<dyn Hittable>>) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.bounding_box().is_some());
+        if unbounded.is_empty() {
+            return Self::new_bounded(bounded);
+        }
+
+        // unbounded objects (such as infinite planes) can't be sorted into the tree,
+        // so they're tested separately, alongside it
+        let right: Rc<dyn Hittable> = Rc::new(unbounded.into_iter().collect::<HittableVec>());
+        let left = if bounded.is_empty() {
+            Rc::clone(&right)
+        } else {
+            Rc::new(Self::new_bounded(bounded))
+        };
+        Self {
+            left,
+            right,
+            bbox: None,
+            motion: None,
+        }
+    }
+
+    fn new_bounded(mut objects: Vec<Rc<dyn Hittable>>) -> Self {
         let mut bbox = BoundingBox3::empty();
+        let mut motion = [BoundingBox3::empty(), BoundingBox3::empty()];
         for object in &objects {
             bbox = BoundingBox3::extending_opt(Some(bbox).as_ref(), object.bounding_box());
+            if let Some([box0, box1]) = object.motion_bounds() {
+                motion = [
+                    BoundingBox3::extending(&motion[0], &box0),
+                    BoundingBox3::extending(&motion[1], &box1),
+                ];
+            }
         }
+        let motion = (motion[0] != motion[1]).then_some(motion);
 
         let axis = bbox.longest_axis();
 
@@ -195,14 +296,19 @@ impl BVHNode {
                 let mid = objects.len() / 2;
                 let split = objects.split_off(mid);
 
-                let left: Rc<dyn Hittable> = Rc::new(BVHNode::new(objects));
-                let right: Rc<dyn Hittable> = Rc::new(BVHNode::new(split));
+                let left: Rc<dyn Hittable> = Rc::new(BVHNode::new_bounded(objects));
+                let right: Rc<dyn Hittable> = Rc::new(BVHNode::new_bounded(split));
 
                 (left, right)
             }
         };
 
-        Self { left, right, bbox }
+        Self {
+            left,
+            right,
+            bbox: Some(bbox),
+            motion,
+        }
     }
 
     fn cmp_box<'a>(a: &'a Rc<dyn Hittable>, b: &'a Rc<dyn Hittable>, axis: Axis) -> Ordering {
@@ -217,7 +323,19 @@ impl BVHNode {
 
 impl Hittable for BVHNode {
     fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
-        if !self.bbox.hit(&ray.ignore_time(), ray_t.clone()) {
+        let time = ray.time();
+        let culled = match (&self.motion, &self.bbox) {
+            // fit the box to where moving objects are at the time of the ray; interpolated
+            // boxes only enclose them between times 0 and 1, so rays at other times can't
+            // be culled
+            (Some([box0, box1]), _) => {
+                (0.0..=1.0).contains(&time)
+                    && !BoundingBox3::lerp(box0, box1, time).hit(&ray.ignore_time(), ray_t.clone())
+            }
+            (None, Some(bbox)) => !bbox.hit(&ray.ignore_time(), ray_t.clone()),
+            (None, None) => false,
+        };
+        if culled {
             return None;
         }
 
@@ -231,6 +349,59 @@ impl Hittable for BVHNode {
     }
 
     fn bounding_box(&self) -> Option<&BoundingBox3> {
-        Some(&self.bbox)
+        self.bbox.as_ref()
+    }
+
+    fn bvh_children(&self) -> Option<[&Rc<dyn Hittable>; 2]> {
+        Some([&self.left, &self.right])
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        match &self.motion {
+            Some(motion) => Some(motion.clone()),
+            None => self.bbox.as_ref().map(|bbox| [bbox.clone(), bbox.clone()]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bounding_sphere() {
+        let bbox =
+            BoundingBox3::bounded_by(&Point3::new(-1.0, -1.0, -1.0), &Point3::new(1.0, 1.0, 1.0));
+        let sphere = BoundingSphere::from_box(&bbox).unwrap();
+        assert!((sphere.radius() - 3f64.sqrt()).abs() < 1e-9);
+        assert!(sphere.contains(&Point3::new(0.9, -0.9, 0.9)));
+
+        let origin = Point3::new(0.0, 0.0, 5.0);
+        assert!(sphere.intersects(&origin, &Vec3::new(0.0, 0.3, -1.0)));
+        assert!(!sphere.intersects(&origin, &Vec3::new(0.0, 1.0, -1.0)));
+        assert!(!sphere.intersects(&origin, &Vec3::new(0.0, 0.0, 1.0)));
+
+        assert!(BoundingSphere::from_box(&BoundingBox3::universe()).is_none());
+    }
+
+    #[test]
+    fn moving_objects() {
+        use crate::{hittable::Sphere, material::Lambertian, Color, Material};
+
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        // moves from x = 0 to x = 10 between times 0 and 1
+        let path = Ray3::new(Point3::origin(), Vec3::new(10.0, 0.0, 0.0));
+        let moving = Sphere::new(path, 1.0, Rc::clone(&mat)).hittable();
+        let still = Sphere::stationary(Point3::new(0.0, 5.0, 0.0), 1.0, mat).hittable();
+        let bvh = BVHNode::new(vec![moving, still]);
+
+        let hit = |x: f64, time: f64| {
+            let ray = Ray4::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), time);
+            bvh.hit(&ray, Interval::new(0.001, f64::INFINITY)).is_some()
+        };
+        assert!(hit(5.0, 0.5));
+        assert!(!hit(5.0, 0.0));
+        // objects are never culled at times outside of the shutter interval
+        assert!(hit(-5.0, -0.5));
     }
 }
@@ -0,0 +1,668 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::rng::random;
+
+use crate::{vec::Normalized, Color, OrthonormalBasis, Vec3};
+
+/// The kind of scattering a [`BSDFSample`] was drawn from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lobe {
+    /// Light is scattered across the whole hemisphere, e.g. a Lambertian surface.
+    Diffuse,
+    /// Light is scattered in a cone around a preferred direction, e.g. a fuzzy metal.
+    Glossy,
+    /// Light is scattered in exactly one direction, e.g. a mirror or clear glass.
+    Specular,
+}
+
+/// A direction sampled from a [`BSDF`].
+#[derive(Debug)]
+pub struct BSDFSample {
+    /// The direction the path continues in.
+    pub direction: Vec3,
+    /// The value of the BSDF for `direction`, including the cosine term.
+    pub value: Color,
+    /// The probability density of having sampled `direction`.
+    pub pdf: f64,
+    /// The lobe `direction` was sampled from.
+    pub lobe: Lobe,
+    /// The color channel the path carries from here on, if the BSDF split the light into
+    /// its channels.
+    pub channel: Option<usize>,
+}
+
+/// A Bidirectional Scattering Distribution Function, describing how light arriving at a single
+/// point on a surface is scattered.
+///
+/// All directions are in world space. `wo` is the unit vector pointing back along the incoming
+/// ray (towards the viewer), and `wi` is the direction the path continues in.
+pub trait BSDF: std::fmt::Debug {
+    /// Evaluates the BSDF for the pair of directions, multiplied by the cosine of the angle
+    /// between `wi` and the surface normal.
+    fn eval(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Color;
+
+    /// Samples a direction `wi` to continue the path in, proportionally to the BSDF where possible.
+    /// Returns `None` if the ray is absorbed.
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample>;
+
+    /// The probability density with which [`BSDF::sample`] would produce `wi`.
+    fn pdf(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> f64;
+
+    /// The lobe which scatters the most light into `wi`, for classifying a direction which
+    /// wasn't produced by [`BSDF::sample`], such as one towards a light.
+    fn lobe(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> Lobe {
+        Lobe::Diffuse
+    }
+
+    /// Whether this BSDF can only be sampled, not evaluated: [`BSDF::eval`] and [`BSDF::pdf`]
+    /// are zero for every direction except the one produced by [`BSDF::sample`].
+    /// Such BSDFs cannot be combined with light sampling.
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn into_bsdf(self) -> Rc<dyn BSDF>
+    where
+        Self: Sized + 'static,
+    {
+        Rc::new(self)
+    }
+}
+
+/// Ideal diffuse reflection, following Lambert's Cosine Law.
+#[derive(Debug)]
+pub struct DiffuseBSDF {
+    albedo: Color,
+    basis: OrthonormalBasis,
+}
+
+impl DiffuseBSDF {
+    pub fn new(albedo: Color, normal: &Vec3<Normalized>) -> Self {
+        Self {
+            albedo,
+            basis: OrthonormalBasis::new(&(*normal).into()),
+        }
+    }
+}
+
+impl BSDF for DiffuseBSDF {
+    fn eval(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Color {
+        let mut value = self.albedo;
+        value.set_brightness(self.pdf(wo, wi));
+        value
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let direction = self.basis.transform(&Vec3::random_on_sphere_cosine());
+        let pdf = self.pdf(wo, &direction);
+        Some(BSDFSample {
+            direction,
+            value: self.eval(wo, &direction),
+            pdf,
+            lobe: Lobe::Diffuse,
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, wi: &Vec3) -> f64 {
+        let cos_theta = Vec3::dot(&self.basis.w(), &wi.as_unit());
+        f64::max(0.0, cos_theta / PI)
+    }
+}
+
+/// Mirror-like reflection, optionally perturbed by `fuzz`.
+/// Fuzzed directions have no closed-form density, so this is always treated as a delta BSDF.
+#[derive(Debug)]
+pub struct ReflectionBSDF {
+    albedo: Color,
+    normal: Vec3<Normalized>,
+    fuzz: f64,
+}
+
+impl ReflectionBSDF {
+    pub fn new(albedo: Color, normal: &Vec3<Normalized>, fuzz: f64) -> Self {
+        Self {
+            albedo,
+            normal: *normal,
+            fuzz,
+        }
+    }
+}
+
+impl BSDF for ReflectionBSDF {
+    fn eval(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let reflected = Vec3::from(-*wo).reflect(&self.normal);
+        let direction = reflected.as_unit() + (self.fuzz * Vec3::random_in_unit_sphere());
+
+        if Vec3::dot(&direction, &self.normal) < 0.0 {
+            // the ray has been scattered below the surface of the object
+            return None;
+        }
+
+        Some(BSDFSample {
+            direction,
+            value: self.albedo,
+            pdf: 1.0,
+            lobe: if self.fuzz > 0.0 {
+                Lobe::Glossy
+            } else {
+                Lobe::Specular
+            },
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+/// Glass-like scattering; rays are either reflected or refracted.
+///
+/// Rough (frosted) glass is approximated by reflecting or refracting about a microfacet normal
+/// drawn from the GGX distribution, rather than about the surface normal.
+#[derive(Debug)]
+pub struct DielectricBSDF {
+    normal: Vec3<Normalized>,
+    /// The ratio of refractive indices across the boundary, `η / η′`
+    index_ratio: f64,
+    /// Perceptual roughness, from `0.0..=1.0`
+    roughness: f64,
+    /// Separate index ratios for the red, green and blue channels, if the glass disperses light
+    dispersion: Option<[f64; 3]>,
+    /// The channel the path was already split into by an earlier dispersive surface
+    channel: Option<usize>,
+}
+
+impl DielectricBSDF {
+    /// `index_ratio` is the ratio of the refractive index the ray is leaving over the
+    /// refractive index the ray is entering.
+    pub fn new(normal: &Vec3<Normalized>, index_ratio: f64) -> Self {
+        Self {
+            normal: *normal,
+            index_ratio,
+            roughness: 0.0,
+            dispersion: None,
+            channel: None,
+        }
+    }
+
+    /// Splits light into its color channels, refracting each with its own index ratio.
+    /// One channel is chosen at random for each path, and only that channel is carried on.
+    pub fn with_dispersion(mut self, index_ratios: [f64; 3]) -> Self {
+        self.dispersion = Some(index_ratios);
+        self
+    }
+
+    /// Continues a path which an earlier dispersive surface already split into `channel`,
+    /// rather than choosing a new channel.
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Chooses the normal to scatter about; for rough glass, a microfacet normal visible from `wo`.
+    fn scattering_normal(&self, wo: &Vec3<Normalized>) -> Vec3<Normalized> {
+        if self.roughness <= 0.0 {
+            return self.normal;
+        }
+
+        let basis = OrthonormalBasis::new(&self.normal.into());
+        let alpha = self.roughness * self.roughness;
+        // microfacets facing away from `wo` can't be hit; retry a few times before giving up
+        for _ in 0..8 {
+            let microfacet = basis.transform(&sample_ggx(alpha)).as_unit();
+            if wo.dot(&microfacet) > 0.0 {
+                return microfacet;
+            }
+        }
+        self.normal
+    }
+}
+
+/// A color with `brightness` in `channel`, and black in the other channels.
+fn channel_color(channel: usize, brightness: f64) -> Color {
+    let mut rgb = [0.0; 3];
+    rgb[channel] = brightness;
+    Color::new(rgb[0], rgb[1], rgb[2])
+}
+
+/// Samples a microfacet normal from the GGX distribution with width `alpha`,
+/// relative to a normal along the z axis.
+fn sample_ggx(alpha: f64) -> Vec3 {
+    let u: f64 = random();
+    let tan2_theta = alpha * alpha * u / (1.0 - u);
+    let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * random::<f64>();
+
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// The likelihood of a ray to reflect off a dielectric, based on the Schlick approximation.
+pub fn reflectance(cos: f64, refraction_idx: f64) -> f64 {
+    let r0 = (1.0 - refraction_idx) / (1.0 + refraction_idx);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0) * (1.0 - cos).powf(5.0)
+}
+
+/// The reflectance of a conductor (metal) with complex refractive index `eta + ik`,
+/// averaged over both polarizations.
+pub fn conductor_reflectance(cos: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos * cos;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+impl BSDF for DielectricBSDF {
+    fn eval(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let normal = self.scattering_normal(wo);
+        let direction = -*wo;
+        let cos_theta = wo.dot(&normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        // with dispersion, follow a single channel for the rest of the path; weighting it by 3
+        // when it is chosen keeps the average white
+        let (index_ratio, value, channel) = match (self.dispersion, self.channel) {
+            (Some(ratios), Some(channel)) => {
+                (ratios[channel], channel_color(channel, 1.0), Some(channel))
+            }
+            (Some(ratios), None) => {
+                let channel = ((random::<f64>() * 3.0) as usize).min(2);
+                (ratios[channel], channel_color(channel, 3.0), Some(channel))
+            }
+            (None, _) => (self.index_ratio, Color::white(), None),
+        };
+
+        let cannot_refract = index_ratio * sin_theta > 1.0;
+        let will_reflect = cannot_refract || (reflectance(cos_theta, index_ratio) > random());
+
+        let direction = if will_reflect {
+            Vec3::from(direction).reflect(&normal)
+        } else {
+            direction.refract(&normal, index_ratio)
+        };
+
+        Some(BSDFSample {
+            direction,
+            value,
+            pdf: 1.0,
+            lobe: if self.roughness > 0.0 {
+                Lobe::Glossy
+            } else {
+                Lobe::Specular
+            },
+            channel,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+/// An infinitely thin sheet of glass: light is either reflected, or passes straight through
+/// without being bent. Inter-reflections inside the sheet are accounted for in the reflectance.
+#[derive(Debug)]
+pub struct ThinDielectricBSDF {
+    normal: Vec3<Normalized>,
+    refraction_index: f64,
+}
+
+impl ThinDielectricBSDF {
+    pub fn new(normal: &Vec3<Normalized>, refraction_index: f64) -> Self {
+        Self {
+            normal: *normal,
+            refraction_index,
+        }
+    }
+}
+
+impl BSDF for ThinDielectricBSDF {
+    fn eval(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let cos_theta = wo.dot(&self.normal).abs().min(1.0);
+        let r = reflectance(cos_theta, 1.0 / self.refraction_index);
+        // light bouncing back and forth between the two faces forms a geometric series
+        let r = if r < 1.0 {
+            r + (1.0 - r) * (1.0 - r) * r / (1.0 - r * r)
+        } else {
+            r
+        };
+
+        let direction = if r > random() {
+            Vec3::from(-*wo).reflect(&self.normal)
+        } else {
+            (-*wo).into()
+        };
+
+        Some(BSDFSample {
+            direction,
+            value: Color::white(),
+            pdf: 1.0,
+            lobe: Lobe::Specular,
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+/// The classic Blinn-Phong model: a diffuse lobe plus a specular highlight around the half-vector,
+/// normalized so that the highlight does not gain energy as `exponent` changes.
+#[derive(Debug)]
+pub struct BlinnPhongBSDF {
+    diffuse: Color,
+    specular: Color,
+    exponent: f64,
+    basis: OrthonormalBasis,
+}
+
+impl BlinnPhongBSDF {
+    pub fn new(diffuse: Color, specular: Color, exponent: f64, normal: &Vec3<Normalized>) -> Self {
+        Self {
+            diffuse,
+            specular,
+            exponent,
+            basis: OrthonormalBasis::new(&(*normal).into()),
+        }
+    }
+
+    /// The light scattered into `wi` by the diffuse and specular lobes, without the cosine term.
+    fn lobe_values(&self, wo: &Vec3<Normalized>, wi: &Vec3<Normalized>) -> (Color, Color) {
+        let normal = self.basis.w();
+        let half = (*wo + *wi).as_unit();
+        let cos_half = f64::max(0.0, Vec3::dot(&normal, &half));
+
+        let mut diffuse = self.diffuse;
+        diffuse.set_brightness(1.0 / PI);
+        let mut specular = self.specular;
+        specular.set_brightness((self.exponent + 8.0) / (8.0 * PI) * cos_half.powf(self.exponent));
+        (diffuse, specular)
+    }
+
+    /// The probability of sampling the diffuse lobe rather than the specular one.
+    fn diffuse_weight(&self) -> f64 {
+        let diffuse = self.diffuse.luminance().max(0.0);
+        let specular = self.specular.luminance().max(0.0);
+        if diffuse + specular <= 0.0 {
+            return 1.0;
+        }
+        diffuse / (diffuse + specular)
+    }
+}
+
+impl BSDF for BlinnPhongBSDF {
+    fn eval(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Color {
+        let wi = wi.as_unit();
+        let cos_theta = Vec3::dot(&self.basis.w(), &wi);
+        if cos_theta <= 0.0 {
+            return Color::black();
+        }
+
+        let (diffuse, specular) = self.lobe_values(wo, &wi);
+        let mut value = Color::add(&diffuse, &specular);
+        value.set_brightness(cos_theta);
+        value
+    }
+
+    fn lobe(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Lobe {
+        let (diffuse, specular) = self.lobe_values(wo, &wi.as_unit());
+        if diffuse.luminance() >= specular.luminance() {
+            Lobe::Diffuse
+        } else {
+            Lobe::Glossy
+        }
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let (direction, lobe) = if random::<f64>() < self.diffuse_weight() {
+            let direction = self.basis.transform(&Vec3::random_on_sphere_cosine());
+            (direction, Lobe::Diffuse)
+        } else {
+            // sample a half-vector distributed by cos^n around the normal
+            let cos_theta = random::<f64>().powf(1.0 / (self.exponent + 1.0));
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * PI * random::<f64>();
+            let half = self.basis.transform(&Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ));
+            let direction = Vec3::from(-*wo).reflect(&half.as_unit());
+            (direction, Lobe::Glossy)
+        };
+
+        let pdf = self.pdf(wo, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BSDFSample {
+            direction,
+            value: self.eval(wo, &direction),
+            pdf,
+            lobe,
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> f64 {
+        let wi = wi.as_unit();
+        let normal = self.basis.w();
+        let cos_theta = Vec3::dot(&normal, &wi);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let half = (*wo + wi).as_unit();
+        let cos_half = f64::max(0.0, Vec3::dot(&normal, &half));
+        let wo_dot_half = Vec3::dot(wo, &half);
+        let specular_pdf = if wo_dot_half > 0.0 {
+            (self.exponent + 1.0) / (2.0 * PI) * cos_half.powf(self.exponent) / (4.0 * wo_dot_half)
+        } else {
+            0.0
+        };
+
+        let weight = self.diffuse_weight();
+        weight * (cos_theta / PI) + (1.0 - weight) * specular_pdf
+    }
+}
+
+/// The Kajiya-Kay model of light scattered by thin fibers such as hair and fur, which depends
+/// on the direction along the fiber rather than on a surface normal.
+///
+/// The diffuse lobe scatters light around the fiber, and the specular lobe reflects it into a
+/// cone around the fiber, producing the bright bands seen on real hair. The specular lobe is
+/// only approximately normalized.
+#[derive(Debug)]
+pub struct HairBSDF {
+    diffuse: Color,
+    specular: Color,
+    exponent: f64,
+    tangent: Vec3<Normalized>,
+}
+
+impl HairBSDF {
+    pub fn new(diffuse: Color, specular: Color, exponent: f64, tangent: &Vec3<Normalized>) -> Self {
+        Self {
+            diffuse,
+            specular,
+            exponent,
+            tangent: *tangent,
+        }
+    }
+}
+
+impl BSDF for HairBSDF {
+    fn eval(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Color {
+        let cos_i = Vec3::dot(&self.tangent, &wi.as_unit()).clamp(-1.0, 1.0);
+        let cos_o = Vec3::dot(&self.tangent, wo).clamp(-1.0, 1.0);
+        let sin_i = (1.0 - cos_i * cos_i).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).sqrt();
+
+        // the cosine term around a cylinder integrates to `sin_i`
+        let mut diffuse = self.diffuse;
+        diffuse.set_brightness(sin_i / (PI * PI));
+
+        // light is reflected into the cone mirroring `wo` about the plane normal to the fiber
+        let cone = f64::max(0.0, sin_i * sin_o - cos_i * cos_o);
+        let mut specular = self.specular;
+        specular.set_brightness(
+            cone.powf(self.exponent) * ((self.exponent + 1.0) / (2.0 * PI)).sqrt() / (2.0 * PI),
+        );
+
+        Color::add(&diffuse, &specular)
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let direction: Vec3 = Vec3::random_unit_vector().into();
+        Some(BSDFSample {
+            direction,
+            value: self.eval(wo, &direction),
+            pdf: self.pdf(wo, &direction),
+            lobe: Lobe::Diffuse,
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> f64 {
+        // fibers scatter light in every direction
+        1.0 / (4.0 * PI)
+    }
+}
+
+/// Uniform scattering in every direction, used as the phase function of participating media.
+#[derive(Debug)]
+pub struct IsotropicBSDF {
+    albedo: Color,
+}
+
+impl IsotropicBSDF {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl BSDF for IsotropicBSDF {
+    fn eval(&self, wo: &Vec3<Normalized>, wi: &Vec3) -> Color {
+        let mut value = self.albedo;
+        value.set_brightness(self.pdf(wo, wi));
+        value
+    }
+
+    fn sample(&self, wo: &Vec3<Normalized>) -> Option<BSDFSample> {
+        let direction: Vec3 = Vec3::random_unit_vector().into();
+        Some(BSDFSample {
+            direction,
+            value: self.eval(wo, &direction),
+            pdf: self.pdf(wo, &direction),
+            lobe: Lobe::Diffuse,
+            channel: None,
+        })
+    }
+
+    fn pdf(&self, _wo: &Vec3<Normalized>, _wi: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conductor_normal_incidence() {
+        // at normal incidence, the reflectance is ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2)
+        let (eta, k): (f64, f64) = (0.2, 3.9);
+        let expected = ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+        assert!((conductor_reflectance(1.0, eta, k) - expected).abs() < 1e-9);
+        // all light is reflected at grazing angles
+        assert!((conductor_reflectance(0.0, eta, k) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dispersion() {
+        let normal = Vec3::new(0.0, 0.0, 1.0).as_unit();
+        let bsdf = DielectricBSDF::new(&normal, 1.0 / 1.5).with_dispersion([
+            1.0 / 1.49,
+            1.0 / 1.5,
+            1.0 / 1.52,
+        ]);
+        let wo = Vec3::new(1.0, 0.0, 1.0).as_unit();
+
+        let (mut total, mut bent) = ([0.0; 3], [0.0; 3]);
+        let samples = 30_000;
+        for _ in 0..samples {
+            let sample = bsdf.sample(&wo).unwrap();
+            let value = [sample.value.r(), sample.value.g(), sample.value.b()];
+            // only one channel is carried through
+            assert_eq!(value.iter().filter(|&&c| c > 0.0).count(), 1);
+            let channel = value.iter().position(|&c| c > 0.0).unwrap();
+            assert_eq!(sample.channel, Some(channel));
+            total[channel] += value[channel] / samples as f64;
+            if sample.direction.z() < 0.0 {
+                bent[channel] = sample.direction.x().abs();
+            }
+        }
+
+        // each channel averages out to white
+        assert!(total.iter().all(|c| (c - 1.0).abs() < 0.05));
+        // blue light is bent further towards the normal than red
+        assert!(bent[2] < bent[1] && bent[1] < bent[0]);
+
+        // a path already split into a channel stays in it, without being weighted again
+        let bsdf = DielectricBSDF::new(&normal, 1.0 / 1.5)
+            .with_dispersion([1.0 / 1.49, 1.0 / 1.5, 1.0 / 1.52])
+            .with_channel(Some(2));
+        for _ in 0..100 {
+            let sample = bsdf.sample(&wo).unwrap();
+            assert_eq!(sample.channel, Some(2));
+            assert_eq!(sample.value, Color::new(0.0, 0.0, 1.0));
+        }
+    }
+}
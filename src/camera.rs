@@ -1,13 +1,71 @@
+use crate::rng::random;
 use indicatif::{ProgressBar, ProgressStyle};
-use rand::random;
 
 use crate::{
-    export::ImageWriter,
-    pdf::{HittablePDF, PDF},
+    analysis::{ExposureReport, Metering},
+    bsdf::Lobe,
+    color::ColorSpace,
+    config::ConfigModel,
+    export::{ImageWriter, RenderMetadata},
+    hittable::{BackFace, HitRecord, BACKGROUND_LIGHT_GROUP},
+    pdf::{HittablePDF, MixedPDF, BSDFPDF, PDF},
+    ray::RayDifferentials,
     vec::Normalized,
-    Color, Hittable, Interval, Point3, Ray4, Vec2, Vec3,
+    Angle, Axis, Color, Hittable, Interval, Point2, Point3, Ray4, Texture, Vec2, Vec3,
 };
-use std::{error::Error, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    f64::consts::PI,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+pub mod accumulator;
+pub use accumulator::Accumulator;
+pub mod aperture;
+pub use aperture::Aperture;
+mod aov;
+mod bake;
+pub mod depth;
+pub use depth::DepthMapping;
+pub mod error;
+pub use error::{CameraBuildError, CameraBuildErrors};
+pub mod lens;
+pub use lens::ThinLens;
+mod light_group;
+use light_group::PathRadiance;
+pub mod overlay;
+pub use overlay::Overlay;
+pub mod probe;
+pub use probe::{CubeFace, CubeMap, Probe};
+pub mod tiles;
+pub use tiles::{Tile, TileOrder};
+
+/// The longest side of the downscaled image metered by [`CameraBuilder::auto_exposure`],
+/// in pixels.
+const METERING_SIZE: u32 = 64;
+/// The number of samples taken of each pixel of the metered image.
+const METERING_SAMPLES: u32 = 4;
+/// The number of samples taken of each pixel before [`CameraBuilder::noise_threshold`] may
+/// stop a render; with fewer, the variance of a pixel's samples says little about its error.
+const NOISE_THRESHOLD_MIN_SAMPLES: u32 = 16;
+
+/// An object found by [`Camera::pick`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pick {
+    /// The name of the object, if it has one; see [`crate::hittable::Named`]
+    pub name: Option<String>,
+    /// The point on the object's surface seen through the pixel
+    pub point: Point3,
+    /// The surface normal at `point`, facing the camera
+    pub normal: Vec3<Normalized>,
+    /// The distance from the camera to `point`
+    pub distance: f64,
+    /// The index of the triangle hit within its mesh, if it was a mesh
+    pub primitive_index: Option<usize>,
+}
 
 #[derive(Debug)]
 #[must_use]
@@ -24,8 +82,14 @@ pub struct CameraBuilder<'a> {
     samples_per_px: u32,
     /// The maximum number of times a ray may bounce in a scene.
     max_depth: u32,
+    /// The maximum number of bounces of each [`BounceKind`] a ray may make.
+    max_bounces: [u32; 3],
     /// What to render if a ray doesn't hit anything
     background: Background,
+    /// A multiplier on the brightness of the background
+    background_strength: f64,
+    /// Rotation of the background about the y axis, in **radians**
+    background_rotation: f64,
     /// The centre of the camera; where rays are shot from.
     camera_center: Point3,
     /// The point the camera is looking towards.
@@ -37,12 +101,48 @@ pub struct CameraBuilder<'a> {
     vup: Vec3<Normalized>,
     /// The variation in angle of fired rays through each pixel, in **radians**.
     defocus_angle: f64,
+    /// The shape of the defocus disk; if unset, it's a circle.
+    aperture: Option<Aperture>,
+    /// If set, replaces [`Self::vfov`] and [`Self::defocus_angle`].
+    lens: Option<ThinLens>,
     /// The distance from [`Self::camera_center`] to the plane of perfect focus.
     focal_length: f64,
     /// The [`ImageWriter`] used for writing the resulting image
     export_writer: Option<Box<dyn ImageWriter + 'a>>,
-
-    errors: Vec<String>,
+    /// Whether samples with invalid radiance are detected and reported.
+    diagnostics: SampleDiagnostics,
+    /// Called as rendering progresses
+    progress: ProgressHook<'a>,
+    /// User-provided descriptions of the render, such as the scene's name
+    metadata: RenderMetadata,
+    /// Whether to print an [`ExposureReport`] after rendering.
+    exposure_report: bool,
+    /// A change in exposure, in stops, applied to the rendered image
+    exposure: f64,
+    /// If set, the exposure is metered from a quick pass before rendering
+    auto_exposure: Option<Metering>,
+    /// If set, only light from this light group is rendered.
+    light_group: Option<String>,
+    /// The light groups rendered into images of their own, and the writers of those images
+    light_group_outputs: Vec<(String, Box<dyn ImageWriter + 'a>)>,
+    /// The space the scene's colors are given in, and light is rendered in
+    working_space: ColorSpace,
+    /// The space the rendered image is written in
+    output_space: ColorSpace,
+    /// If set, rendering stops after the first pass to finish past this duration.
+    time_budget: Option<Duration>,
+    /// If set, the random number generator is seeded with this before rendering
+    seed: Option<u64>,
+    /// If set, rendering stops after the first pass to bring the image's relative error below this
+    noise_threshold: Option<f64>,
+    /// If set, the image is rendered in tiles of this size, in this order, rather than by row
+    tiles: Option<(TileOrder, u32)>,
+    /// How the light arriving along each camera ray is estimated
+    integrator: Integrator,
+    /// Debug lines drawn over the rendered image
+    overlay: Option<Overlay>,
+
+    errors: Vec<CameraBuildError>,
 }
 
 impl<'a> CameraBuilder<'a> {
@@ -50,11 +150,18 @@ impl<'a> CameraBuilder<'a> {
         Self::default()
     }
 
-    fn add_error(&mut self, err: String) {
-        self.errors.push(format!("CameraBuilder::{err}"));
+    /// Starts from the settings in a scene's `[camera]` and `[background]` tables (see
+    /// [`ConfigModel::configure_camera`]). Any setting can then be overridden by calling its
+    /// method as usual, such as to change the number of samples from the command line.
+    pub fn from_config(config: &ConfigModel) -> Self {
+        config.configure_camera(Self::new())
     }
 
-    fn error(&mut self, condition: bool, err: String) {
+    fn add_error(&mut self, err: CameraBuildError) {
+        self.errors.push(err);
+    }
+
+    fn error(&mut self, condition: bool, err: CameraBuildError) {
         if condition {
             self.add_error(err);
         }
@@ -63,11 +170,11 @@ impl<'a> CameraBuilder<'a> {
     pub fn dimensions(mut self, width: u32, height: u32) -> Self {
         self.error(
             width <= 1,
-            format!("dimensions: Invalid width: must be greater than 1, found {width}"),
+            CameraBuildError::invalid("dimensions", "width", "greater than 1", width),
         );
         self.error(
             height <= 1,
-            format!("dimensions: Invalid height: must be greater than 1, found {height}"),
+            CameraBuildError::invalid("dimensions", "height", "greater than 1", height),
         );
 
         self.image_width = width;
@@ -78,11 +185,16 @@ impl<'a> CameraBuilder<'a> {
     pub fn with_aspect_ratio(mut self, width: u32, aspect_ratio: f64) -> Self {
         self.error(
             width <= 1,
-            format!("with_aspect_ratio: Invalid width: must be greater than 1, found {width}"),
+            CameraBuildError::invalid("with_aspect_ratio", "width", "greater than 1", width),
         );
         self.error(
             !(0.1..=100.0).contains(&aspect_ratio),
-            format!("with_aspect_ratio: Invalid aspect_ratio: must be between 0.1 and 100.0, found {aspect_ratio}"),
+            CameraBuildError::invalid(
+                "with_aspect_ratio",
+                "aspect_ratio",
+                "between 0.1 and 100.0",
+                aspect_ratio,
+            ),
         );
 
         self.image_width = width;
@@ -94,35 +206,92 @@ impl<'a> CameraBuilder<'a> {
     pub fn max_depth(mut self, depth: u32) -> Self {
         self.error(
             depth < 1,
-            format!("max_depth: Invalid depth: must be at least 1, found {depth}"),
+            CameraBuildError::invalid("max_depth", "depth", "at least 1", depth),
         );
         self.max_depth = depth;
         self
     }
 
+    /// Limits the number of bounces of one kind a ray may make, in addition to
+    /// [`CameraBuilder::max_depth`]. For example, a high limit on transmission lets light pass
+    /// through many layers of glass, without also allowing long diffuse paths which add
+    /// little to the image but take a long time to trace. A limit of 0 disables that kind
+    /// of bounce entirely.
+    pub fn max_bounces(mut self, kind: BounceKind, depth: u32) -> Self {
+        self.max_bounces[kind as usize] = depth;
+        self
+    }
+
     pub fn background(mut self, bg: Background) -> Self {
         if let Background::Constant(col) = bg {
             self.error(
                 !col.is_valid(),
-                format!("background: Invalid color {col:?} provided."),
+                CameraBuildError::invalid(
+                    "background",
+                    "color",
+                    "within 0.0 and 1.0",
+                    format!("{col:?}"),
+                ),
             );
         }
         self.background = bg;
         self
     }
 
+    /// Scales the brightness of the background, and so the light it casts on the scene,
+    /// by `strength`; by default, 1.0.
+    pub fn background_strength(mut self, strength: f64) -> Self {
+        self.error(
+            !(strength >= 0.0 && strength.is_finite()),
+            CameraBuildError::invalid("background_strength", "strength", "at least 0.0", strength),
+        );
+        self.background_strength = strength;
+        self
+    }
+
+    /// Rotates the background about the y axis by `angle`, counterclockwise when seen
+    /// from above; useful for turning an [`Background::Environment`] to light the scene
+    /// from another side.
+    pub fn background_rotation(mut self, angle: Angle) -> Self {
+        self.error(
+            !angle.is_finite(),
+            CameraBuildError::invalid("background_rotation", "angle", "finite", angle),
+        );
+        self.background_rotation = angle.as_radians();
+        self
+    }
+
     pub fn antialias(mut self, antialiasing_type: AntialiasingType, samples_per_px: u32) -> Self {
         self.error(
             samples_per_px < 1,
-            format!(
-                "antialias: Invalid samples_per_px: must be at least 1, found {samples_per_px}"
-            ),
+            CameraBuildError::invalid("antialias", "samples_per_px", "at least 1", samples_per_px),
         );
         self.antialiasing_type = antialiasing_type;
         self.samples_per_px = samples_per_px;
         self
     }
 
+    /// Sets how pixels are sampled, keeping the number of samples per pixel.
+    pub fn antialiasing(mut self, antialiasing_type: AntialiasingType) -> Self {
+        self.antialiasing_type = antialiasing_type;
+        self
+    }
+
+    /// Sets the number of samples taken per pixel, keeping how they're placed.
+    pub fn samples_per_px(mut self, samples_per_px: u32) -> Self {
+        self.error(
+            samples_per_px < 1,
+            CameraBuildError::invalid(
+                "samples_per_px",
+                "samples_per_px",
+                "at least 1",
+                samples_per_px,
+            ),
+        );
+        self.samples_per_px = samples_per_px;
+        self
+    }
+
     pub fn camera_center(mut self, center: Point3) -> Self {
         self.camera_center = center;
         self
@@ -131,18 +300,18 @@ impl<'a> CameraBuilder<'a> {
     pub fn focal_length(mut self, length: f64) -> Self {
         self.error(
             length <= 0.0,
-            format!("focal_length: Invalid length: must be greater than 0.0, found {length}"),
+            CameraBuildError::invalid("focal_length", "length", "greater than 0.0", length),
         );
         self.focal_length = length;
         self
     }
 
-    pub fn vfov(mut self, deg: f64) -> Self {
+    pub fn vfov(mut self, angle: Angle) -> Self {
         self.error(
-            !(0.01..360.0).contains(&deg),
-            format!("vfov: Invalid deg: must be between 0.01 and 360.0, found {deg}"),
+            !(0.01..360.0).contains(&angle.as_degrees()),
+            CameraBuildError::invalid("vfov", "angle", "between 0.01° and 360°", angle),
         );
-        self.vfov = deg.to_radians();
+        self.vfov = angle.as_radians();
         self
     }
 
@@ -151,17 +320,67 @@ impl<'a> CameraBuilder<'a> {
         self
     }
 
+    /// Points the camera at the centre of `world`, and moves it along its current viewing
+    /// direction until all of the world's bounding box is in view, with `padding` (a fraction
+    /// of the box's size, such as 0.1) to spare; handy for previewing imported models of
+    /// unknown scale. The camera is also focused on the centre of the world.
+    ///
+    /// The field of view and dimensions set so far are used to fit the world, so this should
+    /// be called after [`CameraBuilder::vfov`] (or [`CameraBuilder::lens`]) and
+    /// [`CameraBuilder::dimensions`].
+    pub fn frame_scene(mut self, world: &impl Hittable, padding: f64) -> Self {
+        self.error(
+            !(padding >= 0.0 && padding.is_finite()),
+            CameraBuildError::invalid("frame_scene", "padding", "at least 0.0", padding),
+        );
+        let Some(sphere) = world.bounding_sphere().filter(|s| s.radius() > 0.0) else {
+            self.add_error(CameraBuildError::UnboundedWorld);
+            return self;
+        };
+
+        // fit the sphere around the box in the narrower of the two fields of view
+        let vfov = self.lens.map_or(self.vfov, |lens| lens.vfov().as_radians());
+        let aspect_ratio = f64::from(self.image_width) / f64::from(self.image_height);
+        let half_vfov = vfov / 2.0;
+        let half_hfov = (half_vfov.tan() * aspect_ratio).atan();
+        let distance = sphere.radius() * (1.0 + padding) / half_vfov.min(half_hfov).sin();
+
+        let direction = (self.camera_center - self.camera_target).as_unit();
+        self.camera_target = sphere.center();
+        self.camera_center = sphere.center() + direction * distance;
+        self.focal_length = distance;
+        self
+    }
+
     pub fn vup(mut self, vec: Vec3) -> Self {
         self.vup = vec.as_unit();
         self
     }
 
-    pub fn defocus_angle(mut self, angle: f64) -> Self {
+    pub fn defocus_angle(mut self, angle: Angle) -> Self {
         self.error(
-            !(0.0..180.0).contains(&angle),
-            format!("defocus_angle: Invalid angle: must be between 0.0 and 180.0, found {angle}"),
+            !(0.0..180.0).contains(&angle.as_degrees()),
+            CameraBuildError::invalid("defocus_angle", "angle", "between 0° and 180°", angle),
         );
-        self.defocus_angle = angle.to_radians();
+        self.defocus_angle = angle.as_radians();
+        self
+    }
+
+    /// Sets the field of view and depth of field from a physical lens, in place of
+    /// [`CameraBuilder::vfov`] and [`CameraBuilder::defocus_angle`]. The lens focuses at
+    /// [`CameraBuilder::focal_length`], which for a lens is the distance to the subject.
+    pub fn lens(mut self, lens: ThinLens) -> Self {
+        if let Some(error) = lens.validate() {
+            self.add_error(error);
+        }
+        self.lens = Some(lens);
+        self
+    }
+
+    /// Shapes the defocus disk (see [`CameraBuilder::defocus_angle`]), and so the bokeh of
+    /// out-of-focus highlights.
+    pub fn aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = Some(aperture);
         self
     }
 
@@ -170,14 +389,262 @@ impl<'a> CameraBuilder<'a> {
         self
     }
 
-    pub fn build(mut self) -> Result<Camera<'a>, Vec<String>> {
-        self.error(self.export_writer.is_none(),"build: Missing export format: include the `.writer()` parameter to specify the export format".to_string());
+    pub fn diagnostics(mut self, diagnostics: SampleDiagnostics) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Brightens the rendered image by `stops` (or darkens it, if negative), doubling its
+    /// brightness with each stop. With [`CameraBuilder::auto_exposure`], this is added to
+    /// the metered exposure, as exposure compensation.
+    pub fn exposure(mut self, stops: f64) -> Self {
+        self.error(
+            !stops.is_finite(),
+            CameraBuildError::invalid("exposure", "stops", "finite", stops),
+        );
+        self.exposure = stops;
+        self
+    }
+
+    /// Meters the scene with a quick, low-resolution pass before rendering, and exposes the
+    /// image so that it averages to middle grey, so that changing the scene's lights doesn't
+    /// require the exposure to be set again by hand.
+    pub fn auto_exposure(mut self, metering: Metering) -> Self {
+        self.auto_exposure = Some(metering);
+        self
+    }
+
+    /// Prints a luminance histogram and suggested exposure to stderr after rendering.
+    pub fn exposure_report(mut self, enabled: bool) -> Self {
+        self.exposure_report = enabled;
+        self
+    }
+
+    /// Only renders the light emitted by objects in `group` (see
+    /// [`LightGroup`](crate::hittable::LightGroup)), or by the background if `group` is
+    /// [`BACKGROUND_LIGHT_GROUP`]. Light from other groups still bounces around the scene, but
+    /// isn't counted; rendering every group with the same settings and adding the images
+    /// together gives the full render.
+    pub fn light_group(mut self, group: &str) -> Self {
+        self.light_group = Some(group.to_string());
+        self
+    }
+
+    /// Also renders the light emitted by objects in `group` (or by the background, for
+    /// [`BACKGROUND_LIGHT_GROUP`]) into an image of its own, written with `writer` in the same
+    /// pass as [`Camera::render`]. Each group's image counts only that group's light, whatever
+    /// [`Self::light_group`] is set to, so the images of every group add up to the full render
+    /// and can be rebalanced in compositing.
+    pub fn light_group_output(mut self, group: &str, writer: Box<dyn ImageWriter + 'a>) -> Self {
+        self.error(
+            self.light_group_outputs
+                .iter()
+                .any(|(name, _)| name == group),
+            CameraBuildError::invalid(
+                "light_group_output",
+                "group",
+                "a group without an output",
+                group,
+            ),
+        );
+        self.light_group_outputs.push((group.to_string(), writer));
+        self
+    }
+
+    /// Sets the space the scene's colors are given in, which light is also rendered in.
+    /// Defaults to [`ColorSpace::LinearSrgb`].
+    pub fn working_space(mut self, space: ColorSpace) -> Self {
+        self.working_space = space;
+        self
+    }
+
+    /// Sets the space the rendered image is written in. Defaults to
+    /// [`ColorSpace::LinearSrgb`]; other spaces are mainly useful with floating-point formats
+    /// such as [`ExrWriter`](crate::export::ExrWriter), since 8-bit formats assume sRGB.
+    pub fn output_space(mut self, space: ColorSpace) -> Self {
+        self.output_space = space;
+        self
+    }
+
+    /// Renders progressively, one sample per pixel at a time, and stops after the first pass
+    /// which ends past `budget`, writing the image with however many samples were taken.
+    /// The number of samples per pixel set with [`CameraBuilder::antialias`] is still the
+    /// most that will be taken. Progress is reported for the rows of each pass in turn.
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.error(
+            budget.is_zero(),
+            CameraBuildError::invalid("time_budget", "budget", "greater than zero", "0s"),
+        );
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Renders progressively, as [`CameraBuilder::time_budget`] does, and stops after the
+    /// first pass which brings the estimated error of the image below `threshold`, relative
+    /// to its brightness; for example, 0.01 stops once the error is about 1%. The error of
+    /// each pixel is estimated from the variance of its samples (see
+    /// [`Accumulator::mean_relative_error`]), so at least 16 samples are always taken of each
+    /// pixel (or all of them, if fewer are set). If a time budget is also set, rendering stops at
+    /// whichever is reached first.
+    pub fn noise_threshold(mut self, threshold: f64) -> Self {
+        self.error(
+            !(threshold > 0.0 && threshold.is_finite()),
+            CameraBuildError::invalid(
+                "noise_threshold",
+                "threshold",
+                "greater than 0.0",
+                threshold,
+            ),
+        );
+        self.noise_threshold = Some(threshold);
+        self
+    }
+
+    /// Renders the image in square tiles `size` pixels across, in the given `order`, rather
+    /// than row by row, so that previews (see [`ImageWriter::preview`]) can fill in from the
+    /// centre of the image first. Progress is reported per tile. Has no effect on progressive
+    /// renders (see [`CameraBuilder::time_budget`] and [`CameraBuilder::noise_threshold`]),
+    /// which render the whole image in each pass.
+    pub fn tiles(mut self, order: TileOrder, size: u32) -> Self {
+        self.error(
+            size < 1,
+            CameraBuildError::invalid("tiles", "size", "at least 1", size),
+        );
+        self.tiles = Some((order, size));
+        self
+    }
+
+    /// Sets how the light arriving at the camera is estimated; by default,
+    /// [`Integrator::PathTraced`]. It can be changed after the camera is built with
+    /// [`Camera::set_integrator`].
+    pub fn integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Draws `overlay` over the rendered image, such as axes and a ground grid to help with
+    /// orientation while authoring a scene. Only [`Camera::render`] draws the overlay; other
+    /// outputs, like [`Camera::render_depth`], are left untouched.
+    pub fn overlay(mut self, overlay: Overlay) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    /// Adds an entry to the metadata embedded in the output image, alongside the
+    /// camera's own settings (see [`RenderMetadata`]).
+    pub fn metadata(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Sets the title embedded in the output image's metadata, such as the scene's name.
+    pub fn title(self, title: impl ToString) -> Self {
+        self.metadata("Title", title)
+    }
+
+    /// Seeds the random number generator before rendering, so that rendering the same scene
+    /// with the same settings gives the same image (see [`rng`](crate::rng)). The seed is
+    /// recorded in the image's metadata. By default, a seed is chosen at random, and recorded
+    /// all the same, so any render can be repeated.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets a callback to be run after each row of the image is rendered,
+    /// with the number of rows completed and the total number of rows
+    /// (or tiles, if rendering in [tiles](CameraBuilder::tiles)).
+    pub fn on_progress(mut self, callback: impl FnMut(u32, u32) + 'a) -> Self {
+        self.progress = ProgressHook(Some(Box::new(callback)));
+        self
+    }
+
+    pub fn build(mut self) -> Result<Camera<'a>, CameraBuildErrors> {
+        self.error(
+            self.export_writer.is_none(),
+            CameraBuildError::MissingWriter,
+        );
 
         if !self.errors.is_empty() {
-            return Err(self.errors);
+            return Err(CameraBuildErrors::new(self.errors));
         }
         Ok(Camera::build(self))
     }
+
+    /// Builds a parallel stereo pair of cameras, offset to either side of this camera's
+    /// position by half of `eye_separation`. The left and right views are written to
+    /// `left` and `right` respectively.
+    pub fn stereo(
+        mut self,
+        eye_separation: f64,
+        left: Box<dyn ImageWriter + 'a>,
+        right: Box<dyn ImageWriter + 'a>,
+    ) -> Result<[Camera<'a>; 2], CameraBuildErrors> {
+        self.error(
+            eye_separation <= 0.0,
+            CameraBuildError::invalid(
+                "stereo",
+                "eye_separation",
+                "greater than 0.0",
+                eye_separation,
+            ),
+        );
+
+        // the camera's "right" direction; see `Camera::build`
+        let w = (self.camera_center - self.camera_target).as_unit();
+        let offset = Vec3::from(self.vup.cross(&w)).as_unit() * (eye_separation / 2.0);
+
+        let mut left_eye = self.duplicate().writer(left).metadata("Stereo eye", "left");
+        left_eye.camera_center = self.camera_center - offset;
+        left_eye.camera_target = self.camera_target - offset;
+
+        let mut right_eye = self.writer(right).metadata("Stereo eye", "right");
+        right_eye.camera_center = right_eye.camera_center + offset;
+        right_eye.camera_target = right_eye.camera_target + offset;
+
+        Ok([left_eye.build()?, right_eye.build()?])
+    }
+
+    /// Copies every setting except the writers and progress callback.
+    pub(crate) fn duplicate(&self) -> Self {
+        Self {
+            image_width: self.image_width,
+            image_height: self.image_height,
+            vfov: self.vfov,
+            antialiasing_type: self.antialiasing_type,
+            samples_per_px: self.samples_per_px,
+            max_depth: self.max_depth,
+            max_bounces: self.max_bounces,
+            background: self.background.clone(),
+            background_strength: self.background_strength,
+            background_rotation: self.background_rotation,
+            camera_center: self.camera_center,
+            camera_target: self.camera_target,
+            vup: self.vup,
+            defocus_angle: self.defocus_angle,
+            aperture: self.aperture.clone(),
+            lens: self.lens,
+            focal_length: self.focal_length,
+            export_writer: None,
+            diagnostics: self.diagnostics,
+            progress: ProgressHook(None),
+            metadata: self.metadata.clone(),
+            exposure_report: self.exposure_report,
+            exposure: self.exposure,
+            auto_exposure: self.auto_exposure,
+            light_group: self.light_group.clone(),
+            light_group_outputs: Vec::new(),
+            working_space: self.working_space,
+            output_space: self.output_space,
+            time_budget: self.time_budget,
+            seed: self.seed,
+            noise_threshold: self.noise_threshold,
+            tiles: self.tiles,
+            integrator: self.integrator,
+            overlay: self.overlay.clone(),
+            errors: self.errors.clone(),
+        }
+    }
 }
 
 impl Default for CameraBuilder<'_> {
@@ -189,19 +656,40 @@ impl Default for CameraBuilder<'_> {
             antialiasing_type: AntialiasingType::Square,
             samples_per_px: 10,
             max_depth: 10,
+            max_bounces: [u32::MAX; 3],
             background: Background::Sky,
+            background_strength: 1.0,
+            background_rotation: 0.0,
             camera_center: Point3::origin(),
             camera_target: Point3::new(0.0, 0.0, -1.0),
-            vup: Vec3::new(0.0, 1.0, 0.0).as_unit(),
+            vup: Axis::Y.unit(),
             defocus_angle: 0.0_f64.to_radians(),
+            aperture: None,
+            lens: None,
             focal_length: 1.0,
             export_writer: None,
+            diagnostics: SampleDiagnostics::Off,
+            progress: ProgressHook(None),
+            metadata: RenderMetadata::new(),
+            exposure_report: false,
+            exposure: 0.0,
+            auto_exposure: None,
+            light_group: None,
+            light_group_outputs: Vec::new(),
+            working_space: ColorSpace::LinearSrgb,
+            output_space: ColorSpace::LinearSrgb,
+            time_budget: None,
+            seed: None,
+            noise_threshold: None,
+            tiles: None,
+            integrator: Integrator::PathTraced,
+            overlay: None,
             errors: Vec::new(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 /// How pixels are sampled during antialiasing
 pub enum AntialiasingType {
     /// Sample points from a `1px × 1px` square centred on the pixel's centre
@@ -210,12 +698,71 @@ pub enum AntialiasingType {
     Disc,
 }
 
-#[derive(Debug)]
+/// How samples with NaN, infinite, or negative radiance are handled.
+/// These are always the result of a numerical bug, such as a zero pdf or a degenerate normal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleDiagnostics {
+    /// Invalid samples are not checked for.
+    Off,
+    /// Invalid samples are discarded, and counted per material.
+    /// A summary is printed to stderr after rendering.
+    Report,
+    /// As [`SampleDiagnostics::Report`], but pixels with any invalid samples are colored magenta.
+    Highlight,
+}
+
+/// How the light arriving along each camera ray is estimated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    /// Full global illumination, following light through any number of bounces.
+    PathTraced,
+    /// A fast preview, for keeping interactive views responsive on heavy scenes. Diffuse
+    /// surfaces are only lit by light arriving directly from lights and the background, and
+    /// at most [`Integrator::PREVIEW_SAMPLES`] samples are taken per pixel. Mirrors and glass
+    /// are still followed, so they don't turn black.
+    Preview,
+}
+
+impl Integrator {
+    /// The most samples per pixel taken by [`Integrator::Preview`].
+    pub const PREVIEW_SAMPLES: u32 = 4;
+}
+
+/// The kinds of bounce which can be limited separately; see [`CameraBuilder::max_bounces`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BounceKind {
+    /// Scattering across the hemisphere, from diffuse surfaces and volumes
+    Diffuse = 0,
+    /// Reflection from mirrors, metals, and the surfaces of glass
+    Glossy = 1,
+    /// Refraction through glass, into or out of an object
+    Transmission = 2,
+}
+
+impl BounceKind {
+    /// The kind of bounce made by scattering from `lobe` into `direction`, at a surface with
+    /// `normal` facing the incoming ray: diffuse for the diffuse lobe (whichever side it
+    /// scatters to), and otherwise glossy, unless the path passes through the surface.
+    fn of(lobe: Lobe, direction: &Vec3, normal: &Vec3<Normalized>) -> Self {
+        match lobe {
+            Lobe::Diffuse => BounceKind::Diffuse,
+            Lobe::Glossy | Lobe::Specular if direction.dot(normal) < 0.0 => {
+                BounceKind::Transmission
+            }
+            Lobe::Glossy | Lobe::Specular => BounceKind::Glossy,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Background {
     /// Produces a constant color across the background
     Constant(Color),
     /// Produce a sky gradient based on the shot ray's y-value
     Sky,
+    /// Wraps a texture around the scene, as an equirectangular (latitude-longitude) map:
+    /// `u` runs around the horizon, starting from -x, and `v` from the bottom to the top.
+    Environment(Rc<dyn Texture>),
 }
 
 #[derive(Debug)]
@@ -235,8 +782,6 @@ pub struct Camera<'a> {
     pxdelta_v: Vec3,
     /// How pixels are sampled during antialiasing.
     antialiasing_type: AntialiasingType,
-    /// How many random samples are made per pixel during antialiasing.
-    samples_per_px: u32,
     /// A fraction (`0.0..=1.0`) to multiply each sample by for antialiasing.
     /// Should be equal to `1.0 / samples_per_px`.
     px_sample_scale: f64,
@@ -246,17 +791,63 @@ pub struct Camera<'a> {
     sqrt_spp_scale: f64,
     /// The maximum number of times a ray may bounce in a scene.
     max_depth: u32,
+    /// The maximum number of bounces of each [`BounceKind`] a ray may make.
+    max_bounces: [u32; 3],
     /// What to render if a ray doesn't hit anything
     background: Background,
+    /// A multiplier on the brightness of the background
+    background_strength: f64,
+    /// Rotation of the background about the y axis, in radians
+    background_rotation: f64,
     /// The variation in angle of fired rays through each pixel, in radians.
     defocus_angle: f64,
     /// A vector crossing half the width of the defocus disk.
     defocus_disk_u: Vec3,
     /// A vector crossing half the height of the defocus disk.
     defocus_disk_v: Vec3,
+    /// The shape of the defocus disk; if unset, it's a circle.
+    aperture: Option<Aperture>,
     /// The [`ImageWriter`] used for writing the resulting image
     export_writer: ImageWriterWrapper<'a>,
     // export_writer: Box<dyn ImageWriter>,
+    /// Whether samples with invalid radiance are detected and reported.
+    diagnostics: SampleDiagnostics,
+    /// The number of invalid samples produced by each type of material.
+    invalid_samples: RefCell<BTreeMap<&'static str, u64>>,
+    /// Called as rendering progresses
+    progress: ProgressHook<'a>,
+    /// Describes the settings used to render the image
+    metadata: RenderMetadata,
+    /// Whether to print an [`ExposureReport`] after rendering.
+    exposure_report: bool,
+    /// A change in exposure, in stops, applied to the rendered image
+    exposure: f64,
+    /// If set, the exposure is metered from a quick pass before rendering
+    auto_exposure: Option<Metering>,
+    /// The multiplier on each pixel from the exposure, including any metered exposure
+    exposure_scale: f64,
+    /// If set, only light from this light group is rendered.
+    light_group: Option<String>,
+    /// The light groups rendered into images of their own, and the writers of those images
+    light_group_outputs: Vec<(String, ImageWriterWrapper<'a>)>,
+    /// The space the scene's colors are given in, and light is rendered in
+    working_space: ColorSpace,
+    /// The space the rendered image is written in
+    output_space: ColorSpace,
+    /// If set, rendering stops after the first pass to finish past this duration.
+    time_budget: Option<Duration>,
+    /// The random number generator is seeded with this before rendering
+    seed: u64,
+    /// If set, rendering stops after the first pass to bring the image's relative error below this
+    noise_threshold: Option<f64>,
+    /// If set, the image is rendered in tiles of this size, in this order, rather than by row
+    tiles: Option<(TileOrder, u32)>,
+    /// How the light arriving along each camera ray is estimated
+    integrator: Integrator,
+    /// The number of samples per pixel requested, which the integrator may reduce
+    samples_per_px: u32,
+    /// Debug lines drawn over the rendered image
+    overlay: Option<Overlay>,
 }
 
 /// This Wrapper is used so that the ImageWriter can be borrowed mutably independently of the
@@ -274,6 +865,33 @@ impl ImageWriterWrapper<'_> {
     fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
         self.0.write(colors)
     }
+
+    fn preview(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.0.preview(colors)
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        self.0.write_metadata(metadata)
+    }
+}
+
+/// A callback run as rendering progresses; see [`CameraBuilder::on_progress`].
+struct ProgressHook<'a>(Option<Box<dyn FnMut(u32, u32) + 'a>>);
+
+impl std::fmt::Debug for ProgressHook<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressHook")
+            .field(&self.0.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl ProgressHook<'_> {
+    fn call(&mut self, rows_done: u32, rows_total: u32) {
+        if let Some(callback) = &mut self.0 {
+            callback(rows_done, rows_total);
+        }
+    }
 }
 
 impl<'a> Camera<'a> {
@@ -286,18 +904,44 @@ impl<'a> Camera<'a> {
             image_height,
             camera_center,
             camera_target,
-            vfov,
+            mut vfov,
             background,
+            background_strength,
+            background_rotation,
             vup,
             antialiasing_type,
             samples_per_px,
             max_depth,
-            defocus_angle,
+            max_bounces,
+            mut defocus_angle,
+            aperture,
+            lens,
             focal_length,
             export_writer,
+            diagnostics,
+            progress,
+            mut metadata,
+            exposure_report,
+            exposure,
+            auto_exposure,
+            light_group,
+            light_group_outputs,
+            working_space,
+            output_space,
+            time_budget,
+            seed,
+            noise_threshold,
+            tiles,
+            integrator,
+            overlay,
             errors: _,
         } = builder;
 
+        if let Some(lens) = &lens {
+            vfov = lens.vfov().as_radians();
+            defocus_angle = lens.defocus_angle(focal_length).as_radians();
+        }
+
         let fwidth = f64::from(image_width);
         let fheight = f64::from(image_height);
 
@@ -341,10 +985,75 @@ impl<'a> Camera<'a> {
         let sqrt_spp = f64::from(samples_per_px).sqrt() as u32;
         let samples_per_px = sqrt_spp * sqrt_spp;
 
+        // |> Metadata <|
+        let point = |p: Point3| format!("{}, {}, {}", p.x(), p.y(), p.z());
+        metadata.insert(
+            "Software",
+            concat!("raytracing ", env!("CARGO_PKG_VERSION")),
+        );
+        metadata.insert("Dimensions", format!("{image_width}x{image_height}"));
+        metadata.insert("Samples per pixel", samples_per_px);
+        metadata.insert("Antialiasing", format!("{antialiasing_type:?}"));
+        metadata.insert("Max depth", max_depth);
+        for (kind, max) in [
+            BounceKind::Diffuse,
+            BounceKind::Glossy,
+            BounceKind::Transmission,
+        ]
+        .into_iter()
+        .zip(max_bounces)
+        {
+            if max != u32::MAX {
+                metadata.insert(format!("Max {kind:?} bounces"), max);
+            }
+        }
+        metadata.insert("Camera center", point(camera_center));
+        metadata.insert("Camera target", point(camera_target));
+        if let Some(lens) = &lens {
+            metadata.insert(
+                "Lens",
+                format!("{}mm f/{}", lens.focal_length_mm(), lens.f_stop()),
+            );
+        }
+        metadata.insert("Vertical FOV (degrees)", vfov.to_degrees());
+        metadata.insert("Color space", output_space);
+        metadata.insert("Defocus angle (degrees)", defocus_angle.to_degrees());
+        metadata.insert("Focal length", focal_length);
+        let description = match &background {
+            Background::Environment(_) => "Environment".to_string(),
+            background => format!("{background:?}"),
+        };
+        metadata.insert("Background", description);
+        metadata.insert("Background strength", background_strength);
+        if let Some(group) = &light_group {
+            metadata.insert("Light group", group);
+        }
+        metadata.insert(
+            "Background rotation (degrees)",
+            background_rotation.to_degrees(),
+        );
+        if let Some(budget) = time_budget {
+            metadata.insert("Time budget", format!("{:.2}s", budget.as_secs_f64()));
+        }
+        let seed = seed.unwrap_or_else(rand::random);
+        metadata.insert("Seed", seed);
+        if exposure != 0.0 {
+            metadata.insert("Exposure (stops)", exposure);
+        }
+        if let Some(metering) = auto_exposure {
+            metadata.insert("Metering", format!("{metering:?}"));
+        }
+        if let Some(threshold) = noise_threshold {
+            metadata.insert("Noise threshold", threshold);
+        }
+        if let Some((order, size)) = tiles {
+            metadata.insert("Tiles", format!("{order:?}, {size}px"));
+        }
+
         let px_sample_scale = 1.0 / f64::from(samples_per_px);
         let sqrt_spp_scale = 1.0 / f64::from(sqrt_spp);
 
-        Self {
+        let mut camera = Self {
             image_width,
             image_height,
             camera_center,
@@ -352,65 +1061,516 @@ impl<'a> Camera<'a> {
             pxdelta_u,
             pxdelta_v,
             antialiasing_type,
-            samples_per_px,
             px_sample_scale,
             sqrt_spp,
             sqrt_spp_scale,
             max_depth,
+            max_bounces,
             background,
+            background_strength,
+            background_rotation,
             defocus_angle,
             defocus_disk_u,
             defocus_disk_v,
+            aperture,
             export_writer: ImageWriterWrapper(export_writer.unwrap()),
+            diagnostics,
+            invalid_samples: RefCell::new(BTreeMap::new()),
+            progress,
+            metadata,
+            exposure_report,
+            exposure,
+            auto_exposure,
+            exposure_scale: exposure.exp2(),
+            light_group,
+            light_group_outputs: light_group_outputs
+                .into_iter()
+                .map(|(group, writer)| (group, ImageWriterWrapper(writer)))
+                .collect(),
+            working_space,
+            output_space,
+            time_budget,
+            seed,
+            noise_threshold,
+            tiles,
+            integrator,
+            samples_per_px,
+            overlay,
+        };
+        camera.set_integrator(integrator);
+        camera
+    }
+
+    /// Switches how the light arriving at the camera is estimated, such as to
+    /// [`Integrator::Preview`] while a scene is being edited, and back for the final render.
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+        let samples_per_px = match integrator {
+            Integrator::PathTraced => self.samples_per_px,
+            Integrator::Preview => self.samples_per_px.min(Integrator::PREVIEW_SAMPLES),
+        };
+
+        self.sqrt_spp = f64::from(samples_per_px).sqrt() as u32;
+        self.sqrt_spp_scale = 1.0 / f64::from(self.sqrt_spp);
+        self.px_sample_scale = 1.0 / f64::from(self.sqrt_spp * self.sqrt_spp);
+        self.metadata
+            .insert("Samples per pixel", self.sqrt_spp * self.sqrt_spp);
+        self.metadata
+            .insert("Integrator", format!("{integrator:?}"));
+    }
+
+    /// The number of invalid samples produced by each type of material during rendering.
+    /// Always empty unless diagnostics are enabled with [`CameraBuilder::diagnostics`].
+    pub fn invalid_samples(&self) -> BTreeMap<&'static str, u64> {
+        self.invalid_samples.borrow().clone()
+    }
+
+    /// Renders several views of the same world in turn, so that the world and its
+    /// bounding volume hierarchy only need to be built once.
+    pub fn render_all(cameras: &mut [Camera<'a>], world: &impl Hittable, lights: Rc<dyn Hittable>) {
+        for cam in cameras {
+            cam.render(world, Rc::clone(&lights));
+        }
+    }
+
+    /// The width and height, in pixels, of the rendered image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// The number of samples taken per pixel, which is rounded down to a square number.
+    pub fn samples_per_px(&self) -> u32 {
+        self.sqrt_spp * self.sqrt_spp
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// The position of the camera; where rays are shot from.
+    pub fn center(&self) -> Point3 {
+        self.camera_center
+    }
+
+    /// Projects `point` onto the image, returning its position in pixels from the top-left
+    /// corner of the image, or `None` if the point is behind the camera.
+    /// The position may lie outside of the image.
+    pub fn project(&self, point: &Point3) -> Option<Point2> {
+        let [[fx, _, cx], [_, fy, cy], _] = self.intrinsics();
+        let right = self.pxdelta_u.as_unit();
+        let down = self.pxdelta_v.as_unit();
+        let forward = self.pxdelta_u.cross(&self.pxdelta_v).as_unit();
+
+        let offset = *point - self.camera_center;
+        let depth = offset.dot(&forward);
+        if depth <= 0.0 {
+            return None;
         }
+        Some(Point2::new(
+            fx * offset.dot(&right) / depth + cx,
+            fy * offset.dot(&down) / depth + cy,
+        ))
+    }
+
+    /// Finds the object seen through the center of pixel `(x, y)`, where `(0, 0)` is the
+    /// top-left pixel, so that front-ends can select objects by clicking on them.
+    /// Returns `None` if the pixel shows the background.
+    pub fn pick(&self, x: u32, y: u32, world: &impl Hittable) -> Option<Pick> {
+        let ray = self.center_ray(x, y);
+        let hit = world.hit(&ray, Interval::new(0.001, f64::INFINITY))?;
+        Some(Pick {
+            name: hit.name().map(str::to_string),
+            point: hit.point(),
+            normal: hit.normal(),
+            distance: (hit.point() - self.camera_center).len(),
+            primitive_index: hit.primitive_index(),
+        })
+    }
+
+    /// The ray from the center of the camera through the center of pixel `(i, j)`, ignoring
+    /// defocus blur and antialiasing.
+    fn center_ray(&self, i: u32, j: u32) -> Ray4 {
+        let pixel = self.pixel_00 + f64::from(i) * self.pxdelta_u + f64::from(j) * self.pxdelta_v;
+        Ray4::new(self.camera_center, pixel - self.camera_center, 0.0)
+    }
+
+    /// The pinhole intrinsic matrix of the camera, `[[fx, 0, cx], [0, fy, cy], [0, 0, 1]]`,
+    /// in pixels.
+    pub fn intrinsics(&self) -> [[f64; 3]; 3] {
+        let forward = self.pxdelta_u.cross(&self.pxdelta_v).as_unit();
+        // the viewport plane is perpendicular to the view direction
+        let focal_length = (self.pixel_00 - self.camera_center).dot(&forward);
+        [
+            [
+                focal_length / self.pxdelta_u.len(),
+                0.0,
+                f64::from(self.image_width) / 2.0,
+            ],
+            [
+                0.0,
+                focal_length / self.pxdelta_v.len(),
+                f64::from(self.image_height) / 2.0,
+            ],
+            [0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// The camera-to-world transform as a row-major 4x4 matrix. Following the OpenGL
+    /// convention, the camera looks along its local -z axis, with +y up and +x to the right.
+    pub fn camera_to_world(&self) -> [[f64; 4]; 4] {
+        let right = self.pxdelta_u.as_unit();
+        let up = -self.pxdelta_v.as_unit();
+        let back = -self.pxdelta_u.cross(&self.pxdelta_v).as_unit();
+        let c = self.camera_center;
+        [
+            [right.x(), up.x(), back.x(), c.x()],
+            [right.y(), up.y(), back.y(), c.y()],
+            [right.z(), up.z(), back.z(), c.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// Traces a single randomly-placed sample through pixel `(i, j)`, where `(0, 0)` is the
+    /// top-left pixel. Samples can be averaged with an [`Accumulator`] by applications
+    /// that run their own render loop instead of [`Camera::render`]; the camera's
+    /// [`ImageWriter`] is not used. Samples are exposed and converted to the output color
+    /// space, as the pixels of a rendered image are.
+    pub fn sample(&self, i: u32, j: u32, world: &impl Hittable, lights: Rc<dyn Hittable>) -> Color {
+        let mut color = self.sample_radiance(i, j, world, lights);
+        color.set_brightness(self.exposure_scale);
+        self.working_space.convert(color, self.output_space)
+    }
+
+    /// The light arriving along a single randomly-placed sample through pixel `(i, j)`,
+    /// before exposure.
+    fn sample_radiance(
+        &self,
+        i: u32,
+        j: u32,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) -> Color {
+        // a random stratum is equivalent to sampling the whole pixel
+        let strata_i = crate::rng::random_range(0..self.sqrt_spp);
+        let strata_j = crate::rng::random_range(0..self.sqrt_spp);
+        let ray = self.get_ray(i, j, strata_i, strata_j);
+        self.ray_color(&ray, self.max_depth, world, lights)
+    }
+
+    fn invalid_sample_count(&self) -> u64 {
+        self.invalid_samples.borrow().values().sum()
     }
 
     pub fn render(&mut self, world: &impl Hittable, lights: Rc<dyn Hittable>) {
+        crate::rng::seed(self.seed);
+        if let Some(metering) = self.auto_exposure {
+            let metered = self.meter(world, &lights, metering);
+            self.exposure_scale = (self.exposure + metered).exp2();
+            self.metadata.insert("Metered exposure (stops)", metered);
+        }
+
+        if self.time_budget.is_some() || self.noise_threshold.is_some() {
+            self.render_progressive(world, lights);
+            return;
+        }
+
+        if let Some((order, size)) = self.tiles {
+            self.render_tiles(world, lights, order, size);
+            return;
+        }
+
         let Self {
             ref image_width,
             ref image_height,
             ..
         } = self;
 
-        let bar = ProgressBar::new((*image_height).into());
-        let style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} rows ({per_sec}, {eta})").unwrap().progress_chars("=>-");
-        bar.set_style(style);
+        let bar = Self::progress_bar(*image_height);
 
         self.export_writer
             .write_header(*image_width, *image_height)
             .unwrap();
+        let start = Instant::now();
 
         let mut buf: Vec<Color> =
             Vec::with_capacity((self.image_height * self.image_width).try_into().unwrap());
+        let mut group_bufs =
+            vec![Vec::with_capacity(buf.capacity()); self.light_group_outputs.len()];
 
         for j in 0..*image_height {
             for i in 0..*image_width {
-                let mut px_color = Color::black();
+                let (color, groups) = self.render_pixel(i, j, world, &lights);
+                buf.push(color);
+                for (group_buf, color) in group_bufs.iter_mut().zip(groups) {
+                    group_buf.push(color);
+                }
+            }
+            bar.inc(1);
+            self.export_writer.preview(&buf).unwrap();
+            self.progress.call(j + 1, *image_height);
+        }
+        bar.finish();
+
+        self.draw_overlay(&mut buf);
+        self.finish(&buf, start);
+        self.write_light_groups(group_bufs);
+    }
+
+    /// Finds the exposure, in stops, which brings the image to middle grey, from a pass over
+    /// a downscaled copy of the image.
+    fn meter(&self, world: &impl Hittable, lights: &Rc<dyn Hittable>, metering: Metering) -> f64 {
+        let scale = (f64::from(self.image_width.max(self.image_height)) / f64::from(METERING_SIZE))
+            .max(1.0);
+        let width = (f64::from(self.image_width) / scale).ceil() as u32;
+        let height = (f64::from(self.image_height) / scale).ceil() as u32;
+
+        // metering samples aren't part of the image, so they shouldn't be reported
+        let invalid_samples = self.invalid_samples.borrow().clone();
+        let mut colors = Vec::with_capacity((width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                let x = (((f64::from(i) + 0.5) * scale) as u32).min(self.image_width - 1);
+                let y = (((f64::from(j) + 0.5) * scale) as u32).min(self.image_height - 1);
+                let mut color = Color::black();
+                for _ in 0..METERING_SAMPLES {
+                    color += self.sample_radiance(x, y, world, Rc::clone(lights));
+                }
+                color.set_brightness(1.0 / f64::from(METERING_SAMPLES));
+                colors.push(color);
+            }
+        }
+        *self.invalid_samples.borrow_mut() = invalid_samples;
+
+        metering.exposure(&colors, width, height)
+    }
 
-                for strata_j in 0..self.sqrt_spp {
-                    for strata_i in 0..self.sqrt_spp {
-                        let ray = self.get_ray(i, j, strata_i, strata_j);
-                        px_color += self.ray_color(&ray, self.max_depth, world, Rc::clone(&lights));
+    /// Renders every sample of pixel `(i, j)`, giving its color in the output space, and the
+    /// mean light from each light group with an output (see [`Camera::write_light_groups`]).
+    fn render_pixel(
+        &self,
+        i: u32,
+        j: u32,
+        world: &impl Hittable,
+        lights: &Rc<dyn Hittable>,
+    ) -> (Color, Vec<Color>) {
+        let mut radiance = self.no_light();
+        let invalid_before = self.invalid_sample_count();
+
+        for strata_j in 0..self.sqrt_spp {
+            for strata_i in 0..self.sqrt_spp {
+                let ray = self.get_ray(i, j, strata_i, strata_j);
+                let sample =
+                    self.path_color(&ray, self.max_depth, [0; 3], world, Rc::clone(lights));
+                radiance.add(&sample);
+            }
+        }
+
+        let PathRadiance {
+            total: mut px_color,
+            mut groups,
+        } = radiance;
+        for color in &mut groups {
+            color.set_brightness(self.px_sample_scale);
+        }
+        px_color.set_brightness(self.px_sample_scale * self.exposure_scale);
+        if self.diagnostics == SampleDiagnostics::Highlight
+            && self.invalid_sample_count() > invalid_before
+        {
+            px_color = Color::debug_magenta();
+        }
+        (
+            self.working_space.convert(px_color, self.output_space),
+            groups,
+        )
+    }
+
+    /// Renders the image one tile at a time, in `order`; see [`CameraBuilder::tiles`].
+    /// Previews are of the whole image, with the tiles yet to be rendered left black.
+    fn render_tiles(
+        &mut self,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+        order: TileOrder,
+        size: u32,
+    ) {
+        let (width, height) = (self.image_width, self.image_height);
+        self.export_writer.write_header(width, height).unwrap();
+        let start = Instant::now();
+
+        let tiles = order.tiles(width, height, size);
+        let total = tiles.len() as u32;
+        let bar = ProgressBar::new(total.into());
+        let style = ProgressStyle::with_template(
+            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} tiles ({per_sec}, {eta})",
+        )
+        .unwrap()
+        .progress_chars("=>-");
+        bar.set_style(style);
+
+        let mut buf = vec![Color::black(); (width * height) as usize];
+        let mut group_bufs = vec![buf.clone(); self.light_group_outputs.len()];
+        for (done, tile) in tiles.into_iter().enumerate() {
+            for j in tile.y.clone() {
+                for i in tile.x.clone() {
+                    let index = (j * width + i) as usize;
+                    let (color, groups) = self.render_pixel(i, j, world, &lights);
+                    buf[index] = color;
+                    for (group_buf, color) in group_bufs.iter_mut().zip(groups) {
+                        group_buf[index] = color;
                     }
                 }
+            }
+            bar.inc(1);
+            self.export_writer.preview(&buf).unwrap();
+            self.progress.call(done as u32 + 1, total);
+        }
+        bar.finish();
+
+        self.draw_overlay(&mut buf);
+        self.finish(&buf, start);
+        self.write_light_groups(group_bufs);
+    }
 
-                px_color.set_brightness(self.px_sample_scale);
-                buf.push(px_color);
+    /// Renders one sample per pixel in each pass, cycling through the strata of each pixel,
+    /// until every sample is taken, a pass finishes after the time budget has run out, or
+    /// the image's estimated error falls below the noise threshold.
+    fn render_progressive(&mut self, world: &impl Hittable, lights: Rc<dyn Hittable>) {
+        let (width, height) = (self.image_width, self.image_height);
+        self.export_writer.write_header(width, height).unwrap();
+        let start = Instant::now();
+
+        let passes = self.sqrt_spp * self.sqrt_spp;
+        let bar = ProgressBar::new(passes.into());
+        let style = ProgressStyle::with_template(
+            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} passes",
+        )
+        .unwrap()
+        .progress_chars("=>-");
+        bar.set_style(style);
+
+        let mut samples = Accumulator::new(width, height);
+        let mut group_samples: Vec<_> = (0..self.light_group_outputs.len())
+            .map(|_| Accumulator::new(width, height))
+            .collect();
+        let mut invalid = vec![false; (width * height) as usize];
+        let mut buf = Vec::new();
+        let mut pass = 0;
+        while pass < passes {
+            let (strata_i, strata_j) = (pass % self.sqrt_spp, pass / self.sqrt_spp);
+            for j in 0..height {
+                for i in 0..width {
+                    let invalid_before = self.invalid_sample_count();
+                    let ray = self.get_ray(i, j, strata_i, strata_j);
+                    let radiance =
+                        self.path_color(&ray, self.max_depth, [0; 3], world, Rc::clone(&lights));
+                    samples.add_sample(i, j, radiance.total);
+                    for (group_samples, color) in group_samples.iter_mut().zip(radiance.groups) {
+                        group_samples.add_sample(i, j, color);
+                    }
+                    if self.invalid_sample_count() > invalid_before {
+                        invalid[(j * width + i) as usize] = true;
+                    }
+                }
+                self.progress.call(j + 1, height);
             }
+            pass += 1;
             bar.inc(1);
+
+            buf = samples
+                .resolve()
+                .into_iter()
+                .zip(&invalid)
+                .map(|(mut color, &invalid)| {
+                    if invalid && self.diagnostics == SampleDiagnostics::Highlight {
+                        Color::debug_magenta()
+                    } else {
+                        color.set_brightness(self.exposure_scale);
+                        self.working_space.convert(color, self.output_space)
+                    }
+                })
+                .collect();
+            self.export_writer.preview(&buf).unwrap();
+
+            if self
+                .time_budget
+                .is_some_and(|budget| start.elapsed() >= budget)
+            {
+                break;
+            }
+            if pass >= NOISE_THRESHOLD_MIN_SAMPLES
+                && self
+                    .noise_threshold
+                    .is_some_and(|threshold| samples.mean_relative_error() < threshold)
+            {
+                break;
+            }
+        }
+        bar.finish();
+
+        self.metadata.insert("Samples per pixel", pass);
+        if self.noise_threshold.is_some() {
+            self.metadata
+                .insert("Estimated error", samples.mean_relative_error());
+        }
+        self.draw_overlay(&mut buf);
+        self.finish(&buf, start);
+        self.write_light_groups(group_samples.iter().map(Accumulator::resolve).collect());
+    }
+
+    fn draw_overlay(&self, buf: &mut [Color]) {
+        if let Some(overlay) = &self.overlay {
+            overlay.draw(self, buf);
+        }
+    }
+
+    fn progress_bar(rows: u32) -> ProgressBar {
+        let bar = ProgressBar::new(rows.into());
+        let style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} rows ({per_sec}, {eta})").unwrap().progress_chars("=>-");
+        bar.set_style(style);
+        bar
+    }
+
+    /// Writes the finished image and its metadata, and prints any reports.
+    fn finish(&mut self, buf: &[Color], start: Instant) {
+        self.export_writer.write(buf).unwrap();
+
+        self.metadata.insert(
+            "Render time",
+            format!("{:.2}s", start.elapsed().as_secs_f64()),
+        );
+        self.export_writer.write_metadata(&self.metadata).unwrap();
+        self.report_invalid_samples();
+        if self.exposure_report {
+            eprintln!("{}", ExposureReport::from_buffer(buf));
+        }
+    }
+
+    fn report_invalid_samples(&self) {
+        let total = self.invalid_sample_count();
+        if total == 0 {
+            return;
         }
 
-        self.export_writer.write(&buf).unwrap();
+        eprintln!("warning: {total} samples had NaN, infinite, or negative radiance");
+        for (material, count) in self.invalid_samples.borrow().iter() {
+            eprintln!("  {material}: {count}");
+        }
     }
 
     /// Constructs a camera [`Ray4`] originating from the camera's `center` and directed at a
     /// randomly sampled point around the pixel location `(i, j)`, for stratified sample square
     /// `(strata_i, strata_j)`, at a random time between 0.0 and 1.0.
     fn get_ray(&self, i: u32, j: u32, strata_i: u32, strata_j: u32) -> Ray4 {
-        let offset = {
-            let x = ((f64::from(strata_i) + rand::random::<f64>()) * self.sqrt_spp_scale) - 0.5;
-            let y = ((f64::from(strata_j) + rand::random::<f64>()) * self.sqrt_spp_scale) - 0.5;
-            Vec2::new(x, y)
+        let offset = match self.antialiasing_type {
+            AntialiasingType::Square => {
+                let x = ((f64::from(strata_i) + crate::rng::random::<f64>()) * self.sqrt_spp_scale)
+                    - 0.5;
+                let y = ((f64::from(strata_j) + crate::rng::random::<f64>()) * self.sqrt_spp_scale)
+                    - 0.5;
+                Vec2::new(x, y)
+            }
+            // stratification is not applied to the disc
+            AntialiasingType::Disc => Vec2::random_in_unit_circle() * 0.5,
         };
 
         // px_sample is equal to the center of the pixel (offset in the 3d plane by 2d vectors i(Δu) and j(Δv))
@@ -426,7 +1586,56 @@ impl<'a> Camera<'a> {
         };
 
         let ray_direction = px_sample - ray_origin;
-        Ray4::new(ray_origin, ray_direction, random())
+
+        // the footprint of each sample shrinks as more samples are averaged
+        let spread = self.sqrt_spp_scale.max(0.125);
+        let differentials = RayDifferentials {
+            rx_origin: ray_origin,
+            rx_direction: ray_direction + self.pxdelta_u * spread,
+            ry_origin: ray_origin,
+            ry_direction: ray_direction + self.pxdelta_v * spread,
+        };
+        Ray4::new(ray_origin, ray_direction, random()).with_differentials(differentials)
+    }
+
+    /// The differentials of a ray leaving `hit` in `direction` after a perfectly specular
+    /// reflection or refraction of `ray`, treating the surface as locally flat.
+    fn specular_differentials(
+        ray: &Ray4,
+        hit: &HitRecord,
+        direction: &Vec3,
+    ) -> Option<RayDifferentials> {
+        let differentials = ray.differentials()?;
+        let footprint = hit.footprint()?;
+        // the normal faces against the incoming ray
+        let n: Vec3 = hit.normal().into();
+        let incoming = ray.direction().as_unit();
+        let outgoing = direction.as_unit();
+
+        let bend = |offset: Vec3| -> Vec3 {
+            let offset: Vec3 = offset.as_unit().into();
+            if outgoing.dot(&n) > 0.0 {
+                return offset - 2.0 * offset.dot(&n) * n;
+            }
+            // recover the relative index of refraction from the main ray
+            let cos_i = -incoming.dot(&n);
+            let cos_t = -outgoing.dot(&n);
+            let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+            let sin_t = (1.0 - cos_t * cos_t).max(0.0).sqrt();
+            let eta = if sin_i > 1e-6 { sin_t / sin_i } else { 1.0 };
+
+            let cos = (-offset.dot(&n)).min(1.0);
+            let perpendicular = eta * (offset + cos * n);
+            let parallel = -(1.0 - perpendicular.len_squared()).abs().sqrt() * n;
+            perpendicular + parallel
+        };
+
+        Some(RayDifferentials {
+            rx_origin: hit.point() + footprint.dpdx,
+            rx_direction: bend(differentials.rx_direction),
+            ry_origin: hit.point() + footprint.dpdy,
+            ry_direction: bend(differentials.ry_direction),
+        })
     }
 
     fn ray_color(
@@ -436,42 +1645,175 @@ impl<'a> Camera<'a> {
         world: &impl Hittable,
         lights: Rc<dyn Hittable>,
     ) -> Color {
+        self.path_color(ray, depth, [0; 3], world, lights).total
+    }
+
+    /// As [`Camera::ray_color`], where `bounces` counts the bounces of each [`BounceKind`]
+    /// the path has already made. The light from each light group with an output is also
+    /// gathered along the way.
+    fn path_color(
+        &self,
+        ray: &Ray4,
+        depth: u32,
+        mut bounces: [u32; 3],
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) -> PathRadiance {
         if depth == 0 {
             // Exceeded the bounce depth limit :(
-            return Color::black();
+            return self.no_light();
         }
 
         let Some(hit) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) else {
-            return match self.background {
-                Background::Constant(col) => col,
-                Background::Sky => Self::skybox_bg(ray),
-            };
+            return self.emission(BACKGROUND_LIGHT_GROUP, || self.background_color(ray));
         };
 
-        let emission_color = hit
-            .material()
-            .emitted(ray, &hit, hit.u(), hit.v(), &hit.point());
+        if !hit.front_face() {
+            match hit.back_face() {
+                BackFace::Flip => {}
+                BackFace::Absorb => return self.no_light(),
+                BackFace::Cull => {
+                    // continue past the surface; this doesn't count as a bounce
+                    let mut through = Ray4::new(hit.point(), ray.direction(), ray.time())
+                        .with_channel(ray.channel());
+                    if let Some(differentials) = ray.differentials() {
+                        through = through.with_differentials(differentials);
+                    }
+                    return self.path_color(&through, depth, bounces, world, lights);
+                }
+            }
+        }
 
-        let Some(scatter) = hit.material().scatter(ray, &hit) else {
-            // something in the world is hit, but the scattered ray is invalid
-            return emission_color;
+        let emission = self.emission(hit.light_group(), || {
+            hit.material()
+                .emitted(ray, &hit, hit.u(), hit.v(), &hit.point())
+        });
+
+        // the object may allow paths leaving it more (or fewer) bounces than the camera does
+        let depth = match hit.max_depth() {
+            Some(max_depth) => max_depth.saturating_sub(bounces.iter().sum()),
+            None => depth,
         };
+        if depth == 0 {
+            return emission;
+        }
 
-        let light_pdf = HittablePDF::new(Rc::clone(&lights), &hit.point());
-        let scattered = Ray4::new(hit.point(), light_pdf.generate(), ray.time());
-        let pdf_value = light_pdf.value(&scattered.direction());
+        let Some(bsdf) = hit.material().bsdf(ray, &hit) else {
+            // something in the world is hit, but it does not scatter light
+            return emission;
+        };
+
+        let wo = -ray.direction().as_unit();
+
+        // differentials only stay meaningful through mirror-like bounces
+        let mut differentials = None;
+        // once the path has been split into a single color channel, it stays in that channel
+        let mut channel = ray.channel();
+        let (direction, value, pdf_value, kind) = if bsdf.is_delta() {
+            let Some(sample) = bsdf.sample(&wo) else {
+                return emission;
+            };
+            differentials = Self::specular_differentials(ray, &hit, &sample.direction);
+            channel = sample.channel.or(channel);
+            let kind = BounceKind::of(sample.lobe, &sample.direction, &hit.normal());
+            (sample.direction, sample.value, sample.pdf, kind)
+        } else {
+            // sample towards the lights and according to the BSDF with equal probability,
+            // weighting by the combined density of both strategies.
+            let light_pdf: Rc<dyn PDF> =
+                Rc::new(HittablePDF::new(Rc::clone(&lights), &hit.point()));
+            let bsdf_pdf = BSDFPDF::new(Rc::clone(&bsdf), &wo);
+            let mixed_pdf = MixedPDF::equal(vec![Rc::clone(&light_pdf), Rc::new(bsdf_pdf)]);
+
+            // the bounce is classified by the lobe it was sampled from, or for directions
+            // towards the lights, the lobe which scatters the most light into them
+            let (direction, lobe) = if random::<f64>() < 0.5 {
+                let Some(sample) = bsdf.sample(&wo) else {
+                    // the BSDF absorbed the sample
+                    return emission;
+                };
+                (sample.direction, sample.lobe)
+            } else {
+                let direction = light_pdf.generate();
+                (direction, bsdf.lobe(&wo, &direction))
+            };
+            let kind = BounceKind::of(lobe, &direction, &hit.normal());
+            let value = bsdf.eval(&wo, &direction);
+            (direction, value, mixed_pdf.value(&direction), kind)
+        };
 
-        let scattering_pdf = hit.material().scattering_pdf(ray, &hit, &scatter.scattered);
+        if pdf_value <= 0.0 || bounces[kind as usize] >= self.max_bounces[kind as usize] {
+            return emission;
+        }
+        bounces[kind as usize] += 1;
 
-        let sample_color = self.ray_color(&scattered, depth - 1, world, lights);
-        let mut scatter_color = Color::mul(&scatter.attenuation, &sample_color);
-        scatter_color.set_brightness(scattering_pdf / pdf_value);
+        let mut scattered = Ray4::new(hit.point(), direction, ray.time()).with_channel(channel);
+        if let Some(differentials) = differentials {
+            scattered = scattered.with_differentials(differentials);
+        }
+        let sample_color = match (self.integrator, kind) {
+            (Integrator::Preview, BounceKind::Diffuse) => self.direct_light(&scattered, world),
+            _ => self.path_color(&scattered, depth - 1, bounces, world, lights),
+        };
+        let mut radiance = sample_color;
+        radiance.scatter(&value, 1.0 / pdf_value);
+        radiance.add(&emission);
+        // invalid samples from deeper bounces have already been discarded,
+        // so this is attributed to the material which produced it.
+        if self.diagnostics != SampleDiagnostics::Off && !radiance.is_physical() {
+            *self
+                .invalid_samples
+                .borrow_mut()
+                .entry(hit.material().type_name())
+                .or_default() += 1;
+            return self.no_light();
+        }
+        radiance
+    }
 
-        Color::add(&emission_color, &scatter_color)
+    /// The light arriving along `ray` without any further bounces: the light emitted by the
+    /// first surface hit, or the background if nothing is hit.
+    fn direct_light(&self, ray: &Ray4, world: &impl Hittable) -> PathRadiance {
+        match world.hit(ray, Interval::new(0.001, f64::INFINITY)) {
+            Some(hit) => self.emission(hit.light_group(), || {
+                hit.material()
+                    .emitted(ray, &hit, hit.u(), hit.v(), &hit.point())
+            }),
+            None => self.emission(BACKGROUND_LIGHT_GROUP, || self.background_color(ray)),
+        }
     }
 
-    fn skybox_bg(ray: &Ray4) -> Color {
-        let nd = ray.direction().as_unit();
+    /// Whether light from `group` is rendered; see [`CameraBuilder::light_group`].
+    fn counts_light_from(&self, group: &str) -> bool {
+        self.light_group.as_ref().is_none_or(|g| g == group)
+    }
+
+    /// The color of the background seen along `ray`.
+    fn background_color(&self, ray: &Ray4) -> Color {
+        // rotating the background one way is rotating the ray the other
+        let (sin, cos) = (-self.background_rotation).sin_cos();
+        let d = ray.direction().as_unit();
+        let direction = Vec3::new(cos * d.x() + sin * d.z(), d.y(), -sin * d.x() + cos * d.z());
+
+        let mut color = match &self.background {
+            Background::Constant(col) => *col,
+            Background::Sky => Self::skybox_bg(&direction),
+            Background::Environment(texture) => {
+                // as for the uv coordinates of a sphere
+                let theta = (-direction.y()).clamp(-1.0, 1.0).acos();
+                let phi = f64::atan2(-direction.z(), direction.x()) + PI;
+                let point = Point3::new(direction.x(), direction.y(), direction.z());
+                texture.value(phi / (2.0 * PI), theta / PI, &point)
+            }
+        };
+        if self.background_strength != 1.0 {
+            color.set_brightness(self.background_strength);
+        }
+        color
+    }
+
+    fn skybox_bg(direction: &Vec3) -> Color {
+        let nd = direction.as_unit();
         let intensity = (nd.y() + 1.0) * 0.5;
 
         let whiteness = Vec3::new(1.0, 1.0, 1.0) * (1.0 - intensity);
@@ -483,7 +1825,676 @@ impl<'a> Camera<'a> {
 
     fn sample_defocus_disk(&self) -> Point3 {
         // returns a random point in the camera's defocus disc.
-        let pt = Vec2::random_in_unit_circle();
+        let pt = match &self.aperture {
+            Some(aperture) => aperture.sample(),
+            None => Vec2::random_in_unit_circle(),
+        };
         self.camera_center + pt.x() * self.defocus_disk_u + pt.y() * self.defocus_disk_v
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hittable::{ConstantMedium, HittableVec, Parallelogram, Sphere},
+        material::{
+            BlinnPhong, Dielectric, DiffuseLight, Lambertian, Layered, Metal, ThinDielectric,
+        },
+        test_support::{BufferWriter, MetadataWriter},
+        Material,
+    };
+
+    /// Renders `object` inside a "white furnace": a uniform white environment with no other
+    /// objects. Returns the mean luminance of the image, which should be 1.0 for any material
+    /// which neither absorbs nor emits light.
+    fn furnace(object: Rc<dyn Hittable>) -> f64 {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(8, 8)
+            .antialias(AntialiasingType::Square, 64)
+            .max_depth(64)
+            .background(Background::Constant(Color::white()))
+            .camera_center(Point3::new(0.0, 0.0, 3.0))
+            .camera_target(Point3::origin())
+            .vfov(Angle::degrees(30.0))
+            .diagnostics(SampleDiagnostics::Report)
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        let mut world = HittableVec::new();
+        world.add(object);
+        cam.render(&world, HittableVec::new().hittable());
+
+        assert!(
+            cam.invalid_samples().is_empty(),
+            "{:?}",
+            cam.invalid_samples()
+        );
+        drop(cam);
+        buf.iter().map(Color::luminance).sum::<f64>() / buf.len() as f64
+    }
+
+    fn sphere(material: Rc<dyn Material>) -> Rc<dyn Hittable> {
+        Sphere::stationary(Point3::origin(), 1.0, material).hittable()
+    }
+
+    #[test]
+    fn stereo_eyes_are_offset() {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        let [left_eye, right_eye] = CameraBuilder::new()
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .stereo(
+                0.5,
+                Box::new(BufferWriter(&mut left)),
+                Box::new(BufferWriter(&mut right)),
+            )
+            .unwrap();
+
+        // looking down -z, the camera's right is +x
+        assert_eq!(left_eye.camera_center, Point3::new(-0.25, 0.0, 5.0));
+        assert_eq!(right_eye.camera_center, Point3::new(0.25, 0.0, 5.0));
+        assert_eq!(left_eye.metadata.get("Stereo eye"), Some("left"));
+    }
+
+    #[test]
+    fn pick_finds_named_objects() {
+        let mut buf = Vec::new();
+        let cam = CameraBuilder::new()
+            .dimensions(9, 9)
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        let mut world = HittableVec::new();
+        let material = Lambertian::solid(Color::white()).into_mat();
+        world.add(
+            Sphere::stationary(Point3::origin(), 1.0, material)
+                .named("ball")
+                .hittable(),
+        );
+
+        let pick = cam.pick(4, 4, &world).unwrap();
+        assert_eq!(pick.name.as_deref(), Some("ball"));
+        assert!((pick.point - Point3::new(0.0, 0.0, 1.0)).len() < 1e-9);
+        assert!((pick.distance - 4.0).abs() < 1e-9);
+        assert!(cam.pick(0, 0, &world).is_none());
+    }
+
+    #[test]
+    fn background_strength_and_rotation() {
+        /// A texture showing its `u` coordinate; the longitude of an environment.
+        #[derive(Debug)]
+        struct Longitude;
+
+        impl Texture for Longitude {
+            fn value(&self, u: f64, _v: f64, _point: &Point3) -> Color {
+                Color::over_white(u)
+            }
+        }
+
+        let center = |rotation: f64| {
+            let mut buf = Vec::new();
+            let cam = CameraBuilder::new()
+                .dimensions(101, 101)
+                .background(Background::Environment(Rc::new(Longitude)))
+                .background_strength(2.0)
+                .background_rotation(Angle::degrees(rotation))
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            cam.sample(50, 50, &HittableVec::new(), HittableVec::new().hittable())
+                .r()
+        };
+
+        // looking along -z, three quarters of the way around from -x
+        assert!((center(0.0) - 1.5).abs() < 0.01);
+        // turning the environment a quarter turn brings +x into view
+        assert!((center(90.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn light_groups() {
+        // two lights side by side, with the background visible above them
+        let mut world = HittableVec::new();
+        for (group, x) in [("key", -2.0), ("fill", 0.0)] {
+            let light = Parallelogram::new(
+                Point3::new(x, -2.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                DiffuseLight::solid(Color::new(4.0, 4.0, 4.0)).into_mat(),
+            );
+            world.add(light.in_light_group(group).hittable());
+        }
+
+        let render = |group: &str| {
+            let mut buf = Vec::new();
+            let cam = CameraBuilder::new()
+                .dimensions(9, 9)
+                .background(Background::Constant(Color::white()))
+                .camera_center(Point3::new(0.0, 0.0, 5.0))
+                .camera_target(Point3::origin())
+                .vfov(Angle::degrees(30.0))
+                .light_group(group)
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            let lights = HittableVec::new().hittable();
+            [(2, 6), (6, 6), (4, 1)].map(|(i, j)| cam.sample(i, j, &world, Rc::clone(&lights)))
+        };
+
+        let (white, light) = (Color::white(), Color::new(4.0, 4.0, 4.0));
+        let black = Color::black();
+        assert_eq!(render("key"), [light, black, black]);
+        assert_eq!(render("fill"), [black, light, black]);
+        assert_eq!(render(BACKGROUND_LIGHT_GROUP), [black, black, white]);
+
+        // every group's image from a single render
+        let groups = ["key", "fill", BACKGROUND_LIGHT_GROUP];
+        let (mut full, mut bufs) = (Vec::new(), [Vec::new(), Vec::new(), Vec::new()]);
+        let mut builder = CameraBuilder::new()
+            .dimensions(9, 9)
+            .antialias(AntialiasingType::Square, 4)
+            .background(Background::Constant(Color::white()))
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .vfov(Angle::degrees(30.0))
+            .writer(Box::new(BufferWriter(&mut full)));
+        for (group, buf) in groups.into_iter().zip(&mut bufs) {
+            builder = builder.light_group_output(group, Box::new(BufferWriter(buf)));
+        }
+        let mut cam = builder.build().unwrap();
+        cam.render(&world, HittableVec::new().hittable());
+        drop(cam);
+
+        let pixel = |buf: &[Color], (i, j): (usize, usize)| buf[j * 9 + i];
+        for (buf, expected) in bufs.iter().zip([
+            [light, black, black],
+            [black, light, black],
+            [black, black, white],
+        ]) {
+            assert_eq!([(2, 6), (6, 6), (4, 1)].map(|p| pixel(buf, p)), expected);
+        }
+        for (index, color) in full.iter().enumerate() {
+            let sum = bufs
+                .iter()
+                .fold(Color::black(), |sum, buf| Color::add(&sum, &buf[index]));
+            assert!((sum.r() - color.r()).abs() < 1e-9, "{sum:?} != {color:?}");
+        }
+
+        assert!(CameraBuilder::new()
+            .writer(Box::new(BufferWriter(&mut Vec::new())))
+            .light_group_output("key", Box::new(BufferWriter(&mut Vec::new())))
+            .light_group_output("key", Box::new(BufferWriter(&mut Vec::new())))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn bounce_limits() {
+        let glass = sphere(Dielectric::new(1.5).into_mat());
+        let mut world = HittableVec::new();
+        world.add(glass);
+
+        // the mean brightness of the centre of a glass ball, in front of a white background
+        let center = |transmission: u32| {
+            let mut buf = Vec::new();
+            let cam = CameraBuilder::new()
+                .dimensions(9, 9)
+                .background(Background::Constant(Color::white()))
+                .camera_center(Point3::new(0.0, 0.0, 5.0))
+                .camera_target(Point3::origin())
+                .max_bounces(BounceKind::Transmission, transmission)
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            let lights = HittableVec::new().hittable();
+            (0..200)
+                .map(|_| cam.sample(4, 4, &world, Rc::clone(&lights)).r())
+                .sum::<f64>()
+                / 200.0
+        };
+
+        // light passes in and back out of the ball
+        assert!(center(2) > 0.9);
+        // only the reflection off the front of the ball is left
+        assert!(center(1) < 0.2);
+        assert!(center(0) < 0.2);
+
+        // bounces off a purely glossy surface are glossy, even though it can be light sampled
+        let mut world = HittableVec::new();
+        world.add(sphere(
+            BlinnPhong::solid(Color::black(), Color::white(), 20.0).into_mat(),
+        ));
+        let center = |kind: BounceKind| {
+            let mut buf = Vec::new();
+            let cam = CameraBuilder::new()
+                .dimensions(9, 9)
+                .background(Background::Constant(Color::white()))
+                .camera_center(Point3::new(0.0, 0.0, 5.0))
+                .camera_target(Point3::origin())
+                .max_bounces(kind, 0)
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            let lights = HittableVec::new().hittable();
+            (0..200)
+                .map(|_| cam.sample(4, 4, &world, Rc::clone(&lights)).r())
+                .sum::<f64>()
+                / 200.0
+        };
+        assert_eq!(center(BounceKind::Glossy), 0.0);
+        assert!(center(BounceKind::Diffuse) > 0.2);
+    }
+
+    #[test]
+    fn back_faces() {
+        let mut buf = Vec::new();
+        let cam = CameraBuilder::new()
+            .dimensions(9, 9)
+            .background(Background::Constant(Color::white()))
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        // a red mirror facing away from the camera
+        let mirror = || {
+            Parallelogram::new(
+                Point3::new(-10.0, -10.0, 0.0),
+                Vec3::new(0.0, 20.0, 0.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Metal::new(Color::new(1.0, 0.0, 0.0)).into_mat(),
+            )
+        };
+        let center = |back_face| {
+            let mut world = HittableVec::new();
+            world.add(mirror().with_back_face(back_face).hittable());
+            cam.sample(4, 4, &world, HittableVec::new().hittable())
+        };
+
+        assert_eq!(center(BackFace::Flip), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(center(BackFace::Absorb), Color::black());
+        assert_eq!(center(BackFace::Cull), Color::white());
+    }
+
+    #[test]
+    fn furnace_conserves_energy() {
+        let white = || Lambertian::solid(Color::white()).into_mat();
+        let materials = [
+            ("lambertian", white()),
+            ("metal", Metal::new(Color::white()).into_mat()),
+            (
+                "fuzzy metal",
+                Metal::with_fuzz(Color::white(), 0.5).into_mat(),
+            ),
+            ("dielectric", Dielectric::new(1.5).into_mat()),
+            ("thin dielectric", ThinDielectric::new(1.5).into_mat()),
+            ("clearcoat", Layered::clearcoat(white()).into_mat()),
+        ];
+
+        for (name, material) in materials {
+            let mean = furnace(sphere(material));
+            assert!(
+                (mean - 1.0).abs() < 0.03,
+                "{name}: expected 1.0, found {mean}"
+            );
+        }
+
+        let medium = ConstantMedium::colored(sphere(white()), 2.0, Color::white()).hittable();
+        let mean = furnace(medium);
+        assert!(
+            (mean - 1.0).abs() < 0.03,
+            "medium: expected 1.0, found {mean}"
+        );
+    }
+
+    #[test]
+    fn furnace_does_not_gain_energy() {
+        // these materials absorb some light, but must never reflect more than they receive
+        let materials = [
+            ("gold", Metal::gold().into_mat()),
+            ("silver", Metal::silver().fuzzed(0.3).into_mat()),
+            (
+                "blinn-phong",
+                BlinnPhong::solid(Color::over_white(0.5), Color::over_white(0.5), 32.0).into_mat(),
+            ),
+        ];
+
+        for (name, material) in materials {
+            let mean = furnace(sphere(material));
+            assert!(mean < 1.03, "{name}: gained energy, found {mean}");
+        }
+    }
+    #[test]
+    fn tiles() {
+        // keeps the first preview
+        #[derive(Debug)]
+        struct FirstPreview<'a>(&'a mut Vec<Color>);
+        impl ImageWriter for FirstPreview<'_> {
+            fn write_header(&mut self, _width: u32, _height: u32) -> Result<(), Box<dyn Error>> {
+                Ok(())
+            }
+
+            fn write(&mut self, _colors: &[Color]) -> Result<(), Box<dyn Error>> {
+                Ok(())
+            }
+
+            fn preview(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+                if self.0.is_empty() {
+                    self.0.extend_from_slice(colors);
+                }
+                Ok(())
+            }
+        }
+
+        let (mut preview, mut tiles) = (Vec::new(), 0);
+        let mut cam = CameraBuilder::new()
+            .dimensions(9, 6)
+            .background(Background::Constant(Color::white()))
+            .tiles(TileOrder::Spiral, 3)
+            .on_progress(|_, _| tiles += 1)
+            .writer(Box::new(FirstPreview(&mut preview)))
+            .build()
+            .unwrap();
+        cam.render(&HittableVec::new(), HittableVec::new().hittable());
+        drop(cam);
+        assert_eq!(tiles, 6);
+
+        // only the tile at the centre of the image has been rendered
+        for j in 0..6 {
+            for i in 0..9 {
+                let rendered = (3..6).contains(&i) && j < 3;
+                assert_eq!(preview[j * 9 + i] == Color::white(), rendered);
+            }
+        }
+
+        assert!(CameraBuilder::new()
+            .tiles(TileOrder::Hilbert, 0)
+            .writer(Box::new(BufferWriter(&mut Vec::new())))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn time_budget() {
+        let rows_rendered = |budget: Duration| {
+            let (mut buf, mut rows) = (Vec::new(), 0);
+            let mut cam = CameraBuilder::new()
+                .dimensions(4, 4)
+                .antialias(AntialiasingType::Square, 9)
+                .background(Background::Constant(Color::white()))
+                .time_budget(budget)
+                .on_progress(|_, _| rows += 1)
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            cam.render(&HittableVec::new(), HittableVec::new().hittable());
+            drop(cam);
+            assert_eq!(buf, [Color::white(); 16]);
+            rows
+        };
+
+        // the first pass always finishes
+        assert_eq!(rows_rendered(Duration::from_nanos(1)), 4);
+        // but no more than the requested samples are taken
+        assert_eq!(rows_rendered(Duration::from_secs(3600)), 9 * 4);
+    }
+
+    #[test]
+    fn seeded_renders_repeat() {
+        let render = |seed: Option<u64>| {
+            let (mut buf, mut metadata) = (Vec::new(), RenderMetadata::new());
+            let cam = CameraBuilder::new()
+                .dimensions(4, 4)
+                .antialias(AntialiasingType::Disc, 4)
+                .background(Background::Constant(Color::white()))
+                .camera_center(Point3::new(0.0, 0.0, 3.0))
+                .camera_target(Point3::origin())
+                .writer(Box::new(MetadataWriter(&mut buf, &mut metadata)));
+            let mut cam = match seed {
+                Some(seed) => cam.seed(seed),
+                None => cam,
+            }
+            .build()
+            .unwrap();
+            let mut world = HittableVec::new();
+            world.add(
+                Sphere::stationary(
+                    Point3::origin(),
+                    1.0,
+                    Lambertian::solid(Color::white()).into_mat(),
+                )
+                .hittable(),
+            );
+            cam.render(&world, HittableVec::new().hittable());
+            drop(cam);
+            let seed: u64 = metadata.get("Seed").unwrap().parse().unwrap();
+            (buf, seed)
+        };
+
+        assert_eq!(render(Some(1)), render(Some(1)));
+        assert_ne!(render(Some(1)).0, render(Some(2)).0);
+        // unseeded renders record the seed they were given, which repeats them
+        let (buf, seed) = render(None);
+        assert_eq!(render(Some(seed)), (buf, seed));
+    }
+
+    #[test]
+    fn exposure() {
+        let render = |builder: CameraBuilder| {
+            let mut buf = Vec::new();
+            let mut cam = builder
+                .dimensions(4, 4)
+                .antialias(AntialiasingType::Square, 1)
+                .background(Background::Constant(Color::over_white(0.045)))
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            cam.render(&HittableVec::new(), HittableVec::new().hittable());
+            drop(cam);
+            buf[0].luminance()
+        };
+
+        assert!((render(CameraBuilder::new().exposure(1.0)) - 0.09).abs() < 1e-9);
+        // metering brings the background up two stops, to middle grey
+        let metered = render(CameraBuilder::new().auto_exposure(Metering::Average));
+        assert!((metered - 0.18).abs() < 1e-3);
+        let compensated = render(
+            CameraBuilder::new()
+                .auto_exposure(Metering::CenterWeighted)
+                .exposure(-1.0),
+        );
+        assert!((compensated - 0.09).abs() < 1e-3);
+    }
+
+    #[test]
+    fn samples_match_the_render() {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(4, 4)
+            .antialias(AntialiasingType::Square, 1)
+            .background(Background::Constant(Color::new(0.1, 0.05, 0.02)))
+            .exposure(1.5)
+            .output_space(ColorSpace::AcesCg)
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+        cam.render(&HittableVec::new(), HittableVec::new().hittable());
+        let sample = cam.sample(1, 2, &HittableVec::new(), HittableVec::new().hittable());
+        drop(cam);
+
+        let rendered = buf[2 * 4 + 1];
+        for (a, b) in [
+            (sample.r(), rendered.r()),
+            (sample.g(), rendered.g()),
+            (sample.b(), rendered.b()),
+        ] {
+            assert!((a - b).abs() < 1e-9);
+        }
+        assert!((sample.luminance() - Color::new(0.1, 0.05, 0.02).luminance()).abs() > 0.01);
+    }
+
+    #[test]
+    fn frame_scene() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let mut world = HittableVec::new();
+        world.add(Sphere::stationary(Point3::new(100.0, 0.0, 0.0), 10.0, mat).hittable());
+
+        let mut buf = Vec::new();
+        let cam = CameraBuilder::new()
+            .dimensions(40, 20)
+            .vfov(Angle::degrees(60.0))
+            .frame_scene(&world, 0.0)
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+        // looking along -z, as by default, from far enough to fit the bounding box
+        let center = cam.center();
+        assert!((center.x() - 100.0).abs() < 1e-6 && center.y().abs() < 1e-6);
+        let radius = 10.0 * 3f64.sqrt();
+        assert!((center.z() - radius / 30f64.to_radians().sin()).abs() < 1e-3);
+        let projected = cam.project(&Point3::new(100.0, 0.0, 0.0)).unwrap();
+        assert!((projected.x() - 20.0).abs() < 1e-6 && (projected.y() - 10.0).abs() < 1e-6);
+
+        let errors = CameraBuilder::new()
+            .frame_scene(&HittableVec::new(), 0.1)
+            .writer(Box::new(BufferWriter(&mut Vec::new())))
+            .build()
+            .unwrap_err();
+        assert_eq!(errors.errors(), [CameraBuildError::UnboundedWorld]);
+    }
+
+    #[test]
+    fn build_errors() {
+        use miette::Diagnostic;
+
+        let Err(errors) = CameraBuilder::new().dimensions(0, 100).build() else {
+            panic!("expected the camera to be rejected");
+        };
+        assert_eq!(
+            errors.errors(),
+            [
+                CameraBuildError::InvalidSetting {
+                    method: "dimensions",
+                    parameter: "width",
+                    expected: "greater than 1",
+                    found: "0".to_string(),
+                },
+                CameraBuildError::MissingWriter,
+            ]
+        );
+        assert_eq!(
+            errors.errors()[0].to_string(),
+            "CameraBuilder::dimensions: Invalid width: must be greater than 1, found 0"
+        );
+        let help = errors.errors()[0].help().unwrap().to_string();
+        assert_eq!(help, "see the documentation of `CameraBuilder::dimensions`");
+        assert_eq!(errors.related().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn noise_threshold() {
+        let (mut buf, mut rows) = (Vec::new(), 0);
+        let mut cam = CameraBuilder::new()
+            .dimensions(4, 4)
+            .antialias(AntialiasingType::Square, 36)
+            .background(Background::Constant(Color::white()))
+            .noise_threshold(0.01)
+            .on_progress(|_, _| rows += 1)
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+        cam.render(&HittableVec::new(), HittableVec::new().hittable());
+        drop(cam);
+        assert_eq!(buf, [Color::white(); 16]);
+        // a flat background has no noise, but a few samples can't show that
+        assert_eq!(rows, NOISE_THRESHOLD_MIN_SAMPLES * 4);
+
+        assert!(CameraBuilder::new()
+            .noise_threshold(0.0)
+            .writer(Box::new(BufferWriter(&mut Vec::new())))
+            .build()
+            .is_err());
+    }
+    #[test]
+    fn preview_integrator() {
+        // inside a grey room lit by a small lamp, about half of the light on the walls has
+        // bounced off of other walls first
+        let mut world = HittableVec::new();
+        let walls = Lambertian::solid(Color::over_white(0.5)).into_mat();
+        world.add(Sphere::stationary(Point3::origin(), 10.0, walls).hittable());
+        let lamp = DiffuseLight::solid(Color::over_white(50.0)).into_mat();
+        let lamp = Parallelogram::new(
+            Point3::new(-1.0, 5.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            lamp,
+        )
+        .hittable();
+        world.add(Rc::clone(&lamp));
+
+        let mean = |integrator: Integrator| {
+            let mut buf = Vec::new();
+            let mut cam = CameraBuilder::new()
+                .dimensions(8, 8)
+                .antialias(AntialiasingType::Square, 64)
+                .max_depth(16)
+                .camera_target(Point3::new(0.0, -1.0, 0.0))
+                .integrator(integrator)
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            cam.render(&world, Rc::clone(&lamp));
+            let samples = cam.sqrt_spp * cam.sqrt_spp;
+            drop(cam);
+            let mean = buf.iter().map(Color::luminance).sum::<f64>() / buf.len() as f64;
+            (mean, samples)
+        };
+
+        let (path_traced, samples) = mean(Integrator::PathTraced);
+        assert_eq!(samples, 64);
+        let (preview, samples) = mean(Integrator::Preview);
+        assert_eq!(samples, Integrator::PREVIEW_SAMPLES);
+        assert!(preview > 0.0);
+        assert!(preview < path_traced * 0.75, "{preview} vs {path_traced}");
+    }
+
+    #[test]
+    fn depth_override() {
+        // a mirror filling the view, which reflects the white background back at the camera
+        let mirror = || {
+            Parallelogram::new(
+                Point3::new(-10.0, -10.0, -2.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::new(0.0, 20.0, 0.0),
+                Metal::new(Color::white()).into_mat(),
+            )
+        };
+        let mean = |object: Rc<dyn Hittable>| {
+            let mut world = HittableVec::new();
+            world.add(object);
+            let mut buf = Vec::new();
+            let mut cam = CameraBuilder::new()
+                .dimensions(4, 4)
+                .antialias(AntialiasingType::Square, 1)
+                .max_depth(1)
+                .background(Background::Constant(Color::white()))
+                .writer(Box::new(BufferWriter(&mut buf)))
+                .build()
+                .unwrap();
+            cam.render(&world, HittableVec::new().hittable());
+            drop(cam);
+            buf.iter().map(Color::luminance).sum::<f64>() / buf.len() as f64
+        };
+
+        // the reflection needs a second bounce, which only the mirror allows
+        assert_eq!(mean(mirror().hittable()), 0.0);
+        assert!(mean(mirror().with_max_depth(2).hittable()) > 0.99);
+    }
+}
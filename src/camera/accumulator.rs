@@ -0,0 +1,185 @@
+use crate::Color;
+
+/// Averages samples per pixel, for applications which drive their own progressive render loop
+/// (such as an interactive preview) rather than using [`Camera::render`](crate::Camera::render).
+///
+/// Samples are typically produced by [`Camera::sample`](crate::Camera::sample).
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    sums: Vec<Color>,
+    /// The sums of the squares of each channel, for estimating variance
+    squares: Vec<[f64; 3]>,
+    counts: Vec<u32>,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+        Self {
+            width,
+            height,
+            sums: vec![Color::black(); len],
+            squares: vec![[0.0; 3]; len],
+            counts: vec![0; len],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel ({x}, {y}) is outside of the {}x{} image",
+            self.width,
+            self.height
+        );
+        (y * self.width + x) as usize
+    }
+
+    /// Adds a sample of the color of pixel `(x, y)`, where `(0, 0)` is the top-left pixel.
+    pub fn add_sample(&mut self, x: u32, y: u32, color: Color) {
+        let i = self.index(x, y);
+        self.sums[i] += color;
+        let [r, g, b] = &mut self.squares[i];
+        *r += color.r() * color.r();
+        *g += color.g() * color.g();
+        *b += color.b() * color.b();
+        self.counts[i] += 1;
+    }
+
+    /// The number of samples taken of pixel `(x, y)`.
+    pub fn samples(&self, x: u32, y: u32) -> u32 {
+        self.counts[self.index(x, y)]
+    }
+
+    /// The estimated variance of the average of pixel `(x, y)`, for each channel. This shrinks
+    /// as more samples are taken; pixels with fewer than two samples are black.
+    pub fn variance(&self, x: u32, y: u32) -> Color {
+        let i = self.index(x, y);
+        let count = self.counts[i];
+        if count < 2 {
+            return Color::black();
+        }
+
+        let n = f64::from(count);
+        let sum = self.sums[i];
+        let channel = |sum: f64, square: f64| {
+            // the unbiased sample variance, divided by the number of samples averaged
+            ((square - sum * sum / n) / (n - 1.0)).max(0.0) / n
+        };
+        let [r, g, b] = self.squares[i];
+        Color::new(
+            channel(sum.r(), r),
+            channel(sum.g(), g),
+            channel(sum.b(), b),
+        )
+    }
+
+    /// The standard error of pixel `(x, y)` relative to its average, in the channel with the
+    /// largest relative error. Black pixels which have never seen any light have no error;
+    /// pixels with fewer than two samples have an unknown (infinite) error.
+    pub fn relative_error(&self, x: u32, y: u32) -> f64 {
+        let i = self.index(x, y);
+        let count = self.counts[i];
+        if count < 2 {
+            return f64::INFINITY;
+        }
+
+        let n = f64::from(count);
+        let (sum, variance) = (self.sums[i], self.variance(x, y));
+        [
+            (sum.r(), variance.r()),
+            (sum.g(), variance.g()),
+            (sum.b(), variance.b()),
+        ]
+        .into_iter()
+        .map(|(sum, variance)| match (sum / n).abs() {
+            mean if mean > 0.0 => variance.sqrt() / mean,
+            _ => 0.0,
+        })
+        .fold(0.0, f64::max)
+    }
+
+    /// The [relative error](Accumulator::relative_error) of the whole image, averaged over
+    /// its pixels, for deciding when a progressive render has converged. The error is
+    /// unknown (infinite) until every pixel has at least two samples.
+    pub fn mean_relative_error(&self) -> f64 {
+        let mut total = 0.0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                total += self.relative_error(x, y);
+            }
+        }
+        total / f64::from(self.width * self.height)
+    }
+
+    /// The average of the samples of each pixel, in row-major order.
+    /// Pixels without any samples are black.
+    pub fn resolve(&self) -> Vec<Color> {
+        self.sums
+            .iter()
+            .zip(&self.counts)
+            .map(|(sum, &count)| {
+                let mut color = *sum;
+                if count > 0 {
+                    color.set_brightness(1.0 / f64::from(count));
+                }
+                color
+            })
+            .collect()
+    }
+
+    /// Discards all samples, such as after the camera or scene changes.
+    pub fn clear(&mut self) {
+        self.sums.fill(Color::black());
+        self.squares.fill([0.0; 3]);
+        self.counts.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_averages() {
+        let mut acc = Accumulator::new(2, 1);
+        acc.add_sample(1, 0, Color::white());
+        acc.add_sample(1, 0, Color::black());
+
+        assert_eq!(acc.samples(1, 0), 2);
+        assert_eq!(acc.resolve(), [Color::black(), Color::over_white(0.5)]);
+
+        acc.clear();
+        assert_eq!(acc.samples(1, 0), 0);
+    }
+
+    #[test]
+    fn variance() {
+        let mut acc = Accumulator::new(2, 1);
+        for value in [1.0, 3.0, 1.0, 3.0] {
+            acc.add_sample(0, 0, Color::new(value, 2.0, 0.0));
+            acc.add_sample(1, 0, Color::white());
+        }
+
+        // the sample variance is 4/3, and the variance of an average of 4 samples is 1/3
+        let variance = acc.variance(0, 0);
+        assert!((variance.r() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!((variance.g(), variance.b()), (0.0, 0.0));
+        assert!((acc.relative_error(0, 0) - (1.0f64 / 3.0).sqrt() / 2.0).abs() < 1e-9);
+        assert_eq!(acc.relative_error(1, 0), 0.0);
+        assert!((acc.mean_relative_error() - (1.0f64 / 3.0).sqrt() / 4.0).abs() < 1e-9);
+
+        acc.clear();
+        acc.add_sample(0, 0, Color::white());
+        assert_eq!(acc.relative_error(0, 0), f64::INFINITY);
+    }
+}
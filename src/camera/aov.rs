@@ -0,0 +1,192 @@
+//! Auxiliary outputs (AOVs) for compositing: surface normals, object IDs, and variance.
+//!
+//! Like [`Camera::render_depth`], normals and object IDs trace one ray through the centre of
+//! each pixel, so the edges of objects aren't blended together.
+
+use std::{rc::Rc, time::Instant};
+
+use super::{Accumulator, Camera};
+use crate::{Color, HitRecord, Hittable, Interval};
+
+impl Camera<'_> {
+    /// Traces one ray through the centre of each pixel, and converts the first surface hit
+    /// (if any) into a color with `shade`. Writes the header and previews, but leaves
+    /// finishing the image to the caller.
+    pub(super) fn trace_first_hits(
+        &mut self,
+        world: &impl Hittable,
+        mut shade: impl FnMut(Option<HitRecord>) -> Color,
+    ) -> (Vec<Color>, Instant) {
+        let (width, height) = (self.image_width, self.image_height);
+        self.export_writer.write_header(width, height).unwrap();
+        let bar = Self::progress_bar(height);
+        let start = Instant::now();
+
+        let mut buf = Vec::with_capacity((width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                let ray = self.center_ray(i, j);
+                buf.push(shade(world.hit(&ray, Interval::new(0.001, f64::INFINITY))));
+            }
+            bar.inc(1);
+            self.export_writer.preview(&buf).unwrap();
+            self.progress.call(j + 1, height);
+        }
+        bar.finish();
+
+        (buf, start)
+    }
+
+    /// Renders the world-space normal of the first surface seen through each pixel, with the
+    /// x, y and z components in the red, green and blue channels. Normals face the camera,
+    /// and components are kept in `-1.0..=1.0`, so use a floating-point format such as
+    /// [`PfmWriter`](crate::export::PfmWriter) or [`ExrWriter`](crate::export::ExrWriter).
+    /// Pixels where nothing is hit are black.
+    pub fn render_normals(&mut self, world: &impl Hittable) {
+        let (buf, start) = self.trace_first_hits(world, |hit| match hit {
+            Some(hit) => {
+                let n = hit.normal();
+                Color::new(n.x(), n.y(), n.z())
+            }
+            None => Color::black(),
+        });
+        self.finish(&buf, start);
+    }
+
+    /// Renders the ID of the named object (see [`Named`](crate::hittable::Named)) seen through
+    /// each pixel into all three channels, for building masks in compositing. IDs are
+    /// numbered from 1 in the order objects first appear, scanning from the top-left;
+    /// pixels where nothing (or an unnamed object) is hit are 0. The name of each ID is
+    /// recorded in the metadata.
+    pub fn render_object_ids(&mut self, world: &impl Hittable) {
+        let mut names: Vec<String> = Vec::new();
+        let (buf, start) = self.trace_first_hits(world, |hit| {
+            let Some(name) = hit.as_ref().and_then(HitRecord::name) else {
+                return Color::black();
+            };
+            let id = match names.iter().position(|n| n == name) {
+                Some(index) => index + 1,
+                None => {
+                    names.push(name.to_string());
+                    names.len()
+                }
+            };
+            Color::over_white(id as f64)
+        });
+
+        let ids: Vec<String> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{}={name}", i + 1))
+            .collect();
+        self.metadata.insert("Object IDs", ids.join(", "));
+        self.finish(&buf, start);
+    }
+
+    /// Renders the estimated variance of each pixel of [`Camera::render`] with the same
+    /// samples per pixel, per channel, as a measure of how noisy the image is. Variance is
+    /// usually far below 1.0, so use a floating-point format such as
+    /// [`PfmWriter`](crate::export::PfmWriter) or [`ExrWriter`](crate::export::ExrWriter).
+    /// The average relative error of the pixels is recorded in the metadata.
+    pub fn render_variance(&mut self, world: &impl Hittable, lights: Rc<dyn Hittable>) {
+        let (width, height) = (self.image_width, self.image_height);
+        self.export_writer.write_header(width, height).unwrap();
+        let bar = Self::progress_bar(height);
+        let start = Instant::now();
+
+        let mut samples = Accumulator::new(width, height);
+        let mut buf = Vec::with_capacity((width * height) as usize);
+        let (mut error_sum, mut error_count) = (0.0, 0);
+        for j in 0..height {
+            for i in 0..width {
+                for strata_j in 0..self.sqrt_spp {
+                    for strata_i in 0..self.sqrt_spp {
+                        let ray = self.get_ray(i, j, strata_i, strata_j);
+                        let color = self.ray_color(&ray, self.max_depth, world, Rc::clone(&lights));
+                        samples.add_sample(i, j, color);
+                    }
+                }
+
+                let error = samples.relative_error(i, j);
+                if error.is_finite() {
+                    error_sum += error;
+                    error_count += 1;
+                }
+                buf.push(samples.variance(i, j));
+            }
+            bar.inc(1);
+            self.export_writer.preview(&buf).unwrap();
+            self.progress.call(j + 1, height);
+        }
+        bar.finish();
+
+        if error_count > 0 {
+            let error = error_sum / f64::from(error_count);
+            self.metadata
+                .insert("Relative error", format!("{:.2}%", error * 100.0));
+        }
+        self.finish(&buf, start);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        camera::{AntialiasingType, Background},
+        hittable::{HittableVec, Sphere},
+        material::Lambertian,
+        test_support::BufferWriter,
+        CameraBuilder, Material, Point3,
+    };
+
+    #[test]
+    fn object_ids() {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(9, 9)
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        let material = Lambertian::solid(Color::white()).into_mat();
+        let mut world = HittableVec::new();
+        for (name, x) in [("left", -0.6), ("right", 0.6)] {
+            let sphere = Sphere::stationary(Point3::new(x, 0.0, 0.0), 0.5, material.clone());
+            world.add(sphere.named(name).hittable());
+        }
+
+        cam.render_object_ids(&world);
+        drop(cam);
+        let row: Vec<f64> = buf[36..45].iter().map(Color::r).collect();
+        assert_eq!(row[0], 0.0);
+        assert_eq!(row[3], 1.0);
+        assert_eq!(row[5], 2.0);
+    }
+
+    #[test]
+    fn variance() {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(9, 9)
+            .antialias(AntialiasingType::Square, 16)
+            .background(Background::Constant(Color::white()))
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        let material = Lambertian::solid(Color::over_white(0.5)).into_mat();
+        let mut world = HittableVec::new();
+        world.add(Sphere::stationary(Point3::origin(), 1.0, material).hittable());
+
+        cam.render_variance(&world, HittableVec::new().hittable());
+        drop(cam);
+        // the constant background has no noise, but the diffuse sphere does
+        assert_eq!(buf[0], Color::black());
+        assert!(buf[40].luminance() > 0.0);
+    }
+}
@@ -0,0 +1,71 @@
+//! Shaped apertures, which give out-of-focus highlights ("bokeh") the shape of the aperture.
+
+use crate::{Point3, Texture, Vec2};
+
+/// The transmission of the camera's aperture, tabulated from a texture so that points on the
+/// defocus disk can be chosen in proportion to how much light passes through them.
+///
+/// The texture's unit square is stretched over the square enclosing the defocus disk, so an
+/// image of a white star on black gives star-shaped bokeh.
+#[derive(Debug, Clone)]
+pub struct Aperture {
+    resolution: u32,
+    /// The running total of the transmission of each cell, row by row, normalized to end at 1.0
+    cdf: Vec<f64>,
+}
+
+impl Aperture {
+    /// Tabulates the luminance of `texture` over a grid of `resolution` × `resolution` cells.
+    pub fn from_texture(texture: &dyn Texture, resolution: u32) -> Self {
+        assert!(resolution > 0, "Invalid aperture resolution (expected > 0)");
+
+        let mut cdf = Vec::with_capacity((resolution * resolution) as usize);
+        let mut total = 0.0;
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let u = (f64::from(i) + 0.5) / f64::from(resolution);
+                let v = (f64::from(j) + 0.5) / f64::from(resolution);
+                total += texture.value(u, v, &Point3::origin()).luminance().max(0.0);
+                cdf.push(total);
+            }
+        }
+        assert!(total > 0.0, "Aperture must let some light through");
+        for value in &mut cdf {
+            *value /= total;
+        }
+
+        Self { resolution, cdf }
+    }
+
+    /// Picks a random point on the aperture, with each of `x` and `y` in `-1.0..=1.0`.
+    pub fn sample(&self) -> Vec2 {
+        let target: f64 = crate::rng::random();
+        let cell = self
+            .cdf
+            .partition_point(|&value| value <= target)
+            .min(self.cdf.len() - 1) as u32;
+
+        let (i, j) = (cell % self.resolution, cell / self.resolution);
+        let u = (f64::from(i) + crate::rng::random::<f64>()) / f64::from(self.resolution);
+        let v = (f64::from(j) + crate::rng::random::<f64>()) / f64::from(self.resolution);
+        Vec2::new(u * 2.0 - 1.0, v * 2.0 - 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::texture::ImageTexture;
+
+    #[test]
+    fn samples_follow_transmission() {
+        // only the right half of the aperture is open
+        let image = ImageTexture::new(vec![0, 0, 0, 255, 255, 255], (2, 1));
+        let aperture = Aperture::from_texture(&image, 8);
+        for _ in 0..1000 {
+            let point = aperture.sample();
+            assert!((0.0..=1.0).contains(&point.x()));
+            assert!((-1.0..=1.0).contains(&point.y()));
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Baking: rendering the lighting on a mesh into its texture (UV) space rather than through
+//! the camera's lens, for use as a lightmap by other renderers such as game engines.
+
+use std::{borrow::Borrow, f64::consts::PI, rc::Rc, time::Instant};
+
+use crate::{
+    hittable::Triangle,
+    pdf::{CosinePDF, HittablePDF, MixedPDF, PDF},
+    Color, Hittable, Interval, Point3, Ray4, Vec3,
+};
+
+use super::{Camera, SampleDiagnostics};
+
+/// The number of texels that baked values are extended past the edges of each UV island,
+/// so that texture filtering doesn't bleed black into the edges of the mesh.
+const PADDING: u32 = 4;
+
+/// A point on the target mesh which corresponds to a texel.
+#[derive(Debug, Clone, Copy)]
+struct SurfacePoint {
+    point: Point3,
+    normal: Vec3,
+}
+
+impl Camera<'_> {
+    /// Bakes the light arriving at the surface of `target` into a lightmap, written with the
+    /// camera's [`ImageWriter`](crate::export::ImageWriter). The image spans the `0.0..=1.0`
+    /// UV square of the mesh, at the camera's resolution and samples per pixel;
+    /// the camera's position and lens are not used.
+    ///
+    /// Each texel holds the irradiance at that point divided by π, so multiplying it by a
+    /// surface's albedo gives the light the surface reflects. `target` should also be part of
+    /// `world`, so that the mesh shadows itself. Texels not covered by the mesh are black.
+    ///
+    /// `target` is any collection of triangles, such as a slice or
+    /// [`Mesh::triangles`](crate::hittable::Mesh::triangles).
+    pub fn bake_lightmap(
+        &mut self,
+        target: impl IntoIterator<Item = impl Borrow<Triangle>>,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) {
+        let samples = self.sqrt_spp * self.sqrt_spp;
+        self.bake(target, samples, |camera, surface| {
+            camera.irradiance_sample(surface, world, Rc::clone(&lights))
+        });
+    }
+
+    /// Bakes the ambient occlusion of `target` into a greyscale texture, written with the
+    /// camera's [`ImageWriter`](crate::export::ImageWriter) as with [`Camera::bake_lightmap`].
+    ///
+    /// Each texel is the fraction of `rays` cosine-weighted rays which escape without hitting
+    /// anything in `world` within `max_distance`; white texels are completely unoccluded.
+    pub fn bake_ambient_occlusion(
+        &mut self,
+        target: impl IntoIterator<Item = impl Borrow<Triangle>>,
+        world: &impl Hittable,
+        rays: u32,
+        max_distance: f64,
+    ) {
+        assert!(rays > 0, "Ambient occlusion requires at least one ray");
+        assert!(max_distance > 0.0, "Invalid occlusion distance");
+
+        self.metadata.insert("Occlusion rays", rays);
+        self.metadata.insert("Occlusion distance", max_distance);
+        self.bake(target, rays, |_, surface| {
+            let direction = CosinePDF::new(&surface.normal).generate();
+            let ray = Ray4::new(surface.point, direction, crate::rng::random());
+            let distance = max_distance / direction.len();
+            match world.hit(&ray, Interval::new(0.001, distance)) {
+                Some(_) => Color::black(),
+                None => Color::white(),
+            }
+        });
+    }
+
+    /// Bakes `target` in texture space, averaging `samples` calls of `sample` for each texel.
+    fn bake(
+        &mut self,
+        target: impl IntoIterator<Item = impl Borrow<Triangle>>,
+        samples: u32,
+        sample: impl Fn(&Self, &SurfacePoint) -> Color,
+    ) {
+        let target: Vec<_> = target.into_iter().collect();
+        let (width, height) = (self.image_width, self.image_height);
+        let texels = rasterize(&target, width, height);
+
+        self.metadata
+            .insert("Bake target", format!("{} triangles", target.len()));
+        self.export_writer.write_header(width, height).unwrap();
+        let bar = Self::progress_bar(height);
+        let start = Instant::now();
+
+        let mut buf = Vec::with_capacity(texels.len());
+        for j in 0..height {
+            for i in 0..width {
+                let Some(surface) = &texels[(j * width + i) as usize] else {
+                    buf.push(Color::black());
+                    continue;
+                };
+
+                let invalid_before = self.invalid_sample_count();
+                let mut color = Color::black();
+                for _ in 0..samples {
+                    color += sample(self, surface);
+                }
+                color.set_brightness(1.0 / f64::from(samples));
+
+                if self.diagnostics == SampleDiagnostics::Highlight
+                    && self.invalid_sample_count() > invalid_before
+                {
+                    color = Color::debug_magenta();
+                }
+                buf.push(self.working_space.convert(color, self.output_space));
+            }
+            bar.inc(1);
+            self.export_writer.preview(&buf).unwrap();
+            self.progress.call(j + 1, height);
+        }
+        bar.finish();
+
+        let covered = texels.iter().map(Option::is_some).collect();
+        dilate(&mut buf, covered, width, height);
+        self.finish(&buf, start);
+    }
+
+    /// A single-sample estimate of the irradiance at `surface`, divided by π.
+    fn irradiance_sample(
+        &self,
+        surface: &SurfacePoint,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) -> Color {
+        // sample towards the lights and over the cosine-weighted hemisphere with equal probability
+        let light_pdf = HittablePDF::new(Rc::clone(&lights), &surface.point);
+        let cosine_pdf = CosinePDF::new(&surface.normal);
+        let mixed_pdf = MixedPDF::equal(vec![Rc::new(light_pdf), Rc::new(cosine_pdf)]);
+
+        let direction = mixed_pdf.generate();
+        let cos_theta = direction.as_unit().dot(&surface.normal.as_unit());
+        let pdf_value = mixed_pdf.value(&direction);
+        if cos_theta <= 0.0 || pdf_value <= 0.0 {
+            return Color::black();
+        }
+
+        let ray = Ray4::new(surface.point, direction, crate::rng::random());
+        let mut radiance = self.ray_color(&ray, self.max_depth, world, lights);
+        radiance.set_brightness(cos_theta / PI / pdf_value);
+        radiance
+    }
+}
+
+/// Finds the point on `target` at the centre of each texel of a `width` by `height` texture.
+fn rasterize(
+    target: &[impl Borrow<Triangle>],
+    width: u32,
+    height: u32,
+) -> Vec<Option<SurfacePoint>> {
+    let mut texels = vec![None; (width * height) as usize];
+    let (fwidth, fheight) = (f64::from(width), f64::from(height));
+
+    for triangle in target {
+        let triangle = triangle.borrow();
+        let [p0, p1, p2] = triangle.vertices();
+        // texture space has v pointing up; image rows count down
+        let [t0, t1, t2] = triangle
+            .uvs()
+            .map(|uv| (uv.x() * fwidth, (1.0 - uv.y()) * fheight));
+
+        let area = (t1.0 - t0.0) * (t2.1 - t0.1) - (t2.0 - t0.0) * (t1.1 - t0.1);
+        if area.abs() < 1e-12 {
+            continue;
+        }
+
+        let min_x = t0.0.min(t1.0).min(t2.0).floor().max(0.0) as u32;
+        let min_y = t0.1.min(t1.1).min(t2.1).floor().max(0.0) as u32;
+        let max_x = (t0.0.max(t1.0).max(t2.0).ceil() as u32).min(width);
+        let max_y = (t0.1.max(t1.1).max(t2.1).ceil() as u32).min(height);
+
+        for j in min_y..max_y {
+            for i in min_x..max_x {
+                let (x, y) = (f64::from(i) + 0.5, f64::from(j) + 0.5);
+                // barycentric coordinates of the texel centre, relative to corners 1 and 2
+                let a = ((x - t0.0) * (t2.1 - t0.1) - (t2.0 - t0.0) * (y - t0.1)) / area;
+                let b = ((t1.0 - t0.0) * (y - t0.1) - (x - t0.0) * (t1.1 - t0.1)) / area;
+                if a < 0.0 || b < 0.0 || a + b > 1.0 {
+                    continue;
+                }
+
+                texels[(j * width + i) as usize] = Some(SurfacePoint {
+                    point: p0 + a * (p1 - p0) + b * (p2 - p0),
+                    normal: triangle.normal().into(),
+                });
+            }
+        }
+    }
+
+    texels
+}
+
+/// Extends the covered texels of `buf` outwards by [`PADDING`] texels, filling each uncovered
+/// texel with the average of its covered neighbours.
+fn dilate(buf: &mut [Color], mut covered: Vec<bool>, width: u32, height: u32) {
+    let (width, height) = (width as i64, height as i64);
+
+    for _ in 0..PADDING {
+        let mut filled = Vec::new();
+        for j in 0..height {
+            for i in 0..width {
+                if covered[(j * width + i) as usize] {
+                    continue;
+                }
+
+                let mut sum = Color::black();
+                let mut count = 0.0;
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (x, y) = (i + dx, j + dy);
+                    if (0..width).contains(&x)
+                        && (0..height).contains(&y)
+                        && covered[(y * width + x) as usize]
+                    {
+                        sum += buf[(y * width + x) as usize];
+                        count += 1.0;
+                    }
+                }
+
+                if count > 0.0 {
+                    sum.set_brightness(1.0 / count);
+                    filled.push(((j * width + i) as usize, sum));
+                }
+            }
+        }
+
+        for (index, color) in filled {
+            buf[index] = color;
+            covered[index] = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hittable::Mesh, material::Lambertian, Material, Point2};
+
+    #[test]
+    fn texels_map_onto_the_surface() {
+        let material = Lambertian::solid(Color::white()).into_mat();
+        // a unit square in the xz plane, with uvs matching its x and z coordinates
+        let triangles = [
+            Triangle::from_points(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Rc::clone(&material),
+            )
+            .with_uvs([
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 0.0),
+                Point2::new(1.0, 1.0),
+            ]),
+            Triangle::from_points(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(0.0, 0.0, 1.0),
+                material,
+            )
+            .with_uvs([
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 1.0),
+                Point2::new(0.0, 1.0),
+            ]),
+        ];
+
+        let texels = rasterize(&triangles, 4, 4);
+        assert!(texels.iter().all(Option::is_some));
+
+        // the top-left texel is at the far edge of v
+        let point = texels[0].unwrap().point;
+        assert!((point.x() - 0.125).abs() < 1e-9);
+        assert!((point.z() - 0.875).abs() < 1e-9);
+    }
+
+    #[test]
+    fn meshes_can_be_baked() {
+        let material = Lambertian::solid(Color::white()).into_mat();
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, 1.0),
+        ];
+        let uvs = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+        let mesh = Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], material).with_uvs(uvs);
+
+        let triangles: Vec<_> = mesh.triangles().collect();
+        let texels = rasterize(&triangles, 4, 4);
+        assert!(texels.iter().all(Option::is_some));
+        let point = texels[0].unwrap().point;
+        assert!((point.x() - 0.125).abs() < 1e-9);
+        assert!((point.z() - 0.875).abs() < 1e-9);
+    }
+
+    #[test]
+    fn padding_fills_neighbours() {
+        let mut buf = vec![Color::black(); 9];
+        buf[4] = Color::white();
+        let mut covered = vec![false; 9];
+        covered[4] = true;
+
+        dilate(&mut buf, covered, 3, 3);
+        assert!(buf.iter().all(|c| *c == Color::white()));
+    }
+}
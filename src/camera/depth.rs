@@ -0,0 +1,100 @@
+//! Depth (Z-buffer) output, for depth of field in compositing and for datasets.
+
+use super::Camera;
+use crate::{Color, Hittable};
+
+/// How the distance to each surface is stored in a depth image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthMapping {
+    /// The depth in scene units. Pixels where nothing is hit are `0.0`.
+    Metric,
+    /// Depths from `near` to `far` are mapped onto `0.0..=1.0`, and clamped outside of it.
+    /// Pixels where nothing is hit are `1.0`.
+    Normalized { near: f64, far: f64 },
+}
+
+impl DepthMapping {
+    fn map(self, depth: Option<f64>) -> f64 {
+        match (self, depth) {
+            (DepthMapping::Metric, Some(depth)) => depth,
+            (DepthMapping::Metric, None) => 0.0,
+            (DepthMapping::Normalized { near, far }, Some(depth)) => {
+                ((depth - near) / (far - near)).clamp(0.0, 1.0)
+            }
+            (DepthMapping::Normalized { .. }, None) => 1.0,
+        }
+    }
+}
+
+impl Camera<'_> {
+    /// Renders the depth of the first surface seen through each pixel as a greyscale image,
+    /// written with the camera's [`ImageWriter`](crate::export::ImageWriter). Use a linear
+    /// format such as [`DepthPngWriter`](crate::export::DepthPngWriter) or
+    /// [`PfmWriter`](crate::export::PfmWriter) to keep the values intact.
+    ///
+    /// Depth is measured along the camera's view direction, rather than along each ray, so
+    /// flat surfaces facing the camera have a constant depth. One ray is traced through the
+    /// centre of each pixel, ignoring antialiasing and defocus blur, so that the edges of
+    /// objects aren't blended into an in-between depth.
+    pub fn render_depth(&mut self, world: &impl Hittable, mapping: DepthMapping) {
+        if let DepthMapping::Normalized { near, far } = mapping {
+            assert!(far > near, "Invalid depth range (expected near < far)");
+        }
+
+        let forward = self.pxdelta_u.cross(&self.pxdelta_v).as_unit();
+        let center = self.camera_center;
+
+        self.metadata
+            .insert("Depth mapping", format!("{mapping:?}"));
+        let (buf, start) = self.trace_first_hits(world, |hit| {
+            let depth = hit.map(|hit| (hit.point() - center).dot(&forward));
+            Color::over_white(mapping.map(depth))
+        });
+        self.finish(&buf, start);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hittable::{HittableVec, Parallelogram},
+        material::Lambertian,
+        test_support::BufferWriter,
+        CameraBuilder, Material, Point3, Vec3,
+    };
+
+    #[test]
+    fn depth_is_planar() {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(4, 4)
+            .camera_center(Point3::origin())
+            .camera_target(Point3::new(0.0, 0.0, -1.0))
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        // a wall facing the camera, 2 units away
+        let mut world = HittableVec::new();
+        world.add(
+            Parallelogram::new(
+                Point3::new(-10.0, -10.0, -2.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::new(0.0, 20.0, 0.0),
+                Lambertian::solid(Color::white()).into_mat(),
+            )
+            .hittable(),
+        );
+
+        cam.render_depth(
+            &world,
+            DepthMapping::Normalized {
+                near: 1.0,
+                far: 3.0,
+            },
+        );
+        drop(cam);
+        assert!(buf.iter().all(|c| (c.r() - 0.5).abs() < 1e-9));
+    }
+}
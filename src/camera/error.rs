@@ -0,0 +1,105 @@
+//! The ways a [`CameraBuilder`](super::CameraBuilder) can be misconfigured.
+
+use std::fmt::Display;
+
+use miette::Diagnostic;
+
+/// A setting which a [`CameraBuilder`](super::CameraBuilder) rejected.
+#[derive(Debug, Clone, PartialEq, Diagnostic)]
+pub enum CameraBuildError {
+    /// A parameter of a builder method was outside of the values it may take.
+    #[diagnostic(
+        code(camera::invalid_setting),
+        help("see the documentation of `CameraBuilder::{method}`")
+    )]
+    InvalidSetting {
+        /// The builder method which was given the value
+        method: &'static str,
+        parameter: &'static str,
+        /// A description of the values the parameter may take
+        expected: &'static str,
+        found: String,
+    },
+    /// The world passed to [`CameraBuilder::frame_scene`](super::CameraBuilder::frame_scene)
+    /// has no bounds to fit in view.
+    #[diagnostic(
+        code(camera::unbounded_world),
+        help("worlds containing unbounded objects, such as floors, can't be framed; place the camera by hand instead")
+    )]
+    UnboundedWorld,
+    /// No writer was given, so there's nowhere to send the image.
+    #[diagnostic(
+        code(camera::missing_writer),
+        help("include the `.writer()` parameter to specify the export format")
+    )]
+    MissingWriter,
+}
+
+impl CameraBuildError {
+    pub(super) fn invalid(
+        method: &'static str,
+        parameter: &'static str,
+        expected: &'static str,
+        found: impl Display,
+    ) -> Self {
+        Self::InvalidSetting {
+            method,
+            parameter,
+            expected,
+            found: found.to_string(),
+        }
+    }
+}
+
+impl Display for CameraBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSetting {
+                method,
+                parameter,
+                expected,
+                found,
+            } => write!(
+                f,
+                "CameraBuilder::{method}: Invalid {parameter}: must be {expected}, found {found}"
+            ),
+            Self::UnboundedWorld => write!(
+                f,
+                "CameraBuilder::frame_scene: Invalid world: must be bounded and not empty"
+            ),
+            Self::MissingWriter => write!(f, "CameraBuilder::build: Missing export format"),
+        }
+    }
+}
+
+impl std::error::Error for CameraBuildError {}
+
+/// Every setting a [`CameraBuilder`](super::CameraBuilder) rejected, in the order they were
+/// set.
+#[derive(Debug, Clone, PartialEq, Diagnostic)]
+#[diagnostic(code(camera::build))]
+pub struct CameraBuildErrors {
+    #[related]
+    errors: Vec<CameraBuildError>,
+}
+
+impl CameraBuildErrors {
+    pub(super) fn new(errors: Vec<CameraBuildError>) -> Self {
+        Self { errors }
+    }
+
+    pub fn errors(&self) -> &[CameraBuildError] {
+        &self.errors
+    }
+}
+
+impl Display for CameraBuildErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.errors.len() {
+            1 => write!(f, "The camera has an invalid setting"),
+            n => write!(f, "The camera has {n} invalid settings"),
+        }
+    }
+}
+
+impl std::error::Error for CameraBuildErrors {}
@@ -0,0 +1,114 @@
+//! A physical description of the camera's lens, for setting up a camera the way a
+//! photographer would; see [`CameraBuilder::lens`](super::CameraBuilder::lens).
+
+use super::CameraBuildError;
+use crate::Angle;
+
+/// A thin lens, described by the focal length and f-stop printed on a real lens.
+///
+/// The lens sets the camera's field of view from the size of its sensor, and the size of the
+/// defocus disk from its aperture, so that depth of field matches a real camera's.
+/// The plane of focus is still set with
+/// [`CameraBuilder::focal_length`](super::CameraBuilder::focal_length), in scene units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinLens {
+    /// The focal length of the lens, in millimetres
+    focal_length_mm: f64,
+    /// The focal length divided by the diameter of the aperture
+    f_stop: f64,
+    /// The height of the sensor (or film), in millimetres
+    sensor_height_mm: f64,
+    /// The length of one scene unit, in metres
+    unit_m: f64,
+}
+
+impl ThinLens {
+    /// The height of a full-frame (35mm) sensor, in millimetres.
+    pub const FULL_FRAME: f64 = 24.0;
+
+    /// A lens with a focal length of `focal_length_mm` millimetres at an f-stop of `f_stop`,
+    /// on a [full-frame](ThinLens::FULL_FRAME) sensor, in a scene measured in metres.
+    pub fn new(focal_length_mm: f64, f_stop: f64) -> Self {
+        Self {
+            focal_length_mm,
+            f_stop,
+            sensor_height_mm: Self::FULL_FRAME,
+            unit_m: 1.0,
+        }
+    }
+
+    /// Sets the height of the sensor, in millimetres; smaller sensors give a narrower view.
+    pub fn with_sensor_height(mut self, height_mm: f64) -> Self {
+        self.sensor_height_mm = height_mm;
+        self
+    }
+
+    /// Sets the length of one scene unit, in metres; for example, 0.01 for a scene modelled
+    /// in centimetres.
+    pub fn with_scene_unit(mut self, unit_m: f64) -> Self {
+        self.unit_m = unit_m;
+        self
+    }
+
+    pub fn focal_length_mm(&self) -> f64 {
+        self.focal_length_mm
+    }
+
+    pub fn f_stop(&self) -> f64 {
+        self.f_stop
+    }
+
+    /// Describes the first invalid setting, if there is one.
+    pub(super) fn validate(&self) -> Option<CameraBuildError> {
+        [
+            ("focal_length_mm", self.focal_length_mm),
+            ("f_stop", self.f_stop),
+            ("sensor_height_mm", self.sensor_height_mm),
+            ("unit_m", self.unit_m),
+        ]
+        .into_iter()
+        .find(|(_, value)| !(*value > 0.0 && value.is_finite()))
+        .map(|(name, value)| CameraBuildError::invalid("lens", name, "greater than 0.0", value))
+    }
+
+    /// The vertical field of view.
+    pub fn vfov(&self) -> Angle {
+        Angle::radians(2.0 * f64::atan(self.sensor_height_mm / (2.0 * self.focal_length_mm)))
+    }
+
+    /// The radius of the aperture, in scene units.
+    pub fn aperture_radius(&self) -> f64 {
+        let diameter_mm = self.focal_length_mm / self.f_stop;
+        diameter_mm / 2.0 / 1000.0 / self.unit_m
+    }
+
+    /// The defocus angle (see [`CameraBuilder::defocus_angle`](super::CameraBuilder::defocus_angle))
+    /// which gives the aperture's depth of field when focused `focus_distance` scene units
+    /// away.
+    pub fn defocus_angle(&self, focus_distance: f64) -> Angle {
+        Angle::radians(2.0 * f64::atan(self.aperture_radius() / focus_distance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn full_frame_50mm() {
+        let lens = ThinLens::new(50.0, 2.0);
+        assert!((lens.vfov().as_degrees() - 26.99).abs() < 0.01);
+        // a 25mm aperture
+        assert!((lens.aperture_radius() - 0.0125).abs() < 1e-12);
+        assert!(
+            (lens.defocus_angle(2.0).as_radians() - 2.0 * (0.0125f64 / 2.0).atan()).abs() < 1e-12
+        );
+
+        // the same lens in a scene modelled in centimetres
+        let lens = lens.with_scene_unit(0.01);
+        assert!((lens.aperture_radius() - 1.25).abs() < 1e-9);
+
+        assert!(ThinLens::new(50.0, 0.0).validate().is_some());
+        assert!(lens.validate().is_none());
+    }
+}
@@ -0,0 +1,92 @@
+//! Rendering the light from each light group into an image of its own, in the same pass as
+//! the full image; see [`CameraBuilder::light_group_output`].
+//!
+//! [`CameraBuilder::light_group_output`]: super::CameraBuilder::light_group_output
+
+use super::Camera;
+use crate::Color;
+
+/// The light carried back along a path: the light counted towards the main image, and the
+/// light from each group with an output of its own.
+#[derive(Debug, Clone)]
+pub(super) struct PathRadiance {
+    pub(super) total: Color,
+    /// The light from each group with an output, in the order the outputs were added
+    pub(super) groups: Vec<Color>,
+}
+
+impl PathRadiance {
+    /// Scales every part of the light by `value`, then by `scale`, as when it is scattered
+    /// by a surface.
+    pub(super) fn scatter(&mut self, value: &Color, scale: f64) {
+        for color in std::iter::once(&mut self.total).chain(&mut self.groups) {
+            *color = Color::mul(value, color);
+            color.set_brightness(scale);
+        }
+    }
+
+    pub(super) fn add(&mut self, other: &Self) {
+        self.total += other.total;
+        for (color, other) in self.groups.iter_mut().zip(&other.groups) {
+            *color += *other;
+        }
+    }
+
+    pub(super) fn is_physical(&self) -> bool {
+        self.total.is_physical() && self.groups.iter().all(Color::is_physical)
+    }
+}
+
+impl Camera<'_> {
+    /// No light at all.
+    pub(super) fn no_light(&self) -> PathRadiance {
+        PathRadiance {
+            total: Color::black(),
+            groups: vec![Color::black(); self.light_group_outputs.len()],
+        }
+    }
+
+    /// The light `emitted` by an object in `group`, which is only evaluated if the light is
+    /// counted towards the main image (see [`CameraBuilder::light_group`]) or `group` has an
+    /// output.
+    ///
+    /// [`CameraBuilder::light_group`]: super::CameraBuilder::light_group
+    pub(super) fn emission(&self, group: &str, emitted: impl FnOnce() -> Color) -> PathRadiance {
+        let mut radiance = self.no_light();
+        let counted = self.counts_light_from(group);
+        let output = self
+            .light_group_outputs
+            .iter()
+            .position(|(name, _)| name == group);
+        if !counted && output.is_none() {
+            return radiance;
+        }
+
+        let color = emitted();
+        if counted {
+            radiance.total = color;
+        }
+        if let Some(index) = output {
+            radiance.groups[index] = color;
+        }
+        radiance
+    }
+
+    /// Exposes and writes the image of each group with an output, given the mean light of
+    /// each pixel in `bufs`, in the order the outputs were added.
+    pub(super) fn write_light_groups(&mut self, bufs: Vec<Vec<Color>>) {
+        let (width, height) = (self.image_width, self.image_height);
+        for ((group, writer), mut buf) in self.light_group_outputs.iter_mut().zip(bufs) {
+            for color in &mut buf {
+                color.set_brightness(self.exposure_scale);
+                *color = self.working_space.convert(*color, self.output_space);
+            }
+
+            let mut metadata = self.metadata.clone();
+            metadata.insert("Light group", group.as_str());
+            writer.write_header(width, height).unwrap();
+            writer.write(&buf).unwrap();
+            writer.write_metadata(&metadata).unwrap();
+        }
+    }
+}
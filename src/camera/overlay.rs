@@ -0,0 +1,173 @@
+//! Debug overlays drawn over the rendered image, to help with orientation while authoring
+//! scenes: axes at the origin, a ground grid, and the views of other cameras.
+
+use super::Camera;
+use crate::{Color, Point2, Point3};
+
+/// How close to the camera lines are clipped, so that lines passing behind the camera
+/// aren't projected through it.
+const NEAR: f64 = 1e-3;
+
+/// Lines in world space, drawn over the image after rendering with
+/// [`CameraBuilder::overlay`](super::CameraBuilder::overlay). Lines are always drawn on top
+/// of the scene, even if they're hidden behind objects.
+#[derive(Debug, Clone, Default)]
+pub struct Overlay {
+    lines: Vec<(Point3, Point3, Color)>,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws a line from `from` to `to`.
+    pub fn line(mut self, from: Point3, to: Point3, color: Color) -> Self {
+        self.lines.push((from, to, color));
+        self
+    }
+
+    /// Draws the positive x, y and z axes from the origin, `length` units long, in red, green
+    /// and blue respectively.
+    pub fn axes(self, length: f64) -> Self {
+        let origin = Point3::origin();
+        self.line(origin, Point3::new(length, 0.0, 0.0), Color::red())
+            .line(origin, Point3::new(0.0, length, 0.0), Color::green())
+            .line(origin, Point3::new(0.0, 0.0, length), Color::blue())
+    }
+
+    /// Draws a grey grid on the ground (the plane `y = 0`), with lines `spacing` apart out to
+    /// `extent` from the origin along x and z.
+    pub fn grid(mut self, spacing: f64, extent: f64) -> Self {
+        assert!(spacing > 0.0, "Invalid grid spacing (expected spacing > 0)");
+        let color = Color::over_white(0.5);
+        let count = (extent / spacing).floor() as i32;
+        for i in -count..=count {
+            let offset = f64::from(i) * spacing;
+            self = self
+                .line(
+                    Point3::new(offset, 0.0, -extent),
+                    Point3::new(offset, 0.0, extent),
+                    color,
+                )
+                .line(
+                    Point3::new(-extent, 0.0, offset),
+                    Point3::new(extent, 0.0, offset),
+                    color,
+                );
+        }
+        self
+    }
+
+    /// Marks the position and view of another camera, such as one of a
+    /// [stereo pair](super::CameraBuilder::stereo): lines from its centre to the corners of its
+    /// viewport, and the outline of the viewport.
+    pub fn camera(mut self, camera: &Camera<'_>) -> Self {
+        let color = Color::new(1.0, 1.0, 0.0);
+        let (width, height) = (camera.image_width, camera.image_height);
+        let corner = camera.pixel_00 - (camera.pxdelta_u + camera.pxdelta_v) / 2.0;
+        let corners = [
+            corner,
+            corner + camera.pxdelta_u * width,
+            corner + camera.pxdelta_u * width + camera.pxdelta_v * height,
+            corner + camera.pxdelta_v * height,
+        ];
+        for (i, &c) in corners.iter().enumerate() {
+            self = self
+                .line(camera.camera_center, c, color)
+                .line(c, corners[(i + 1) % 4], color);
+        }
+        self
+    }
+
+    /// Draws the lines over `buf`, an image rendered by `camera`.
+    pub(super) fn draw(&self, camera: &Camera<'_>, buf: &mut [Color]) {
+        let forward = camera.pxdelta_u.cross(&camera.pxdelta_v).as_unit();
+        let depth = |p: &Point3| (*p - camera.camera_center).dot(&forward);
+
+        for (from, to, color) in &self.lines {
+            let (mut from, mut to) = (*from, *to);
+            let (d0, d1) = (depth(&from), depth(&to));
+            if d0 < NEAR && d1 < NEAR {
+                continue;
+            }
+            // clip the part of the line behind the camera
+            if d0 < NEAR {
+                from = from + (to - from) * ((NEAR - d0) / (d1 - d0));
+            } else if d1 < NEAR {
+                to = to + (from - to) * ((NEAR - d1) / (d0 - d1));
+            }
+
+            let (Some(a), Some(b)) = (camera.project(&from), camera.project(&to)) else {
+                continue;
+            };
+            draw_line(a, b, *color, camera.image_width, camera.image_height, buf);
+        }
+    }
+}
+
+/// Draws a line between the points `a` and `b` of a `width` by `height` image.
+fn draw_line(a: Point2, b: Point2, color: Color, width: u32, height: u32, buf: &mut [Color]) {
+    let (w, h) = (f64::from(width), f64::from(height));
+    // clip the line to the image (Liang-Barsky), so far-off points don't take forever to draw
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    for (p, q) in [(-dx, a.x()), (dx, w - a.x()), (-dy, a.y()), (dy, h - a.y())] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return;
+    }
+
+    let (x0, y0) = (a.x() + t0 * dx, a.y() + t0 * dy);
+    let length = (t1 - t0) * dx.abs().max(dy.abs());
+    let steps = length.ceil().max(1.0) as u32;
+    for step in 0..=steps {
+        let t = f64::from(step) / f64::from(steps) * (t1 - t0);
+        let (x, y) = ((x0 + t * dx).floor(), (y0 + t * dy).floor());
+        if (0.0..w).contains(&x) && (0.0..h).contains(&y) {
+            buf[(y as u32 * width + x as u32) as usize] = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        camera::{AntialiasingType, Background},
+        hittable::HittableVec,
+        test_support::BufferWriter,
+        CameraBuilder, Hittable,
+    };
+
+    #[test]
+    fn axes() {
+        let mut buf = Vec::new();
+        let mut cam = CameraBuilder::new()
+            .dimensions(21, 21)
+            .antialias(AntialiasingType::Square, 1)
+            .background(Background::Constant(Color::black()))
+            .camera_center(Point3::new(0.0, 0.0, 5.0))
+            .camera_target(Point3::origin())
+            .overlay(Overlay::new().axes(1.0))
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        cam.render(&HittableVec::new(), HittableVec::new().hittable());
+        drop(cam);
+        // the x axis runs right from the centre, and the y axis up
+        assert_eq!(buf[10 * 21 + 12], Color::red());
+        assert_eq!(buf[8 * 21 + 10], Color::green());
+        assert_eq!(buf[0], Color::black());
+    }
+}
@@ -0,0 +1,322 @@
+//! Light probes: the light arriving from every direction at a point in the scene, for use by
+//! real-time renderers which can't trace rays themselves.
+
+use std::{error::Error, f64::consts::PI, io::Write, rc::Rc};
+
+use super::Camera;
+use crate::{
+    export::{ExrWriter, ImageWriter},
+    Color, Hittable, Point3, Ray4, Vec3,
+};
+
+/// The light arriving at a point, projected onto the first nine (order 2) real spherical
+/// harmonics. This is enough to reproduce the diffuse lighting at the point almost exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    position: Point3,
+    /// Coefficients in the order `(l, m)` = `(0, 0)`, `(1, -1)`, `(1, 0)`, `(1, 1)`, `(2, -2)`,
+    /// `(2, -1)`, `(2, 0)`, `(2, 1)`, `(2, 2)`.
+    coefficients: [Color; 9],
+}
+
+impl Probe {
+    pub fn position(&self) -> Point3 {
+        self.position
+    }
+
+    /// The spherical harmonic coefficients of the incoming radiance, in world space.
+    pub fn coefficients(&self) -> &[Color; 9] {
+        &self.coefficients
+    }
+
+    /// The irradiance on a surface at the probe facing `normal`, divided by π, as in a texel of
+    /// [`Camera::bake_lightmap`]. Multiplying it by a surface's albedo gives the light the
+    /// surface reflects.
+    pub fn irradiance(&self, normal: &Vec3) -> Color {
+        // convolution of the radiance with the clamped cosine lobe, per band
+        const BANDS: [f64; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+
+        let basis = sh_basis(normal);
+        let mut color = Color::black();
+        for (i, coefficient) in self.coefficients.iter().enumerate() {
+            let band = match i {
+                0 => 0,
+                1..=3 => 1,
+                _ => 2,
+            };
+            let mut term = *coefficient;
+            term.set_brightness(BANDS[band] * basis[i] / PI);
+            color += term;
+        }
+        color
+    }
+
+    /// Writes `probes` as a JSON array, with each probe in the form
+    /// `{"position": [x, y, z], "sh": [[r, g, b], ...]}`.
+    pub fn write_json(probes: &[Probe], out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "[")?;
+        for (i, probe) in probes.iter().enumerate() {
+            let p = probe.position;
+            let sh = probe
+                .coefficients
+                .iter()
+                .map(|c| format!("[{}, {}, {}]", c.r(), c.g(), c.b()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let separator = if i + 1 < probes.len() { "," } else { "" };
+            writeln!(
+                out,
+                r#"  {{"position": [{}, {}, {}], "sh": [{}]}}{}"#,
+                p.x(),
+                p.y(),
+                p.z(),
+                sh,
+                separator
+            )?;
+        }
+        writeln!(out, "]")
+    }
+
+    /// Writes `probes` as an [OpenEXR](crate::export::ExrWriter) image with one row per probe:
+    /// the `sh` part holds the nine coefficients of each probe as its `R`, `G` and `B` channels,
+    /// and the `position` part repeats its position across the row as `X`, `Y` and `Z`.
+    pub fn write_exr(probes: &[Probe], out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let height = u32::try_from(probes.len())?;
+        let sh: Vec<Color> = probes.iter().flat_map(|probe| probe.coefficients).collect();
+        let positions: Vec<Color> = probes
+            .iter()
+            .flat_map(|probe| {
+                let p = probe.position;
+                [Color::new(p.x(), p.y(), p.z()); 9]
+            })
+            .collect();
+
+        let mut exr = ExrWriter::new();
+        for (name, channels, pixels) in [
+            ("sh", ["R", "G", "B"], sh),
+            ("position", ["X", "Y", "Z"], positions),
+        ] {
+            let mut layer = exr.layer(name, &channels);
+            layer.write_header(9, height)?;
+            layer.write(&pixels)?;
+        }
+        exr.finish(out)
+    }
+}
+
+/// A face of a [`CubeMap`], named by the axis it faces along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// A short name for the face, such as `+X` or `-Z`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "+X",
+            CubeFace::NegativeX => "-X",
+            CubeFace::PositiveY => "+Y",
+            CubeFace::NegativeY => "-Y",
+            CubeFace::PositiveZ => "+Z",
+            CubeFace::NegativeZ => "-Z",
+        }
+    }
+
+    /// Every face, in the order used by OpenGL and most engines.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// The direction through a point on the face, where `s` runs left to right and `t` top to
+    /// bottom, both from `-1.0` to `1.0`. Follows the OpenGL cube map layout.
+    fn direction(self, s: f64, t: f64) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3::new(1.0, -t, -s),
+            CubeFace::NegativeX => Vec3::new(-1.0, -t, s),
+            CubeFace::PositiveY => Vec3::new(s, 1.0, t),
+            CubeFace::NegativeY => Vec3::new(s, -1.0, -t),
+            CubeFace::PositiveZ => Vec3::new(s, -t, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-s, -t, -1.0),
+        }
+    }
+}
+
+/// The radiance arriving at a point from every direction, as the six faces of a cube.
+#[derive(Debug, Clone)]
+pub struct CubeMap {
+    size: u32,
+    /// Faces in the order of [`CubeFace::ALL`]
+    faces: [Vec<Color>; 6],
+}
+
+impl CubeMap {
+    /// The width and height of each face, in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn face(&self, face: CubeFace) -> &[Color] {
+        &self.faces[face as usize]
+    }
+
+    /// Writes one face of the cube map as an image.
+    pub fn write_face(
+        &self,
+        face: CubeFace,
+        writer: &mut dyn ImageWriter,
+    ) -> Result<(), Box<dyn Error>> {
+        writer.write_header(self.size, self.size)?;
+        writer.write(self.face(face))
+    }
+
+    /// Writes every face of the cube map as a part of a single
+    /// [OpenEXR](crate::export::ExrWriter) image, named by [`CubeFace::name`], keeping the full
+    /// range of the radiance.
+    pub fn write_exr(&self, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let mut exr = ExrWriter::new();
+        for face in CubeFace::ALL {
+            self.write_face(face, exr.layer(face.name(), &["R", "G", "B"]).as_mut())?;
+        }
+        exr.finish(out)
+    }
+}
+
+impl Camera<'_> {
+    /// Measures the light arriving at each of `positions`, tracing `samples` rays from each.
+    /// The camera's position and lens are not used.
+    pub fn irradiance_probes(
+        &self,
+        positions: &[Point3],
+        samples: u32,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) -> Vec<Probe> {
+        assert!(samples > 0, "Probes require at least one sample");
+
+        positions
+            .iter()
+            .map(|&position| {
+                let mut coefficients = [Color::black(); 9];
+                for _ in 0..samples {
+                    let direction: Vec3 = Vec3::random_unit_vector().into();
+                    let ray = Ray4::new(position, direction, crate::rng::random());
+                    let radiance = self.ray_color(&ray, self.max_depth, world, Rc::clone(&lights));
+                    for (coefficient, basis) in coefficients.iter_mut().zip(sh_basis(&direction)) {
+                        let mut term = radiance;
+                        term.set_brightness(basis);
+                        *coefficient += term;
+                    }
+                }
+
+                // Monte Carlo estimate over the sphere, with a uniform pdf of 1 / 4π
+                for coefficient in &mut coefficients {
+                    coefficient.set_brightness(4.0 * PI / f64::from(samples));
+                }
+                Probe {
+                    position,
+                    coefficients,
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the radiance arriving at `position` into a cube map with faces of `size` by
+    /// `size` pixels, using the camera's samples per pixel.
+    pub fn cube_map(
+        &self,
+        position: Point3,
+        size: u32,
+        world: &impl Hittable,
+        lights: Rc<dyn Hittable>,
+    ) -> CubeMap {
+        let samples = self.sqrt_spp * self.sqrt_spp;
+        let scale = 2.0 / f64::from(size);
+
+        let faces = CubeFace::ALL.map(|face| {
+            let mut buf = Vec::with_capacity((size * size) as usize);
+            for j in 0..size {
+                for i in 0..size {
+                    let mut color = Color::black();
+                    for _ in 0..samples {
+                        let s = (f64::from(i) + crate::rng::random::<f64>()) * scale - 1.0;
+                        let t = (f64::from(j) + crate::rng::random::<f64>()) * scale - 1.0;
+                        let ray = Ray4::new(position, face.direction(s, t), crate::rng::random());
+                        color += self.ray_color(&ray, self.max_depth, world, Rc::clone(&lights));
+                    }
+                    color.set_brightness(1.0 / f64::from(samples));
+                    buf.push(color);
+                }
+            }
+            buf
+        });
+
+        CubeMap { size, faces }
+    }
+}
+
+/// The first nine real spherical harmonics, evaluated in `direction`.
+fn sh_basis(direction: &Vec3) -> [f64; 9] {
+    let d = direction.as_unit();
+    let (x, y, z) = (d.x(), d.y(), d.z());
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{export::PpmWriter, hittable::HittableVec, Background, CameraBuilder};
+
+    #[test]
+    fn uniform_environment() {
+        let mut sink = Vec::new();
+        let camera = CameraBuilder::new()
+            .dimensions(2, 2)
+            .background(Background::Constant(Color::white()))
+            .writer(Box::new(PpmWriter::new(&mut sink)))
+            .build()
+            .unwrap();
+        let world = HittableVec::new();
+        let lights: Rc<dyn Hittable> = Rc::new(HittableVec::new());
+
+        let probes = camera.irradiance_probes(&[Point3::origin()], 4096, &world, lights);
+        for normal in [Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.5)] {
+            let irradiance = probes[0].irradiance(&normal);
+            assert!((irradiance.g() - 1.0).abs() < 0.1, "{irradiance:?}");
+        }
+
+        let mut json = Vec::new();
+        Probe::write_json(&probes, &mut json).unwrap();
+        assert!(String::from_utf8(json)
+            .unwrap()
+            .contains(r#""position": [0, 0, 0]"#));
+
+        let mut exr = Vec::new();
+        Probe::write_exr(&probes, &mut exr).unwrap();
+        assert_eq!(exr[..4], [0x76, 0x2f, 0x31, 0x01]);
+        let cube_map = camera.cube_map(Point3::origin(), 2, &world, Rc::new(HittableVec::new()));
+        let mut exr = Vec::new();
+        cube_map.write_exr(&mut exr).unwrap();
+        assert!(exr.windows(2).any(|name| name == b"-Z"));
+    }
+}
@@ -0,0 +1,142 @@
+//! The order the tiles of an image are rendered in; see [`CameraBuilder::tiles`].
+//!
+//! [`CameraBuilder::tiles`]: super::CameraBuilder::tiles
+
+use std::ops::Range;
+
+/// A rectangle of pixels which are rendered together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    /// The columns of the tile
+    pub x: Range<u32>,
+    /// The rows of the tile
+    pub y: Range<u32>,
+}
+
+/// The order the tiles of an image are rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Rows of tiles from the top of the image to the bottom, each from left to right.
+    #[default]
+    Scanline,
+    /// A square spiral outwards from the centre of the image, so that a preview shows the
+    /// subject (which is usually near the centre) first.
+    Spiral,
+    /// Along a Hilbert curve, which keeps each tile next to the one before it, so that the
+    /// image fills in as a growing patch rather than a thin strip.
+    Hilbert,
+}
+
+impl TileOrder {
+    /// Splits a `width` by `height` image into square tiles `size` pixels across, in this
+    /// order. Tiles on the right and bottom edges are cut short by the edge of the image.
+    pub fn tiles(self, width: u32, height: u32, size: u32) -> Vec<Tile> {
+        assert!(size > 0, "Invalid tile size (expected > 0)");
+        let (columns, rows) = (width.div_ceil(size), height.div_ceil(size));
+        let tile = |(tx, ty): (u32, u32)| Tile {
+            x: tx * size..((tx + 1) * size).min(width),
+            y: ty * size..((ty + 1) * size).min(height),
+        };
+
+        let count = (columns * rows) as usize;
+        let mut order = Vec::with_capacity(count);
+        match self {
+            Self::Scanline => {
+                order.extend((0..rows).flat_map(|ty| (0..columns).map(move |tx| (tx, ty))));
+            }
+            Self::Spiral => {
+                // walk right, down, left, then up, one step further every second turn,
+                // keeping the tiles which fall inside of the image
+                let (mut x, mut y) = (
+                    i64::from(columns.saturating_sub(1) / 2),
+                    i64::from(rows.saturating_sub(1) / 2),
+                );
+                let inside = |x: i64, y: i64| {
+                    (0..i64::from(columns)).contains(&x) && (0..i64::from(rows)).contains(&y)
+                };
+                let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+                let mut turn = 0;
+                if count > 0 {
+                    order.push((x as u32, y as u32));
+                }
+                while order.len() < count {
+                    let (dx, dy) = directions[turn % 4];
+                    for _ in 0..turn / 2 + 1 {
+                        x += dx;
+                        y += dy;
+                        if inside(x, y) {
+                            order.push((x as u32, y as u32));
+                        }
+                    }
+                    turn += 1;
+                }
+            }
+            Self::Hilbert => {
+                let side = columns.max(rows).next_power_of_two();
+                order.extend(
+                    (0..side * side)
+                        .map(|d| hilbert_point(side, d))
+                        .filter(|&(tx, ty)| tx < columns && ty < rows),
+                );
+            }
+        }
+        order.into_iter().map(tile).collect()
+    }
+}
+
+/// The point at distance `d` along the Hilbert curve filling a `side` by `side` square,
+/// where `side` is a power of two.
+fn hilbert_point(side: u32, d: u32) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        // rotate the quadrant so that the curve through it joins up with its neighbours
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn orders_cover_image() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut covered = vec![0; 70 * 45];
+            for tile in order.tiles(70, 45, 16) {
+                for y in tile.y.clone() {
+                    for x in tile.x.clone() {
+                        covered[(y * 70 + x) as usize] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&c| c == 1), "{order:?}");
+        }
+
+        // the spiral starts from the tile at the centre
+        let first = &TileOrder::Spiral.tiles(70, 45, 16)[0];
+        assert!(first.x.contains(&35) && first.y.contains(&22));
+
+        // each tile along the Hilbert curve is next to the one before
+        let tiles = TileOrder::Hilbert.tiles(64, 64, 8);
+        for pair in tiles.windows(2) {
+            let dx = pair[0].x.start.abs_diff(pair[1].x.start);
+            let dy = pair[0].y.start.abs_diff(pair[1].y.start);
+            assert_eq!(dx + dy, 8);
+        }
+    }
+}
@@ -1,18 +1,252 @@
 use crate::{
-    hittable::{HittableVec, Parallelogram, Sphere},
-    material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Metal},
-    texture::{Checkerboard, SolidColor},
-    Color, Hittable, Material, Point3, Texture, Vec3,
+    boundingbox::BVHNode,
+    camera::AntialiasingType,
+    hittable::{
+        BackFace, BilinearPatch, ConstantMedium, DepthOverride, Disc, Ellipsoid, FlipFace, Grid,
+        HittableVec, LightGroup, Named, Parallelogram, Ring, RoundedBox, Sided, Slab, Sphere,
+        SphereProjection, Triangle,
+    },
+    material::{
+        library, BlinnPhong, Dielectric, DiffuseLight, Isotropic, Lambertian, LightPower,
+        MaterialSlot, Metal, ThinDielectric,
+    },
+    texture::{
+        Checkerboard, CheckerboardSpace, ImageTexture, Invert, Mix, Multiply, Noise, Remap,
+        SolidColor,
+    },
+    Angle, Axis, Background, CameraBuilder, Color, Hittable, Interval, Material, Point3, Texture,
+    Vec3,
 };
 use miette::{bail, Result};
 use owo_colors::OwoColorize;
-use std::{collections::HashMap, path::PathBuf, rc::Rc, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
 
 #[derive(Debug)]
 pub struct ConfigModel {
+    // textures are already baked into `materials`; they are kept for debugging output.
+    #[allow(dead_code)]
     textures: TextureStorage,
     materials: MaterialStorage,
     objects: Vec<ObjectModel>,
+    camera: CameraModel,
+    background: Option<BackgroundModel>,
+}
+
+/// The optional `[background]` table; unset values keep the camera's settings.
+#[derive(Debug)]
+struct BackgroundModel {
+    background: Option<Background>,
+    strength: Option<f64>,
+    /// Rotation about the y axis
+    rotation: Option<Angle>,
+}
+
+impl BackgroundModel {
+    fn parse(table: &toml::Table, textures: &TextureStorage) -> Result<Self> {
+        let key = "config.background";
+        let background = match table.get("type") {
+            None => None,
+            Some(toml::Value::String(t)) => match t.to_uppercase().as_str() {
+                "SKY" => Some(Background::Sky),
+                "COLOR" | "CONSTANT" => {
+                    let color =
+                        require_value(table, "color", key)?.parse_color(&format!("{key}.color"))?;
+                    Some(Background::Constant(color))
+                }
+                "ENVIRONMENT" => {
+                    let id = require_value(table, "texture", key)?
+                        .parse_texture(&format!("{key}.texture"), textures)?;
+                    Some(Background::Environment(Rc::clone(
+                        textures.get(&id).unwrap(),
+                    )))
+                }
+                _ => bail!(
+                    help = "Backgrounds may be Sky, Color, or Environment.",
+                    "Unknown background type {}.",
+                    t.green()
+                ),
+            },
+            Some(_) => bail!("{} must be a string.", format!("{key}.type").green()),
+        };
+
+        let strength = table
+            .get("strength")
+            .map(|v| v.parse_floatlike(&format!("{key}.strength")))
+            .transpose()?;
+        if strength.is_some_and(|s| s < 0.0) {
+            bail!(
+                "{} must not be negative.",
+                format!("{key}.strength").green()
+            );
+        }
+        let rotation = table
+            .get("rotation")
+            .map(|v| v.parse_angle(&format!("{key}.rotation")))
+            .transpose()?;
+
+        Ok(Self {
+            background,
+            strength,
+            rotation,
+        })
+    }
+}
+
+/// The optional `[camera]` table; unset values keep the builder's settings.
+#[derive(Debug, Default)]
+struct CameraModel {
+    dimensions: Option<(u32, u32)>,
+    antialiasing: Option<AntialiasingType>,
+    samples: Option<u32>,
+    max_depth: Option<u32>,
+    center: Option<Point3>,
+    target: Option<Point3>,
+    vup: Option<Vec3>,
+    vfov: Option<Angle>,
+    focal_length: Option<f64>,
+    defocus_angle: Option<Angle>,
+    exposure: Option<f64>,
+    seed: Option<u64>,
+    title: Option<String>,
+}
+
+impl CameraModel {
+    fn parse(table: &toml::Table) -> Result<Self> {
+        let key = "config.camera";
+        let positive = |name: &str| -> Result<Option<u32>> {
+            let Some(value) = table.get(name) else {
+                return Ok(None);
+            };
+            match value.as_integer().and_then(|i| u32::try_from(i).ok()) {
+                Some(n) if n > 0 => Ok(Some(n)),
+                _ => bail!(
+                    "{} must be a positive integer.",
+                    format!("{key}.{name}").green()
+                ),
+            }
+        };
+
+        let dimensions = match (positive("width")?, positive("height")?) {
+            (Some(width), Some(height)) => Some((width, height)),
+            (None, None) => None,
+            _ => bail!(
+                "{} and {} must be set together.",
+                format!("{key}.width").green(),
+                format!("{key}.height").green()
+            ),
+        };
+        let antialiasing = match table.get("antialiasing") {
+            Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("square") => {
+                Some(AntialiasingType::Square)
+            }
+            Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("disc") => {
+                Some(AntialiasingType::Disc)
+            }
+            None => None,
+            Some(_) => bail!(
+                "{} must be {} or {}.",
+                format!("{key}.antialiasing").green(),
+                r#""square""#.purple(),
+                r#""disc""#.purple()
+            ),
+        };
+        let get = |name: &str| {
+            table
+                .get(name)
+                .map(|value| (format!("{key}.{name}"), value))
+        };
+
+        Ok(Self {
+            dimensions,
+            antialiasing,
+            samples: positive("samples")?,
+            max_depth: positive("max_depth")?,
+            center: get("center")
+                .map(|(key, v)| v.parse_point3(&key))
+                .transpose()?,
+            target: get("target")
+                .map(|(key, v)| v.parse_point3(&key))
+                .transpose()?,
+            vup: get("vup").map(|(key, v)| v.parse_vec3(&key)).transpose()?,
+            vfov: get("vfov")
+                .map(|(key, v)| v.parse_angle(&key))
+                .transpose()?,
+            focal_length: get("focal_length")
+                .map(|(key, v)| v.parse_length(&key))
+                .transpose()?,
+            defocus_angle: get("defocus_angle")
+                .map(|(key, v)| v.parse_angle(&key))
+                .transpose()?,
+            exposure: get("exposure")
+                .map(|(key, v)| v.parse_floatlike(&key))
+                .transpose()?,
+            seed: match table.get("seed") {
+                Some(value) => match value.as_integer().and_then(|i| u64::try_from(i).ok()) {
+                    Some(seed) => Some(seed),
+                    None => bail!(
+                        "{} must be a non-negative integer.",
+                        format!("{key}.seed").green()
+                    ),
+                },
+                None => None,
+            },
+            title: match table.get("title") {
+                Some(toml::Value::String(title)) => Some(title.clone()),
+                Some(_) => bail!("{} must be a string.", format!("{key}.title").green()),
+                None => None,
+            },
+        })
+    }
+
+    fn configure<'a>(&self, mut builder: CameraBuilder<'a>) -> CameraBuilder<'a> {
+        if let Some((width, height)) = self.dimensions {
+            builder = builder.dimensions(width, height);
+        }
+        if let Some(antialiasing) = self.antialiasing {
+            builder = builder.antialiasing(antialiasing);
+        }
+        if let Some(samples) = self.samples {
+            builder = builder.samples_per_px(samples);
+        }
+        if let Some(max_depth) = self.max_depth {
+            builder = builder.max_depth(max_depth);
+        }
+        if let Some(center) = self.center {
+            builder = builder.camera_center(center);
+        }
+        if let Some(target) = self.target {
+            builder = builder.camera_target(target);
+        }
+        if let Some(vup) = self.vup {
+            builder = builder.vup(vup);
+        }
+        if let Some(vfov) = self.vfov {
+            builder = builder.vfov(vfov);
+        }
+        if let Some(focal_length) = self.focal_length {
+            builder = builder.focal_length(focal_length);
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            builder = builder.defocus_angle(defocus_angle);
+        }
+        if let Some(exposure) = self.exposure {
+            builder = builder.exposure(exposure);
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        if let Some(title) = &self.title {
+            builder = builder.title(title);
+        }
+        builder
+    }
 }
 
 #[derive(Debug)]
@@ -21,12 +255,35 @@ enum TextureModel {
         color: Color,
     },
     Checkerboard {
-        scale: f64,
+        scale: Vec3,
+        space: CheckerboardSpace,
         color1: TextureStorageId,
         color2: TextureStorageId,
     },
     Image {
-        path: PathBuf,
+        texture: ImageTexture,
+    },
+    Mix {
+        a: TextureStorageId,
+        b: TextureStorageId,
+        factor: TextureStorageId,
+    },
+    Multiply {
+        a: TextureStorageId,
+        b: TextureStorageId,
+    },
+    Invert {
+        input: TextureStorageId,
+    },
+    Remap {
+        input: TextureStorageId,
+        range: (f64, f64),
+        colors: (Color, Color),
+    },
+    Noise {
+        scale: f64,
+        octaves: u32,
+        seed: u64,
     },
 }
 
@@ -35,8 +292,23 @@ enum MaterialModel {
     Lambertian(TextureStorageId),
     DiffuseLight(TextureStorageId),
     Isotropic(TextureStorageId),
-    Metal { albedo: Color, fuzz: f64 },
-    Dielectric { refractive_index: f64 },
+    Metal {
+        albedo: Color,
+        fuzz: f64,
+    },
+    BlinnPhong {
+        diffuse: TextureStorageId,
+        specular: Color,
+        exponent: f64,
+    },
+    Dielectric {
+        refractive_index: f64,
+        roughness: Option<TextureStorageId>,
+        abbe_number: Option<f64>,
+    },
+    ThinDielectric {
+        refractive_index: f64,
+    },
 }
 
 #[derive(Debug)]
@@ -45,6 +317,15 @@ enum ObjectModel {
         center: Point3,
         radius: f64,
         material: MaterialStorageId,
+        projection: SphereProjection,
+        texture_rotation: Angle,
+        /// If set, the sphere is hollow, with a shell this thick
+        thickness: Option<f64>,
+    },
+    Ellipsoid {
+        center: Point3,
+        radii: Vec3,
+        material: MaterialStorageId,
     },
     Parallelogram {
         corner: Point3,
@@ -56,16 +337,147 @@ enum ObjectModel {
         points: [Point3; 3],
         material: MaterialStorageId,
     },
+    BilinearPatch {
+        // at (u, v) of (0, 0), (1, 0), (0, 1) and (1, 1)
+        corners: [Point3; 4],
+        material: MaterialStorageId,
+    },
     Disc {
         center: Point3,
         // radial vectors
         vectors: [Vec3; 2],
         material: MaterialStorageId,
     },
+    Ring {
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        material: MaterialStorageId,
+    },
+    RoundedBox {
+        // opposite corners
+        corners: [Point3; 2],
+        radius: f64,
+        material: MaterialStorageId,
+    },
+    Slab {
+        axis: Axis,
+        // the coordinates of the two planes along `axis`
+        extent: (f64, f64),
+        material: MaterialStorageId,
+    },
+    Grid {
+        object: Box<ObjectModel>,
+        grid: Grid,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectModel>,
+        density: f64,
+        albedo: Rc<dyn Texture>,
+        density_texture: Option<Rc<dyn Texture>>,
+    },
+    Named {
+        name: String,
+        object: Box<ObjectModel>,
+    },
+    FlipFace(Box<ObjectModel>),
+    Sided {
+        back_face: BackFace,
+        object: Box<ObjectModel>,
+    },
+    LightGroup {
+        group: String,
+        object: Box<ObjectModel>,
+    },
+    DepthOverride {
+        max_depth: u32,
+        object: Box<ObjectModel>,
+    },
 }
 
 #[derive(Debug)]
-struct TextureStorage(HashMap<TextureStorageId, Rc<dyn Texture>>, usize);
+struct TextureStorage(HashMap<TextureStorageId, Rc<dyn Texture>>, usize, Assets);
+
+/// Where the files a scene refers to, such as image textures, are read from.
+#[derive(Debug, Clone)]
+pub enum Assets {
+    /// Files are read from disk. Relative paths are looked up in `base`, then in each of the
+    /// `search_paths` in order (which are themselves relative to `base`).
+    FileSystem {
+        base: PathBuf,
+        search_paths: Vec<PathBuf>,
+    },
+    /// Files are looked up among those bundled with the scene, by the paths written in the
+    /// scene; see [`SceneArchive`](crate::archive::SceneArchive).
+    Bundled(Rc<BTreeMap<PathBuf, Vec<u8>>>),
+}
+
+impl Default for Assets {
+    /// Reads files relative to the working directory.
+    fn default() -> Self {
+        Self::FileSystem {
+            base: PathBuf::new(),
+            search_paths: Vec::new(),
+        }
+    }
+}
+
+impl Assets {
+    /// Reads files from disk, relative to the directory containing the scene at `scene_path`.
+    pub fn relative_to(scene_path: impl AsRef<Path>) -> Self {
+        Self::FileSystem {
+            base: scene_path.as_ref().parent().unwrap_or(Path::new("")).into(),
+            search_paths: Vec::new(),
+        }
+    }
+
+    /// Adds a directory to look for files in, if they aren't found relative to the scene.
+    /// Has no effect on bundled files.
+    pub fn with_search_path(mut self, path: impl Into<PathBuf>) -> Self {
+        if let Self::FileSystem { search_paths, .. } = &mut self {
+            search_paths.push(path.into());
+        }
+        self
+    }
+
+    /// Adds the search paths listed in the scene's `asset_paths` key, if any.
+    pub(crate) fn with_scene_search_paths(mut self, table: &toml::Table) -> Result<Self> {
+        let Some(paths) = table.get("asset_paths") else {
+            return Ok(self);
+        };
+        for (i, path) in paths.parse_array("config.asset_paths")?.iter().enumerate() {
+            self = self.with_search_path(path.parse_pathbuf(&format!("config.asset_paths.{i}"))?);
+        }
+        Ok(self)
+    }
+
+    /// Finds the file the scene refers to as `path`, returning the path it can be opened at.
+    pub(crate) fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            Self::FileSystem { base, search_paths } => {
+                let exists = |path: &Path| path.try_exists().is_ok_and(|e| e);
+                if path.is_absolute() {
+                    return exists(path).then(|| path.to_path_buf());
+                }
+                std::iter::once(base.join(path))
+                    .chain(search_paths.iter().map(|dir| base.join(dir).join(path)))
+                    .find(|candidate| exists(candidate))
+            }
+            Self::Bundled(files) => files.contains_key(path).then(|| path.to_path_buf()),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            Self::FileSystem { .. } => Ok(Box::new(File::open(path)?)),
+            Self::Bundled(files) => match files.get(path) {
+                Some(data) => Ok(Box::new(Cursor::new(data.as_slice()))),
+                None => Err(io::ErrorKind::NotFound.into()),
+            },
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum TextureStorageId {
@@ -75,16 +487,66 @@ enum TextureStorageId {
 
 type MaterialStorage = HashMap<String, Rc<dyn Material>>;
 
+/// Materials named with this prefix are taken from the built-in [`library`].
+const LIBRARY_PREFIX: &str = "@library/";
+/// Colors named with this prefix are taken from the scene's `[palette]` table.
+const PALETTE_PREFIX: &str = "$palette.";
+
+/// Replaces every `"$palette.name"` in the scene with the color `name` is given in its
+/// `[palette]` table, so that colors used throughout the scene can be changed in one place.
+fn apply_palette(table: &toml::Table) -> Result<toml::Table> {
+    fn substitute(value: &mut toml::Value, key: &str, palette: &toml::Table) -> Result<()> {
+        match value {
+            toml::Value::String(s) => {
+                if let Some(name) = s.strip_prefix(PALETTE_PREFIX) {
+                    let Some(color) = palette.get(name) else {
+                        bail!(
+                            help = "Colors must be defined in the [palette] table to be used.",
+                            "{} refers to an unknown palette color {}.",
+                            key.green(),
+                            name.purple()
+                        );
+                    };
+                    *value = color.clone();
+                }
+            }
+            toml::Value::Array(values) => {
+                for (i, v) in values.iter_mut().enumerate() {
+                    substitute(v, &format!("{key}.{i}"), palette)?;
+                }
+            }
+            toml::Value::Table(table) => {
+                for (k, v) in table.iter_mut() {
+                    substitute(v, &format!("{key}.{k}"), palette)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    let mut table = table.clone();
+    let palette = match table.remove("palette") {
+        Some(toml::Value::Table(palette)) => palette,
+        Some(_) => bail!("{} must be a table.", "config.palette".green()),
+        None => toml::Table::new(),
+    };
+    // checked up front, so that mistakes are reported against the palette itself
+    for (name, color) in &palette {
+        let _ = color.parse_color(&format!("config.palette.{name}"))?;
+    }
+    for (key, value) in table.iter_mut() {
+        substitute(value, &format!("config.{key}"), &palette)?;
+    }
+    Ok(table)
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct MaterialStorageId(String);
 
 impl TextureStorage {
-    pub fn new() -> Self {
-        Self(HashMap::new(), 0)
-    }
-
-    pub fn with_capacity(capacity: usize) -> Self {
-        Self(HashMap::with_capacity(capacity), 0)
+    pub fn with_capacity(capacity: usize, assets: Assets) -> Self {
+        Self(HashMap::with_capacity(capacity), 0, assets)
     }
 
     pub fn gen_id(&mut self) -> usize {
@@ -94,14 +556,14 @@ impl TextureStorage {
 
     pub fn push_anon(&mut self, texture: TextureModel) -> TextureStorageId {
         let id = TextureStorageId::Anonymous(self.gen_id());
-        let tex = texture.as_texture(&self);
+        let tex = texture.into_texture(self);
         self.0.entry(id.clone()).insert_entry(tex);
         id
     }
 
     pub fn push_named(&mut self, key: String, texture: TextureModel) -> TextureStorageId {
         let id = TextureStorageId::Named(key);
-        let tex = texture.as_texture(&self);
+        let tex = texture.into_texture(self);
         self.0.entry(id.clone()).insert_entry(tex);
         id
     }
@@ -116,14 +578,75 @@ impl TextureStorage {
     }
 }
 
+/// Metric length units, and the number of metres in each.
+const LENGTH_UNITS: &[(&str, f64)] = &[("mm", 0.001), ("cm", 0.01), ("km", 1000.0), ("m", 1.0)];
+
+/// Angle units, and the number of degrees in each.
+const ANGLE_UNITS: &[(&str, f64)] = &[
+    ("deg", 1.0),
+    ("°", 1.0),
+    ("rad", 180.0 / std::f64::consts::PI),
+];
+
+/// Reads a plain number, or a string of a number followed by one of `units` (the first
+/// which matches, so longer suffixes must come first), scaled by that unit.
+fn parse_with_units(value: &toml::Value, units: &[(&str, f64)]) -> Option<f64> {
+    match value {
+        toml::Value::Float(f) => Some(*f),
+        toml::Value::Integer(i) => Some(*i as f64),
+        toml::Value::String(s) => units.iter().find_map(|(suffix, scale)| {
+            let number = s.trim().strip_suffix(suffix)?.trim_end();
+            number.parse::<f64>().ok().map(|n| n * scale)
+        }),
+        _ => None,
+    }
+}
+
+/// Parses an `[x, y, z]` array, parsing each component with `component`.
+fn parse_triple(
+    value: &toml::Value,
+    key: &str,
+    noun: &str,
+    component: impl Fn(&toml::Value, &str) -> Result<f64>,
+) -> Result<[f64; 3]> {
+    let toml::Value::Array(arr) = value else {
+        bail!(
+            "{} must be a valid 3D {}, represented as {}.",
+            key.green(),
+            noun,
+            "[x, y, z]".purple()
+        );
+    };
+
+    if arr.len() != 3 {
+        bail!(
+            "{} must be a valid {} {}, represented as {}.",
+            key.green(),
+            "3D".bold(),
+            noun,
+            "[x, y, z]".purple()
+        );
+    }
+
+    let mut res: [f64; 3] = [f64::NAN; 3];
+
+    for i in 0..3 {
+        res[i] = component(&arr[i], &format!("{}.{}", key, i))?;
+    }
+    Ok(res)
+}
+
 trait ValueExt {
     fn parse_color(&self, key: &str) -> Result<Color>;
     fn parse_floatlike(&self, key: &str) -> Result<f64>;
+    fn parse_length(&self, key: &str) -> Result<f64>;
+    fn parse_angle(&self, key: &str) -> Result<Angle>;
     fn parse_pathbuf(&self, key: &str) -> Result<PathBuf>;
     fn parse_point3(&self, key: &str) -> Result<Point3>;
     fn parse_vec3(&self, key: &str) -> Result<Vec3>;
+    fn parse_lengths(&self, key: &str) -> Result<Vec3>;
 
-    fn parse_array<'a, 'b>(&'a self, key: &'b str) -> Result<&'a Vec<toml::Value>>;
+    fn parse_array<'a>(&'a self, key: &str) -> Result<&'a Vec<toml::Value>>;
     fn parse_texture(&self, key: &str, storage: &TextureStorage) -> Result<TextureStorageId>;
     fn parse_material(&self, key: &str, storage: &MaterialStorage) -> Result<MaterialStorageId>;
 }
@@ -155,6 +678,32 @@ impl ValueExt for toml::Value {
         }
     }
 
+    /// A length in scene units, or with a metric unit (`"2.5cm"`), taking a scene unit to be
+    /// one metre.
+    fn parse_length(&self, key: &str) -> Result<f64> {
+        parse_with_units(self, LENGTH_UNITS).ok_or_else(|| {
+            miette::miette!(
+                "{} must be a length, such as {} or {}.",
+                key.green(),
+                "2.5".purple(),
+                r#""2.5cm""#.purple()
+            )
+        })
+    }
+
+    /// An angle in **degrees**, or with a unit (`"15deg"` or `"0.25rad"`).
+    fn parse_angle(&self, key: &str) -> Result<Angle> {
+        let degrees = parse_with_units(self, ANGLE_UNITS).ok_or_else(|| {
+            miette::miette!(
+                "{} must be an angle, such as {} or {}.",
+                key.green(),
+                r#""15deg""#.purple(),
+                r#""0.25rad""#.purple()
+            )
+        })?;
+        Ok(Angle::degrees(degrees))
+    }
+
     fn parse_pathbuf(&self, key: &str) -> Result<PathBuf> {
         match self {
             toml::Value::String(s) => Ok(PathBuf::from(s)),
@@ -164,59 +713,25 @@ impl ValueExt for toml::Value {
         }
     }
 
+    /// A position, with each coordinate a length (see [`ValueExt::parse_length`]).
     fn parse_point3(&self, key: &str) -> Result<Point3> {
-        let toml::Value::Array(arr) = self else {
-            bail!(
-                "{} must be a valid 3D point, represented as {}.",
-                key.green(),
-                "[x, y, z]".purple()
-            );
-        };
-
-        if arr.len() != 3 {
-            bail!(
-                "{} must be a valid {} point, represented as {}.",
-                key.green(),
-                "3D".bold(),
-                "[x, y, z]".purple()
-            );
-        }
-
-        let mut res: [f64; 3] = [f64::NAN; 3];
-
-        for i in 0..3 {
-            res[i] = arr[i].parse_floatlike(&format!("{}.{}", key, i))?;
-        }
-        Ok(Point3::from(res))
+        parse_triple(self, key, "point", toml::Value::parse_length).map(Point3::from)
     }
 
+    /// A direction or scale, with each component a plain number.
     fn parse_vec3(&self, key: &str) -> Result<Vec3> {
-        let toml::Value::Array(arr) = self else {
-            bail!(
-                "{} must be a valid 3D vector, represented as {}.",
-                key.green(),
-                "[x, y, z]".purple()
-            );
-        };
-
-        if arr.len() != 3 {
-            bail!(
-                "{} must be a valid {} vector, represented as {}.",
-                key.green(),
-                "3D".bold(),
-                "[x, y, z]".purple()
-            );
-        }
-
-        let mut res: [f64; 3] = [f64::NAN; 3];
+        parse_triple(self, key, "vector", toml::Value::parse_floatlike)
+            .map(|res| Vec3::from(Point3::from(res)))
+    }
 
-        for i in 0..3 {
-            res[i] = arr[i].parse_floatlike(&format!("{}.{}", key, i))?;
-        }
-        Ok(Vec3::from(Point3::from(res)))
+    /// A size or offset, such as the radii of an ellipsoid, with each component a length
+    /// (see [`ValueExt::parse_length`]).
+    fn parse_lengths(&self, key: &str) -> Result<Vec3> {
+        parse_triple(self, key, "vector", toml::Value::parse_length)
+            .map(|res| Vec3::from(Point3::from(res)))
     }
 
-    fn parse_array<'a, 'b>(&'a self, key: &'b str) -> Result<&'a Vec<toml::Value>> {
+    fn parse_array<'a>(&'a self, key: &str) -> Result<&'a Vec<toml::Value>> {
         match self {
             toml::Value::Array(a) => Ok(a),
             _ => {
@@ -249,6 +764,19 @@ impl ValueExt for toml::Value {
     fn parse_material(&self, key: &str, storage: &MaterialStorage) -> Result<MaterialStorageId> {
         match self {
             toml::Value::String(a) => {
+                if let Some(name) = a.strip_prefix(LIBRARY_PREFIX) {
+                    if !storage.contains_key(a) {
+                        bail!(
+                            help = format!(
+                                "The material library contains: {}",
+                                library::NAMES.join(", ").purple()
+                            ),
+                            "{} refers to {}, which is not in the material library.",
+                            key.green(),
+                            name.purple()
+                        );
+                    }
+                }
                 if !storage.contains_key(a) {
                     bail!(
                         help = format!("No material named {} has been loaded.", a.purple()),
@@ -268,10 +796,10 @@ impl ValueExt for toml::Value {
     }
 }
 
-fn require_value<'a, 'b>(
+fn require_value<'a>(
     table: &'a toml::Table,
-    key: &'b str,
-    parent_key: &'b str,
+    key: &str,
+    parent_key: &str,
 ) -> Result<&'a toml::Value> {
     if let Some(value) = table.get(key) {
         Ok(value)
@@ -283,6 +811,131 @@ fn require_value<'a, 'b>(
     }
 }
 
+/// Parses an input to a texture node: the name of another texture, a color, a decimal number
+/// (a shade of grey), or an inline texture table.
+fn parse_texture_input(
+    table: &toml::Table,
+    input: &str,
+    name: &str,
+    storage: &mut TextureStorage,
+) -> Result<TextureStorageId> {
+    let value = require_value(table, input, &format!("config.textures.{name}"))?;
+    let key = format!("config.textures.{name}.{input}");
+    match value {
+        toml::Value::String(s) if storage.contains_named_key(s) => {
+            value.parse_texture(&key, storage)
+        }
+        toml::Value::Table(inline) => {
+            let texture = TextureModel::parse(&format!("{name}.{input}"), inline, storage)?;
+            Ok(storage.push_anon(texture))
+        }
+        toml::Value::Float(f) => Ok(storage.push_anon(TextureModel::SolidColor {
+            color: Color::over_white(*f),
+        })),
+        _ => match value.parse_color(&key) {
+            Ok(color) => Ok(storage.push_anon(TextureModel::SolidColor { color })),
+            Err(_) => bail!(
+                help =
+                    "Inputs may be the name of a texture, a color, a number, or a texture table.",
+                "{} does not describe a valid texture.",
+                key.green()
+            ),
+        },
+    }
+}
+
+/// Orders the textures so that each comes after any other named textures it refers to.
+fn texture_order(texture_table: &toml::Table) -> Result<Vec<&String>> {
+    fn references<'a>(value: &'a toml::Value, out: &mut Vec<&'a str>) {
+        match value {
+            toml::Value::String(s) => out.push(s),
+            toml::Value::Array(values) => values.iter().for_each(|v| references(v, out)),
+            toml::Value::Table(table) => table
+                .iter()
+                .filter(|(key, _)| *key != "type")
+                .for_each(|(_, v)| references(v, out)),
+            _ => {}
+        }
+    }
+
+    fn visit<'a>(
+        name: &'a String,
+        texture_table: &'a toml::Table,
+        visiting: &mut Vec<&'a String>,
+        order: &mut Vec<&'a String>,
+    ) -> Result<()> {
+        if order.contains(&name) {
+            return Ok(());
+        }
+        if visiting.contains(&name) {
+            bail!(
+                "{} refers to itself.",
+                format!("config.textures.{name}").green()
+            );
+        }
+        visiting.push(name);
+        let mut refs = Vec::new();
+        references(&texture_table[name], &mut refs);
+        for (dependency, _) in texture_table.iter().filter(|(k, _)| refs.contains(&&k[..])) {
+            visit(dependency, texture_table, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(name);
+        Ok(())
+    }
+
+    let mut order = Vec::with_capacity(texture_table.len());
+    for name in texture_table.keys() {
+        visit(name, texture_table, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+/// The table of the material `name`, with the fields of the material it `extends` (and so on)
+/// filled in wherever it doesn't set them itself.
+fn resolve_material(name: &str, material_table: &toml::Table) -> Result<toml::Table> {
+    let mut chain = vec![name];
+    let mut resolved = toml::Table::new();
+    let mut current = name;
+    loop {
+        let Some(toml::Value::Table(table)) = material_table.get(current) else {
+            bail!(
+                "{} must be a table.",
+                format!("config.materials.{current}").green()
+            );
+        };
+        for (key, value) in table {
+            if !resolved.contains_key(key) {
+                resolved.insert(key.clone(), value.clone());
+            }
+        }
+
+        let key = format!("config.materials.{current}.extends");
+        match table.get("extends") {
+            None => break,
+            Some(toml::Value::String(base)) if material_table.contains_key(base) => {
+                if chain.contains(&&base[..]) {
+                    bail!(
+                        "{} extends itself.",
+                        format!("config.materials.{name}").green()
+                    );
+                }
+                chain.push(base);
+                current = base;
+            }
+            Some(toml::Value::String(base)) => bail!(
+                help = "Materials may only extend other materials in the scene.",
+                "{} refers to an unknown material {}.",
+                key.green(),
+                base.purple()
+            ),
+            Some(_) => bail!("{} must be a string.", key.green()),
+        }
+    }
+    resolved.remove("extends");
+    Ok(resolved)
+}
+
 impl TextureModel {
     pub fn parse(
         name: &str,
@@ -303,8 +956,40 @@ impl TextureModel {
                 Ok(Self::SolidColor { color })
             }
             "CHECKERBOARD" | "CHECKER" => {
+                let space = match table.get("space") {
+                    Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("world") => {
+                        CheckerboardSpace::World
+                    }
+                    Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("uv") => {
+                        CheckerboardSpace::Uv
+                    }
+                    None => CheckerboardSpace::World,
+                    Some(_) => bail!(
+                        "{} must be {} or {}.",
+                        format!("config.textures.{name}.space").green(),
+                        r#""world""#.purple(),
+                        r#""uv""#.purple()
+                    ),
+                };
+
+                // a single size, or one per axis: [u, v] or [x, y, z]
                 let scale = require_value(table, "scale", &format!("config.textures.{name}"))?;
-                let scale = scale.parse_floatlike(&format!("config.textures.{name}.scale"))?;
+                let key = format!("config.textures.{name}.scale");
+                let scale = match scale {
+                    toml::Value::Array(sizes) if sizes.len() == 2 => Vec3::new(
+                        sizes[0].parse_floatlike(&format!("{key}.0"))?,
+                        sizes[1].parse_floatlike(&format!("{key}.1"))?,
+                        1.0,
+                    ),
+                    toml::Value::Array(_) => scale.parse_vec3(&key)?,
+                    _ => {
+                        let scale = scale.parse_floatlike(&key)?;
+                        Vec3::new(scale, scale, scale)
+                    }
+                };
+                if scale.x() <= 0.0 || scale.y() <= 0.0 || scale.z() <= 0.0 {
+                    bail!("{} must be greater than 0.", key.green());
+                }
 
                 /*
                 # Two referenced textures
@@ -337,6 +1022,7 @@ impl TextureModel {
 
                 Ok(Self::Checkerboard {
                     scale,
+                    space,
                     color1: ind1,
                     color2: ind2,
                 })
@@ -344,7 +1030,7 @@ impl TextureModel {
             "IMAGE" => {
                 let value = require_value(table, "path", &format!("config.textures.{name}"))?;
                 let path = value.parse_pathbuf(&format!("config.textures.{name}.path"))?;
-                if !path.try_exists().is_ok_and(|e| e == true) {
+                let Some(path) = texture_storage.2.resolve(&path) else {
                     bail!(miette::diagnostic!(
                         help = format!(
                             "attempted to load from {}",
@@ -353,14 +1039,112 @@ impl TextureModel {
                         "Failed to find file {}.",
                         path.display().green(),
                     ));
+                };
+                let texture = texture_storage
+                    .2
+                    .open(&path)
+                    .map_err(|e| e.into())
+                    .and_then(|file| ImageTexture::try_load(png::Decoder::new(file)));
+                match texture {
+                    Ok(texture) => Ok(Self::Image { texture }),
+                    Err(e) => bail!(miette::diagnostic!(
+                        help = format!(
+                            "attempted to load from {}",
+                            format!("config.textures.{}.path", name).purple(),
+                        ),
+                        "Failed to load image {}: {}",
+                        path.display().green(),
+                        e,
+                    )),
+                }
+            }
+            "MIX" => Ok(Self::Mix {
+                a: parse_texture_input(table, "a", name, texture_storage)?,
+                b: parse_texture_input(table, "b", name, texture_storage)?,
+                factor: parse_texture_input(table, "factor", name, texture_storage)?,
+            }),
+            "MASK" => Ok(Self::Mix {
+                a: parse_texture_input(table, "a", name, texture_storage)?,
+                b: parse_texture_input(table, "b", name, texture_storage)?,
+                factor: parse_texture_input(table, "mask", name, texture_storage)?,
+            }),
+            "MULTIPLY" => Ok(Self::Multiply {
+                a: parse_texture_input(table, "a", name, texture_storage)?,
+                b: parse_texture_input(table, "b", name, texture_storage)?,
+            }),
+            "INVERT" => Ok(Self::Invert {
+                input: parse_texture_input(table, "input", name, texture_storage)?,
+            }),
+            "REMAP" => {
+                let input = parse_texture_input(table, "input", name, texture_storage)?;
+                let range = match table.get("range") {
+                    Some(range) => {
+                        let key = format!("config.textures.{name}.range");
+                        let range = range.parse_array(&key)?;
+                        if range.len() != 2 {
+                            bail!("{} must be an array of length 2.", key.green());
+                        }
+                        let low = range[0].parse_floatlike(&format!("{key}.0"))?;
+                        let high = range[1].parse_floatlike(&format!("{key}.1"))?;
+                        if low >= high {
+                            bail!("{} must be increasing.", key.green());
+                        }
+                        (low, high)
+                    }
+                    None => (0.0, 1.0),
+                };
+                let colors = require_value(table, "colors", &format!("config.textures.{name}"))?;
+                let key = format!("config.textures.{name}.colors");
+                let colors = colors.parse_array(&key)?;
+                if colors.len() != 2 {
+                    bail!("{} must be an array of length 2.", key.green());
+                }
+                let colors = (
+                    colors[0].parse_color(&format!("{key}.0"))?,
+                    colors[1].parse_color(&format!("{key}.1"))?,
+                );
+                Ok(Self::Remap {
+                    input,
+                    range,
+                    colors,
+                })
+            }
+            "NOISE" | "PERLIN" => {
+                let scale = require_value(table, "scale", &format!("config.textures.{name}"))?;
+                let scale = scale.parse_floatlike(&format!("config.textures.{name}.scale"))?;
+                if scale <= 0.0 {
+                    bail!(
+                        "{} must be greater than 0.",
+                        format!("config.textures.{name}.scale").green()
+                    );
                 }
-                Ok(Self::Image { path })
+                let octaves = match table.get("octaves") {
+                    Some(toml::Value::Integer(n @ 1..=16)) => *n as u32,
+                    Some(_) => bail!(
+                        "{} must be an integer from 1 to 16.",
+                        format!("config.textures.{name}.octaves").green()
+                    ),
+                    None => 1,
+                };
+                let seed = match table.get("seed") {
+                    Some(toml::Value::Integer(n @ 0..)) => *n as u64,
+                    Some(_) => bail!(
+                        "{} must be a non-negative integer.",
+                        format!("config.textures.{name}.seed").green()
+                    ),
+                    None => 0,
+                };
+                Ok(Self::Noise {
+                    scale,
+                    octaves,
+                    seed,
+                })
             }
             _ => {
                 bail!(miette::diagnostic!(
                     help = format!(
                         "valid colors include: {}",
-                        r#""color" | "checkerboard" | "image""#.purple()
+                        r#""color" | "checkerboard" | "image" | "mix" | "mask" | "multiply" | "invert" | "remap" | "noise""#.purple()
                     ),
                     "{} must be a valid texture type.",
                     format!("config.textures.{}.type", name).green(),
@@ -369,20 +1153,56 @@ impl TextureModel {
         }
     }
 
-    pub fn as_texture(self, texture_storage: &TextureStorage) -> Rc<dyn Texture> {
+    pub fn into_texture(self, texture_storage: &TextureStorage) -> Rc<dyn Texture> {
         match self {
             TextureModel::SolidColor { color } => SolidColor::new(color).into_texture(),
             TextureModel::Checkerboard {
                 scale,
+                space,
                 color1,
                 color2,
             } => Checkerboard::new(
-                scale,
+                1.0,
                 Rc::clone(texture_storage.get(&color1).unwrap()),
                 Rc::clone(texture_storage.get(&color2).unwrap()),
             )
+            .with_axis_scale(scale)
+            .with_space(space)
+            .into_texture(),
+            TextureModel::Image { texture } => texture.into_texture(),
+            TextureModel::Mix { a, b, factor } => Mix::new(
+                Rc::clone(texture_storage.get(&a).unwrap()),
+                Rc::clone(texture_storage.get(&b).unwrap()),
+                Rc::clone(texture_storage.get(&factor).unwrap()),
+            )
+            .into_texture(),
+            TextureModel::Multiply { a, b } => Multiply::new(
+                Rc::clone(texture_storage.get(&a).unwrap()),
+                Rc::clone(texture_storage.get(&b).unwrap()),
+            )
+            .into_texture(),
+            TextureModel::Invert { input } => {
+                Invert::new(Rc::clone(texture_storage.get(&input).unwrap())).into_texture()
+            }
+            TextureModel::Remap {
+                input,
+                range,
+                colors,
+            } => Remap::new(
+                Rc::clone(texture_storage.get(&input).unwrap()),
+                range,
+                colors.0,
+                colors.1,
+            )
             .into_texture(),
-            TextureModel::Image { path: _ } => todo!(),
+            TextureModel::Noise {
+                scale,
+                octaves,
+                seed,
+            } => Noise::new(scale)
+                .with_octaves(octaves)
+                .with_seed(seed)
+                .into_texture(),
         }
     }
 }
@@ -434,7 +1254,64 @@ impl MaterialModel {
                 let refractive_index =
                     value.parse_floatlike(&format!("config.materials.{name}.refractive_index"))?;
 
-                Ok(Self::Dielectric { refractive_index })
+                // roughness may be given as either a texture or a constant
+                let roughness = match table.get("roughness") {
+                    Some(value @ toml::Value::String(_)) => Some(value.parse_texture(
+                        &format!("config.materials.{name}.roughness"),
+                        texture_storage,
+                    )?),
+                    Some(value) => {
+                        let roughness =
+                            value.parse_floatlike(&format!("config.materials.{name}.roughness"))?;
+                        let color = Color::new(roughness, roughness, roughness);
+                        Some(texture_storage.push_anon(TextureModel::SolidColor { color }))
+                    }
+                    None => None,
+                };
+
+                let key = format!("config.materials.{name}.abbe_number");
+                let abbe_number = match table.get("abbe_number") {
+                    Some(value) => match value.parse_floatlike(&key)? {
+                        v if v > 0.0 => Some(v),
+                        _ => bail!("{} must be a positive number.", key.green()),
+                    },
+                    None => None,
+                };
+
+                Ok(Self::Dielectric {
+                    refractive_index,
+                    roughness,
+                    abbe_number,
+                })
+            }
+            "PHONG" | "BLINNPHONG" | "BLINN_PHONG" => {
+                let value = require_value(table, "texture", &format!("config.materials.{name}"))?;
+                let diffuse = value
+                    .parse_texture(&format!("config.materials.{name}.texture"), texture_storage)?;
+
+                let value = require_value(table, "specular", &format!("config.materials.{name}"))?;
+                let specular = value.parse_color(&format!("config.materials.{name}.specular"))?;
+
+                let value = require_value(table, "exponent", &format!("config.materials.{name}"))?;
+                let exponent =
+                    value.parse_floatlike(&format!("config.materials.{name}.exponent"))?;
+
+                Ok(Self::BlinnPhong {
+                    diffuse,
+                    specular,
+                    exponent,
+                })
+            }
+            "THINDIELECTRIC" | "THIN_DIELECTRIC" | "THINGLASS" => {
+                let value = require_value(
+                    table,
+                    "refractive_index",
+                    &format!("config.materials.{name}"),
+                )?;
+                let refractive_index =
+                    value.parse_floatlike(&format!("config.materials.{name}.refractive_index"))?;
+
+                Ok(Self::ThinDielectric { refractive_index })
             }
             "ISOTROPIC" => {
                 let value = require_value(table, "texture", &format!("config.materials.{name}"))?;
@@ -460,6 +1337,52 @@ impl MaterialModel {
                     color.set_brightness(brightness);
                 }
 
+                // or in physical units, with the color only setting the hue
+                let powers = [
+                    ("watts", LightPower::Watts as fn(f64) -> LightPower),
+                    ("lumens", LightPower::Lumens),
+                    ("nits", LightPower::Nits),
+                ];
+                let mut given = powers
+                    .into_iter()
+                    .filter_map(|(key, unit)| table.get(key).map(|value| (key, unit, value)));
+                if let Some((key, unit, value)) = given.next() {
+                    let key = format!("config.materials.{name}.{key}");
+                    if given.next().is_some() || table.contains_key("brightness") {
+                        bail!(
+                            help = "Lights may be given one of brightness, watts, lumens, or nits.",
+                            "{} conflicts with another brightness.",
+                            key.green()
+                        );
+                    }
+                    let amount = value.parse_floatlike(&key)?;
+                    if amount < 0.0 {
+                        bail!("{} must not be negative.", key.green());
+                    }
+                    let power = unit(amount);
+
+                    let area = match power {
+                        LightPower::Nits(_) => 1.0,
+                        _ => {
+                            let key = format!("config.materials.{name}.area");
+                            let area =
+                                require_value(table, "area", &format!("config.materials.{name}"))?
+                                    .parse_floatlike(&key)?;
+                            if area <= 0.0 {
+                                bail!("{} must be a positive number.", key.green());
+                            }
+                            area
+                        }
+                    };
+                    if color.luminance() <= 0.0 {
+                        bail!(
+                            "{} must not be black for a light given in physical units.",
+                            format!("config.materials.{name}.color").green()
+                        );
+                    }
+                    color.set_brightness(power.radiance(area) / color.luminance());
+                }
+
                 let tex_id = texture_storage.push_anon(TextureModel::SolidColor { color });
                 Ok(Self::DiffuseLight(tex_id))
             }
@@ -467,7 +1390,7 @@ impl MaterialModel {
                 bail!(miette::diagnostic!(
                     help = format!(
                         "valid material types include: {}",
-                        r#""metal" | "light" | "lambertian" | "dielectric""#.purple()
+                        r#""metal" | "light" | "lambertian" | "dielectric" | "thindielectric" | "phong""#.purple()
                     ),
                     "{} must be a valid material type.",
                     format!("config.materials.{}.type", name).green(),
@@ -476,7 +1399,7 @@ impl MaterialModel {
         }
     }
 
-    pub fn as_material(self, texture_storage: &TextureStorage) -> Rc<dyn Material> {
+    pub fn into_material(self, texture_storage: &TextureStorage) -> Rc<dyn Material> {
         match self {
             MaterialModel::Lambertian(sid) => {
                 Lambertian::new(Rc::clone(texture_storage.get(&sid).unwrap())).into_mat()
@@ -488,8 +1411,35 @@ impl MaterialModel {
                 Isotropic::new(Rc::clone(texture_storage.get(&sid).unwrap())).into_mat()
             }
             MaterialModel::Metal { albedo, fuzz } => Metal::with_fuzz(albedo, fuzz).into_mat(),
-            MaterialModel::Dielectric { refractive_index } => {
-                Dielectric::new(refractive_index).into_mat()
+            MaterialModel::BlinnPhong {
+                diffuse,
+                specular,
+                exponent,
+            } => BlinnPhong::new(
+                Rc::clone(texture_storage.get(&diffuse).unwrap()),
+                specular,
+                exponent,
+            )
+            .into_mat(),
+            MaterialModel::Dielectric {
+                refractive_index,
+                roughness,
+                abbe_number,
+            } => {
+                let mut material = match roughness {
+                    Some(sid) => Dielectric::frosted(
+                        refractive_index,
+                        Rc::clone(texture_storage.get(&sid).unwrap()),
+                    ),
+                    None => Dielectric::new(refractive_index),
+                };
+                if let Some(abbe_number) = abbe_number {
+                    material = material.with_abbe_number(abbe_number);
+                }
+                material.into_mat()
+            }
+            MaterialModel::ThinDielectric { refractive_index } => {
+                ThinDielectric::new(refractive_index).into_mat()
             }
         }
     }
@@ -497,53 +1447,103 @@ impl MaterialModel {
 
 impl ObjectModel {
     pub fn parse(
-        index: usize,
+        key: &str,
         table: &toml::Table,
         materials: &MaterialStorage,
-        objects: &mut Vec<Self>,
+        textures: &TextureStorage,
     ) -> Result<Self> {
         let Some(toml::Value::String(obj_type)) = table.get("type") else {
-            bail!(
-                "{} must be a string.",
-                format!("config.materials.{}.type", index).green()
-            );
+            bail!("{} must be a string.", format!("{key}.type").green());
         };
 
         match &obj_type.to_ascii_uppercase()[..] {
             "SPHERE" => {
-                let value = require_value(table, "center", &format!("config.objects.{index}"))?;
-                let center = value.parse_point3(&format!("config.objects.{index}.center"))?;
-                let value = require_value(table, "radius", &format!("config.objects.{index}"))?;
-                let radius = value.parse_floatlike(&format!("config.objects.{index}.radius"))?;
-                let value = require_value(table, "material", &format!("config.objects.{index}"))?;
-                let material =
-                    value.parse_material(&format!("config.objects.{index}.material"), materials)?;
+                let value = require_value(table, "center", key)?;
+                let center = value.parse_point3(&format!("{key}.center"))?;
+                let value = require_value(table, "radius", key)?;
+                let radius = value.parse_length(&format!("{key}.radius"))?;
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+                let projection = match table.get("projection") {
+                    None => SphereProjection::Equirectangular,
+                    Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("equirectangular") => {
+                        SphereProjection::Equirectangular
+                    }
+                    Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("cube") => {
+                        SphereProjection::CubeMap
+                    }
+                    Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("angular") => {
+                        SphereProjection::Angular
+                    }
+                    Some(_) => bail!(
+                        "{} must be {}, {} or {}.",
+                        format!("{key}.projection").green(),
+                        r#""equirectangular""#.purple(),
+                        r#""cube""#.purple(),
+                        r#""angular""#.purple()
+                    ),
+                };
+                let texture_rotation = table
+                    .get("texture_rotation")
+                    .map(|v| v.parse_angle(&format!("{key}.texture_rotation")))
+                    .transpose()?
+                    .unwrap_or(Angle::ZERO);
+                let thickness = table
+                    .get("thickness")
+                    .map(|v| v.parse_length(&format!("{key}.thickness")))
+                    .transpose()?;
+                if let Some(thickness) = thickness {
+                    if !(0.0 < thickness && thickness < radius) {
+                        bail!(
+                            "{} must be greater than 0 and less than the radius.",
+                            format!("{key}.thickness").green()
+                        );
+                    }
+                }
                 Ok(Self::Sphere {
                     center,
                     radius,
                     material,
+                    projection,
+                    texture_rotation,
+                    thickness,
+                })
+            }
+            "ELLIPSOID" => {
+                let value = require_value(table, "center", key)?;
+                let center = value.parse_point3(&format!("{key}.center"))?;
+                let value = require_value(table, "radii", key)?;
+                let radii = value.parse_lengths(&format!("{key}.radii"))?;
+                if radii.x() <= 0.0 || radii.y() <= 0.0 || radii.z() <= 0.0 {
+                    bail!("{} must be positive.", format!("{key}.radii").green());
+                }
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+                Ok(Self::Ellipsoid {
+                    center,
+                    radii,
+                    material,
                 })
             }
             "PARALLELOGRAM" => {
-                let value = require_value(table, "corner", &format!("config.objects.{index}"))?;
-                let corner = value.parse_point3(&format!("config.objects.{index}.corner"))?;
-                let value = require_value(table, "material", &format!("config.objects.{index}"))?;
-                let material =
-                    value.parse_material(&format!("config.objects.{index}.material"), materials)?;
+                let value = require_value(table, "corner", key)?;
+                let corner = value.parse_point3(&format!("{key}.corner"))?;
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
 
-                let vecs = require_value(table, "vectors", &format!("config.objects.{index}"))?;
-                let vecs = vecs.parse_array(&format!("config.objects.{index}.vectors"))?;
+                let vecs = require_value(table, "vectors", key)?;
+                let vecs = vecs.parse_array(&format!("{key}.vectors"))?;
 
                 if vecs.len() != 2 {
                     bail!(
                         "{} must be an array of length 2.",
-                        format!("config.objects.{index}.vectors").green()
+                        format!("{key}.vectors").green()
                     );
                 }
 
                 let vectors = [
-                    vecs[0].parse_vec3(&format!("config.objects.{index}.vectors.0"))?,
-                    vecs[1].parse_vec3(&format!("config.objects.{index}.vectors.1"))?,
+                    vecs[0].parse_lengths(&format!("{key}.vectors.0"))?,
+                    vecs[1].parse_lengths(&format!("{key}.vectors.1"))?,
                 ];
                 Ok(Self::Parallelogram {
                     corner,
@@ -551,26 +1551,68 @@ impl ObjectModel {
                     material,
                 })
             }
+            "TRIANGLE" => {
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+
+                let pts = require_value(table, "points", key)?;
+                let pts = pts.parse_array(&format!("{key}.points"))?;
+
+                if pts.len() != 3 {
+                    bail!(
+                        "{} must be an array of length 3.",
+                        format!("{key}.points").green()
+                    );
+                }
+
+                let points = [
+                    pts[0].parse_point3(&format!("{key}.points.0"))?,
+                    pts[1].parse_point3(&format!("{key}.points.1"))?,
+                    pts[2].parse_point3(&format!("{key}.points.2"))?,
+                ];
+                Ok(Self::Triangle { points, material })
+            }
+            "BILINEARPATCH" | "BILINEAR_PATCH" => {
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+
+                let pts = require_value(table, "corners", key)?;
+                let pts = pts.parse_array(&format!("{key}.corners"))?;
+
+                if pts.len() != 4 {
+                    bail!(
+                        "{} must be an array of length 4.",
+                        format!("{key}.corners").green()
+                    );
+                }
+
+                let corners = [
+                    pts[0].parse_point3(&format!("{key}.corners.0"))?,
+                    pts[1].parse_point3(&format!("{key}.corners.1"))?,
+                    pts[2].parse_point3(&format!("{key}.corners.2"))?,
+                    pts[3].parse_point3(&format!("{key}.corners.3"))?,
+                ];
+                Ok(Self::BilinearPatch { corners, material })
+            }
             "DISC" => {
-                let value = require_value(table, "center", &format!("config.objects.{index}"))?;
-                let center = value.parse_point3(&format!("config.objects.{index}.center"))?;
-                let value = require_value(table, "material", &format!("config.objects.{index}"))?;
-                let material =
-                    value.parse_material(&format!("config.objects.{index}.material"), materials)?;
+                let value = require_value(table, "center", key)?;
+                let center = value.parse_point3(&format!("{key}.center"))?;
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
 
-                let vecs = require_value(table, "vectors", &format!("config.objects.{index}"))?;
-                let vecs = vecs.parse_array(&format!("config.objects.{index}.vectors"))?;
+                let vecs = require_value(table, "vectors", key)?;
+                let vecs = vecs.parse_array(&format!("{key}.vectors"))?;
 
                 if vecs.len() != 2 {
                     bail!(
                         "{} must be an array of length 2.",
-                        format!("config.objects.{index}.vectors").green()
+                        format!("{key}.vectors").green()
                     );
                 }
 
                 let vectors = [
-                    vecs[0].parse_vec3(&format!("config.objects.{index}.vectors.0"))?,
-                    vecs[1].parse_vec3(&format!("config.objects.{index}.vectors.1"))?,
+                    vecs[0].parse_lengths(&format!("{key}.vectors.0"))?,
+                    vecs[1].parse_lengths(&format!("{key}.vectors.1"))?,
                 ];
                 Ok(Self::Disc {
                     center,
@@ -578,28 +1620,251 @@ impl ObjectModel {
                     material,
                 })
             }
+            "RING" => {
+                let value = require_value(table, "center", key)?;
+                let center = value.parse_point3(&format!("{key}.center"))?;
+                let value = require_value(table, "normal", key)?;
+                let normal = value.parse_vec3(&format!("{key}.normal"))?;
+                let value = require_value(table, "inner_radius", key)?;
+                let inner_radius = value.parse_length(&format!("{key}.inner_radius"))?;
+                let value = require_value(table, "outer_radius", key)?;
+                let outer_radius = value.parse_length(&format!("{key}.outer_radius"))?;
+                if !(0.0 <= inner_radius && inner_radius < outer_radius) {
+                    bail!(
+                        "{} must be at least 0 and less than {}.",
+                        format!("{key}.inner_radius").green(),
+                        format!("{key}.outer_radius").green()
+                    );
+                }
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+                Ok(Self::Ring {
+                    center,
+                    normal,
+                    inner_radius,
+                    outer_radius,
+                    material,
+                })
+            }
+            "ROUNDED_BOX" => {
+                let value = require_value(table, "corners", key)?;
+                let corners = value.parse_array(&format!("{key}.corners"))?;
+                if corners.len() != 2 {
+                    bail!(
+                        "{} must be an array of length 2.",
+                        format!("{key}.corners").green()
+                    );
+                }
+                let corners = [
+                    corners[0].parse_point3(&format!("{key}.corners.0"))?,
+                    corners[1].parse_point3(&format!("{key}.corners.1"))?,
+                ];
+                let value = require_value(table, "radius", key)?;
+                let radius = value.parse_length(&format!("{key}.radius"))?;
+                let shortest = Axis::iter()
+                    .map(|axis| (corners[1][axis] - corners[0][axis]).abs())
+                    .fold(f64::INFINITY, f64::min);
+                if !(0.0 <= radius && radius <= shortest / 2.0) {
+                    bail!(
+                        "{} must be at least 0 and at most half the shortest side of the box.",
+                        format!("{key}.radius").green()
+                    );
+                }
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+                Ok(Self::RoundedBox {
+                    corners,
+                    radius,
+                    material,
+                })
+            }
+            "SLAB" => {
+                let axis = match require_value(table, "axis", key)? {
+                    toml::Value::String(s) if s.eq_ignore_ascii_case("x") => Axis::X,
+                    toml::Value::String(s) if s.eq_ignore_ascii_case("y") => Axis::Y,
+                    toml::Value::String(s) if s.eq_ignore_ascii_case("z") => Axis::Z,
+                    _ => bail!(
+                        "{} must be {}, {} or {}.",
+                        format!("{key}.axis").green(),
+                        r#""x""#.purple(),
+                        r#""y""#.purple(),
+                        r#""z""#.purple()
+                    ),
+                };
+                let value = require_value(table, "extent", key)?;
+                let extent = value.parse_array(&format!("{key}.extent"))?;
+                if extent.len() != 2 {
+                    bail!(
+                        "{} must be an array of length 2.",
+                        format!("{key}.extent").green()
+                    );
+                }
+                let extent = (
+                    extent[0].parse_length(&format!("{key}.extent.0"))?,
+                    extent[1].parse_length(&format!("{key}.extent.1"))?,
+                );
+                if extent.0 > extent.1 {
+                    bail!(
+                        "{} must be in increasing order.",
+                        format!("{key}.extent").green()
+                    );
+                }
+                let value = require_value(table, "material", key)?;
+                let material = value.parse_material(&format!("{key}.material"), materials)?;
+
+                Ok(Self::Slab {
+                    axis,
+                    extent,
+                    material,
+                })
+            }
+            "GRID" => {
+                let value = require_value(table, "object", key)?;
+                let toml::Value::Table(object_table) = value else {
+                    bail!("{} must be a table.", format!("{key}.object").green());
+                };
+                let object = ObjectModel::parse(
+                    &format!("{key}.object"),
+                    object_table,
+                    materials,
+                    textures,
+                )?;
+
+                let counts = require_value(table, "count", key)?;
+                let counts = counts.parse_array(&format!("{key}.count"))?;
+                let counts: Vec<u32> = counts
+                    .iter()
+                    .filter_map(|count| count.as_integer()?.try_into().ok())
+                    .filter(|&count| count > 0)
+                    .collect();
+                let Ok(counts) = <[u32; 3]>::try_from(counts) else {
+                    bail!(
+                        "{} must be an array of 3 positive integers, represented as {}.",
+                        format!("{key}.count").green(),
+                        "[x, y, z]".purple()
+                    );
+                };
+
+                let value = require_value(table, "spacing", key)?;
+                let spacing = value.parse_lengths(&format!("{key}.spacing"))?;
+
+                let mut grid = Grid::new(counts, spacing);
+                if let Some(value) = table.get("jitter") {
+                    let jitter = match value {
+                        toml::Value::Array(_) => value.parse_lengths(&format!("{key}.jitter"))?,
+                        _ => {
+                            let jitter = value.parse_length(&format!("{key}.jitter"))?;
+                            Vec3::new(jitter, jitter, jitter)
+                        }
+                    };
+                    if jitter.x() < 0.0 || jitter.y() < 0.0 || jitter.z() < 0.0 {
+                        bail!("{} must not be negative.", format!("{key}.jitter").green());
+                    }
+                    grid = grid.with_jitter(jitter);
+                }
+
+                Ok(Self::Grid {
+                    object: Box::new(object),
+                    grid,
+                })
+            }
+            "CONSTANTMEDIUM" => {
+                let value = require_value(table, "boundary", key)?;
+                let toml::Value::Table(boundary_table) = value else {
+                    bail!("{} must be a table.", format!("{key}.boundary").green());
+                };
+                let boundary = ObjectModel::parse(
+                    &format!("{key}.boundary"),
+                    boundary_table,
+                    materials,
+                    textures,
+                )?;
+
+                let value = require_value(table, "density", key)?;
+                let density = value.parse_floatlike(&format!("{key}.density"))?;
+                if !(density > 0.0 && density.is_finite()) {
+                    bail!(
+                        "{} must be greater than {}.",
+                        format!("{key}.density").green(),
+                        "0.0".purple()
+                    );
+                }
+
+                // the name of a texture, or a color
+                let texture = |input: &str| -> Result<Rc<dyn Texture>> {
+                    let value = require_value(table, input, key)?;
+                    let key = format!("{key}.{input}");
+                    match value {
+                        toml::Value::String(s) if textures.contains_named_key(s) => {
+                            let id = value.parse_texture(&key, textures)?;
+                            Ok(Rc::clone(textures.get(&id).unwrap()))
+                        }
+                        _ => match value.parse_color(&key) {
+                            Ok(color) => Ok(SolidColor::new(color).into_texture()),
+                            Err(_) => bail!(
+                                help = "Inputs may be the name of a texture or a color.",
+                                "{} does not describe a valid texture.",
+                                key.green()
+                            ),
+                        },
+                    }
+                };
+                let albedo = texture("albedo")?;
+                let density_texture = match table.get("density_texture") {
+                    Some(_) => Some(texture("density_texture")?),
+                    None => None,
+                };
+
+                Ok(Self::ConstantMedium {
+                    boundary: Box::new(boundary),
+                    density,
+                    albedo,
+                    density_texture,
+                })
+            }
             _ => {
                 bail!(miette::diagnostic!(
                     help = format!(
                         "valid object types include: {}",
-                        r#""sphere" | "parallelogram" | "triangle" | "disc""#.purple()
+                        r#""sphere" | "ellipsoid" | "parallelogram" | "triangle" | "bilinear_patch" | "disc" | "ring" | "rounded_box" | "slab" | "grid" | "constant_medium""#
+                            .purple()
                     ),
                     "{} must be a valid object type.",
-                    format!("config.objects.{}.type", index).green(),
+                    format!("{key}.type").green(),
                 ));
             }
         }
     }
 
-    pub fn as_hittable(self, material_storage: &MaterialStorage) -> Rc<dyn Hittable> {
+    pub fn into_hittable(self, material_storage: &MaterialStorage) -> Rc<dyn Hittable> {
         match self {
             ObjectModel::Sphere {
                 center,
                 radius,
                 material,
-            } => Sphere::stationary(
+                projection,
+                texture_rotation,
+                thickness,
+            } => {
+                let sphere = Sphere::stationary(
+                    center,
+                    radius,
+                    Rc::clone(material_storage.get(&material.0).unwrap()),
+                )
+                .with_projection(projection)
+                .with_texture_rotation(texture_rotation);
+                match thickness {
+                    Some(thickness) => sphere.hollow(thickness).hittable(),
+                    None => sphere.hittable(),
+                }
+            }
+            ObjectModel::Ellipsoid {
                 center,
-                radius,
+                radii,
+                material,
+            } => Ellipsoid::new(
+                center,
+                radii,
                 Rc::clone(material_storage.get(&material.0).unwrap()),
             )
             .hittable(),
@@ -614,18 +1879,124 @@ impl ObjectModel {
                 Rc::clone(material_storage.get(&material.0).unwrap()),
             )
             .hittable(),
-            ObjectModel::Triangle { points, material } => todo!(),
+            ObjectModel::Triangle { points, material } => Triangle::from_points(
+                points[0],
+                points[1],
+                points[2],
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
+            ObjectModel::BilinearPatch { corners, material } => BilinearPatch::new(
+                corners,
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
             ObjectModel::Disc {
                 center,
                 vectors,
                 material,
-            } => todo!(),
+            } => Disc::from_center(
+                center,
+                vectors[0],
+                vectors[1],
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
+            ObjectModel::Ring {
+                center,
+                normal,
+                inner_radius,
+                outer_radius,
+                material,
+            } => Ring::new(
+                center,
+                normal,
+                inner_radius,
+                outer_radius,
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
+            ObjectModel::RoundedBox {
+                corners,
+                radius,
+                material,
+            } => RoundedBox::new(
+                &corners[0],
+                &corners[1],
+                radius,
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
+            ObjectModel::Slab {
+                axis,
+                extent,
+                material,
+            } => Slab::new(
+                axis,
+                Interval::new(extent.0, extent.1),
+                Rc::clone(material_storage.get(&material.0).unwrap()),
+            )
+            .hittable(),
+            ObjectModel::Grid { object, grid } => {
+                let copies = grid.build(object.into_hittable(material_storage));
+                BVHNode::new(copies.into()).hittable()
+            }
+            ObjectModel::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+                density_texture,
+            } => {
+                let medium =
+                    ConstantMedium::new(boundary.into_hittable(material_storage), density, albedo);
+                match density_texture {
+                    Some(texture) => medium.with_density_texture(texture).hittable(),
+                    None => medium.hittable(),
+                }
+            }
+            ObjectModel::Named { name, object } => {
+                Named::new(name, object.into_hittable(material_storage)).hittable()
+            }
+            ObjectModel::FlipFace(object) => {
+                FlipFace::new(object.into_hittable(material_storage)).hittable()
+            }
+            ObjectModel::Sided { back_face, object } => {
+                Sided::new(object.into_hittable(material_storage), back_face).hittable()
+            }
+            ObjectModel::LightGroup { group, object } => {
+                LightGroup::new(group, object.into_hittable(material_storage)).hittable()
+            }
+            ObjectModel::DepthOverride { max_depth, object } => {
+                DepthOverride::new(max_depth, object.into_hittable(material_storage)).hittable()
+            }
         }
     }
 }
 
 impl ConfigModel {
+    /// Parses a scene whose files are read relative to the working directory.
+    /// Prefer [`ConfigModel::open`] for scenes stored on disk.
     pub fn from_table(table: &toml::Table) -> Result<Self> {
+        Self::from_table_with_assets(table, Assets::default())
+    }
+
+    /// Reads the scene at `path`. Files the scene refers to are found relative to the scene
+    /// itself, then in the directories listed in its `asset_paths`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let Ok(source) = std::fs::read_to_string(path) else {
+            bail!("Failed to read scene {}.", path.display().green());
+        };
+        let table = source
+            .parse::<toml::Table>()
+            .map_err(|e| miette::miette!("Invalid TOML: {}", e))?;
+        Self::from_table_with_assets(&table, Assets::relative_to(path))
+    }
+
+    /// Like [`ConfigModel::from_table`], but reads the files the scene refers to from `assets`.
+    pub fn from_table_with_assets(table: &toml::Table, assets: Assets) -> Result<Self> {
+        let table = &apply_palette(table)?;
+        let assets = assets.with_scene_search_paths(table)?;
         let Some(toml::Value::Table(texture_table)) = table.get("textures") else {
             bail!("{} must be a table.", "config.textures".green());
         };
@@ -638,12 +2009,12 @@ impl ConfigModel {
             bail!("{} must be a list of tables.", "config.objects".green());
         };
 
-        let mut textures = TextureStorage::with_capacity(texture_table.len());
+        let mut textures = TextureStorage::with_capacity(texture_table.len(), assets);
         let mut materials = HashMap::with_capacity(texture_table.len());
         let mut objects = Vec::with_capacity(object_array.len());
 
-        for (texture_id, texture) in texture_table {
-            let toml::Value::Table(texture_table) = texture else {
+        for texture_id in texture_order(texture_table)? {
+            let toml::Value::Table(texture_table) = &texture_table[texture_id] else {
                 bail!(
                     "{} must be a table.",
                     format!("config.textures.{}", texture_id).green()
@@ -654,21 +2025,23 @@ impl ConfigModel {
             textures.push_named(texture_id.clone(), texture);
         }
 
-        for (material_id, material) in material_table {
-            let toml::Value::Table(material_table) = material else {
-                bail!(
-                    "{} must be a table.",
-                    format!("config.materials.{}", material_id).green()
-                );
-            };
-
+        for material_id in material_table.keys() {
+            // materials may extend others, overriding some of their fields
+            let resolved = resolve_material(material_id, material_table)?;
             materials.insert(
                 material_id.clone(),
-                MaterialModel::parse(material_id, material_table, &mut textures)?
-                    .as_material(&textures),
+                MaterialModel::parse(material_id, &resolved, &mut textures)?
+                    .into_material(&textures),
             );
         }
 
+        // library materials may be used by objects without being listed in `config.materials`
+        for name in library::NAMES {
+            materials
+                .entry(format!("{LIBRARY_PREFIX}{name}"))
+                .or_insert_with(|| library::get(name).unwrap());
+        }
+
         for (i, object) in object_array.iter().enumerate() {
             let toml::Value::Table(object_table) = object else {
                 bail!(
@@ -677,38 +2050,200 @@ impl ConfigModel {
                 );
             };
 
-            let object = ObjectModel::parse(i, object_table, &materials, &mut objects)?;
+            let key = format!("config.objects.{i}");
+            let mut object = ObjectModel::parse(&key, object_table, &materials, &textures)?;
+            match object_table.get("flip_face") {
+                Some(toml::Value::Boolean(true)) => {
+                    object = ObjectModel::FlipFace(Box::new(object));
+                }
+                Some(toml::Value::Boolean(false)) | None => {}
+                Some(_) => bail!("{} must be a boolean.", format!("{key}.flip_face").green()),
+            }
+            let back_face = match object_table.get("back_face") {
+                Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("flip") => {
+                    Some(BackFace::Flip)
+                }
+                Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("absorb") => {
+                    Some(BackFace::Absorb)
+                }
+                Some(toml::Value::String(s)) if s.eq_ignore_ascii_case("cull") => {
+                    Some(BackFace::Cull)
+                }
+                None => None,
+                Some(_) => bail!(
+                    "{} must be {}, {} or {}.",
+                    format!("{key}.back_face").green(),
+                    r#""flip""#.purple(),
+                    r#""absorb""#.purple(),
+                    r#""cull""#.purple()
+                ),
+            };
+            if let Some(back_face) = back_face {
+                object = ObjectModel::Sided {
+                    back_face,
+                    object: Box::new(object),
+                }
+            }
+            match object_table.get("light_group") {
+                Some(toml::Value::String(group)) => {
+                    object = ObjectModel::LightGroup {
+                        group: group.clone(),
+                        object: Box::new(object),
+                    }
+                }
+                Some(_) => bail!("{} must be a string.", format!("{key}.light_group").green()),
+                None => {}
+            }
+            if let Some(value) = object_table.get("max_depth") {
+                let Some(max_depth) = value
+                    .as_integer()
+                    .and_then(|depth| u32::try_from(depth).ok())
+                    .filter(|&depth| depth > 0)
+                else {
+                    bail!(
+                        "{} must be a positive integer.",
+                        format!("{key}.max_depth").green()
+                    );
+                };
+                object = ObjectModel::DepthOverride {
+                    max_depth,
+                    object: Box::new(object),
+                }
+            }
+            match object_table.get("name") {
+                Some(toml::Value::String(name)) => {
+                    object = ObjectModel::Named {
+                        name: name.clone(),
+                        object: Box::new(object),
+                    }
+                }
+                Some(_) => bail!("{} must be a string.", format!("{key}.name").green()),
+                None => {}
+            }
             objects.push(object);
         }
 
+        let camera = match table.get("camera") {
+            Some(toml::Value::Table(camera)) => CameraModel::parse(camera)?,
+            Some(_) => bail!("{} must be a table.", "config.camera".green()),
+            None => CameraModel::default(),
+        };
+
+        let background = match table.get("background") {
+            Some(toml::Value::Table(background)) => {
+                Some(BackgroundModel::parse(background, &textures)?)
+            }
+            Some(_) => bail!("{} must be a table.", "config.background".green()),
+            None => None,
+        };
+
         Ok(Self {
             textures,
             materials,
             objects,
+            camera,
+            background,
         })
     }
 
+    /// Applies the scene's `[camera]` and `[background]` settings, if it has any, to
+    /// `builder`; see also [`CameraBuilder::from_config`].
+    pub fn configure_camera<'a>(&self, builder: CameraBuilder<'a>) -> CameraBuilder<'a> {
+        let mut builder = self.camera.configure(builder);
+        let Some(model) = &self.background else {
+            return builder;
+        };
+        if let Some(background) = &model.background {
+            builder = builder.background(background.clone());
+        }
+        if let Some(strength) = model.strength {
+            builder = builder.background_strength(strength);
+        }
+        if let Some(rotation) = model.rotation {
+            builder = builder.background_rotation(rotation);
+        }
+        builder
+    }
+
     pub fn as_world(self) -> HittableVec {
         let mut world = HittableVec::new();
         for object in self.objects {
-            world.add(object.as_hittable(&self.materials));
+            world.add(object.into_hittable(&self.materials));
         }
         world
     }
+
+    /// As [`ConfigModel::as_world`], but the world's materials can later be replaced with
+    /// those of an updated config, without rebuilding any geometry; see [`LiveMaterials`].
+    pub fn as_live_world(self) -> (HittableVec, LiveMaterials) {
+        let slots: HashMap<String, Rc<MaterialSlot>> = self
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                let slot = MaterialSlot::new(Rc::clone(material));
+                (name.clone(), Rc::new(slot))
+            })
+            .collect();
+        let storage: MaterialStorage = slots
+            .iter()
+            .map(|(name, slot)| (name.clone(), Rc::clone(slot) as Rc<dyn Material>))
+            .collect();
+
+        let mut world = HittableVec::new();
+        for object in self.objects {
+            world.add(object.into_hittable(&storage));
+        }
+        (world, LiveMaterials(slots))
+    }
+}
+
+/// The materials of a world built with [`ConfigModel::as_live_world`], which can be swapped
+/// out for those of an updated config while keeping the world's objects (and any BVH built
+/// over them) intact, for quick iteration on a scene's look.
+#[derive(Debug)]
+pub struct LiveMaterials(HashMap<String, Rc<MaterialSlot>>);
+
+impl LiveMaterials {
+    /// Replaces each material with the material of the same name in `updated`, including
+    /// changes to the textures it uses. The objects in `updated` are ignored; apply its camera
+    /// settings with [`ConfigModel::configure_camera`].
+    ///
+    /// Fails without changing anything if `updated` is missing any of the materials.
+    pub fn update(&self, updated: &ConfigModel) -> Result<()> {
+        if let Some(name) = self
+            .0
+            .keys()
+            .find(|name| !updated.materials.contains_key(*name))
+        {
+            bail!(
+                "{} was removed, but the scene may use it.",
+                format!("config.materials.{name}").green()
+            );
+        }
+
+        for (name, slot) in &self.0 {
+            slot.replace(Rc::clone(&updated.materials[name]));
+        }
+        Ok(())
+    }
 }
 
 impl FromStr for ConfigModel {
     type Err = miette::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::from_table(&s.parse::<toml::Table>().unwrap())
+        let table = s
+            .parse::<toml::Table>()
+            .map_err(|e| miette::miette!("Invalid TOML: {}", e))?;
+        Self::from_table(&table)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    const SAMPLE: &'static str = r##"
+    use crate::{HitRecord, Interval, Ray4};
+    const SAMPLE: &str = r##"
 
 [textures.red]
 type = "SolidColor"
@@ -719,6 +2254,24 @@ type = "Checkerboard"
 textures = ["#ff0", 0xfff]
 scale = 1.0
 
+[textures.tiles]
+type = "Checkerboard"
+textures = [0x000, 0xfff]
+space = "uv"
+scale = [0.1, 0.05]
+
+[textures.marble]
+type = "Remap"
+input = { type = "Noise", scale = 0.5, octaves = 4 }
+range = [0.3, 0.7]
+colors = ["#222222", "#eeeeee"]
+
+[textures.aged]
+type = "Mix"
+a = "marble"
+b = "cx"
+factor = 0.25
+
 # [textures.world]
 # type = "Image"
 # path = "assets/textures/earth.png"
@@ -752,6 +2305,35 @@ type = "Parallelogram"
 corner = [-3, -2, 5]
 vectors = [[0, 0, -4], [0, 4, 0]]
 material = "solid_red"
+back_face = "cull"
+
+[[objects]]
+type = "Sphere"
+name = "ball"
+center = [0, 0, 0]
+radius = 1
+material = "@library/gold"
+
+[[objects]]
+type = "Grid"
+count = [4, 1, 3]
+spacing = [2, 0, 2]
+jitter = 0.1
+object = { type = "Sphere", center = [0, 5, 0], radius = 0.5, material = "solid_red" }
+
+[[objects]]
+type = "Sphere"
+light_group = "key"
+center = [0, 8, 0]
+radius = 1
+material = "light2"
+
+[background]
+type = "Environment"
+texture = "marble"
+strength = 0.5
+# degrees about the y axis
+rotation = 90
 "##;
 
     #[test]
@@ -761,4 +2343,401 @@ material = "solid_red"
         dbg!(_world);
         Ok(())
     }
+
+    #[test]
+    fn texture_references() {
+        // textures are parsed in alphabetical order, but `aged` depends on `marble`
+        assert!(SAMPLE.parse::<ConfigModel>().is_ok());
+
+        let cyclic = SAMPLE.replace("a = \"marble\"", "a = \"aged\"");
+        assert!(cyclic.parse::<ConfigModel>().is_err());
+    }
+
+    #[test]
+    fn live_materials() {
+        let (world, materials) = SAMPLE.parse::<ConfigModel>().unwrap().as_live_world();
+        // towards the parallelogram
+        let ray = Ray4::new(Point3::new(0.0, 0.0, 3.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let material = || {
+            let hit = world.hit(&ray, Interval::universe()).unwrap();
+            hit.material().type_name()
+        };
+        assert_eq!(material(), "Lambertian");
+
+        let metal = SAMPLE.replace(
+            "type = \"SolidColor\"\ncolor = 0xff0000",
+            "type = \"Metal\"\nalbedo = 0xff0000\nfuzz = 0.0",
+        );
+        materials.update(&metal.parse().unwrap()).unwrap();
+        assert_eq!(material(), "Metal");
+    }
+
+    #[test]
+    fn unknown_library_material() {
+        let config = SAMPLE.replace("@library/gold", "@library/unobtainium");
+        assert!(config.parse::<ConfigModel>().is_err());
+    }
+
+    #[test]
+    fn slab() {
+        let config = SAMPLE.to_string()
+            + r#"
+[[objects]]
+type = "Slab"
+axis = "y"
+extent = [-2.0, -1.5]
+material = "solid_red"
+"#;
+        let world = config.parse::<ConfigModel>().unwrap().as_world();
+        let ray = Ray4::new(Point3::new(1e6, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = world
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert_eq!(hit.t(), 11.5);
+    }
+
+    /// Each block is added to the sample scene, which must parse, then must fail to parse once
+    /// `valid` is replaced by `invalid`.
+    #[test]
+    fn rejected_values() {
+        let cases = [
+            (
+                "[materials.prism]\ntype = \"dielectric\"\nrefractive_index = 1.62\nabbe_number = 36.4\n",
+                "abbe_number = 36.4",
+                "abbe_number = 0",
+            ),
+            (
+                "[[objects]]\ntype = \"Slab\"\naxis = \"y\"\nextent = [-2.0, -1.5]\nmaterial = \"solid_red\"\n",
+                "axis = \"y\"",
+                "axis = \"w\"",
+            ),
+            (
+                "[[objects]]\ntype = \"Ring\"\ncenter = [0, 0, 0]\nnormal = [0, 1, 0]\ninner_radius = 1.5\nouter_radius = 2.5\nmaterial = \"solid_red\"\n",
+                "inner_radius = 1.5",
+                "inner_radius = 3.0",
+            ),
+            (
+                "[materials.bulb]\ntype = \"ColoredLight\"\ncolor = 0xffffff\nlumens = 800\narea = 0.01\n",
+                "area = 0.01",
+                "",
+            ),
+            (
+                "[materials.bulb]\ntype = \"ColoredLight\"\ncolor = 0xffffff\nlumens = 800\narea = 0.01\n",
+                "area = 0.01",
+                "area = 0.01\nbrightness = 2",
+            ),
+            (
+                "[materials.bulb]\ntype = \"ColoredLight\"\ncolor = 0xffffff\nnits = 1000\n",
+                "color = 0xffffff",
+                "color = 0x000000",
+            ),
+            (
+                "[[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"solid_red\"\nmax_depth = 64\n",
+                "max_depth = 64",
+                "max_depth = 0",
+            ),
+            (
+                "[[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"solid_red\"\nflip_face = true\n",
+                "flip_face = true",
+                "flip_face = 1",
+            ),
+            (
+                "[[objects]]\ntype = \"Ellipsoid\"\ncenter = [0, 0, 0]\nradii = [2, 1, 1]\nmaterial = \"solid_red\"\n",
+                "radii = [2, 1, 1]",
+                "radii = [2, 0, 1]",
+            ),
+            (
+                "[[objects]]\ntype = \"BilinearPatch\"\ncorners = [[0, 0, 1], [1, 0, -1], [0, 1, -1], [1, 1, 1]]\nmaterial = \"solid_red\"\n",
+                ", [1, 1, 1]]",
+                "]",
+            ),
+            (
+                "[[objects]]\ntype = \"ConstantMedium\"\ndensity = 0.5\nalbedo = \"red\"\ndensity_texture = 0xfff\nboundary = { type = \"Sphere\", center = [0, 0, 0], radius = 2, material = \"solid_red\" }\n",
+                "density = 0.5",
+                "density = 0",
+            ),
+            (
+                "[[objects]]\ntype = \"ConstantMedium\"\ndensity = 0.5\nalbedo = \"red\"\nboundary = { type = \"Sphere\", center = [0, 0, 0], radius = 2, material = \"solid_red\" }\n",
+                "albedo = \"red\"",
+                "albedo = \"missing\"",
+            ),
+            (
+                "[[objects]]\ntype = \"Rounded_Box\"\ncorners = [[-1, 0, -1], [1, 1, 1]]\nradius = 0.25\nmaterial = \"solid_red\"\n",
+                "radius = 0.25",
+                "radius = 0.75",
+            ),
+            (
+                "[[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"solid_red\"\nprojection = \"cube\"\n",
+                "\"cube\"",
+                "\"mercator\"",
+            ),
+            (
+                "[[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nthickness = \"5cm\"\nmaterial = \"solid_red\"\n",
+                "\"5cm\"",
+                "1.5",
+            ),
+            (
+                "[[objects]]\ntype = \"Sphere\"\ncenter = [\"10cm\", 0, \"-1m\"]\nradius = \"2.5cm\"\nmaterial = \"solid_red\"\ntexture_rotation = \"45deg\"\n",
+                "\"45deg\"",
+                "\"45 degrees\"",
+            ),
+        ];
+
+        for (block, valid, invalid) in cases {
+            let config = format!("{SAMPLE}\n{block}");
+            if let Err(err) = config.parse::<ConfigModel>() {
+                panic!("{block} should parse: {err:?}");
+            }
+            assert!(block.contains(valid));
+            let config = config.replace(valid, invalid);
+            assert!(
+                config.parse::<ConfigModel>().is_err(),
+                "{block} should be rejected with {invalid:?}"
+            );
+        }
+    }
+
+    /// Builds a world of only the objects and materials in `scene`, and hits it with a ray
+    /// straight down onto the origin. `scene` may use the white material `white`.
+    fn hit_from_above(scene: &str) -> (Ray4, HitRecord) {
+        let config = format!(
+            "[textures]\n[materials.white]\ntype = \"SolidColor\"\ncolor = 0xffffff\n{scene}"
+        );
+        let world = config.parse::<ConfigModel>().unwrap().as_world();
+        let ray = Ray4::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = world
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        (ray, hit)
+    }
+
+    #[test]
+    fn object_overrides() {
+        let sphere = "[[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"white\"\n";
+        let (_, plain) = hit_from_above(sphere);
+        assert_eq!(plain.max_depth(), None);
+        assert!(plain.front_face());
+
+        let (_, deep) = hit_from_above(&format!("{sphere}max_depth = 64\n"));
+        assert_eq!(deep.max_depth(), Some(64));
+        let (_, flipped) = hit_from_above(&format!("{sphere}flip_face = true\n"));
+        assert!(!flipped.front_face());
+    }
+
+    #[test]
+    fn dispersive_dielectric() {
+        let glass = |extra: &str| {
+            let (ray, hit) = hit_from_above(&format!(
+                "[materials.prism]\ntype = \"dielectric\"\nrefractive_index = 1.62\n{extra}\n\
+                 [[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"prism\"\n"
+            ));
+            let bsdf = hit.material().bsdf(&ray, &hit).unwrap();
+            bsdf.sample(&-ray.direction().as_unit()).unwrap()
+        };
+        // only dispersive glass splits light into its channels
+        assert!(glass("abbe_number = 36.4").channel.is_some());
+        assert!(glass("").channel.is_none());
+    }
+
+    #[test]
+    fn light_power() {
+        let emitted = |power: &str| {
+            let (ray, hit) = hit_from_above(&format!(
+                "[materials.bulb]\ntype = \"ColoredLight\"\ncolor = 0xffffff\n{power}\n\
+                 [[objects]]\ntype = \"Sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"bulb\"\n"
+            ));
+            hit.material()
+                .emitted(&ray, &hit, hit.u(), hit.v(), &hit.point())
+                .luminance()
+        };
+        let lumens = LightPower::Lumens(800.0).radiance(0.01);
+        assert!((emitted("lumens = 800\narea = 0.01") - lumens).abs() < 1e-6 * lumens);
+        let nits = LightPower::Nits(1000.0).radiance(1.0);
+        assert!((emitted("nits = 1000") - nits).abs() < 1e-6 * nits);
+        assert!((emitted("brightness = 3") - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn palette() {
+        let config = SAMPLE.to_string()
+            + r##"
+[palette]
+brick_red = "#a13d2d"
+sky = 0x87ceeb
+
+[materials.brick]
+type = "SolidColor"
+color = "$palette.brick_red"
+
+[materials.lamp]
+type = "ColoredLight"
+color = "$palette.sky"
+"##;
+        assert!(config.parse::<ConfigModel>().is_ok());
+
+        let table: toml::Table = config.parse().unwrap();
+        let applied = apply_palette(&table).unwrap();
+        assert!(!applied.contains_key("palette"));
+        assert_eq!(
+            applied["materials"]["lamp"]["color"].as_integer(),
+            Some(0x87ceeb)
+        );
+
+        let unknown = config.replace(r#""$palette.sky""#, r#""$palette.ocean""#);
+        assert!(unknown.parse::<ConfigModel>().is_err());
+        let invalid = config.replace("sky = 0x87ceeb", r#"sky = "blue""#);
+        assert!(invalid.parse::<ConfigModel>().is_err());
+    }
+
+    #[test]
+    fn material_extends() {
+        let config = SAMPLE.to_string()
+            + r#"
+[materials.base_metal]
+type = "Metal"
+albedo = 0xcccccc
+fuzz = 0.1
+
+[materials.rough_metal]
+extends = "base_metal"
+fuzz = 0.5
+
+[materials.gold_metal]
+extends = "rough_metal"
+albedo = 0xffd700
+"#;
+        assert!(config.parse::<ConfigModel>().is_ok());
+
+        let table: toml::Table = config.parse().unwrap();
+        let toml::Value::Table(materials) = &table["materials"] else {
+            panic!("config.materials must be a table");
+        };
+        let gold = resolve_material("gold_metal", materials).unwrap();
+        assert_eq!(gold["type"].as_str(), Some("Metal"));
+        assert_eq!(gold["fuzz"].as_float(), Some(0.5));
+        assert_eq!(gold["albedo"].as_integer(), Some(0xffd700));
+        assert!(!gold.contains_key("extends"));
+
+        let unknown = config.replace(r#"extends = "base_metal""#, r#"extends = "nothing""#);
+        assert!(unknown.parse::<ConfigModel>().is_err());
+        let cycle = config.replace(r#"extends = "base_metal""#, r#"extends = "gold_metal""#);
+        assert!(cycle.parse::<ConfigModel>().is_err());
+    }
+
+    #[test]
+    fn camera() {
+        use crate::test_support::BufferWriter;
+
+        let config = SAMPLE.to_string()
+            + r#"
+[camera]
+width = 64
+height = 48
+samples = 4
+center = [0, 1, 5]
+target = [0, 1, 0]
+vfov = "30deg"
+"#;
+        let config: ConfigModel = config.parse().unwrap();
+        let (mut buf, mut overridden) = (Vec::new(), Vec::new());
+        let camera = CameraBuilder::from_config(&config)
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+        assert_eq!(camera.dimensions(), (64, 48));
+        assert_eq!(camera.center(), Point3::new(0.0, 1.0, 5.0));
+
+        // settings made afterwards take precedence
+        let camera = CameraBuilder::from_config(&config)
+            .dimensions(32, 32)
+            .writer(Box::new(BufferWriter(&mut overridden)))
+            .build()
+            .unwrap();
+        assert_eq!(camera.dimensions(), (32, 32));
+
+        let base = SAMPLE.to_string() + "[camera]\nwidth = 64\nheight = 48\n";
+        assert!(base.parse::<ConfigModel>().is_ok());
+        assert!(base
+            .replace("height = 48\n", "")
+            .parse::<ConfigModel>()
+            .is_err());
+        assert!((base.clone() + "samples = 0\n")
+            .parse::<ConfigModel>()
+            .is_err());
+        assert!((base.clone() + "seed = 42\ntitle = \"Box\"\n")
+            .parse::<ConfigModel>()
+            .is_ok());
+        assert!((base.clone() + "seed = -1\n")
+            .parse::<ConfigModel>()
+            .is_err());
+    }
+
+    #[test]
+    fn units() {
+        let value = |s: &str| toml::Value::String(s.to_string());
+        assert!((value("2.5cm").parse_length("x").unwrap() - 0.025).abs() < 1e-12);
+        assert!((value("3 mm").parse_length("x").unwrap() - 0.003).abs() < 1e-12);
+        assert_eq!(value("2m").parse_length("x").unwrap(), 2.0);
+        assert_eq!(toml::Value::Float(1.5).parse_length("x").unwrap(), 1.5);
+        assert!(value("2.5").parse_length("x").is_err());
+        assert!(value("2.5in").parse_length("x").is_err());
+
+        let angle = |v: toml::Value| v.parse_angle("x").map(Angle::as_degrees);
+        assert_eq!(angle(toml::Value::Integer(90)).unwrap(), 90.0);
+        assert!((angle(value("15deg")).unwrap() - 15.0).abs() < 1e-12);
+        let radians = value("0.5rad").parse_angle("x").unwrap().as_radians();
+        assert!((radians - 0.5).abs() < 1e-12);
+        assert!(angle(value("15")).is_err());
+
+        // sizes may have units, but directions may not
+        let array = |v: [&str; 3]| toml::Value::Array(v.map(value).to_vec());
+        let radii = array(["1cm", "2cm", "3m"]).parse_lengths("x").unwrap();
+        assert!((radii - Vec3::new(0.01, 0.02, 3.0)).len() < 1e-12);
+        assert!(array(["1cm", "0m", "0m"]).parse_vec3("x").is_err());
+        let direction = toml::Value::Array(vec![
+            toml::Value::Integer(0),
+            toml::Value::Float(1.0),
+            toml::Value::Integer(0),
+        ]);
+        assert_eq!(direction.parse_vec3("x").unwrap(), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn asset_paths() {
+        let dir = std::env::temp_dir().join(format!("asset-paths-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("scenes")).unwrap();
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::copy("assets/earth.png", dir.join("shared/earth.png")).unwrap();
+
+        let scene = |asset_paths: &str| {
+            format!(
+                "{asset_paths}\nobjects = []\n[materials]\n\
+                 [textures.earth]\ntype = \"Image\"\npath = \"earth.png\"\n"
+            )
+        };
+        let path = dir.join("scenes/scene.toml");
+        std::fs::write(&path, scene("")).unwrap();
+        let missing = ConfigModel::open(&path);
+        std::fs::write(&path, scene(r#"asset_paths = ["../shared"]"#)).unwrap();
+        let found = ConfigModel::open(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(missing.is_err());
+        found.unwrap();
+    }
+
+    #[test]
+    fn corrupt_image() {
+        let path = std::env::temp_dir().join(format!("corrupt-{}.png", std::process::id()));
+        std::fs::write(&path, b"not a png").unwrap();
+        let config = format!(
+            "objects = []\n[materials]\n[textures.broken]\ntype = \"Image\"\npath = {:?}\n",
+            path.display().to_string()
+        );
+        let error = config.parse::<ConfigModel>().unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        let message = format!("{error}");
+        assert!(message.contains("Failed to load image"), "{message}");
+    }
 }
@@ -0,0 +1,274 @@
+//! Batch rendering of labelled images for training and evaluating machine-learning models.
+//!
+//! Each frame is rendered from a random viewpoint around a target, under one of a set of
+//! lighting setups, and written to `<index>.png` alongside a `<index>.json` label file:
+//!
+//! ```json
+//! {
+//!   "image": "00000.png",
+//!   "width": 256,
+//!   "height": 256,
+//!   "camera_to_world": [[...], [...], [...], [...]],
+//!   "intrinsics": [[fx, 0, cx], [0, fy, cy], [0, 0, 1]],
+//!   "lighting": 0,
+//!   "objects": [{"id": 0, "bbox": [x_min, y_min, x_max, y_max]}]
+//! }
+//! ```
+//!
+//! Object IDs are indices into the list of objects passed to [`DatasetGenerator::generate`].
+//! Bounding boxes are in pixels, and are the projection of each object's 3D bounding box
+//! clipped to the image; objects are listed even if they are hidden behind other objects.
+
+use std::{error::Error, f64::consts::TAU, fs::File, io::Write, path::Path, rc::Rc};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    export::PngWriter, hittable::HittableVec, Angle, Background, Camera, CameraBuilder, Hittable,
+    Point3, Vec3,
+};
+
+/// A background and set of light sources which a frame can be rendered under.
+#[derive(Debug, Clone)]
+pub struct LightingSetup {
+    pub background: Background,
+    /// Emissive objects, which are added to the scene and sampled as lights.
+    pub lights: Vec<Rc<dyn Hittable>>,
+}
+
+#[derive(Debug)]
+#[must_use]
+pub struct DatasetGenerator<'a> {
+    /// Settings shared by every frame; the camera's position and target are replaced.
+    camera: CameraBuilder<'a>,
+    /// The point every frame looks towards
+    target: Point3,
+    /// The range of distances of the camera from `target`
+    distance: (f64, f64),
+    /// The range of angles of the camera above the horizon, in **radians**
+    elevation: (f64, f64),
+    lighting: Vec<LightingSetup>,
+    seed: u64,
+}
+
+impl<'a> DatasetGenerator<'a> {
+    /// Creates a generator which renders with the settings of `camera`, looking towards
+    /// `target` from anywhere between the horizon and 60° above it.
+    pub fn new(camera: CameraBuilder<'a>, target: Point3) -> Self {
+        Self {
+            camera,
+            target,
+            distance: (1.0, 1.0),
+            elevation: (0.0, 60.0_f64.to_radians()),
+            lighting: Vec::new(),
+            seed: 0,
+        }
+    }
+
+    /// Sets the range of distances of the camera from the target.
+    pub fn distance(mut self, min: f64, max: f64) -> Self {
+        assert!(0.0 < min && min <= max, "Invalid distance range");
+        self.distance = (min, max);
+        self
+    }
+
+    /// Sets the range of angles of the camera above the horizon.
+    pub fn elevation(mut self, min: Angle, max: Angle) -> Self {
+        let (min, max) = (min.as_degrees(), max.as_degrees());
+        assert!(
+            -90.0 < min && min <= max && max < 90.0,
+            "Invalid elevation range (expected -90° < min <= max < 90°)"
+        );
+        self.elevation = (min.to_radians(), max.to_radians());
+        self
+    }
+
+    /// Adds a lighting setup; each frame uses one at random. Without any, frames use the
+    /// camera's background and no lights.
+    pub fn lighting(mut self, setup: LightingSetup) -> Self {
+        self.lighting.push(setup);
+        self
+    }
+
+    /// Sets the seed used to choose viewpoints and lighting, so a dataset can be regenerated.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Renders `frames` images of `objects` into `output_dir`, which must already exist.
+    pub fn generate(
+        &self,
+        output_dir: &Path,
+        frames: u32,
+        objects: &[Rc<dyn Hittable>],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        for index in 0..frames {
+            let azimuth = rng.random_range(0.0..TAU);
+            let elevation = rng.random_range(self.elevation.0..=self.elevation.1);
+            let distance = rng.random_range(self.distance.0..=self.distance.1);
+            let lighting =
+                (!self.lighting.is_empty()).then(|| rng.random_range(0..self.lighting.len()));
+
+            let direction = Vec3::new(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                elevation.cos() * azimuth.cos(),
+            );
+
+            let mut world: HittableVec = objects.iter().cloned().collect();
+            let mut lights = HittableVec::new();
+            let mut camera = self
+                .camera
+                .duplicate()
+                .camera_center(self.target + direction * distance)
+                .camera_target(self.target)
+                .metadata("Dataset frame", index);
+            if let Some(setup) = lighting.map(|i| &self.lighting[i]) {
+                for light in &setup.lights {
+                    world.add(Rc::clone(light));
+                    lights.add(Rc::clone(light));
+                }
+                camera = camera.background(setup.background.clone());
+            }
+
+            let name = format!("{index:05}");
+            let mut image = File::create(output_dir.join(format!("{name}.png")))?;
+            let mut camera = camera
+                .writer(PngWriter::new(&mut image).into_box())
+                .build()?;
+            camera.render(&world, lights.hittable());
+
+            let mut label = File::create(output_dir.join(format!("{name}.json")))?;
+            write_label(&mut label, &name, &camera, lighting, objects)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_label(
+    out: &mut dyn Write,
+    name: &str,
+    camera: &Camera,
+    lighting: Option<usize>,
+    objects: &[Rc<dyn Hittable>],
+) -> std::io::Result<()> {
+    let matrix = |rows: &[&[f64]]| {
+        let rows: Vec<String> = rows
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = row.iter().map(f64::to_string).collect();
+                format!("[{}]", values.join(", "))
+            })
+            .collect();
+        format!("[{}]", rows.join(", "))
+    };
+
+    let (width, height) = camera.dimensions();
+    let camera_to_world = camera.camera_to_world();
+    let intrinsics = camera.intrinsics();
+
+    let mut boxes = Vec::new();
+    for (id, object) in objects.iter().enumerate() {
+        if let Some([x_min, y_min, x_max, y_max]) = image_bounds(camera, object.as_ref()) {
+            boxes.push(format!(
+                r#"{{"id": {id}, "bbox": [{x_min}, {y_min}, {x_max}, {y_max}]}}"#
+            ));
+        }
+    }
+
+    writeln!(out, "{{")?;
+    writeln!(out, r#"  "image": "{name}.png","#)?;
+    writeln!(out, r#"  "width": {width},"#)?;
+    writeln!(out, r#"  "height": {height},"#)?;
+    writeln!(
+        out,
+        r#"  "camera_to_world": {},"#,
+        matrix(&camera_to_world.each_ref().map(|r| r.as_slice()))
+    )?;
+    writeln!(
+        out,
+        r#"  "intrinsics": {},"#,
+        matrix(&intrinsics.each_ref().map(|r| r.as_slice()))
+    )?;
+    match lighting {
+        Some(i) => writeln!(out, r#"  "lighting": {i},"#)?,
+        None => writeln!(out, r#"  "lighting": null,"#)?,
+    }
+    writeln!(out, r#"  "objects": [{}]"#, boxes.join(", "))?;
+    writeln!(out, "}}")
+}
+
+/// The bounding rectangle `[x_min, y_min, x_max, y_max]` of `object` in the image, if it is
+/// in view. Objects which extend behind the camera are left out, as their projection
+/// is unbounded.
+fn image_bounds(camera: &Camera, object: &dyn Hittable) -> Option<[f64; 4]> {
+    let bbox = object.bounding_box()?;
+    let (width, height) = camera.dimensions();
+
+    let mut bounds = [
+        f64::INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    for x in [*bbox.x().start(), *bbox.x().end()] {
+        for y in [*bbox.y().start(), *bbox.y().end()] {
+            for z in [*bbox.z().start(), *bbox.z().end()] {
+                let p = camera.project(&Point3::new(x, y, z))?;
+                bounds[0] = bounds[0].min(p.x());
+                bounds[1] = bounds[1].min(p.y());
+                bounds[2] = bounds[2].max(p.x());
+                bounds[3] = bounds[3].max(p.y());
+            }
+        }
+    }
+
+    let [x_min, y_min, x_max, y_max] = bounds;
+    let clipped = [
+        x_min.max(0.0),
+        y_min.max(0.0),
+        x_max.min(f64::from(width)),
+        y_max.min(f64::from(height)),
+    ];
+    (clipped[0] < clipped[2] && clipped[1] < clipped[3]).then_some(clipped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{export::PpmWriter, hittable::Sphere, material::Lambertian, Color, Material};
+
+    #[test]
+    fn centered_object_is_boxed() {
+        let mut sink = Vec::new();
+        let camera = CameraBuilder::new()
+            .dimensions(100, 100)
+            .vfov(Angle::degrees(90.0))
+            .camera_center(Point3::new(0.0, 0.0, 2.0))
+            .camera_target(Point3::origin())
+            .writer(PpmWriter::new(&mut sink).into_box())
+            .build()
+            .unwrap();
+
+        let sphere = Sphere::stationary(
+            Point3::origin(),
+            1.0,
+            Lambertian::solid(Color::white()).into_mat(),
+        );
+        // the near face of the sphere's bounding box is 1 unit away, and exactly fills the view
+        let [x_min, y_min, x_max, y_max] = image_bounds(&camera, &sphere).unwrap();
+        assert!(x_min.abs() < 1e-9 && y_min.abs() < 1e-9);
+        assert!((x_max - 100.0).abs() < 1e-9 && (y_max - 100.0).abs() < 1e-9);
+
+        let behind = Sphere::stationary(
+            Point3::new(0.0, 0.0, 5.0),
+            1.0,
+            Lambertian::solid(Color::white()).into_mat(),
+        );
+        assert!(image_bounds(&camera, &behind).is_none());
+    }
+}
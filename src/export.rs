@@ -5,6 +5,74 @@ use crate::Color;
 pub trait ImageWriter: std::fmt::Debug {
     fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>>;
     fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>>;
+
+    /// Called as rendering progresses, with the pixels rendered so far in row-major order.
+    /// When rendering in [tiles](crate::CameraBuilder::tiles), every pixel is given, with
+    /// those yet to be rendered left black. Most writers only output the finished image,
+    /// and ignore this.
+    fn preview(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        let _ = colors;
+        Ok(())
+    }
+
+    /// Called after [`ImageWriter::write`] with the settings the image was rendered with.
+    /// Formats which can store text alongside the image should embed it.
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        let _ = metadata;
+        Ok(())
+    }
+}
+
+/// Key-value descriptions of how an image was rendered, such as its sample count, camera
+/// parameters, and render time, so that an image can be traced back to the settings
+/// that produced it. Keys are kept in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct RenderMetadata(Vec<(String, String)>);
+
+impl RenderMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of `key`, replacing any existing value.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl ToString) {
+        let key = key.into();
+        let value = value.to_string();
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Encodes an entire image as a PNG in memory. Pixels missing from the end of `colors`
+/// are left black.
+pub fn encode_png(width: u32, height: u32, colors: &[Color]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut buf = vec![0; (width * height * 3) as usize];
+    for (px, color) in buf.chunks_exact_mut(3).zip(colors) {
+        px.copy_from_slice(&color.as_rgb_ints());
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&buf)?;
+    writer.finish()?;
+    Ok(out)
 }
 
 pub struct PpmWriter<'a>(&'a mut dyn Write);
@@ -84,4 +152,932 @@ impl ImageWriter for PngWriter<'_> {
             panic!();
         }
     }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        if let PngWriter::Ready(w) = self {
+            // tEXt chunks may come after the image data, as long as they precede IEND
+            for (key, value) in metadata.iter() {
+                w.write_text_chunk(&png::text_metadata::TEXtChunk::new(key, value))?;
+            }
+            Ok(())
+        } else {
+            panic!();
+        }
+    }
+}
+
+/// Writes a 16-bit greyscale PNG from the red channel of each pixel, without gamma
+/// correction, for depth maps and other data which isn't a color.
+///
+/// Values are multiplied by `scale` and clamped to `0..=65535`; the default scale maps
+/// `0.0..=1.0` onto the full range. For metric depth, a scale of `1000.0` stores
+/// millimetres when the scene is in metres.
+pub enum DepthPngWriter<'a> {
+    Waiting(Option<&'a mut dyn Write>, f64),
+    Ready(png::Writer<&'a mut dyn Write>, f64),
+}
+
+impl<'a> DepthPngWriter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Self {
+        Self::Waiting(Some(output), f64::from(u16::MAX))
+    }
+
+    pub fn with_scale(output: &'a mut dyn Write, scale: f64) -> Self {
+        Self::Waiting(Some(output), scale)
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+}
+
+impl std::fmt::Debug for DepthPngWriter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DepthPngWriter").finish()
+    }
+}
+
+impl ImageWriter for DepthPngWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        if let DepthPngWriter::Waiting(w, scale) = self {
+            let mut encoder = png::Encoder::new(std::mem::take(w).unwrap(), width, height);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Sixteen);
+            let writer = encoder.write_header()?;
+            *self = DepthPngWriter::Ready(writer, *scale);
+            Ok(())
+        } else {
+            panic!();
+        }
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        if let DepthPngWriter::Ready(w, scale) = self {
+            // 16-bit samples are stored big-endian
+            let buf: Vec<u8> = colors
+                .iter()
+                .flat_map(|c| {
+                    let value = (c.r() * *scale).round().clamp(0.0, f64::from(u16::MAX));
+                    (value as u16).to_be_bytes()
+                })
+                .collect();
+            w.write_image_data(&buf)?;
+            Ok(())
+        } else {
+            panic!();
+        }
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        if let DepthPngWriter::Ready(w, _) = self {
+            for (key, value) in metadata.iter() {
+                w.write_text_chunk(&png::text_metadata::TEXtChunk::new(key, value))?;
+            }
+            Ok(())
+        } else {
+            panic!();
+        }
+    }
+}
+
+/// Writes the raw linear color buffer as 32-bit floats, without gamma correction, clamping,
+/// or quantization, for analysis or tone mapping by other tools.
+///
+/// The output is a [Portable FloatMap](https://www.pauldebevec.com/Research/HDR/PFM/):
+/// a short text header (`PF`, the dimensions, and a negative scale marking little-endian data)
+/// followed by RGB triples, with rows stored from the bottom of the image up.
+pub struct PfmWriter<'a> {
+    output: &'a mut dyn Write,
+    width: u32,
+}
+
+impl<'a> PfmWriter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Self {
+        Self { output, width: 0 }
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+}
+
+impl std::fmt::Debug for PfmWriter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PfmWriter").finish()
+    }
+}
+
+impl ImageWriter for PfmWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.width = width;
+        write!(self.output, "PF\n{width} {height}\n-1.0\n")?;
+        Ok(())
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        let mut buf: Vec<u8> = Vec::with_capacity(colors.len() * 12);
+        for row in colors.chunks_exact(self.width as usize).rev() {
+            for color in row {
+                for channel in [color.r(), color.g(), color.b()] {
+                    buf.extend_from_slice(&(channel as f32).to_le_bytes());
+                }
+            }
+        }
+        self.output.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+/// Writes an uncompressed 24-bit Windows bitmap.
+pub struct BmpWriter<'a> {
+    output: &'a mut dyn Write,
+    dimensions: (u32, u32),
+}
+
+impl<'a> BmpWriter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Self {
+        Self {
+            output,
+            dimensions: (0, 0),
+        }
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+}
+
+impl std::fmt::Debug for BmpWriter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BmpWriter").finish()
+    }
+}
+
+impl ImageWriter for BmpWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        // the headers include the file size, so are written along with the pixels
+        self.dimensions = (width, height);
+        Ok(())
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        let (width, height) = self.dimensions;
+        // each row is padded to a multiple of 4 bytes
+        let row_size = (width * 3).div_ceil(4) * 4;
+        let image_size = row_size * height;
+        let header_size = 14 + 40;
+
+        let mut out = Vec::with_capacity((header_size + image_size) as usize);
+        // file header
+        out.extend_from_slice(b"BM");
+        out.extend_from_slice(&(header_size + image_size).to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&header_size.to_le_bytes());
+        // BITMAPINFOHEADER
+        out.extend_from_slice(&40_u32.to_le_bytes());
+        out.extend_from_slice(&(width as i32).to_le_bytes());
+        out.extend_from_slice(&(height as i32).to_le_bytes());
+        out.extend_from_slice(&1_u16.to_le_bytes()); // color planes
+        out.extend_from_slice(&24_u16.to_le_bytes()); // bits per pixel
+        out.extend_from_slice(&0_u32.to_le_bytes()); // no compression
+        out.extend_from_slice(&image_size.to_le_bytes());
+        out.extend_from_slice(&2835_i32.to_le_bytes()); // 72 DPI, in pixels per metre
+        out.extend_from_slice(&2835_i32.to_le_bytes());
+        out.extend_from_slice(&[0; 8]); // palette sizes
+
+        // rows are stored bottom-up, in BGR order
+        for row in colors.chunks_exact(width as usize).rev() {
+            for color in row {
+                let [r, g, b] = color.as_gamma_corrected().as_rgb_ints();
+                out.extend_from_slice(&[b, g, r]);
+            }
+            out.resize(out.len() + (row_size - width * 3) as usize, 0);
+        }
+
+        self.output.write_all(&out)?;
+        Ok(())
+    }
+}
+
+/// Writes an uncompressed 8-bit RGB baseline TIFF.
+pub struct TiffWriter<'a> {
+    output: &'a mut dyn Write,
+    dimensions: (u32, u32),
+}
+
+impl<'a> TiffWriter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Self {
+        Self {
+            output,
+            dimensions: (0, 0),
+        }
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+}
+
+impl std::fmt::Debug for TiffWriter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TiffWriter").finish()
+    }
+}
+
+impl ImageWriter for TiffWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.dimensions = (width, height);
+        Ok(())
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        const SHORT: u16 = 3;
+        const LONG: u16 = 4;
+        const RATIONAL: u16 = 5;
+        const ENTRIES: u32 = 13;
+
+        let (width, height) = self.dimensions;
+        let image_size = width * height * 3;
+
+        // layout: header, image file directory, out-of-line tag values, then the pixels
+        let ifd_offset = 8;
+        let bits_offset = ifd_offset + 2 + ENTRIES * 12 + 4;
+        let x_res_offset = bits_offset + 6;
+        let y_res_offset = x_res_offset + 8;
+        let pixels_offset = y_res_offset + 8;
+
+        let mut out = Vec::with_capacity((pixels_offset + image_size) as usize);
+        out.extend_from_slice(b"II*\0");
+        out.extend_from_slice(&ifd_offset.to_le_bytes());
+
+        out.extend_from_slice(&(ENTRIES as u16).to_le_bytes());
+        let mut entry = |tag: u16, kind: u16, count: u32, value: u32| {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            // SHORT values are left-justified in the value field
+            if kind == SHORT && count == 1 {
+                out.extend_from_slice(&(value as u16).to_le_bytes());
+                out.extend_from_slice(&[0; 2]);
+            } else {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        // entries must be sorted by tag
+        entry(256, LONG, 1, width); // ImageWidth
+        entry(257, LONG, 1, height); // ImageLength
+        entry(258, SHORT, 3, bits_offset); // BitsPerSample
+        entry(259, SHORT, 1, 1); // Compression: none
+        entry(262, SHORT, 1, 2); // PhotometricInterpretation: RGB
+        entry(273, LONG, 1, pixels_offset); // StripOffsets
+        entry(277, SHORT, 1, 3); // SamplesPerPixel
+        entry(278, LONG, 1, height); // RowsPerStrip
+        entry(279, LONG, 1, image_size); // StripByteCounts
+        entry(282, RATIONAL, 1, x_res_offset); // XResolution
+        entry(283, RATIONAL, 1, y_res_offset); // YResolution
+        entry(284, SHORT, 1, 1); // PlanarConfiguration: interleaved
+        entry(296, SHORT, 1, 2); // ResolutionUnit: inches
+        out.extend_from_slice(&0_u32.to_le_bytes()); // no further directories
+
+        for _ in 0..3 {
+            out.extend_from_slice(&8_u16.to_le_bytes());
+        }
+        for _ in 0..2 {
+            // 72 DPI
+            out.extend_from_slice(&72_u32.to_le_bytes());
+            out.extend_from_slice(&1_u32.to_le_bytes());
+        }
+
+        for color in colors {
+            out.extend_from_slice(&color.as_gamma_corrected().as_rgb_ints());
+        }
+
+        self.output.write_all(&out)?;
+        Ok(())
+    }
+}
+
+/// Sends the same image to several writers, so that one render can produce, say, a PNG file
+/// and a PPM on stdout, or a quick preview alongside an archival copy.
+///
+/// Every writer is given every call, even after one of them fails; the first error is
+/// returned once all have been called.
+///
+/// # Examples
+/// ```
+/// use raytracing::export::{ImageWriter, PngWriter, PpmWriter, TeeWriter};
+/// use raytracing::Color;
+///
+/// let (mut png, mut ppm) = (Vec::new(), Vec::new());
+/// let mut tee = TeeWriter::new()
+///     .with(PngWriter::new(&mut png).into_box())
+///     .with(PpmWriter::new(&mut ppm).into_box());
+/// tee.write_header(1, 1).unwrap();
+/// tee.write(&[Color::white()]).unwrap();
+/// drop(tee);
+///
+/// assert_eq!(ppm, b"P3\n1 1\n255\n255 255 255\n");
+/// assert!(png.starts_with(b"\x89PNG"));
+/// ```
+#[derive(Debug, Default)]
+pub struct TeeWriter<'a> {
+    writers: Vec<Box<dyn ImageWriter + 'a>>,
+}
+
+impl<'a> TeeWriter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a writer to send the image to.
+    pub fn with(mut self, writer: Box<dyn ImageWriter + 'a>) -> Self {
+        self.writers.push(writer);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.writers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+
+    /// Calls `f` on every writer, returning the first error.
+    fn each(
+        &mut self,
+        mut f: impl FnMut(&mut dyn ImageWriter) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.writers
+            .iter_mut()
+            .map(|writer| f(writer.as_mut()))
+            .fold(Ok(()), Result::and)
+    }
+}
+
+impl<'a> FromIterator<Box<dyn ImageWriter + 'a>> for TeeWriter<'a> {
+    fn from_iter<T: IntoIterator<Item = Box<dyn ImageWriter + 'a>>>(iter: T) -> Self {
+        Self {
+            writers: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl ImageWriter for TeeWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.each(|writer| writer.write_header(width, height))
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.each(|writer| writer.write(colors))
+    }
+
+    fn preview(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.each(|writer| writer.preview(colors))
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        self.each(|writer| writer.write_metadata(metadata))
+    }
+}
+
+/// Collects the frames of an animation, such as a turntable, and encodes them as an
+/// animated PNG.
+///
+/// Each frame is rendered by its own [`Camera`](crate::Camera), given a writer from
+/// [`ApngWriter::frame`]. All frames must have the same dimensions.
+#[derive(Debug)]
+pub struct ApngWriter {
+    frames_per_second: u16,
+    /// How many times the animation plays; 0 loops forever.
+    plays: u32,
+    dimensions: Option<(u32, u32)>,
+    /// The RGB bytes of each frame
+    frames: Vec<Vec<u8>>,
+}
+
+impl ApngWriter {
+    pub fn new(frames_per_second: u16) -> Self {
+        assert!(frames_per_second > 0, "Invalid frame rate (expected > 0)");
+        Self {
+            frames_per_second,
+            plays: 0,
+            dimensions: None,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets how many times the animation plays before stopping; by default it loops forever.
+    pub fn plays(mut self, plays: u32) -> Self {
+        self.plays = plays;
+        self
+    }
+
+    /// A writer which appends the image it is given to the animation as a new frame.
+    pub fn frame(&mut self) -> Box<dyn ImageWriter + '_> {
+        Box::new(ApngFrame(self))
+    }
+
+    /// Encodes the animation to `output`.
+    pub fn finish(self, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let Some((width, height)) = self.dimensions else {
+            return Err("An animation must have at least one frame".into());
+        };
+
+        let mut encoder = png::Encoder::new(output, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, self.plays)?;
+        encoder.set_frame_delay(1, self.frames_per_second)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(frame)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ApngFrame<'a>(&'a mut ApngWriter);
+
+impl ImageWriter for ApngFrame<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        match self.0.dimensions {
+            Some(dimensions) if dimensions != (width, height) => Err(format!(
+                "Frame dimensions {width}x{height} differ from the animation's {}x{}",
+                dimensions.0, dimensions.1
+            )
+            .into()),
+            _ => {
+                self.0.dimensions = Some((width, height));
+                Ok(())
+            }
+        }
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        let frame = colors.iter().flat_map(|c| c.as_rgb_ints()).collect();
+        self.0.frames.push(frame);
+        Ok(())
+    }
+}
+
+/// Collects several images of the same scene, such as the beauty pass, normals, depth and
+/// object IDs, and encodes them as the named parts of a single multi-part, tiled
+/// [OpenEXR](https://openexr.com/en/latest/OpenEXRFileLayout.html) file for compositing.
+///
+/// Each layer is rendered by its own [`Camera`](crate::Camera), given a writer from
+/// [`ExrWriter::layer`]. All layers must have the same dimensions. Channels are stored as
+/// uncompressed 32-bit floats, and the metadata each layer is rendered with is stored as
+/// string attributes in its header.
+#[derive(Debug)]
+pub struct ExrWriter {
+    tile_size: u32,
+    dimensions: Option<(u32, u32)>,
+    layers: Vec<ExrLayer>,
+}
+
+#[derive(Debug)]
+struct ExrLayer {
+    name: String,
+    /// The names of the channels taken from the red, green and blue components, in order
+    channels: Vec<String>,
+    pixels: Vec<Color>,
+    metadata: RenderMetadata,
+}
+
+/// The attributes every part's header defines; metadata can't replace these.
+const EXR_ATTRIBUTES: [&str; 12] = [
+    "channels",
+    "chunkCount",
+    "compression",
+    "dataWindow",
+    "displayWindow",
+    "lineOrder",
+    "name",
+    "pixelAspectRatio",
+    "screenWindowCenter",
+    "screenWindowWidth",
+    "tiles",
+    "type",
+];
+
+impl ExrWriter {
+    pub fn new() -> Self {
+        Self {
+            tile_size: 64,
+            dimensions: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Sets the width and height of the tiles the image is stored in; by default, 64.
+    pub fn tile_size(mut self, tile_size: u32) -> Self {
+        assert!(tile_size > 0, "Invalid tile size (expected > 0)");
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// A writer which adds the image it is given as a new part named `name`. Up to three
+    /// `channels` are taken from the red, green and blue components of each pixel, in order;
+    /// for example `["R", "G", "B"]` for a beauty pass, or `["Z"]` for depth.
+    pub fn layer(&mut self, name: &str, channels: &[&str]) -> Box<dyn ImageWriter + '_> {
+        assert!(
+            (1..=3).contains(&channels.len()),
+            "Invalid channel count (expected 1 to 3)"
+        );
+        assert!(
+            self.layers.iter().all(|layer| layer.name != name),
+            "Duplicate layer name {name:?}"
+        );
+        self.layers.push(ExrLayer {
+            name: name.to_string(),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            pixels: Vec::new(),
+            metadata: RenderMetadata::new(),
+        });
+        let index = self.layers.len() - 1;
+        Box::new(ExrPart {
+            writer: self,
+            index,
+        })
+    }
+
+    /// Encodes the layers to `output`.
+    pub fn finish(self, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let Some((width, height)) = self.dimensions else {
+            return Err("An EXR file must have at least one layer".into());
+        };
+        if let Some(layer) = self
+            .layers
+            .iter()
+            .find(|layer| layer.pixels.len() != (width * height) as usize)
+        {
+            return Err(format!("Layer {:?} was never written", layer.name).into());
+        }
+
+        let tile = self.tile_size;
+        let (tiles_x, tiles_y) = (width.div_ceil(tile), height.div_ceil(tile));
+        let chunk_count = tiles_x * tiles_y;
+
+        let mut long_names = false;
+        let mut headers = Vec::new();
+        for layer in &self.layers {
+            long_names |= exr_header(&mut headers, layer, (width, height), tile, chunk_count);
+        }
+        // an empty header ends the list
+        headers.push(0);
+
+        let mut chunks = Vec::new();
+        for (part, layer) in self.layers.iter().enumerate() {
+            // channels are stored in alphabetical order
+            let mut channels: Vec<(&String, usize)> = layer.channels.iter().zip(0..).collect();
+            channels.sort();
+
+            for ty in 0..tiles_y {
+                for tx in 0..tiles_x {
+                    let xs = tx * tile..((tx + 1) * tile).min(width);
+                    let ys = ty * tile..((ty + 1) * tile).min(height);
+
+                    let mut data = Vec::new();
+                    for y in ys {
+                        for &(_, component) in &channels {
+                            for x in xs.clone() {
+                                let color = layer.pixels[(y * width + x) as usize];
+                                let value = [color.r(), color.g(), color.b()][component];
+                                data.extend_from_slice(&(value as f32).to_le_bytes());
+                            }
+                        }
+                    }
+
+                    let mut chunk = Vec::with_capacity(data.len() + 24);
+                    // part number, tile coordinates, then mipmap level (always 0)
+                    for n in [part as u32, tx, ty, 0, 0, data.len() as u32] {
+                        chunk.extend_from_slice(&n.to_le_bytes());
+                    }
+                    chunk.extend_from_slice(&data);
+                    chunks.push(chunk);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+        // version 2, multi-part
+        let flags = 2 | 0x1000 | if long_names { 0x400 } else { 0 };
+        out.extend_from_slice(&(flags as u32).to_le_bytes());
+        out.extend_from_slice(&headers);
+
+        // one offset table per part, which together list every chunk in order
+        let mut offset = (out.len() + chunks.len() * 8) as u64;
+        for chunk in &chunks {
+            out.extend_from_slice(&offset.to_le_bytes());
+            offset += chunk.len() as u64;
+        }
+        for chunk in &chunks {
+            out.extend_from_slice(chunk);
+        }
+
+        output.write_all(&out)?;
+        Ok(())
+    }
+}
+
+impl Default for ExrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the header of one part to `out`.
+/// Returns whether any of its names are too long for the standard 31 characters.
+fn exr_header(
+    out: &mut Vec<u8>,
+    layer: &ExrLayer,
+    (width, height): (u32, u32),
+    tile_size: u32,
+    chunk_count: u32,
+) -> bool {
+    let mut long_names = false;
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        long_names |= name.len() > 31;
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        out.extend_from_slice(kind.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        out.extend_from_slice(value);
+    };
+
+    let mut channels = Vec::new();
+    let mut names: Vec<&String> = layer.channels.iter().collect();
+    names.sort();
+    for name in names {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        // 32-bit float, not perceptually linear, and sampled at every pixel
+        channels.extend_from_slice(&2_u32.to_le_bytes());
+        channels.extend_from_slice(&[0; 4]);
+        channels.extend_from_slice(&1_u32.to_le_bytes());
+        channels.extend_from_slice(&1_u32.to_le_bytes());
+    }
+    channels.push(0);
+
+    let window: Vec<u8> = [0, 0, width - 1, height - 1]
+        .iter()
+        .flat_map(|n| n.to_le_bytes())
+        .collect();
+    let tiles: Vec<u8> = [tile_size.to_le_bytes(), tile_size.to_le_bytes()]
+        .concat()
+        .into_iter()
+        // a single resolution level
+        .chain([0])
+        .collect();
+
+    // attributes are conventionally sorted by name
+    attribute("channels", "chlist", &channels);
+    attribute("chunkCount", "int", &chunk_count.to_le_bytes());
+    attribute("compression", "compression", &[0]);
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("name", "string", layer.name.as_bytes());
+    attribute("pixelAspectRatio", "float", &1.0_f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1.0_f32.to_le_bytes());
+    attribute("tiles", "tiledesc", &tiles);
+    attribute("type", "string", b"tiledimage");
+    for (key, value) in layer.metadata.iter() {
+        if !EXR_ATTRIBUTES.contains(&key) {
+            attribute(key, "string", value.as_bytes());
+        }
+    }
+    out.push(0);
+
+    long_names || layer.channels.iter().any(|name| name.len() > 31)
+}
+
+#[derive(Debug)]
+struct ExrPart<'a> {
+    writer: &'a mut ExrWriter,
+    index: usize,
+}
+
+impl ImageWriter for ExrPart<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        match self.writer.dimensions {
+            Some(dimensions) if dimensions != (width, height) => Err(format!(
+                "Layer dimensions {width}x{height} differ from the image's {}x{}",
+                dimensions.0, dimensions.1
+            )
+            .into()),
+            _ => {
+                self.writer.dimensions = Some((width, height));
+                Ok(())
+            }
+        }
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.writer.layers[self.index].pixels = colors.to_vec();
+        Ok(())
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        self.writer.layers[self.index].metadata = metadata.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apng_frames() {
+        let mut animation = ApngWriter::new(24);
+        for color in [Color::red(), Color::green(), Color::blue()] {
+            let mut frame = animation.frame();
+            frame.write_header(2, 2).unwrap();
+            frame.write(&[color; 4]).unwrap();
+        }
+        assert!(animation.frame().write_header(3, 2).is_err());
+
+        let mut out = Vec::new();
+        animation.finish(&mut out).unwrap();
+
+        let reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let control = reader.info().animation_control().unwrap();
+        assert_eq!(control.num_frames, 3);
+        assert_eq!(control.num_plays, 0);
+    }
+
+    #[test]
+    fn exr_layers() {
+        let mut exr = ExrWriter::new().tile_size(2);
+        for (name, channels, value) in [
+            ("beauty", &["R", "G", "B"][..], 0.5),
+            ("depth", &["Z"], 4.0),
+        ] {
+            let mut layer = exr.layer(name, channels);
+            layer.write_header(3, 2).unwrap();
+            layer.write(&[Color::over_white(value); 6]).unwrap();
+        }
+        assert!(exr.layer("normal", &["X"]).write_header(2, 2).is_err());
+        // the rejected layer was never written
+        let mut out = Vec::new();
+        assert!(exr.finish(&mut out).is_err());
+
+        let mut exr = ExrWriter::new().tile_size(2);
+        let mut layer = exr.layer("depth", &["Z"]);
+        layer.write_header(3, 2).unwrap();
+        layer.write(&[Color::over_white(4.0); 6]).unwrap();
+        drop(layer);
+        exr.finish(&mut out).unwrap();
+
+        assert_eq!(out[..4], [0x76, 0x2f, 0x31, 0x01]);
+        // version 2, multi-part
+        assert_eq!(u32::from_le_bytes(out[4..8].try_into().unwrap()), 0x1002);
+
+        // a 3x2 image in 2x2 tiles has two chunks; the second is a 1x2 tile
+        let offset = |i: usize| {
+            let at = out.len() - 8 * 2 - (24 + 16) - (24 + 8) + 8 * i;
+            u64::from_le_bytes(out[at..at + 8].try_into().unwrap()) as usize
+        };
+        let last = &out[offset(1)..];
+        let ints: Vec<u32> = last[..24]
+            .chunks(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(ints, [0, 1, 0, 0, 0, 8]);
+        assert_eq!(
+            last[24..],
+            [4.0_f32.to_le_bytes(), 4.0_f32.to_le_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn tee_writer() {
+        let mut animation = ApngWriter::new(24);
+        animation.frame().write_header(3, 2).unwrap();
+
+        let mut ppm = Vec::new();
+        let mut tee: TeeWriter = [animation.frame(), PpmWriter::new(&mut ppm).into_box()]
+            .into_iter()
+            .collect();
+        assert_eq!(tee.len(), 2);
+        // the frame rejects the header, but the PPM is still written
+        assert!(tee.write_header(1, 1).is_err());
+        tee.write(&[Color::black()]).unwrap();
+        drop(tee);
+        assert_eq!(ppm, b"P3\n1 1\n255\n0 0 0\n");
+    }
+
+    #[test]
+    fn png_metadata() {
+        let mut metadata = RenderMetadata::new();
+        metadata.insert("Title", "Cornell Box");
+        metadata.insert("Samples per pixel", 100);
+        metadata.insert("Title", "Cornell Box 2");
+
+        let mut out = Vec::new();
+        let mut writer = PngWriter::new(&mut out);
+        writer.write_header(1, 1).unwrap();
+        writer.write(&[Color::white()]).unwrap();
+        writer.write_metadata(&metadata).unwrap();
+        drop(writer);
+
+        let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        reader.finish().unwrap();
+
+        let text: Vec<(&str, &str)> = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect();
+        assert_eq!(
+            text,
+            [("Title", "Cornell Box 2"), ("Samples per pixel", "100")]
+        );
+    }
+
+    #[test]
+    fn depth_png_is_16_bit() {
+        let mut out = Vec::new();
+        let mut writer = DepthPngWriter::with_scale(&mut out, 1000.0);
+        writer.write_header(2, 1).unwrap();
+        writer
+            .write(&[Color::over_white(1.5), Color::over_white(100.0)])
+            .unwrap();
+        drop(writer);
+
+        let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        // millimetres, clamped to the largest 16-bit value
+        assert_eq!(
+            buf,
+            [1500u16.to_be_bytes(), u16::MAX.to_be_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn pfm_is_linear() {
+        let mut out = Vec::new();
+        let mut writer = PfmWriter::new(&mut out);
+        writer.write_header(1, 2).unwrap();
+        // values outside of 0..=1 are kept
+        writer
+            .write(&[Color::new(2.5, 0.5, 0.0), Color::new(0.25, -1.0, 0.0)])
+            .unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+
+        let floats: Vec<f32> = out[header.len()..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        // the bottom row comes first
+        assert_eq!(floats, [0.25, -1.0, 0.0, 2.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn bmp_rows_are_padded() {
+        let mut out = Vec::new();
+        let mut writer = BmpWriter::new(&mut out);
+        writer.write_header(3, 2).unwrap();
+        writer.write(&[Color::white(); 6]).unwrap();
+
+        // 3px * 3 bytes = 9 bytes per row, padded to 12
+        assert_eq!(out.len(), 54 + 12 * 2);
+        assert_eq!(&out[0..2], b"BM");
+        assert_eq!(
+            &out[54..66],
+            &[255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn tiff_layout() {
+        let mut out = Vec::new();
+        let mut writer = TiffWriter::new(&mut out);
+        writer.write_header(4, 3).unwrap();
+        writer.write(&[Color::red(); 12]).unwrap();
+
+        assert_eq!(&out[0..4], b"II*\0");
+        // the pixels are at the end, pointed to by StripOffsets
+        let pixels = out.len() - 4 * 3 * 3;
+        assert_eq!(&out[pixels..pixels + 3], &[255, 0, 0]);
+    }
 }
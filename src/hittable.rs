@@ -3,13 +3,41 @@ use std::{
     rc::Rc,
 };
 
-use rand::random;
+use crate::rng::random;
 
 use crate::{
-    boundingbox::BoundingBox3, material::Isotropic, texture::Texture, vec::Normalized, Axis, Color,
-    Interval, Material, Point2, Point3, Ray3, Ray4, Vec3,
+    boundingbox::{BoundingBox3, BoundingSphere},
+    material::Isotropic,
+    matrix::Matrix4,
+    onb::OrthonormalBasis,
+    texture::{Footprint, Texture},
+    vec::Normalized,
+    Angle, Axis, Color, Direction, Interval, Material, Point2, Point3, Ray3, Ray4, Vec2, Vec3,
 };
 
+pub mod bezier;
+pub mod curve;
+pub mod grid;
+pub mod lod;
+pub mod mesh;
+pub mod metaballs;
+pub mod point_cloud;
+pub mod scene_graph;
+pub mod subdivision;
+pub mod triangle_bvh;
+pub mod wireframe;
+pub use bezier::{BezierPatch, BilinearPatch};
+pub use curve::Curve;
+pub use grid::Grid;
+pub use lod::Lod;
+pub use mesh::Mesh;
+pub use metaballs::Metaballs;
+pub use point_cloud::{CloudPoint, PointCloud};
+pub use scene_graph::{Named, SceneGraph, SceneNode};
+pub use subdivision::SubdivisionSurface;
+pub use triangle_bvh::TriangleBVH;
+pub use wireframe::BoundsWireframe;
+
 #[derive(Debug, Clone)]
 pub struct HitRecord {
     // The point where the ray hit the object
@@ -25,6 +53,40 @@ pub struct HitRecord {
     t: f64,
     // Whether the ray hit the front or back face of the object
     front_face: bool,
+    // The direction along a fiber (such as a hair), for shading models which need it
+    tangent: Option<Vec3<Normalized>>,
+    // The area one pixel covers around the hit, if the ray tracked its differentials
+    footprint: Option<Footprint>,
+    // The weights of each corner of the triangle hit, if the surface is a triangle
+    barycentrics: Option<[f64; 3]>,
+    // The index of the triangle hit within its mesh, if it has one
+    primitive_index: Option<usize>,
+    // The name of the object hit, if it has one
+    name: Option<Rc<str>>,
+    // How the back of the surface is treated, if the object overrides it
+    back_face: Option<BackFace>,
+    // The light group the surface's emission is credited to, if it has one
+    light_group: Option<Rc<str>>,
+    // The bounce limit of paths leaving the surface, if the object overrides it
+    max_depth: Option<u32>,
+}
+
+/// The light group of emissive surfaces which aren't assigned to one; see [`LightGroup`].
+pub const DEFAULT_LIGHT_GROUP: &str = "default";
+/// The light group of the background.
+pub const BACKGROUND_LIGHT_GROUP: &str = "background";
+
+/// How the integrator treats a ray which hits the back of a surface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackFace {
+    /// The surface is double-sided: the normal is flipped to face the ray, and the back is
+    /// shaded just like the front.
+    #[default]
+    Flip,
+    /// The back of the surface blocks light, but doesn't scatter or emit any.
+    Absorb,
+    /// The back of the surface is invisible; rays pass through it.
+    Cull,
 }
 
 impl HitRecord {
@@ -56,6 +118,99 @@ impl HitRecord {
         self.front_face
     }
 
+    /// The direction along the surface's fibers, if it has any; see [`curve::Curve`].
+    pub fn tangent(&self) -> Option<Vec3<Normalized>> {
+        self.tangent
+    }
+
+    pub fn with_tangent(mut self, tangent: Vec3<Normalized>) -> Self {
+        self.tangent = Some(tangent);
+        self
+    }
+
+    /// Replaces the normal used for shading, such as one interpolated from vertex normals, which
+    /// is turned to the side of the surface the ray hit. Which side was hit is still decided
+    /// by the geometry.
+    pub fn with_shading_normal(mut self, normal: Vec3<Normalized>) -> Self {
+        self.normal = if normal.dot(&self.normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        self
+    }
+
+    /// The weight of each of the three corners of the triangle hit, in the order of
+    /// [`Triangle::vertices`], for interpolating per-vertex attributes. Sums to 1.0.
+    pub fn barycentrics(&self) -> Option<[f64; 3]> {
+        self.barycentrics
+    }
+
+    pub fn with_barycentrics(mut self, barycentrics: [f64; 3]) -> Self {
+        self.barycentrics = Some(barycentrics);
+        self
+    }
+
+    /// The index of the triangle hit within the mesh it was loaded from; see
+    /// [`Triangle::with_index`].
+    pub fn primitive_index(&self) -> Option<usize> {
+        self.primitive_index
+    }
+
+    pub fn with_primitive_index(mut self, index: usize) -> Self {
+        self.primitive_index = Some(index);
+        self
+    }
+
+    /// How the back of the surface hit should be treated; see [`Sided`].
+    pub fn back_face(&self) -> BackFace {
+        self.back_face.unwrap_or_default()
+    }
+
+    /// The light group which light emitted by the surface hit belongs to; see [`LightGroup`].
+    pub fn light_group(&self) -> &str {
+        self.light_group.as_deref().unwrap_or(DEFAULT_LIGHT_GROUP)
+    }
+
+    /// The bounce limit of paths leaving the surface hit, if its object overrides the
+    /// camera's; see [`DepthOverride`].
+    pub fn max_depth(&self) -> Option<u32> {
+        self.max_depth
+    }
+
+    /// The name of the object hit; see [`Named`] and [`SceneGraph`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The area of the surface covered by one pixel around the hit, used to filter textures.
+    /// Only available for rays which track their differentials, such as camera rays.
+    pub fn footprint(&self) -> Option<&Footprint> {
+        self.footprint.as_ref()
+    }
+
+    /// Fills in how the texture coordinates change across the footprint, given the rates of
+    /// change `dpdu` and `dpdv` of the hit point with respect to `u` and `v`. Without these,
+    /// the footprint only covers world space.
+    pub fn with_uv_derivatives(mut self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        let Some(footprint) = &mut self.footprint else {
+            return self;
+        };
+        // solve dp = du * dpdu + dv * dpdv in the least-squares sense
+        let (a, b, c) = (dpdu.dot(&dpdu), dpdu.dot(&dpdv), dpdv.dot(&dpdv));
+        let det = a * c - b * b;
+        if det.abs() < 1e-12 {
+            return self;
+        }
+        let solve = |dp: Vec3| {
+            let (pu, pv) = (dpdu.dot(&dp), dpdv.dot(&dp));
+            Vec2::new((c * pu - b * pv) / det, (a * pv - b * pu) / det)
+        };
+        footprint.duvdx = solve(footprint.dpdx);
+        footprint.duvdy = solve(footprint.dpdy);
+        self
+    }
+
     pub fn from_incoming_ray(
         ray: &Ray4,
         point: &Point3,
@@ -67,6 +222,25 @@ impl HitRecord {
     ) -> Self {
         let front_face = Vec3::dot(&ray.direction(), normal) < 0.0;
         let normal = if front_face { *normal } else { -*normal };
+
+        let footprint = ray.differentials().and_then(|d| {
+            // where each offset ray meets the plane tangent to the surface at the hit
+            let offset = |origin: Point3, direction: Vec3| {
+                let denom = normal.dot(&direction);
+                if denom.abs() < 1e-12 {
+                    return None;
+                }
+                let t = normal.dot(&(*point - origin)) / denom;
+                Some((origin + direction * t) - *point)
+            };
+            Some(Footprint {
+                dpdx: offset(d.rx_origin, d.rx_direction)?,
+                dpdy: offset(d.ry_origin, d.ry_direction)?,
+                duvdx: Vec2::new(0.0, 0.0),
+                duvdy: Vec2::new(0.0, 0.0),
+            })
+        });
+
         Self {
             point: *point,
             normal,
@@ -75,6 +249,14 @@ impl HitRecord {
             v,
             front_face,
             material,
+            tangent: None,
+            footprint,
+            barycentrics: None,
+            primitive_index: None,
+            name: None,
+            back_face: None,
+            light_group: None,
+            max_depth: None,
         }
     }
 
@@ -93,9 +275,42 @@ pub trait Hittable: std::fmt::Debug {
     // If hit, the object should return Hit(HitRecord) describing how the hit occurred.
     fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord>;
 
-    // can return None, but will never recieve any [hit()]s.
+    /// A box enclosing the object, or `None` if it is unbounded (such as an infinite plane).
+    /// Unbounded objects can still be hit, but are kept out of the BVH's tree.
     fn bounding_box(&self) -> Option<&BoundingBox3>;
 
+    /// The boxes enclosing the object at times `0.0` and `1.0`. Moving objects stay within
+    /// the linear interpolation of the two at any time, so a BVH can test a box fitted to the
+    /// time of each ray rather than to the object's whole path.
+    /// By default, the object is assumed to be stationary.
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.bounding_box().map(|bbox| [bbox.clone(), bbox.clone()])
+    }
+
+    /// For closed shapes, the parameters `(t_enter, t_exit)` at which the ray passes into and
+    /// back out of the object, anywhere along the ray's line (so `t_enter` is negative if the
+    /// ray starts inside). Used by volumes, which need the span inside their boundary.
+    ///
+    /// By default, this is found from the first two surface hits along the line, which is
+    /// only correct for convex objects; shapes should override it where they can.
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        let enter = self.hit(ray, Interval::universe())?;
+        let exit = self.hit(ray, Interval::new(enter.t + 0.0001, f64::INFINITY))?;
+        Some((enter.t, exit.t))
+    }
+
+    /// A sphere enclosing the object, or `None` if it is unbounded.
+    /// By default, this is the sphere around [`Hittable::bounding_box`].
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.bounding_box().and_then(BoundingSphere::from_box)
+    }
+
+    /// The two halves of a bounding volume hierarchy node, so that the hierarchy can be
+    /// inspected (see [`BoundsWireframe::hierarchy`]); `None` for anything else.
+    fn bvh_children(&self) -> Option<[&Rc<dyn Hittable>; 2]> {
+        None
+    }
+
     fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
         let _ = (origin, direction);
         unimplemented!();
@@ -112,6 +327,46 @@ pub trait Hittable: std::fmt::Debug {
     {
         Rc::new(self)
     }
+
+    /// Attaches a name to the object; see [`Named`].
+    fn named(self, name: &str) -> Named
+    where
+        Self: Sized + 'static,
+    {
+        Named::new(name, self.hittable())
+    }
+
+    /// Sets how the back of the object's surfaces is treated; see [`Sided`].
+    fn with_back_face(self, back_face: BackFace) -> Sided
+    where
+        Self: Sized + 'static,
+    {
+        Sided::new(self.hittable(), back_face)
+    }
+
+    /// Swaps which side of the object's surfaces faces front; see [`FlipFace`].
+    fn flip_face(self) -> FlipFace
+    where
+        Self: Sized + 'static,
+    {
+        FlipFace::new(self.hittable())
+    }
+
+    /// Assigns any light the object emits to a light group; see [`LightGroup`].
+    fn in_light_group(self, group: &str) -> LightGroup
+    where
+        Self: Sized + 'static,
+    {
+        LightGroup::new(group, self.hittable())
+    }
+
+    /// Overrides the bounce limit of paths which hit the object; see [`DepthOverride`].
+    fn with_max_depth(self, max_depth: u32) -> DepthOverride
+    where
+        Self: Sized + 'static,
+    {
+        DepthOverride::new(max_depth, self.hittable())
+    }
 }
 
 #[derive(Debug)]
@@ -120,6 +375,77 @@ pub struct Sphere {
     radius: f64,
     material: Rc<dyn Material>,
     bounding_box: BoundingBox3,
+    /// How textures are wrapped around the sphere
+    projection: SphereProjection,
+    /// Rotation of the texture about the y axis, in radians
+    texture_rotation: f64,
+}
+
+/// How a sphere's surface is mapped onto texture coordinates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SphereProjection {
+    /// Latitude and longitude: `u` runs around the equator, starting from -x, and `v` from
+    /// the south pole to the north pole.
+    #[default]
+    Equirectangular,
+    /// The six faces of a cube map, laid out in a 3x2 grid: +x, -x and +y along the top row,
+    /// and -y, +z and -z along the bottom, each following the OpenGL cube map conventions.
+    /// Avoids the stretching of an equirectangular map near the poles.
+    CubeMap,
+    /// An angular (light probe) map: +z is at the centre of the texture, and the angle from
+    /// it grows linearly out to -z around the edge of the inscribed circle.
+    Angular,
+}
+
+impl SphereProjection {
+    /// The texture coordinates of `point`, on the unit sphere.
+    fn uv(self, point: &Point3) -> Point2 {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        match self {
+            Self::Equirectangular => {
+                let theta = f64::acos(-y);
+                let phi = f64::atan2(-z, x) + PI;
+                Point2::new(phi / (2.0 * PI), theta / PI)
+            }
+            Self::CubeMap => {
+                let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+                // the face (in the order of the atlas), and where on it the point lies,
+                // with `s` running left to right and `t` top to bottom
+                let (face, s, t) = if ax >= ay && ax >= az {
+                    if x > 0.0 {
+                        (0, -z / ax, -y / ax)
+                    } else {
+                        (1, z / ax, -y / ax)
+                    }
+                } else if ay >= az {
+                    if y > 0.0 {
+                        (2, x / ay, z / ay)
+                    } else {
+                        (3, x / ay, -z / ay)
+                    }
+                } else if z > 0.0 {
+                    (4, x / az, -y / az)
+                } else {
+                    (5, -x / az, -y / az)
+                };
+                let (column, row) = (f64::from(face % 3), f64::from(face / 3));
+                let u = (column + (s + 1.0) / 2.0) / 3.0;
+                // rows run down the texture, but `v` runs up it
+                let v = 1.0 - (row + (t + 1.0) / 2.0) / 2.0;
+                Point2::new(u, v)
+            }
+            Self::Angular => {
+                let radial = (x * x + y * y).sqrt();
+                if radial < 1e-12 {
+                    // straight ahead or straight behind; the latter is anywhere on the rim
+                    let u = if z > 0.0 { 0.5 } else { 1.0 };
+                    return Point2::new(u, 0.5);
+                }
+                let r = z.clamp(-1.0, 1.0).acos() / PI / radial;
+                Point2::new((x * r + 1.0) / 2.0, (y * r + 1.0) / 2.0)
+            }
+        }
+    }
 }
 
 impl Sphere {
@@ -129,43 +455,120 @@ impl Sphere {
 
     pub fn new(center: Ray3, radius: f64, material: Rc<dyn Material>) -> Self {
         assert!(radius >= 0.0);
-        let rad_vec = Vec3::new(radius, radius, radius);
-
-        // bb at time = 0
-        let box0 =
-            BoundingBox3::bounded_by(&(center.origin() - rad_vec), &(center.origin() + rad_vec));
-
-        // bb at time = 1
-        let box1 =
-            BoundingBox3::bounded_by(&(center.at(1.0) - rad_vec), &(center.at(1.0) + rad_vec));
+        let [box0, box1] = Self::box_at_times(&center, radius);
 
         Self {
             center,
             radius,
             material,
             bounding_box: BoundingBox3::extending(&box0, &box1),
+            projection: SphereProjection::default(),
+            texture_rotation: 0.0,
         }
     }
 
-    fn get_uv(point: &Point3) -> Point2 {
+    /// Sets how textures are wrapped around the sphere; by default,
+    /// [`SphereProjection::Equirectangular`].
+    pub fn with_projection(mut self, projection: SphereProjection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Turns the texture about the sphere's polar (y) axis by `angle`, counterclockwise
+    /// when seen from above, such as to orient a planet without rotating the sphere.
+    pub fn with_texture_rotation(mut self, angle: Angle) -> Self {
+        self.texture_rotation = angle.as_radians();
+        self
+    }
+
+    /// Hollows out the sphere, leaving a shell `thickness` thick around an empty inside, such
+    /// as a glass sphere with a bubble of air at its centre; see [`Shell`].
+    pub fn hollow(self, thickness: f64) -> Shell {
+        assert!(
+            0.0 < thickness && thickness < self.radius,
+            "Invalid shell thickness (expected between 0.0 and the radius)"
+        );
+        let inner = Self::new(
+            self.center,
+            self.radius - thickness,
+            Rc::clone(&self.material),
+        )
+        .with_projection(self.projection);
+        let inner = Self {
+            texture_rotation: self.texture_rotation,
+            ..inner
+        };
+        Shell::new(self.hittable(), inner.hittable())
+    }
+
+    /// The bounding boxes of the sphere at times 0 and 1.
+    fn box_at_times(center: &Ray3, radius: f64) -> [BoundingBox3; 2] {
+        let rad_vec = Vec3::new(radius, radius, radius);
+        [0.0, 1.0].map(|time| {
+            let center = center.at(time);
+            BoundingBox3::bounded_by(&(center - rad_vec), &(center + rad_vec))
+        })
+    }
+
+    fn get_uv(&self, point: &Point3) -> Point2 {
         // `point` must be inside the unit sphere
         assert!((Point3::origin() - point).len_squared() <= 1.0001);
 
-        let theta = f64::acos(-point.y());
-        let phi = f64::atan2(-point.z(), point.x()) + PI;
+        // rotating the texture one way is rotating the point the other
+        let (sin, cos) = (-self.texture_rotation).sin_cos();
+        let (x, z) = (point.x(), point.z());
+        let point = Point3::new(cos * x + sin * z, point.y(), -sin * x + cos * z);
 
         // u and v range from [0.0, 1.0]
-        let u = phi / (2.0 * PI);
-        let v = theta / PI;
-
-        Point2::new(u, v)
+        self.projection.uv(&point)
     }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
         let current_center = self.center.at(ray.time());
-        let oc = current_center - ray.origin();
+        let (near, far) = self.hit_interval(ray)?;
+
+        // Find the nearest root that lies in the acceptable range.
+        let mut root = near;
+        if !ray_t.surrounds(root) {
+            root = far;
+            if !ray_t.surrounds(root) {
+                return None;
+            }
+        }
+
+        let point = ray.at(root);
+
+        // mathematically guaranteed to be normalized
+        let normal = ((point - current_center) / self.radius).assert_is_normalized();
+
+        let (u, v) = self.get_uv(&Vec3::from(normal).into()).into();
+        let hit = HitRecord::from_incoming_ray(
+            ray,
+            &point,
+            &normal,
+            root,
+            u,
+            v,
+            Rc::clone(&self.material),
+        );
+
+        // the derivatives of the equirectangular mapping, which a rotation about the poles
+        // leaves unchanged; undefined at the poles
+        let (x, y, z) = (normal.x(), normal.y(), normal.z());
+        let sin_theta = (1.0 - y * y).max(0.0).sqrt();
+        if self.projection != SphereProjection::Equirectangular || sin_theta < 1e-6 {
+            return Some(hit);
+        }
+        let dpdu = Vec3::new(z, 0.0, -x) * (2.0 * PI * self.radius);
+        let dpdv =
+            Vec3::new(-x * y / sin_theta, sin_theta, -y * z / sin_theta) * (PI * self.radius);
+        Some(hit.with_uv_derivatives(dpdu, dpdv))
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        let oc = self.center.at(ray.time()) - ray.origin();
         let a = ray.direction().len_squared();
         let h = Vec3::dot(&ray.direction(), &oc);
         let c = oc.len_squared() - self.radius * self.radius;
@@ -176,22 +579,78 @@ impl Hittable for Sphere {
         }
 
         let sqrtd = discriminant.sqrt();
+        Some(((h - sqrtd) / a, (h + sqrtd) / a))
+    }
 
-        // Find the nearest root that lies in the acceptable range.
-        let mut root = (h - sqrtd) / a;
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        Some(Self::box_at_times(&self.center, self.radius))
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        if !self.center.direction().near_zero() {
+            // a moving sphere sweeps out a capsule
+            return BoundingSphere::from_box(&self.bounding_box);
+        }
+        Some(BoundingSphere::new(self.center.origin(), self.radius))
+    }
+}
+
+/// A sphere stretched by a different radius along each axis, such as a squashed planet or an
+/// egg-like pebble. Texture coordinates are mapped as on the equirectangular [`Sphere`] the
+/// ellipsoid was stretched from.
+#[derive(Debug)]
+pub struct Ellipsoid {
+    center: Point3,
+    radii: Vec3,
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl Ellipsoid {
+    pub fn new(center: Point3, radii: Vec3, material: Rc<dyn Material>) -> Self {
+        assert!(
+            radii.x() > 0.0 && radii.y() > 0.0 && radii.z() > 0.0,
+            "Invalid ellipsoid radii (expected > 0.0)"
+        );
+        let bounding_box = BoundingBox3::bounded_by(&(center - radii), &(center + radii));
+        Self {
+            center,
+            radii,
+            material,
+            bounding_box,
+        }
+    }
+
+    /// Maps a point (or direction) into the space where the ellipsoid is the unit sphere.
+    fn to_unit(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            v.x() / self.radii.x(),
+            v.y() / self.radii.y(),
+            v.z() / self.radii.z(),
+        )
+    }
+}
+
+impl Hittable for Ellipsoid {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let (near, far) = self.hit_interval(ray)?;
+        let mut root = near;
         if !ray_t.surrounds(root) {
-            root = (h + sqrtd) / a;
+            root = far;
             if !ray_t.surrounds(root) {
                 return None;
             }
         }
 
         let point = ray.at(root);
-
-        // mathematically guaranteed to be normalized
-        let normal = ((point - current_center) / self.radius).assert_is_normalized();
-
-        let (u, v) = Sphere::get_uv(&Vec3::from(normal).into()).into();
+        let unit = self.to_unit(point - self.center);
+        // the gradient of the implicit surface; stretching the sphere squashes its normals
+        let normal = self.to_unit(unit).as_unit();
+        let (u, v) = SphereProjection::Equirectangular.uv(&unit.into()).into();
         Some(HitRecord::from_incoming_ray(
             ray,
             &point,
@@ -203,6 +662,24 @@ impl Hittable for Sphere {
         ))
     }
 
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        // intersect the unit sphere with the ray stretched into its space; distances along
+        // the ray are unchanged
+        let oc = self.to_unit(self.center - ray.origin());
+        let direction = self.to_unit(ray.direction());
+        let a = direction.len_squared();
+        let h = Vec3::dot(&direction, &oc);
+        let c = oc.len_squared() - 1.0;
+
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        Some(((h - sqrtd) / a, (h + sqrtd) / a))
+    }
+
     fn bounding_box(&self) -> Option<&BoundingBox3> {
         Some(&self.bounding_box)
     }
@@ -236,9 +713,12 @@ impl HittableVec {
     }
 
     pub fn add(&mut self, obj: Rc<dyn Hittable>) {
+        // once an unbounded object is added, the list is unbounded too
+        let unbounded = BoundingBox3::universe();
+        let obj_bbox = obj.bounding_box().unwrap_or(&unbounded);
         self.bounding_box = match &self.bounding_box {
-            Some(bbox) => Some(BoundingBox3::extending(bbox, obj.bounding_box().unwrap())),
-            None => Some(obj.bounding_box().unwrap().clone()),
+            Some(bbox) => Some(BoundingBox3::extending(bbox, obj_bbox)),
+            None => Some(obj_bbox.clone()),
         };
         self.objects.push(obj);
     }
@@ -246,6 +726,10 @@ impl HittableVec {
     pub fn len(&self) -> usize {
         self.objects.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
 
 impl Hittable for HittableVec {
@@ -264,7 +748,50 @@ impl Hittable for HittableVec {
     }
 
     fn bounding_box(&self) -> Option<&BoundingBox3> {
-        self.bounding_box.as_ref()
+        self.bounding_box
+            .as_ref()
+            .filter(|bbox| bbox.x().size().is_finite())
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.bounding_box()?;
+        let mut bounds = [BoundingBox3::empty(), BoundingBox3::empty()];
+        for object in &self.objects {
+            let [box0, box1] = object.motion_bounds()?;
+            bounds = [
+                BoundingBox3::extending(&bounds[0], &box0),
+                BoundingBox3::extending(&bounds[1], &box1),
+            ];
+        }
+        Some(bounds)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            // with nothing to aim at, directions are sampled uniformly
+            return 1.0 / (4.0 * PI);
+        }
+
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            // skip the (comparatively slow) exact test for lights the direction can't reach
+            .filter(|object| {
+                object
+                    .bounding_sphere()
+                    .is_none_or(|sphere| sphere.intersects(origin, direction))
+            })
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::random_unit_vector().into();
+        }
+
+        let index = crate::rng::random_range(0..self.objects.len());
+        self.objects[index].random(origin)
     }
 }
 
@@ -359,7 +886,7 @@ impl Hittable for Parallelogram {
 
         let (u, v) = self.is_interior(alpha, beta)?;
 
-        Some(HitRecord::from_incoming_ray(
+        let hit = HitRecord::from_incoming_ray(
             ray,
             &intersection,
             &self.normal,
@@ -367,7 +894,8 @@ impl Hittable for Parallelogram {
             u,
             v,
             Rc::clone(&self.material),
-        ))
+        );
+        Some(hit.with_uv_derivatives(self.u, self.v))
     }
 
     fn bounding_box(&self) -> Option<&BoundingBox3> {
@@ -389,13 +917,26 @@ impl Hittable for Parallelogram {
     }
 
     fn random(&self, origin: &Point3) -> Vec3 {
-        let p: Point3 =
-            self.corner + (rand::random::<f64>() * self.u) + (rand::random::<f64>() * self.v);
+        let p: Point3 = self.corner
+            + (crate::rng::random::<f64>() * self.u)
+            + (crate::rng::random::<f64>() * self.v);
         p - origin
     }
 }
 
 pub fn box3(a: &Point3, b: &Point3, mat: Rc<dyn Material>) -> Rc<dyn Hittable> {
+    box3_with_materials(a, b, std::array::from_fn(|_| Rc::clone(&mat)))
+}
+
+/// Like [`box3`], but with a different material on each face. The faces are in the order of
+/// [`CubeFace::ALL`](crate::camera::probe::CubeFace::ALL): the faces towards +x, -x, +y, -y,
+/// +z and -z.
+pub fn box3_with_materials(
+    a: &Point3,
+    b: &Point3,
+    materials: [Rc<dyn Material>; 6],
+) -> Rc<dyn Hittable> {
+    let [pos_x, neg_x, pos_y, neg_y, pos_z, neg_z] = materials;
     let mut sides = HittableVec::with_capacity(6);
 
     // Construct the two opposite vertices with the minimum and maximum coordinates.
@@ -410,84 +951,89 @@ pub fn box3(a: &Point3, b: &Point3, mat: Rc<dyn Material>) -> Rc<dyn Hittable> {
         f64::max(a.z(), b.z()),
     );
 
-    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
-    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
-    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+    let [dx, dy, dz] = [Axis::X, Axis::Y, Axis::Z].map(|axis| {
+        let unit: Vec3 = axis.unit().into();
+        unit * (max[axis] - min[axis])
+    });
 
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(min.x(), min.y(), max.z()),
         dx,
         dy,
-        Rc::clone(&mat),
+        pos_z,
     )));
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(max.x(), min.y(), max.z()),
         -dz,
         dy,
-        Rc::clone(&mat),
+        pos_x,
     )));
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(max.x(), min.y(), min.z()),
         -dx,
         dy,
-        Rc::clone(&mat),
+        neg_z,
     )));
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(min.x(), min.y(), min.z()),
         dz,
         dy,
-        Rc::clone(&mat),
+        neg_x,
     )));
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(min.x(), max.y(), max.z()),
         dx,
         -dz,
-        Rc::clone(&mat),
+        pos_y,
     )));
     sides.add(Rc::new(Parallelogram::new(
         Point3::new(min.x(), min.y(), min.z()),
         dx,
         dz,
-        Rc::clone(&mat),
+        neg_y,
     )));
 
     Rc::new(sides)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Triangle {
-    corner: Point3,
     u: Vec3,
     v: Vec3,
-    w: Vec3,
-    d: f64,
+    // the exact corners; recomputing them from the edges would let adjacent triangles drift apart
+    vertices: [Point3; 3],
     normal: Vec3<Normalized>,
     material: Rc<dyn Material>,
     bounding_box: BoundingBox3,
+    // texture coordinates of each corner; if missing, the barycentric coordinates are used
+    uvs: Option<[Point2; 3]>,
+    // the position of this triangle within its mesh
+    index: Option<usize>,
 }
 
 impl Triangle {
     pub fn new(corner: Point3, u: Vec3, v: Vec3, material: Rc<dyn Material>) -> Self {
+        Self::with_vertices([corner, corner + u, corner + v], u, v, material)
+    }
+
+    fn with_vertices(vertices: [Point3; 3], u: Vec3, v: Vec3, material: Rc<dyn Material>) -> Self {
+        let corner = vertices[0];
         let diag_1 = BoundingBox3::bounded_by(&corner, &(corner + u + v));
         let diag_2 = BoundingBox3::bounded_by(&(corner + u), &(corner + v));
 
         let bounding_box = BoundingBox3::extending(&diag_1, &diag_2);
 
-        let n = u.cross(&v);
-        let normal = n.as_unit();
-        let d = Vec3::dot(&normal, &Vec3::from(corner));
-
-        let w = n / Vec3::dot(&n, &n);
+        let normal = u.cross(&v).as_unit();
 
         Self {
-            corner,
             u,
             v,
-            d,
-            w,
+            vertices,
             normal,
             material,
             bounding_box,
+            uvs: None,
+            index: None,
         }
     }
 
@@ -497,7 +1043,57 @@ impl Triangle {
         corner3: Point3,
         material: Rc<dyn Material>,
     ) -> Self {
-        Self::new(corner1, corner2 - corner1, corner3 - corner1, material)
+        Self::with_vertices(
+            [corner1, corner2, corner3],
+            corner2 - corner1,
+            corner3 - corner1,
+            material,
+        )
+    }
+
+    /// Assigns texture coordinates to the three corners, which are interpolated across the face.
+    pub fn with_uvs(mut self, uvs: [Point2; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Records the position of this triangle within the mesh it belongs to, which is
+    /// reported by [`HitRecord::primitive_index`] when it is hit.
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// The three corners of the triangle.
+    pub fn vertices(&self) -> [Point3; 3] {
+        self.vertices
+    }
+
+    pub fn material(&self) -> &Rc<dyn Material> {
+        &self.material
+    }
+
+    /// Whether texture coordinates were assigned with [`Triangle::with_uvs`].
+    pub fn has_uvs(&self) -> bool {
+        self.uvs.is_some()
+    }
+
+    /// The texture coordinates of the three corners.
+    /// Triangles without texture coordinates map onto the lower-left half of the unit square.
+    pub fn uvs(&self) -> [Point2; 3] {
+        self.uvs.unwrap_or([
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.0, 1.0),
+        ])
+    }
+
+    pub fn normal(&self) -> Vec3<Normalized> {
+        self.normal
     }
 
     /// Checks whether the object is hit, assuming the plane it exists on is hit
@@ -510,44 +1106,146 @@ impl Triangle {
         if a < 0.0 || b < 0.0 || (a + b) > 1.0 {
             return None;
         }
+        Some(self.interpolate_uv(a, b))
+    }
 
-        // a, b are identical to u, v coordinates;
-        // both are in fractional space
-        Some((a, b))
+    /// Maps barycentric coordinates to texture coordinates, without checking that they lie
+    /// within the triangle.
+    fn interpolate_uv(&self, a: f64, b: f64) -> (f64, f64) {
+        let Some([uv0, uv1, uv2]) = self.uvs else {
+            // a, b are identical to u, v coordinates;
+            // both are in fractional space
+            return (a, b);
+        };
+
+        let c = 1.0 - a - b;
+        let u = c * uv0.x() + a * uv1.x() + b * uv2.x();
+        let v = c * uv0.y() + a * uv1.y() + b * uv2.y();
+        (u, v)
     }
 }
 
-impl Hittable for Triangle {
-    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
-        let demon = Vec3::dot(&self.normal, &ray.direction());
-
-        // ray is parallel to the plane; no hit
-        if demon.abs() < 1e-8 {
-            return None;
-        }
+/// Intersects `ray` with the triangle with corners `vertices`, returning the distance along
+/// the ray and the barycentric coordinates of the hit, weighting each corner in turn.
+pub(crate) fn intersect_triangle(
+    ray: &Ray4,
+    vertices: &[Point3; 3],
+    ray_t: &Interval,
+) -> Option<(f64, [f64; 3])> {
+    // Watertight ray/triangle intersection (Woop, Benthin & Wald, 2013).
+    // The triangle is transformed into a space where the ray starts at the origin and
+    // points along +z, so the test reduces to a 2D edge test which is exactly
+    // consistent between triangles sharing an edge; rays can't slip between them.
+    let direction = ray.direction();
+    let dir = [direction.x(), direction.y(), direction.z()];
+
+    // permute the axes so that the largest component of the direction is z,
+    // preserving the winding of the triangle
+    let kz = (0..3)
+        .max_by(|&a, &b| dir[a].abs().total_cmp(&dir[b].abs()))
+        .unwrap();
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    if dir[kz] == 0.0 {
+        return None;
+    }
 
-        let t = (self.d - Vec3::dot(&self.normal, &Vec3::from(ray.origin()))) / demon;
-        if !ray_t.contains(t) {
-            return None;
-        }
+    // shear the ray direction onto +z
+    let shear_x = dir[kx] / dir[kz];
+    let shear_y = dir[ky] / dir[kz];
+    let shear_z = 1.0 / dir[kz];
+
+    let vertex = |p: Point3| {
+        let rel: Vec3 = p - ray.origin();
+        let rel = [rel.x(), rel.y(), rel.z()];
+        (
+            rel[kx] - shear_x * rel[kz],
+            rel[ky] - shear_y * rel[kz],
+            shear_z * rel[kz],
+        )
+    };
+    let [a, b, c] = *vertices;
+    let (ax, ay, az) = vertex(a);
+    let (bx, by, bz) = vertex(b);
+    let (cx, cy, cz) = vertex(c);
+
+    // scaled barycentric coordinates; each is the signed area opposite one corner
+    let e0 = cx * by - cy * bx;
+    let e1 = ax * cy - ay * cx;
+    let e2 = bx * ay - by * ax;
+
+    // the ray passes inside the triangle only if all the edges agree
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    let det = e0 + e1 + e2;
+    // ray is parallel to the plane; no hit
+    if det == 0.0 {
+        return None;
+    }
 
-        // check if the hit point is within the planar shape using its planar coordinates
-        let intersection = ray.at(t);
-        let planar_hit_vec = intersection - self.corner;
-        let alpha = Vec3::dot(&self.w, &planar_hit_vec.cross(&self.v));
-        let beta = Vec3::dot(&self.w, &self.u.cross(&planar_hit_vec));
+    let t = (e0 * az + e1 * bz + e2 * cz) / det;
+    if !ray_t.contains(t) {
+        return None;
+    }
+    Some((t, [e0 / det, e1 / det, e2 / det]))
+}
 
-        let (u, v) = self.is_interior(alpha, beta)?;
+/// Builds the record of a hit found by [`intersect_triangle`] on the triangle with edges
+/// `edge_u` and `edge_v` from its first corner. Without texture coordinates for each corner,
+/// the barycentric coordinates of the second and third corners are used.
+pub(crate) fn triangle_hit_record(
+    ray: &Ray4,
+    (edge_u, edge_v): (Vec3, Vec3),
+    normal: &Vec3<Normalized>,
+    uvs: Option<&[Point2; 3]>,
+    (t, barycentrics): (f64, [f64; 3]),
+    material: Rc<dyn Material>,
+) -> HitRecord {
+    let [c, alpha, beta] = barycentrics;
+    let (u, v) = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            c * uv0.x() + alpha * uv1.x() + beta * uv2.x(),
+            c * uv0.y() + alpha * uv1.y() + beta * uv2.y(),
+        ),
+        None => (alpha, beta),
+    };
+
+    let hit = HitRecord::from_incoming_ray(ray, &ray.at(t), normal, t, u, v, material)
+        .with_barycentrics(barycentrics);
+    let Some([uv0, uv1, uv2]) = uvs else {
+        // the barycentric coordinates are the uv coordinates
+        return hit.with_uv_derivatives(edge_u, edge_v);
+    };
+    // the edges from the first corner, in texture space
+    let (du1, dv1) = (uv1.x() - uv0.x(), uv1.y() - uv0.y());
+    let (du2, dv2) = (uv2.x() - uv0.x(), uv2.y() - uv0.y());
+    let det = du1 * dv2 - dv1 * du2;
+    if det.abs() < 1e-12 {
+        return hit;
+    }
+    let dpdu = (dv2 * edge_u - dv1 * edge_v) / det;
+    let dpdv = (du1 * edge_v - du2 * edge_u) / det;
+    hit.with_uv_derivatives(dpdu, dpdv)
+}
 
-        Some(HitRecord::from_incoming_ray(
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let intersection = intersect_triangle(ray, &self.vertices, &ray_t)?;
+        let hit = triangle_hit_record(
             ray,
-            &intersection,
+            (self.u, self.v),
             &self.normal,
-            t,
-            u,
-            v,
+            self.uvs.as_ref(),
+            intersection,
             Rc::clone(&self.material),
-        ))
+        );
+        match self.index {
+            Some(index) => Some(hit.with_primitive_index(index)),
+            None => Some(hit),
+        }
     }
 
     fn bounding_box(&self) -> Option<&BoundingBox3> {
@@ -676,31 +1374,370 @@ impl Hittable for Disc {
     }
 }
 
+/// A flat ring (annulus): a disc with a hole in the middle, such as a washer or the rings of
+/// a planet.
+///
+/// `u` runs outwards across the ring, from `0.0` at the inner edge to `1.0` at the outer
+/// edge, so planet ring textures can be a single strip; `v` runs once around the ring.
+#[derive(Debug)]
+pub struct Ring {
+    center: Point3,
+    /// The plane of the ring is spanned by `basis.u()` and `basis.v()`
+    basis: OrthonormalBasis,
+    inner_radius: f64,
+    outer_radius: f64,
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl Ring {
+    /// Creates a ring around `center`, facing in the direction of `normal`.
+    pub fn new(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        material: Rc<dyn Material>,
+    ) -> Self {
+        assert!(
+            0.0 <= inner_radius && inner_radius < outer_radius,
+            "Invalid ring radii (expected 0 <= inner < outer)"
+        );
+        let basis = OrthonormalBasis::new(&normal);
+
+        // a circle of radius r extends r * sin(angle to the normal) along each axis
+        let w = basis.w();
+        let extent = |n: f64| outer_radius * (1.0 - n * n).max(0.0).sqrt();
+        let extent = Vec3::new(extent(w.x()), extent(w.y()), extent(w.z()));
+        let bounding_box = BoundingBox3::bounded_by(&(center - extent), &(center + extent));
+
+        Self {
+            center,
+            basis,
+            inner_radius,
+            outer_radius,
+            material,
+            bounding_box,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        PI * (self.outer_radius.powi(2) - self.inner_radius.powi(2))
+    }
+}
+
+impl Hittable for Ring {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let normal = self.basis.w();
+        let denom = Vec3::dot(&normal, &ray.direction());
+        // ray is parallel to the plane; no hit
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = Vec3::dot(&normal, &(self.center - ray.origin())) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let offset = point - self.center;
+        let (x, y) = (offset.dot(&self.basis.u()), offset.dot(&self.basis.v()));
+        let radius = (x * x + y * y).sqrt();
+        if radius < self.inner_radius || radius > self.outer_radius {
+            return None;
+        }
+
+        let u = (radius - self.inner_radius) / (self.outer_radius - self.inner_radius);
+        let v = (y.atan2(x) + PI) / (2.0 * PI);
+        Some(HitRecord::from_incoming_ray(
+            ray,
+            &point,
+            &normal,
+            t,
+            u,
+            v,
+            Rc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let Some(hit) = self.hit(
+            &Ray4::new(*origin, *direction, 0.0),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        let dist_squared = hit.t() * hit.t() * direction.len_squared();
+        let cosine = (direction.dot(&hit.normal()) / direction.len()).abs();
+
+        dist_squared / (cosine * self.area())
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        // uniform over the area of the ring
+        let (inner2, outer2) = (self.inner_radius.powi(2), self.outer_radius.powi(2));
+        let radius = (inner2 + crate::rng::random::<f64>() * (outer2 - inner2)).sqrt();
+        let angle = 2.0 * PI * crate::rng::random::<f64>();
+        let (u, v): (Vec3, Vec3) = (self.basis.u().into(), self.basis.v().into());
+        let p: Point3 = self.center + u * (radius * angle.cos()) + v * (radius * angle.sin());
+        p - origin
+    }
+}
+
+/// The number of steps taken towards the surface of a [`RoundedBox`] before giving up;
+/// rays skimming past an edge converge slowly.
+const ROUNDED_BOX_STEPS: u32 = 256;
+/// How close to the surface of a [`RoundedBox`] a step must land to count as a hit.
+const ROUNDED_BOX_EPSILON: f64 = 1e-7;
+
+/// An axis-aligned box with its edges and corners rounded off to `radius`, for objects which
+/// would look unnaturally sharp with the hard edges of [`box3`].
+///
+/// The surface is found by sphere tracing its signed distance field. Texture coordinates are
+/// projected onto each face along its axis, as they would be on the faces of a box.
+#[derive(Debug)]
+pub struct RoundedBox {
+    center: Point3,
+    /// Half the size of the box along each axis
+    half_size: Vec3,
+    radius: f64,
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl RoundedBox {
+    /// Creates a box with opposite corners `a` and `b`, whose edges are rounded to `radius`.
+    /// The radius can be at most half the size of the box along its shortest side.
+    pub fn new(a: &Point3, b: &Point3, radius: f64, material: Rc<dyn Material>) -> Self {
+        let bounding_box = BoundingBox3::bounded_by(a, b);
+        let half_size = Vec3::new(
+            bounding_box.x().size() / 2.0,
+            bounding_box.y().size() / 2.0,
+            bounding_box.z().size() / 2.0,
+        );
+        let shortest = half_size.x().min(half_size.y()).min(half_size.z());
+        assert!(
+            0.0 <= radius && radius <= shortest,
+            "Invalid rounding radius (expected 0 <= radius <= half the shortest side)"
+        );
+
+        Self {
+            center: *a + (*b - *a) / 2.0,
+            half_size,
+            radius,
+            material,
+            bounding_box,
+        }
+    }
+
+    /// The offset of `point` from the box shrunk by `radius` along each axis, reflected into
+    /// the positive octant; negative where the point is within the shrunken box's extent.
+    fn offset(&self, point: &Point3) -> Vec3 {
+        let p = *point - self.center;
+        Vec3::new(
+            p.x().abs() - (self.half_size.x() - self.radius),
+            p.y().abs() - (self.half_size.y() - self.radius),
+            p.z().abs() - (self.half_size.z() - self.radius),
+        )
+    }
+
+    /// The signed distance from `point` to the surface, negative inside the box.
+    fn distance(&self, point: &Point3) -> f64 {
+        let q = self.offset(point);
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside.len() + inside - self.radius
+    }
+
+    fn normal(&self, point: &Point3) -> Vec3<Normalized> {
+        let q = self.offset(point);
+        let p = *point - self.center;
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+        let direction = if outside.len_squared() > 0.0 {
+            outside
+        } else {
+            // within the flat part of a face, which faces along the axis closest to the surface
+            let axis = Axis::iter().max_by(|&a, &b| q[a].total_cmp(&q[b])).unwrap();
+            axis.unit().into()
+        };
+        Vec3::new(
+            direction.x().copysign(p.x()),
+            direction.y().copysign(p.y()),
+            direction.z().copysign(p.z()),
+        )
+        .as_unit()
+    }
+}
+
+impl Hittable for RoundedBox {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        // only march through the part of the ray within the bounding box
+        let mut int = ray_t.clone();
+        for axis in Axis::iter() {
+            let adinv = 1.0 / ray.direction()[axis];
+            let t0 = (self.bounding_box[axis].start() - ray.origin()[axis]) * adinv;
+            let t1 = (self.bounding_box[axis].end() - ray.origin()[axis]) * adinv;
+            int = Interval::positive(t0, t1).overlap(&int)?;
+        }
+
+        let speed = ray.direction().len();
+        let mut t = *int.start();
+        // rays starting inside the box march towards the surface from within
+        let sign = self.distance(&ray.at(t)).signum();
+        let mut found = false;
+        for _ in 0..ROUNDED_BOX_STEPS {
+            let distance = sign * self.distance(&ray.at(t));
+            if distance < ROUNDED_BOX_EPSILON {
+                found = true;
+                break;
+            }
+            t += distance / speed;
+            if t > *int.end() {
+                return None;
+            }
+        }
+        if !found || !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let normal = self.normal(&point);
+        // project onto the face the normal is closest to
+        let axis = Axis::iter()
+            .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+            .unwrap();
+        let (a, b) = match axis {
+            Axis::X => (Axis::Z, Axis::Y),
+            Axis::Y => (Axis::X, Axis::Z),
+            Axis::Z => (Axis::X, Axis::Y),
+        };
+        let fraction = |axis: Axis| {
+            (point[axis] - self.bounding_box[axis].start()) / self.bounding_box[axis].size()
+        };
+        Some(HitRecord::from_incoming_ray(
+            ray,
+            &point,
+            &normal,
+            t,
+            fraction(a),
+            fraction(b),
+            Rc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+}
+
+/// The space between two parallel planes perpendicular to an [`Axis`], such as a floor of
+/// some thickness which extends forever. Slabs are unbounded, so they're kept out of the
+/// tree of a [`BVHNode`](crate::boundingbox::BVHNode).
+///
+/// Texture coordinates repeat every unit along the two other axes.
+#[derive(Debug)]
+pub struct Slab {
+    axis: Axis,
+    /// The coordinates of the two planes along `axis`
+    extent: Interval,
+    material: Rc<dyn Material>,
+}
+
+impl Slab {
+    /// Creates a slab covering `extent` along `axis`; for example, a floor 1 unit thick with
+    /// its top at `y = 0` is `Slab::new(Axis::Y, Interval::new(-1.0, 0.0), material)`.
+    pub fn new(axis: Axis, extent: Interval, material: Rc<dyn Material>) -> Self {
+        assert!(
+            extent.start() <= extent.end(),
+            "Invalid slab extent (expected start <= end)"
+        );
+        Self {
+            axis,
+            extent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Slab {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let (enter, exit) = self.hit_interval(ray)?;
+        let t = if ray_t.surrounds(enter) {
+            enter
+        } else if ray_t.surrounds(exit) {
+            exit
+        } else {
+            return None;
+        };
+
+        let point = ray.at(t);
+        let mid = (self.extent.start() + self.extent.end()) / 2.0;
+        let outward = Direction::new(self.axis, point[self.axis] > mid);
+        let (u_axis, v_axis) = self.axis.others();
+        Some(HitRecord::from_incoming_ray(
+            ray,
+            &point,
+            &outward.unit(),
+            t,
+            point[u_axis].rem_euclid(1.0),
+            point[v_axis].rem_euclid(1.0),
+            Rc::clone(&self.material),
+        ))
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        let origin = ray.origin()[self.axis];
+        let direction = ray.direction()[self.axis];
+        if direction == 0.0 {
+            // parallel to the planes: either always or never inside
+            return self
+                .extent
+                .contains(origin)
+                .then_some((f64::NEG_INFINITY, f64::INFINITY));
+        }
+
+        let t0 = (self.extent.start() - origin) / direction;
+        let t1 = (self.extent.end() - origin) / direction;
+        Some((t0.min(t1), t0.max(t1)))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct Translate {
     object: Rc<dyn Hittable>,
     offset: Vec3,
-    bounding_box: BoundingBox3,
+    bounding_box: Option<BoundingBox3>,
 }
 
 impl Translate {
     pub fn new(object: Rc<dyn Hittable>, offset: Vec3) -> Self {
-        let bbox = object
-            .bounding_box()
-            .expect("Objects without bounding boxes should not be Translated")
-            + offset;
+        let bbox = object.bounding_box().map(|bbox| bbox + offset);
         Self {
             object,
             offset,
             bounding_box: bbox,
         }
     }
+
+    /// Moves the ray backwards by the offset.
+    fn object_ray(&self, ray: &Ray4) -> Ray4 {
+        ray.transformed(|point| point - self.offset, |direction| direction)
+    }
 }
 
 impl Hittable for Translate {
     fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
-        // Move the ray backwards by the offset
-        let offset_ray = Ray4::new(ray.origin() - self.offset, ray.direction(), ray.time());
+        let offset_ray = self.object_ray(ray);
 
         // Determine whether an intersection exists along the offset ray (and if so, where)
         let mut hit = self.object.hit(&offset_ray, ray_t)?;
@@ -710,8 +1747,17 @@ impl Hittable for Translate {
         Some(hit)
     }
 
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(&self.object_ray(ray))
+    }
+
     fn bounding_box(&self) -> Option<&BoundingBox3> {
-        Some(&self.bounding_box)
+        self.bounding_box.as_ref()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        let [box0, box1] = self.object.motion_bounds()?;
+        Some([box0 + self.offset, box1 + self.offset])
     }
 }
 
@@ -720,17 +1766,26 @@ pub struct RotateY {
     object: Rc<dyn Hittable>,
     sin_theta: f64,
     cos_theta: f64,
-    bounding_box: BoundingBox3,
+    bounding_box: Option<BoundingBox3>,
 }
 
 impl RotateY {
-    pub fn new(object: Rc<dyn Hittable>, angle: f64) -> Self {
-        let sin_theta = angle.sin();
-        let cos_theta = angle.cos();
-        let bbox = object
+    pub fn new(object: Rc<dyn Hittable>, angle: Angle) -> Self {
+        let (sin_theta, cos_theta) = angle.sin_cos();
+        let bounding_box = object
             .bounding_box()
-            .expect("Objects without bounding boxes should not be Rotated");
+            .map(|bbox| Self::rotate_box(bbox, sin_theta, cos_theta));
+
+        Self {
+            object,
+            cos_theta,
+            sin_theta,
+            bounding_box,
+        }
+    }
 
+    /// Finds the box enclosing `bbox` once it has been rotated.
+    fn rotate_box(bbox: &BoundingBox3, sin_theta: f64, cos_theta: f64) -> BoundingBox3 {
         let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
         let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
 
@@ -754,12 +1809,32 @@ impl RotateY {
             }
         }
 
-        Self {
-            object,
+        BoundingBox3::bounded_by(&min, &max)
+    }
+
+    /// Transforms the ray from world space to object space.
+    fn object_ray(&self, ray: &Ray4) -> Ray4 {
+        let Self {
             cos_theta,
             sin_theta,
-            bounding_box: BoundingBox3::bounded_by(&min, &max),
-        }
+            ..
+        } = self;
+        ray.transformed(
+            |origin| {
+                Point3::new(
+                    (cos_theta * origin.x()) - (sin_theta * origin.z()),
+                    origin.y(),
+                    (sin_theta * origin.x()) + (cos_theta * origin.z()),
+                )
+            },
+            |direction| {
+                Vec3::new(
+                    (cos_theta * direction.x()) - (sin_theta * direction.z()),
+                    direction.y(),
+                    (sin_theta * direction.x()) + (cos_theta * direction.z()),
+                )
+            },
+        )
     }
 }
 
@@ -770,21 +1845,7 @@ impl Hittable for RotateY {
             sin_theta,
             ..
         } = self;
-        // Transform the ray from world space to object space.
-
-        let origin = Point3::new(
-            (cos_theta * ray.origin().x()) - (sin_theta * ray.origin().z()),
-            ray.origin().y(),
-            (sin_theta * ray.origin().x()) + (cos_theta * ray.origin().z()),
-        );
-
-        let direction = Vec3::new(
-            (cos_theta * ray.direction().x()) - (sin_theta * ray.direction().z()),
-            ray.direction().y(),
-            (sin_theta * ray.direction().x()) + (cos_theta * ray.direction().z()),
-        );
-
-        let rotated_ray = Ray4::new(origin, direction, ray.time());
+        let rotated_ray = self.object_ray(ray);
 
         // Determine whether an intersection exists in object space (and if so, where).
 
@@ -808,91 +1869,777 @@ impl Hittable for RotateY {
         // the conversion from object space to world space should not affect the normalization
         // state of the vector.
         hit.normal = normal.assert_is_normalized();
+        let to_world = |vec: Vec3| {
+            Vec3::new(
+                (cos_theta * vec.x()) + (sin_theta * vec.z()),
+                vec.y(),
+                (-sin_theta * vec.x()) + (cos_theta * vec.z()),
+            )
+        };
+        hit.tangent = hit
+            .tangent
+            .map(|tangent| to_world(tangent.into()).assert_is_normalized());
+        if let Some(footprint) = &mut hit.footprint {
+            footprint.dpdx = to_world(footprint.dpdx);
+            footprint.dpdy = to_world(footprint.dpdy);
+        }
 
         Some(hit)
     }
 
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(&self.object_ray(ray))
+    }
+
     fn bounding_box(&self) -> Option<&BoundingBox3> {
-        Some(&self.bounding_box)
+        self.bounding_box.as_ref()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        let bounds = self.object.motion_bounds()?;
+        Some(bounds.map(|bbox| Self::rotate_box(&bbox, self.sin_theta, self.cos_theta)))
     }
 }
 
+/// Rotates an object by an angle about an arbitrary axis through the origin.
+///
+/// [`RotateX`], [`RotateY`] and [`RotateZ`] are cheaper for rotations about a single axis.
 #[derive(Debug)]
-pub struct ConstantMedium {
-    boundary: Rc<dyn Hittable>,
-    /// equal to -1.0 / density
-    inv_density: f64,
-    phase_fn: Rc<dyn Material>,
+pub struct Rotate {
+    object: Rc<dyn Hittable>,
+    // the rows of the matrix taking object space to world space
+    matrix: [[f64; 3]; 3],
+    bounding_box: Option<BoundingBox3>,
 }
 
-impl ConstantMedium {
-    pub fn new(boundary: Rc<dyn Hittable>, density: f64, texture: Rc<dyn Texture>) -> Self {
+impl Rotate {
+    /// Rotates `object` counterclockwise by `angle` about `axis`, when looking back along it.
+    pub fn around(object: Rc<dyn Hittable>, axis: Vec3, angle: Angle) -> Self {
+        let rows = Matrix4::rotation(axis, angle)
+            .rows()
+            .map(|row| [row[0], row[1], row[2]]);
+        let matrix = [rows[0], rows[1], rows[2]];
+        let bounding_box = object
+            .bounding_box()
+            .map(|bbox| Self::rotate_box(bbox, &matrix));
+
         Self {
-            boundary,
-            inv_density: -1.0 / density,
-            phase_fn: Isotropic::new(texture).into_mat(),
+            object,
+            matrix,
+            bounding_box,
         }
     }
 
-    pub fn colored(boundary: Rc<dyn Hittable>, density: f64, color: Color) -> Self {
-        Self {
-            boundary,
-            inv_density: -1.0 / density,
-            phase_fn: Isotropic::colored(color).into_mat(),
+    /// Finds the box enclosing `bbox` once it has been rotated.
+    fn rotate_box(bbox: &BoundingBox3, matrix: &[[f64; 3]; 3]) -> BoundingBox3 {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+        for x in [bbox.x().start(), bbox.x().end()] {
+            for y in [bbox.y().start(), bbox.y().end()] {
+                for z in [bbox.z().start(), bbox.z().end()] {
+                    let corner = Self::apply(matrix, Vec3::new(*x, *y, *z));
+                    for c in Axis::iter() {
+                        min[c] = f64::min(min[c], corner[c]);
+                        max[c] = f64::max(max[c], corner[c]);
+                    }
+                }
+            }
         }
+
+        BoundingBox3::bounded_by(&min, &max)
     }
-}
 
-impl Hittable for ConstantMedium {
-    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
-        let mut rec1 = self.boundary.hit(ray, Interval::universe())?;
-        let mut rec2 = self
-            .boundary
-            .hit(ray, Interval::new(rec1.t + 0.0001, f64::INFINITY))?;
+    fn apply(matrix: &[[f64; 3]; 3], vec: Vec3) -> Vec3 {
+        let [x, y, z] = matrix.map(|row| row[0] * vec.x() + row[1] * vec.y() + row[2] * vec.z());
+        Vec3::new(x, y, z)
+    }
 
-        if rec1.t < *ray_t.start() {
-            rec1.t = *ray_t.start();
-        }
-        if rec2.t > *ray_t.end() {
-            rec2.t = *ray_t.end();
-        }
+    /// Transforms a vector from object space to world space.
+    fn to_world(&self, vec: Vec3) -> Vec3 {
+        Self::apply(&self.matrix, vec)
+    }
 
-        if rec1.t >= rec2.t {
-            return None;
-        }
-        if rec1.t < 0.0 {
-            rec1.t = 0.0;
-        }
+    /// Transforms a vector from world space to object space; as the matrix is a rotation,
+    /// its inverse is its transpose.
+    fn to_object(&self, vec: Vec3) -> Vec3 {
+        let [x, y, z] = [0, 1, 2].map(|i| {
+            self.matrix[0][i] * vec.x() + self.matrix[1][i] * vec.y() + self.matrix[2][i] * vec.z()
+        });
+        Vec3::new(x, y, z)
+    }
 
-        let ray_len = ray.direction().len();
-        let dist_inside_boundary = (rec2.t - rec1.t) * ray_len;
-        let hit_dist = self.inv_density * f64::ln(random());
+    /// Transforms the ray from world space to object space.
+    fn object_ray(&self, ray: &Ray4) -> Ray4 {
+        ray.transformed(
+            |origin| self.to_object(Vec3::from(origin)).into(),
+            |direction| self.to_object(direction),
+        )
+    }
+}
 
-        if hit_dist > dist_inside_boundary {
-            return None;
+impl Hittable for Rotate {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(&self.object_ray(ray), ray_t)?;
+
+        hit.point = self.to_world(Vec3::from(hit.point())).into();
+        // rotations don't change the length of vectors, so normals stay normalized
+        hit.normal = self.to_world(hit.normal().into()).assert_is_normalized();
+        hit.tangent = hit
+            .tangent
+            .map(|tangent| self.to_world(tangent.into()).assert_is_normalized());
+        if let Some(footprint) = &mut hit.footprint {
+            footprint.dpdx = self.to_world(footprint.dpdx);
+            footprint.dpdy = self.to_world(footprint.dpdy);
         }
 
-        let t = rec1.t + hit_dist / ray_len;
+        Some(hit)
+    }
 
-        Some(HitRecord {
-            t,
-            point: ray.at(t),
-            normal: Vec3::new(1.0, 0.0, 0.0).assert_is_normalized(), // arbitrary
-            front_face: true,                                        // arbitrary
-            material: Rc::clone(&self.phase_fn),
-            u: f64::NAN,
-            v: f64::NAN,
-        })
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(&self.object_ray(ray))
     }
 
     fn bounding_box(&self) -> Option<&BoundingBox3> {
-        self.boundary.bounding_box()
+        self.bounding_box.as_ref()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        let bounds = self.object.motion_bounds()?;
+        Some(bounds.map(|bbox| Self::rotate_box(&bbox, &self.matrix)))
     }
 }
 
-#[cfg(test)]
+/// Defines a rotation about one of the coordinate axes, as a [`Rotate`] about that axis.
+macro_rules! axis_rotation {
+    ($(#[$doc:meta])* $name:ident, $axis:expr) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name(Rotate);
+
+        impl $name {
+            pub fn new(object: Rc<dyn Hittable>, angle: Angle) -> Self {
+                Self(Rotate::around(object, $axis, angle))
+            }
+        }
+
+        impl Hittable for $name {
+            fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+                self.0.hit(ray, ray_t)
+            }
+
+            fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+                self.0.hit_interval(ray)
+            }
+
+            fn bounding_box(&self) -> Option<&BoundingBox3> {
+                self.0.bounding_box()
+            }
+
+            fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+                self.0.motion_bounds()
+            }
+        }
+    };
+}
+
+axis_rotation!(
+    /// Rotates an object about the x axis.
+    RotateX,
+    Vec3::new(1.0, 0.0, 0.0)
+);
+axis_rotation!(
+    /// Rotates an object about the z axis.
+    RotateZ,
+    Vec3::new(0.0, 0.0, 1.0)
+);
+
+/// Places an object with an arbitrary affine transform, such as a node transform from an
+/// imported scene, in place of a chain of [`Translate`] and [`Rotate`] wrappers. The
+/// transform may also scale, shear or mirror the object.
+#[derive(Debug)]
+pub struct Transform {
+    object: Rc<dyn Hittable>,
+    // object space to world space
+    matrix: Matrix4,
+    // world space to object space
+    inverse: Matrix4,
+    // the inverse transpose, which keeps normals perpendicular to the transformed surface
+    normal_matrix: Matrix4,
+    bounding_box: Option<BoundingBox3>,
+}
+
+impl Transform {
+    /// # Panics
+    /// Panics if `matrix` isn't [affine](Matrix4::is_affine) or can't be inverted.
+    pub fn new(object: Rc<dyn Hittable>, matrix: Matrix4) -> Self {
+        assert!(
+            matrix.is_affine(),
+            "Invalid transform (expected an affine matrix)"
+        );
+        let inverse = matrix
+            .inverse()
+            .expect("Invalid transform (the matrix can't be inverted)");
+        let bounding_box = object
+            .bounding_box()
+            .map(|bbox| Self::transform_box(bbox, &matrix));
+
+        Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bounding_box,
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    /// Finds the box enclosing `bbox` once it has been transformed.
+    fn transform_box(bbox: &BoundingBox3, matrix: &Matrix4) -> BoundingBox3 {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+        for x in [bbox.x().start(), bbox.x().end()] {
+            for y in [bbox.y().start(), bbox.y().end()] {
+                for z in [bbox.z().start(), bbox.z().end()] {
+                    let corner = matrix.transform_point(&Point3::new(*x, *y, *z));
+                    for c in Axis::iter() {
+                        min[c] = f64::min(min[c], corner[c]);
+                        max[c] = f64::max(max[c], corner[c]);
+                    }
+                }
+            }
+        }
+
+        BoundingBox3::bounded_by(&min, &max)
+    }
+
+    /// Transforms the ray from world space to object space. The direction isn't
+    /// renormalized, so distances along the ray are the same in both spaces.
+    fn object_ray(&self, ray: &Ray4) -> Ray4 {
+        ray.transformed(
+            |origin| self.inverse.transform_point(&origin),
+            |direction| self.inverse.transform_vector(&direction),
+        )
+    }
+
+    /// Transforms a hit found in object space into world space.
+    fn hit_to_world(mut hit: HitRecord, matrix: &Matrix4, normal_matrix: &Matrix4) -> HitRecord {
+        hit.point = matrix.transform_point(&hit.point);
+        hit.normal = normal_matrix.transform_vector(&hit.normal.into()).as_unit();
+        hit.tangent = hit
+            .tangent
+            .map(|tangent| matrix.transform_vector(&tangent.into()).as_unit());
+        if let Some(footprint) = &mut hit.footprint {
+            footprint.dpdx = matrix.transform_vector(&footprint.dpdx);
+            footprint.dpdy = matrix.transform_vector(&footprint.dpdy);
+        }
+        hit
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let hit = self.object.hit(&self.object_ray(ray), ray_t)?;
+        Some(Self::hit_to_world(hit, &self.matrix, &self.normal_matrix))
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(&self.object_ray(ray))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.bounding_box.as_ref()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        let bounds = self.object.motion_bounds()?;
+        Some(bounds.map(|bbox| Self::transform_box(&bbox, &self.matrix)))
+    }
+}
+
+/// Moves an object by a transform which changes over the shutter interval, for motion blur
+/// on objects which can't move by themselves. At time `t`, the object is transformed by
+/// the matrices at times 0 and 1 [blended](Matrix4::lerp) by `t`, so that every point on
+/// it moves in a straight line; large rotations should be split into several steps.
+#[derive(Debug, Clone)]
+pub struct AnimatedTransform {
+    object: Rc<dyn Hittable>,
+    // object space to world space, at times 0 and 1
+    matrices: [Matrix4; 2],
+    motion_bounds: Option<[BoundingBox3; 2]>,
+    bounding_box: Option<BoundingBox3>,
+}
+
+impl AnimatedTransform {
+    /// # Panics
+    /// Panics if either matrix isn't [affine](Matrix4::is_affine) or can't be inverted.
+    pub fn new(object: Rc<dyn Hittable>, start: Matrix4, end: Matrix4) -> Self {
+        for matrix in [&start, &end] {
+            assert!(
+                matrix.is_affine() && matrix.inverse().is_some(),
+                "Invalid transform (expected an invertible affine matrix)"
+            );
+        }
+
+        // each point moves linearly, so a box fixed to the object sweeps out no more than the
+        // boxes it starts and ends in. If the object moves too, the point at time `t` is
+        // bilinear in the times of the matrix and the object, so lies within the boxes at
+        // the four combinations of the two
+        let motion_bounds = object.motion_bounds().map(|[box0, box1]| {
+            let ends = [
+                Transform::transform_box(&box0, &start),
+                Transform::transform_box(&box1, &end),
+            ];
+            if box0 == box1 {
+                ends
+            } else {
+                let crossed = BoundingBox3::extending(
+                    &Transform::transform_box(&box1, &start),
+                    &Transform::transform_box(&box0, &end),
+                );
+                let all =
+                    BoundingBox3::extending(&BoundingBox3::extending(&ends[0], &ends[1]), &crossed);
+                [all.clone(), all]
+            }
+        });
+        let bounding_box = motion_bounds
+            .as_ref()
+            .map(|[box0, box1]| BoundingBox3::extending(box0, box1));
+
+        Self {
+            object,
+            matrices: [start, end],
+            motion_bounds,
+            bounding_box,
+        }
+    }
+
+    /// The object-to-world matrix at `time`.
+    pub fn matrix_at(&self, time: f64) -> Matrix4 {
+        Matrix4::lerp(&self.matrices[0], &self.matrices[1], time)
+    }
+
+    /// The matrix at the ray's time, its inverse, and the ray in object space.
+    fn object_ray(&self, ray: &Ray4) -> Option<(Matrix4, Matrix4, Ray4)> {
+        let matrix = self.matrix_at(ray.time());
+        // a blend of two invertible matrices can still collapse, such as halfway through a
+        // mirroring
+        let inverse = matrix.inverse()?;
+        let object_ray = ray.transformed(
+            |origin| inverse.transform_point(&origin),
+            |direction| inverse.transform_vector(&direction),
+        );
+        Some((matrix, inverse, object_ray))
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let (matrix, inverse, object_ray) = self.object_ray(ray)?;
+        let hit = self.object.hit(&object_ray, ray_t)?;
+        Some(Transform::hit_to_world(hit, &matrix, &inverse.transpose()))
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        let (_, _, object_ray) = self.object_ray(ray)?;
+        self.object.hit_interval(&object_ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.bounding_box.as_ref()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.motion_bounds.clone()
+    }
+}
+
+/// A participating medium, such as smoke or fog, filling the inside of `boundary`.
+///
+/// The albedo of the medium is given by its texture, evaluated at each scattering point.
+/// The density may also vary spatially by setting a density texture
+/// (see [`ConstantMedium::with_density_texture`]).
+///
+/// A medium has no surface to map, so both textures are evaluated at `(u, v)` of `(0, 0)`;
+/// they should vary with the point instead, as [`Noise`](crate::texture::Noise) or a
+/// [`Checkerboard`](crate::texture::Checkerboard) in object space do.
+#[derive(Debug)]
+pub struct ConstantMedium {
+    boundary: Rc<dyn Hittable>,
+    /// equal to -1.0 / density
+    inv_density: f64,
+    /// Multiplies the density at each point by the texture's luminance, clamped to `0.0..=1.0`.
+    density_texture: Option<Rc<dyn Texture>>,
+    phase_fn: Rc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Rc<dyn Hittable>, density: f64, texture: Rc<dyn Texture>) -> Self {
+        Self {
+            boundary,
+            inv_density: -1.0 / density,
+            density_texture: None,
+            phase_fn: Isotropic::new(texture).into_mat(),
+        }
+    }
+
+    pub fn colored(boundary: Rc<dyn Hittable>, density: f64, color: Color) -> Self {
+        Self {
+            boundary,
+            inv_density: -1.0 / density,
+            density_texture: None,
+            phase_fn: Isotropic::colored(color).into_mat(),
+        }
+    }
+
+    /// Sets the albedo of the medium, evaluated at each scattering point.
+    pub fn with_albedo_texture(mut self, texture: Rc<dyn Texture>) -> Self {
+        self.phase_fn = Isotropic::new(texture).into_mat();
+        self
+    }
+
+    /// Scales the density of the medium by `texture`, so that its maximum density
+    /// is the density it was constructed with.
+    pub fn with_density_texture(mut self, texture: Rc<dyn Texture>) -> Self {
+        self.density_texture = Some(texture);
+        self
+    }
+
+    /// The fraction of the maximum density at `point`.
+    fn density_at(&self, point: &Point3) -> f64 {
+        match &self.density_texture {
+            Some(texture) => texture.value(0.0, 0.0, point).luminance().clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let (enter, exit) = self.boundary.hit_interval(ray)?;
+        let enter = enter.max(*ray_t.start()).max(0.0);
+        let exit = exit.min(*ray_t.end());
+        if enter >= exit {
+            return None;
+        }
+
+        let ray_len = ray.direction().len();
+        let dist_inside_boundary = (exit - enter) * ray_len;
+
+        // Delta tracking: take steps as though the medium had its maximum density everywhere,
+        // and treat each collision as real with probability equal to the local density.
+        let mut dist = 0.0;
+        let t = loop {
+            dist += self.inv_density * f64::ln(random());
+            if dist > dist_inside_boundary {
+                return None;
+            }
+
+            let t = enter + dist / ray_len;
+            if self.density_texture.is_none() || random::<f64>() < self.density_at(&ray.at(t)) {
+                break t;
+            }
+        };
+
+        Some(HitRecord {
+            t,
+            point: ray.at(t),
+            normal: Vec3::new(1.0, 0.0, 0.0).assert_is_normalized(), // arbitrary
+            front_face: true,                                        // arbitrary
+            material: Rc::clone(&self.phase_fn),
+            u: 0.0,
+            v: 0.0,
+            tangent: None,
+            footprint: None,
+            barycentrics: None,
+            primitive_index: None,
+            name: None,
+            back_face: None,
+            light_group: None,
+            max_depth: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.boundary.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.boundary.motion_bounds()
+    }
+}
+
+/// Overrides how the back of an object's surfaces is treated by the integrator, which is
+/// reported by [`HitRecord::back_face`]. By default, surfaces are double-sided
+/// ([`BackFace::Flip`]). Where these are nested, the innermost setting wins.
+#[derive(Debug, Clone)]
+pub struct Sided {
+    object: Rc<dyn Hittable>,
+    back_face: BackFace,
+}
+
+impl Sided {
+    pub fn new(object: Rc<dyn Hittable>, back_face: BackFace) -> Self {
+        Self { object, back_face }
+    }
+}
+
+impl Hittable for Sided {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(ray, ray_t)?;
+        hit.back_face.get_or_insert(self.back_face);
+        Some(hit)
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.object.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.object.motion_bounds()
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.object.bounding_sphere()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+/// Swaps which side of an object's surfaces is the front, as reported by
+/// [`HitRecord::front_face`], without rebuilding its geometry. One-sided emitters such as
+/// [`DiffuseLight`](crate::material::DiffuseLight) only emit from the front, so this turns
+/// a light to face the other way. The normal still faces against the incoming ray.
+#[derive(Debug, Clone)]
+pub struct FlipFace {
+    object: Rc<dyn Hittable>,
+}
+
+impl FlipFace {
+    pub fn new(object: Rc<dyn Hittable>) -> Self {
+        Self { object }
+    }
+}
+
+impl Hittable for FlipFace {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(ray, ray_t)?;
+        hit.front_face = !hit.front_face;
+        Some(hit)
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.object.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.object.motion_bounds()
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.object.bounding_sphere()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+/// A closed object with another cut out of it, leaving a shell between the two surfaces.
+/// The inner surface faces inwards, so that a ray crossing it from the shell passes out of
+/// the material: a [`Dielectric`](crate::material::Dielectric) shell refracts into the empty
+/// inside as glass into air. The inner object must lie within the outer one.
+#[derive(Debug, Clone)]
+pub struct Shell {
+    outer: Rc<dyn Hittable>,
+    inner: FlipFace,
+}
+
+impl Shell {
+    pub fn new(outer: Rc<dyn Hittable>, inner: Rc<dyn Hittable>) -> Self {
+        Self {
+            outer,
+            inner: FlipFace::new(inner),
+        }
+    }
+}
+
+impl Hittable for Shell {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let outer = self.outer.hit(ray, ray_t.clone());
+        let limit = outer.as_ref().map_or(*ray_t.end(), |hit| hit.t);
+        let inner = self.inner.hit(ray, Interval::new(*ray_t.start(), limit));
+        inner.or(outer)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.outer.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.outer.motion_bounds()
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.outer.bounding_sphere()
+    }
+
+    // lights are sampled by their outside
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.outer.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.outer.random(origin)
+    }
+}
+
+/// Assigns the light an object emits to a named group, which is reported by
+/// [`HitRecord::light_group`]. A camera can write the light from each group into an image of
+/// its own as it renders (see
+/// [`CameraBuilder::light_group_output`](crate::CameraBuilder::light_group_output)), so that
+/// the balance of lighting can be adjusted in compositing. Emitters
+/// outside of any group are in [`DEFAULT_LIGHT_GROUP`]. Where groups are nested, the
+/// innermost group wins.
+#[derive(Debug, Clone)]
+pub struct LightGroup {
+    group: Rc<str>,
+    object: Rc<dyn Hittable>,
+}
+
+impl LightGroup {
+    pub fn new(group: impl Into<Rc<str>>, object: Rc<dyn Hittable>) -> Self {
+        Self {
+            group: group.into(),
+            object,
+        }
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+}
+
+impl Hittable for LightGroup {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(ray, ray_t)?;
+        if hit.light_group.is_none() {
+            hit.light_group = Some(Rc::clone(&self.group));
+        }
+        Some(hit)
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.object.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.object.motion_bounds()
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.object.bounding_sphere()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+/// Overrides [`CameraBuilder::max_depth`](crate::CameraBuilder::max_depth) for paths which
+/// hit an object, so that objects which need many bounces to look right, such as a glass
+/// chandelier, don't raise the limit for the whole scene. A path which hits the object may
+/// continue until it has made `max_depth` bounces in total, whether that's more or fewer
+/// than the camera allows. Limits on each [`BounceKind`](crate::camera::BounceKind) still
+/// apply. Where overrides are nested, the innermost applies.
+#[derive(Debug)]
+pub struct DepthOverride {
+    max_depth: u32,
+    object: Rc<dyn Hittable>,
+}
+
+impl DepthOverride {
+    pub fn new(max_depth: u32, object: Rc<dyn Hittable>) -> Self {
+        Self { max_depth, object }
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+}
+
+impl Hittable for DepthOverride {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(ray, ray_t)?;
+        if hit.max_depth.is_none() {
+            hit.max_depth = Some(self.max_depth);
+        }
+        Some(hit)
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.object.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.object.motion_bounds()
+    }
+
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.object.bounding_sphere()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+#[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        boundingbox::BVHNode,
+        material::Lambertian,
+        texture::{ImageTexture, SolidColor},
+    };
     #[test]
     fn sphere_uv_conversions() {
         let uv_tests = [
@@ -904,7 +2651,472 @@ mod test {
             (Point3::new(0.0, 0.0, -1.0), Point2::new(0.75, 0.5)),
         ];
         for (point, res) in uv_tests {
-            assert_eq!(Sphere::get_uv(&point), res);
+            assert_eq!(SphereProjection::Equirectangular.uv(&point), res);
         }
     }
+
+    #[test]
+    fn ring() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let ring = Ring::new(Point3::origin(), Vec3::new(0.0, 1.0, 0.0), 1.0, 2.0, mat);
+        let bbox = ring.bounding_box().unwrap();
+        assert_eq!((*bbox.x().start(), *bbox.x().end()), (-2.0, 2.0));
+        assert!(bbox.y().size() < 0.01);
+
+        let hit = |x: f64| {
+            let ray = Ray4::new(Point3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+            ring.hit(&ray, Interval::new(0.001, f64::INFINITY))
+        };
+        // through the hole, across the ring, and outside of it
+        assert!(hit(0.5).is_none());
+        assert!((hit(1.5).unwrap().u() - 0.5).abs() < 1e-9);
+        assert!((hit(-1.25).unwrap().u() - 0.25).abs() < 1e-9);
+        assert!(hit(2.5).is_none());
+    }
+
+    #[test]
+    fn ellipsoid() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let ellipsoid = Ellipsoid::new(Point3::origin(), Vec3::new(2.0, 1.0, 1.0), mat);
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+
+        let ray = Ray4::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let hit = ellipsoid.hit(&ray, ray_t.clone()).unwrap();
+        assert!((hit.t() - 3.0).abs() < 1e-9);
+        assert!((hit.normal().x() - 1.0).abs() < 1e-9);
+
+        // x^2 / 4 + y^2 = 1 at x = 1, where the normal is along (x / 4, y)
+        let ray = Ray4::new(Point3::new(1.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = ellipsoid.hit(&ray, ray_t.clone()).unwrap();
+        let y = 0.75_f64.sqrt();
+        assert!((hit.point().y() - y).abs() < 1e-9);
+        let expected = Vec3::new(0.25, y, 0.0).as_unit();
+        assert!(hit.normal().dot(&expected) > 1.0 - 1e-9);
+
+        // within the bounding box, but outside the ellipsoid
+        let ray = Ray4::new(Point3::new(1.9, 0.9, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(ellipsoid.hit(&ray, ray_t).is_none());
+    }
+
+    #[test]
+    fn rounded_box() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let rounded = RoundedBox::new(
+            &Point3::new(-1.0, -1.0, -1.0),
+            &Point3::new(1.0, 1.0, 1.0),
+            0.5,
+            mat,
+        );
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let hit = |origin: Point3, direction: Vec3| {
+            rounded.hit(&Ray4::new(origin, direction, 0.0), ray_t.clone())
+        };
+
+        // the flat middle of a face
+        let face = hit(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((face.t() - 4.0).abs() < 1e-6);
+        assert!((face.normal().z() - 1.0).abs() < 1e-9);
+
+        // the corner is cut away by a sphere of radius 0.5 around (0.5, 0.5, 0.5)
+        let diagonal = Vec3::new(-1.0, -1.0, -1.0);
+        let corner = hit(Point3::new(3.0, 3.0, 3.0), diagonal).unwrap();
+        let expected = 2.5 - 0.5 / 3.0_f64.sqrt();
+        assert!((corner.t() - expected).abs() < 1e-6);
+        assert!((corner.normal().x() - 1.0 / 3.0_f64.sqrt()).abs() < 1e-6);
+
+        // past the rounded edge, where a sharp box would be hit
+        assert!(hit(Point3::new(0.95, 0.95, 5.0), Vec3::new(0.0, 0.0, -1.0)).is_none());
+
+        // from inside, as through glass
+        let inside = hit(Point3::origin(), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((inside.t() - 1.0).abs() < 1e-6);
+        assert!(!inside.front_face());
+    }
+
+    #[test]
+    fn sphere_projections() {
+        let close = |a: Point2, b: Point2| (a - b).len() < 1e-9;
+
+        // the centre of each face of the cube map
+        let cube = SphereProjection::CubeMap;
+        assert!(close(
+            cube.uv(&Point3::new(1.0, 0.0, 0.0)),
+            Point2::new(1.0 / 6.0, 0.75)
+        ));
+        assert!(close(
+            cube.uv(&Point3::new(0.0, -1.0, 0.0)),
+            Point2::new(1.0 / 6.0, 0.25)
+        ));
+        assert!(close(
+            cube.uv(&Point3::new(0.0, 0.0, -1.0)),
+            Point2::new(5.0 / 6.0, 0.25)
+        ));
+
+        let angular = SphereProjection::Angular;
+        assert!(close(
+            angular.uv(&Point3::new(0.0, 0.0, 1.0)),
+            Point2::new(0.5, 0.5)
+        ));
+        // a quarter turn from the centre is halfway to the rim
+        assert!(close(
+            angular.uv(&Point3::new(1.0, 0.0, 0.0)),
+            Point2::new(0.75, 0.5)
+        ));
+        assert!(close(
+            angular.uv(&Point3::new(0.0, -1.0, 0.0)),
+            Point2::new(0.5, 0.25)
+        ));
+
+        // a quarter turn counterclockwise brings -x's texture around to +z
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let sphere = Sphere::stationary(Point3::origin(), 1.0, mat)
+            .with_texture_rotation(Angle::degrees(90.0));
+        let uv = sphere.get_uv(&Point3::new(0.0, 0.0, 1.0));
+        assert!(close(uv, Point2::new(0.0, 0.5)) || close(uv, Point2::new(1.0, 0.5)));
+    }
+
+    #[test]
+    fn medium_without_density_is_never_hit() {
+        let boundary = Sphere::stationary(
+            Point3::origin(),
+            1.0,
+            Isotropic::colored(Color::white()).into_mat(),
+        );
+        let medium = ConstantMedium::colored(boundary.hittable(), 100.0, Color::white())
+            .with_density_texture(SolidColor::new(Color::black()).into_texture());
+
+        let ray = Ray4::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        for _ in 0..100 {
+            assert!(medium
+                .hit(&ray, Interval::new(0.0, f64::INFINITY))
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn medium_textures_get_coordinates() {
+        // image textures reject coordinates outside of 0..=1, such as NaN
+        let white = || ImageTexture::new(vec![255; 3], (1, 1)).into_texture();
+        let boundary = Sphere::stationary(
+            Point3::origin(),
+            1.0,
+            Isotropic::colored(Color::white()).into_mat(),
+        );
+        let medium = ConstantMedium::colored(boundary.hittable(), 100.0, Color::black())
+            .with_density_texture(white())
+            .with_albedo_texture(white());
+
+        let ray = Ray4::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = medium.hit(&ray, Interval::new(0.0, f64::INFINITY)).unwrap();
+        assert_eq!((hit.u(), hit.v()), (0.0, 0.0));
+        let bsdf = hit.material().bsdf(&ray, &hit).unwrap();
+        let wo = (-ray.direction()).as_unit();
+        let wi = Vec3::new(0.0, 1.0, 0.0);
+        assert!(bsdf.eval(&wo, &wi).luminance() > 0.0);
+    }
+
+    #[test]
+    fn rotations() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let sphere = Sphere::stationary(Point3::new(3.0, 0.0, 0.0), 1.0, mat).hittable();
+
+        // about the y axis, matches RotateY
+        let around = Rotate::around(
+            Rc::clone(&sphere),
+            Vec3::new(0.0, 2.0, 0.0),
+            Angle::radians(0.7),
+        );
+        let rotate_y = RotateY::new(Rc::clone(&sphere), Angle::radians(0.7));
+        let ray = Ray4::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(2.3, 0.1, -1.9), 0.0);
+        let (a, b) = (
+            around.hit(&ray, Interval::universe()).unwrap(),
+            rotate_y.hit(&ray, Interval::universe()).unwrap(),
+        );
+        assert!((a.t() - b.t()).abs() < 1e-9);
+        assert!(a.normal().dot(&b.normal()) > 1.0 - 1e-9);
+
+        // a quarter turn about x takes +y to +z, and about z takes +x to +y
+        let sphere = Sphere::stationary(
+            Point3::new(0.0, 3.0, 0.0),
+            1.0,
+            Lambertian::solid(Color::white()).into_mat(),
+        )
+        .hittable();
+        let bbox = RotateX::new(sphere, Angle::degrees(90.0))
+            .bounding_box()
+            .unwrap()
+            .clone();
+        assert!((bbox.z().start() - 2.0).abs() < 1e-3 && (bbox.z().end() - 4.0).abs() < 1e-3);
+        assert!((bbox.y().start() + 1.0).abs() < 1e-3 && (bbox.y().end() - 1.0).abs() < 1e-3);
+
+        let rotated = RotateZ::new(Rc::clone(&rotate_y.object), Angle::degrees(90.0));
+        let ray = Ray4::new(Point3::origin(), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let hit = rotated.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.t() - 2.0).abs() < 1e-9);
+        assert!((hit.point().y() - 2.0).abs() < 1e-9);
+        assert!(hit.normal().y() < -1.0 + 1e-9);
+    }
+
+    #[test]
+    fn transform() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let sphere = Sphere::stationary(Point3::origin(), 1.0, mat).hittable();
+
+        // stretched to an ellipsoid 4 wide along x, then moved to (0, 0, -5)
+        let matrix = Matrix4::translation(Vec3::new(0.0, 0.0, -5.0))
+            * Matrix4::scaling(Vec3::new(2.0, 1.0, 1.0));
+        let transformed = Transform::new(sphere, matrix);
+        let bbox = transformed.bounding_box().unwrap();
+        assert!((bbox.x().start() + 2.0).abs() < 1e-3 && (bbox.x().end() - 2.0).abs() < 1e-3);
+
+        let ray = Ray4::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let hit = transformed
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!((hit.t() - 2.0).abs() < 1e-9);
+        assert!((hit.point().x() - 2.0).abs() < 1e-9);
+
+        // the normal of a stretched sphere leans towards the axis it was stretched along
+        let ray = Ray4::new(Point3::new(1.0, 5.0, -5.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = transformed.hit(&ray, Interval::universe()).unwrap();
+        let expected = Vec3::new(0.5 / 2.0, 0.75_f64.sqrt(), 0.0).as_unit();
+        assert!(hit.normal().dot(&expected) > 1.0 - 1e-9);
+    }
+
+    #[test]
+    fn hit_intervals() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let sphere = Sphere::stationary(Point3::origin(), 1.0, mat).hittable();
+        let ray = Ray4::new(Point3::new(0.0, 0.0, 0.5), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        assert_eq!(sphere.hit_interval(&ray), Some((-1.5, 0.5)));
+
+        let moved = Translate::new(sphere, Vec3::new(3.0, 0.0, 0.0)).hittable();
+        let rotated = RotateY::new(moved, Angle::degrees(90.0));
+        // the sphere is now centered at (0, 0, -3)
+        let ray = Ray4::new(Point3::origin(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let (enter, exit) = rotated.hit_interval(&ray).unwrap();
+        assert!((enter - 2.0).abs() < 1e-9 && (exit - 4.0).abs() < 1e-9);
+        assert!(rotated
+            .hit_interval(&Ray4::new(Point3::origin(), Vec3::new(0.0, 1.0, 0.0), 0.0))
+            .is_none());
+
+        // a medium is only entered where the ray is inside its boundary
+        let medium = ConstantMedium::colored(rotated.hittable(), 1e6, Color::white());
+        let hit = medium
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!((2.0..2.001).contains(&hit.t()));
+    }
+
+    #[test]
+    fn slabs() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let floor = Slab::new(Axis::Y, Interval::new(-1.0, 0.0), mat);
+        assert!(floor.bounding_box().is_none());
+
+        let down = Ray4::new(Point3::new(3.5, 2.0, -7.25), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = floor
+            .hit(&down, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert_eq!(hit.t(), 2.0);
+        assert_eq!(hit.normal().y(), 1.0);
+        assert_eq!((hit.u(), hit.v()), (0.75, 0.5));
+        assert_eq!(floor.hit_interval(&down), Some((2.0, 3.0)));
+
+        // parallel rays are inside the slab everywhere, or nowhere
+        let inside = Ray4::new(Point3::new(0.0, -0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert_eq!(
+            floor.hit_interval(&inside),
+            Some((f64::NEG_INFINITY, f64::INFINITY))
+        );
+        let above = Ray4::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(floor.hit_interval(&above).is_none());
+    }
+
+    #[test]
+    fn footprints_follow_differentials() {
+        // a 4x2 parallelogram facing the ray, rotated a quarter turn
+        let quad = Parallelogram::new(
+            Point3::new(-2.0, -1.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Isotropic::colored(Color::white()).into_mat(),
+        );
+        let rotated = RotateY::new(quad.hittable(), Angle::degrees(90.0));
+
+        // neighbouring rays are 0.01 apart at the surface, 5 units away
+        let origin = Point3::new(-5.0, 0.0, 0.0);
+        let differentials = crate::ray::RayDifferentials {
+            rx_origin: origin,
+            rx_direction: Vec3::new(1.0, 0.0, 0.002),
+            ry_origin: origin,
+            ry_direction: Vec3::new(1.0, 0.002, 0.0),
+        };
+        let ray =
+            Ray4::new(origin, Vec3::new(1.0, 0.0, 0.0), 0.0).with_differentials(differentials);
+        let hit = rotated
+            .hit(&ray, Interval::new(0.0, f64::INFINITY))
+            .unwrap();
+
+        let footprint = hit.footprint().unwrap();
+        assert!((footprint.dpdx - Vec3::new(0.0, 0.0, 0.01)).len() < 1e-9);
+        assert!((footprint.dpdy - Vec3::new(0.0, 0.01, 0.0)).len() < 1e-9);
+        // u runs along the 4-unit edge, and v along the 2-unit edge
+        assert!((footprint.duvdx.len() - 0.0025).abs() < 1e-9);
+        assert!((footprint.duvdy.y() - 0.005).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shared_triangle_edges_are_watertight() {
+        let mat = Isotropic::colored(Color::white()).into_mat();
+        let (a, b) = (Point3::new(-0.3, -0.7, 0.1), Point3::new(0.9, 0.3, -0.2));
+        let first = Triangle::from_points(a, b, Point3::new(-0.5, 0.8, 0.0), Rc::clone(&mat));
+        let second = Triangle::from_points(b, a, Point3::new(0.7, -0.9, 0.3), mat);
+
+        // rays aimed at points along the shared edge (away from its ends) must hit at least one of the triangles
+        let origin = Point3::new(0.1, 0.2, -3.0);
+        for i in 1..1000 {
+            let f = f64::from(i) / 1000.0;
+            let target = Point3::new(
+                a.x() + f * (b.x() - a.x()),
+                a.y() + f * (b.y() - a.y()),
+                a.z() + f * (b.z() - a.z()),
+            );
+            let ray = Ray4::new(origin, target - origin, 0.0);
+            let interval = Interval::new(0.0, f64::INFINITY);
+            assert!(
+                first.hit(&ray, interval.clone()).is_some() || second.hit(&ray, interval).is_some(),
+                "ray through {target:?} slipped between the triangles"
+            );
+        }
+    }
+
+    /// The infinite plane `y = 0`.
+    #[derive(Debug)]
+    struct Floor(Rc<dyn Material>);
+
+    impl Hittable for Floor {
+        fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+            let t = -ray.origin().y() / ray.direction().y();
+            if !ray_t.surrounds(t) {
+                return None;
+            }
+            let normal = Direction::PositiveY.unit();
+            let point = ray.at(t);
+            let (u, v) = (point.x(), point.z());
+            Some(HitRecord::from_incoming_ray(
+                ray,
+                &point,
+                &normal,
+                t,
+                u,
+                v,
+                Rc::clone(&self.0),
+            ))
+        }
+
+        fn bounding_box(&self) -> Option<&BoundingBox3> {
+            None
+        }
+    }
+
+    #[test]
+    fn unbounded_objects() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let floor = Floor(Rc::clone(&mat)).hittable();
+        let sphere = Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 0.5, mat).hittable();
+
+        let mut list = HittableVec::new();
+        list.add(Rc::clone(&sphere));
+        list.add(Rc::clone(&floor));
+        assert!(list.bounding_box().is_none());
+
+        let moved = Translate::new(Rc::clone(&floor), Vec3::new(0.0, 1.0, 0.0));
+        assert!(moved.bounding_box().is_none());
+        let rotated = RotateY::new(Rc::clone(&floor), Angle::radians(1.0));
+        assert!(rotated.bounding_box().is_none());
+
+        let bvh = BVHNode::new(vec![sphere, floor, moved.hittable(), rotated.hittable()]);
+        assert!(bvh.bounding_box().is_none());
+        let hit = |x: f64| {
+            let ray = Ray4::new(Point3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+            bvh.hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .unwrap()
+                .t()
+        };
+        assert!((hit(0.0) - 3.5).abs() < 1e-9);
+        assert!((hit(10.0) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn animated_transform() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let sphere = Sphere::stationary(Point3::origin(), 0.5, mat).hittable();
+        let moving = AnimatedTransform::new(
+            Rc::clone(&sphere),
+            Matrix4::identity(),
+            Matrix4::translation(Vec3::new(2.0, 0.0, 0.0)),
+        );
+        let hit = |x: f64, time: f64| {
+            let ray = Ray4::new(Point3::new(x, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), time);
+            moving.hit(&ray, Interval::universe())
+        };
+        assert!(hit(0.0, 0.0).is_some() && hit(0.0, 1.0).is_none());
+        let record = hit(1.0, 0.5).unwrap();
+        assert!((record.t() - 4.5).abs() < 1e-9);
+        assert!((record.normal().z() + 1.0).abs() < 1e-9);
+
+        // the box sweeps across the whole path, and each end fits the sphere at that time
+        let bbox = moving.bounding_box().unwrap();
+        assert!((bbox.x().start() + 0.5).abs() < 1e-3 && (bbox.x().end() - 2.5).abs() < 1e-3);
+        let [start, end] = moving.motion_bounds().unwrap();
+        assert!((start.x().end() - 0.5).abs() < 1e-3 && (end.x().start() - 1.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hollow_sphere() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let shell = Sphere::stationary(Point3::origin(), 1.0, mat).hollow(0.25);
+        let ray = Ray4::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = |t: f64| shell.hit(&ray, Interval::new(t, f64::INFINITY)).unwrap();
+
+        // into the shell, out into the hollow, back into the shell, then out again
+        let faces = [(1.0, true), (1.25, false), (2.75, true), (3.0, false)];
+        let mut t = 0.0;
+        for (expected, front_face) in faces {
+            let record = hit(t);
+            assert!((record.t() - expected).abs() < 1e-9);
+            assert_eq!(record.front_face(), front_face);
+            t = record.t() + 1e-6;
+        }
+        assert!(shell.hit(&ray, Interval::new(t, f64::INFINITY)).is_none());
+    }
+
+    #[test]
+    fn flip_face() {
+        use crate::material::DiffuseLight;
+
+        // a light facing +z, seen from behind
+        let light = Parallelogram::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            DiffuseLight::solid(Color::white()).into_mat(),
+        );
+        let ray = Ray4::new(Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let emitted = |hit: HitRecord| {
+            hit.material()
+                .emitted(&ray, &hit, hit.u(), hit.v(), &hit.point())
+        };
+
+        let hit = light.hit(&ray, Interval::universe()).unwrap();
+        assert!(!hit.front_face());
+        assert_eq!(emitted(hit), Color::black());
+
+        let hit = light.flip_face().hit(&ray, Interval::universe()).unwrap();
+        assert!(hit.front_face());
+        // the normal still faces the ray
+        assert!(hit.normal().z() < 0.0);
+        assert_eq!(emitted(hit), Color::white());
+    }
 }
@@ -0,0 +1,348 @@
+use std::rc::Rc;
+
+use super::{HitRecord, HittableVec, Triangle};
+use crate::{
+    boundingbox::{BVHNode, BoundingBox3},
+    onb::OrthonormalBasis,
+    Hittable, Interval, Material, Point2, Point3, Ray4, Vec3,
+};
+
+/// A bicubic Bézier patch: a smooth surface shaped by a 4x4 grid of control points, as used by
+/// the Utah teapot and many CAD models. The surface passes through the four corner points,
+/// and is pulled towards the others.
+///
+/// Patches are rendered by tessellating them into [`Triangle`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BezierPatch {
+    /// Control points, indexed as `[v][u]`
+    control_points: [[Point3; 4]; 4],
+}
+
+impl BezierPatch {
+    pub fn new(control_points: [[Point3; 4]; 4]) -> Self {
+        Self { control_points }
+    }
+
+    pub fn control_points(&self) -> &[[Point3; 4]; 4] {
+        &self.control_points
+    }
+
+    /// The point on the surface at parameters `(u, v)`, each from `0.0..=1.0`.
+    pub fn point(&self, u: f64, v: f64) -> Point3 {
+        let bu = bernstein(u);
+        let bv = bernstein(v);
+
+        let mut sum = Vec3::empty();
+        for (row, weight_v) in self.control_points.iter().zip(bv) {
+            for (point, weight_u) in row.iter().zip(bu) {
+                sum += Vec3::from(*point) * (weight_u * weight_v);
+            }
+        }
+        Point3::from(sum)
+    }
+
+    /// Splits the patch into a `resolution` by `resolution` grid of quads, each made of two
+    /// triangles. Texture coordinates follow the patch's `(u, v)` parameters.
+    /// Degenerate triangles, where control points coincide, are left out.
+    pub fn tessellate(&self, resolution: u32, material: Rc<dyn Material>) -> Vec<Triangle> {
+        assert!(resolution > 0, "Invalid tessellation resolution");
+
+        let step = 1.0 / f64::from(resolution);
+        let uv = |i: u32, j: u32| Point2::new(f64::from(i) * step, f64::from(j) * step);
+        let vertex = |i: u32, j: u32| {
+            let uv = uv(i, j);
+            self.point(uv.x(), uv.y())
+        };
+
+        let mut triangles = Vec::with_capacity((resolution * resolution * 2) as usize);
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+                    let [p0, p1, p2] =
+                        [corners[a], corners[b], corners[c]].map(|(i, j)| vertex(i, j));
+                    if (p1 - p0).cross(&(p2 - p0)).near_zero() {
+                        continue;
+                    }
+
+                    let uvs = [corners[a], corners[b], corners[c]].map(|(i, j)| uv(i, j));
+                    triangles.push(
+                        Triangle::from_points(p0, p1, p2, Rc::clone(&material))
+                            .with_uvs(uvs)
+                            .with_index(triangles.len()),
+                    );
+                }
+            }
+        }
+        triangles
+    }
+
+    /// Tessellates the patch (see [`BezierPatch::tessellate`]) into a single hittable object.
+    pub fn into_hittable(&self, resolution: u32, material: Rc<dyn Material>) -> Rc<dyn Hittable> {
+        let triangles: Vec<Rc<dyn Hittable>> = self
+            .tessellate(resolution, material)
+            .into_iter()
+            .map(Triangle::hittable)
+            .collect();
+
+        if triangles.is_empty() {
+            // a fully degenerate patch has no surface to hit
+            return HittableVec::new().hittable();
+        }
+        BVHNode::new(triangles).hittable()
+    }
+}
+
+/// A bilinear patch: the surface swept between two opposite edges of a quadrilateral, whose
+/// four corners need not lie in a plane. Flat corners give a flat quadrilateral (the corners
+/// of a parallelogram give the same surface as a [`Parallelogram`](super::Parallelogram));
+/// lifting one corner out of the plane twists it into a saddle, which suits curved walls
+/// and draped sheets.
+///
+/// Unlike a [`BezierPatch`], the patch isn't tessellated; rays are intersected with the
+/// surface itself by Newton's method, so it stays smooth however closely it's seen.
+#[derive(Debug)]
+pub struct BilinearPatch {
+    /// Corners at `(u, v)` of `(0, 0)`, `(1, 0)`, `(0, 1)` and `(1, 1)`
+    corners: [Point3; 4],
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl BilinearPatch {
+    /// Starting parameters for the search; a ray can cross a saddle twice, so the search
+    /// starts once in each quarter of the patch to find both hits.
+    const STARTS: [(f64, f64); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+    const MAX_ITERATIONS: u32 = 16;
+
+    /// Creates a patch from its corners, in the order `(0, 0)`, `(1, 0)`, `(0, 1)`, `(1, 1)`
+    /// in `(u, v)`, so that the first two and last two are opposite edges. The front of the
+    /// patch faces the side from which the corners run counterclockwise as `0, 1, 3, 2`.
+    pub fn new(corners: [Point3; 4], material: Rc<dyn Material>) -> Self {
+        // the surface lies within the convex hull of its corners
+        let bounding_box = BoundingBox3::extending(
+            &BoundingBox3::bounded_by(&corners[0], &corners[3]),
+            &BoundingBox3::bounded_by(&corners[1], &corners[2]),
+        );
+        Self {
+            corners,
+            material,
+            bounding_box,
+        }
+    }
+
+    pub fn corners(&self) -> &[Point3; 4] {
+        &self.corners
+    }
+
+    /// The point on the surface at parameters `(u, v)`, each from `0.0..=1.0`.
+    pub fn point(&self, u: f64, v: f64) -> Point3 {
+        let [p00, p10, p01, p11] = self.corners.map(Vec3::from);
+        let bottom = p00 * (1.0 - u) + p10 * u;
+        let top = p01 * (1.0 - u) + p11 * u;
+        Point3::from(bottom * (1.0 - v) + top * v)
+    }
+
+    /// The rates of change of [`BilinearPatch::point`] along `u` and `v`.
+    pub fn partials(&self, u: f64, v: f64) -> (Vec3, Vec3) {
+        let [p00, p10, p01, p11] = self.corners;
+        let du = (p10 - p00) * (1.0 - v) + (p11 - p01) * v;
+        let dv = (p01 - p00) * (1.0 - u) + (p11 - p10) * u;
+        (du, dv)
+    }
+
+    /// Searches for the parameters where the surface meets the ray, starting from `start`.
+    ///
+    /// The ray is the line where two planes meet, with normals `planes`; the search moves
+    /// `(u, v)` until the point is on both. Returns `None` if the search strays, or doesn't
+    /// settle, or settles outside of the patch.
+    fn solve(&self, ray: &Ray4, planes: [Vec3; 2], start: (f64, f64)) -> Option<(f64, f64)> {
+        let [n1, n2] = planes;
+        let (mut u, mut v) = start;
+        for _ in 0..Self::MAX_ITERATIONS {
+            let offset = self.point(u, v) - ray.origin();
+            let (f1, f2) = (n1.dot(&offset), n2.dot(&offset));
+            let (du, dv) = self.partials(u, v);
+            let (a, b, c, d) = (n1.dot(&du), n1.dot(&dv), n2.dot(&du), n2.dot(&dv));
+
+            let det = a * d - b * c;
+            if det.abs() < 1e-14 {
+                return None;
+            }
+            let step_u = (d * f1 - b * f2) / det;
+            let step_v = (a * f2 - c * f1) / det;
+            u -= step_u;
+            v -= step_v;
+
+            if step_u.abs() + step_v.abs() < 1e-10 {
+                let range = -1e-9..=1.0 + 1e-9;
+                return (range.contains(&u) && range.contains(&v))
+                    .then(|| (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)));
+            }
+            // far outside of the patch, the search won't come back to a hit
+            if u.abs() > 4.0 || v.abs() > 4.0 {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+impl Hittable for BilinearPatch {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bounding_box.hit(&ray.ignore_time(), ray_t.clone()) {
+            return None;
+        }
+
+        let basis = OrthonormalBasis::new(&ray.direction());
+        let planes = [basis.u().into(), basis.v().into()];
+        let len_squared = ray.direction().len_squared();
+
+        let (t, u, v) = Self::STARTS
+            .into_iter()
+            .filter_map(|start| self.solve(ray, planes, start))
+            .map(|(u, v)| {
+                let t = (self.point(u, v) - ray.origin()).dot(&ray.direction()) / len_squared;
+                (t, u, v)
+            })
+            .filter(|&(t, _, _)| ray_t.contains(t))
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        let (du, dv) = self.partials(u, v);
+        let normal = du.cross(&dv);
+        // collapsed corners have no direction to face; this is relative to the size of the
+        // patch, so that small patches aren't mistaken for collapsed ones
+        if normal.len_squared() <= 1e-12 * du.len_squared() * dv.len_squared() {
+            return None;
+        }
+
+        let hit = HitRecord::from_incoming_ray(
+            ray,
+            &ray.at(t),
+            &normal.as_unit(),
+            t,
+            u,
+            v,
+            Rc::clone(&self.material),
+        );
+        Some(hit.with_uv_derivatives(du, dv))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let Some(hit) = self.hit(
+            &Ray4::new(*origin, *direction, 0.0),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        // points are sampled evenly in (u, v), which is denser where the patch is smaller
+        let (du, dv) = self.partials(hit.u(), hit.v());
+        let area = du.cross(&dv).len();
+
+        let dist_squared = hit.t() * hit.t() * direction.len_squared();
+        let cosine = (direction.dot(&hit.normal()) / direction.len()).abs();
+
+        dist_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.point(crate::rng::random::<f64>(), crate::rng::random::<f64>()) - origin
+    }
+}
+
+/// The four cubic Bernstein polynomials, evaluated at `t`.
+pub(super) fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    #[test]
+    fn flat_patch() {
+        // control points evenly spaced on the unit square produce the square itself
+        let control_points = std::array::from_fn(|j| {
+            std::array::from_fn(|i| Point3::new(i as f64 / 3.0, j as f64 / 3.0, 0.0))
+        });
+        let patch = BezierPatch::new(control_points);
+
+        let p = patch.point(0.25, 0.75);
+        assert!((p.x() - 0.25).abs() < 1e-9 && (p.y() - 0.75).abs() < 1e-9);
+
+        let material = Lambertian::solid(Color::white()).into_mat();
+        assert_eq!(patch.tessellate(4, material).len(), 32);
+    }
+
+    #[test]
+    fn bilinear_patch() {
+        let material = Lambertian::solid(Color::white()).into_mat();
+        let down = Vec3::new(0.0, 0.0, -1.0);
+
+        // a flat patch is hit like the parallelogram it spans
+        let flat = BilinearPatch::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(2.0, 1.0, 0.0),
+            ],
+            Rc::clone(&material),
+        );
+        let ray = Ray4::new(Point3::new(0.5, 0.75, 1.0), down, 0.0);
+        let hit = flat.hit(&ray, Interval::universe()).unwrap();
+        assert!(hit.front_face());
+        assert!((hit.t() - 1.0).abs() < 1e-9);
+        assert!((hit.u() - 0.25).abs() < 1e-9 && (hit.v() - 0.75).abs() < 1e-9);
+        let ray = Ray4::new(Point3::new(2.5, 0.5, 1.0), down, 0.0);
+        assert!(flat.hit(&ray, Interval::universe()).is_none());
+
+        // however small the patch is
+        let tiny = BilinearPatch::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(2e-5, 0.0, 0.0),
+                Point3::new(0.0, 1e-5, 0.0),
+                Point3::new(2e-5, 1e-5, 0.0),
+            ],
+            Rc::clone(&material),
+        );
+        let ray = Ray4::new(Point3::new(0.5e-5, 0.75e-5, 1e-5), down, 0.0);
+        let hit = tiny.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.u() - 0.25).abs() < 1e-6 && (hit.v() - 0.75).abs() < 1e-6);
+
+        // a saddle, with z = (2u - 1)(2v - 1)
+        let saddle = BilinearPatch::new(
+            [
+                Point3::new(0.0, 0.0, 1.0),
+                Point3::new(1.0, 0.0, -1.0),
+                Point3::new(0.0, 1.0, -1.0),
+                Point3::new(1.0, 1.0, 1.0),
+            ],
+            material,
+        );
+        let ray = Ray4::new(Point3::new(0.8, 0.9, 5.0), down, 0.0);
+        let hit = saddle.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.point().z() - 0.6 * 0.8).abs() < 1e-9);
+        assert!((hit.u() - 0.8).abs() < 1e-9 && (hit.v() - 0.9).abs() < 1e-9);
+
+        // along the diagonal the saddle curves up at both ends, so a flat ray crosses it
+        // twice, and hits the nearer crossing first
+        let ray = Ray4::new(Point3::new(-0.5, -0.5, 0.5), Vec3::new(1.0, 1.0, 0.0), 0.0);
+        let offset = 0.5 * f64::sqrt(0.5);
+        let hit = saddle.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.u() - (0.5 - offset)).abs() < 1e-9);
+        assert!((saddle.point(hit.u(), hit.v()) - hit.point()).len() < 1e-9);
+        let further = saddle
+            .hit(&ray, Interval::new(hit.t() + 0.001, f64::INFINITY))
+            .unwrap();
+        assert!((further.u() - (0.5 + offset)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,207 @@
+use std::rc::Rc;
+
+use super::{bezier::bernstein, HitRecord, Hittable};
+use crate::{boundingbox::BoundingBox3, Interval, Material, Point3, Ray4, Vec3};
+
+/// A thin tube following a cubic Bézier curve, such as a strand of hair, fur, or grass.
+/// The radius tapers linearly from one end to the other.
+///
+/// The curve is refined into a chain of straight segments, each intersected as a capsule.
+/// Hits record the curve's direction as their [`HitRecord::tangent`], for use by
+/// [`Hair`](crate::material::Hair).
+#[derive(Debug)]
+pub struct Curve {
+    control_points: [Point3; 4],
+    radii: (f64, f64),
+    /// Points along the curve, at the ends of each segment
+    points: Vec<Point3>,
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl Curve {
+    pub fn new(
+        control_points: [Point3; 4],
+        start_radius: f64,
+        end_radius: f64,
+        material: Rc<dyn Material>,
+    ) -> Self {
+        assert!(
+            start_radius >= 0.0 && end_radius >= 0.0,
+            "Invalid curve radius (expected >= 0.0)"
+        );
+
+        let mut curve = Self {
+            control_points,
+            radii: (start_radius, end_radius),
+            points: Vec::new(),
+            material,
+            bounding_box: BoundingBox3::empty(),
+        };
+        curve.refine(16);
+        curve
+    }
+
+    /// Sets the number of straight segments the curve is split into. More segments follow
+    /// tightly bent curves more closely.
+    pub fn with_segments(mut self, segments: u32) -> Self {
+        assert!(segments > 0, "Invalid segment count");
+        self.refine(segments);
+        self
+    }
+
+    fn refine(&mut self, segments: u32) {
+        self.points = (0..=segments)
+            .map(|i| {
+                let weights = bernstein(f64::from(i) / f64::from(segments));
+                let mut sum = Vec3::empty();
+                for (point, weight) in self.control_points.iter().zip(weights) {
+                    sum += Vec3::from(*point) * weight;
+                }
+                Point3::from(sum)
+            })
+            .collect();
+
+        // the curve lies within the convex hull of its control points
+        let radius = self.radii.0.max(self.radii.1);
+        let extent = Vec3::new(radius, radius, radius);
+        self.bounding_box =
+            self.control_points
+                .iter()
+                .fold(BoundingBox3::empty(), |bbox, point| {
+                    BoundingBox3::extending(
+                        &bbox,
+                        &BoundingBox3::bounded_by(&(*point - extent), &(*point + extent)),
+                    )
+                });
+    }
+
+    /// The radius of the curve at parameter `t`, from `0.0..=1.0`.
+    fn radius(&self, t: f64) -> f64 {
+        self.radii.0 + (self.radii.1 - self.radii.0) * t
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let segments = self.points.len() - 1;
+        let ray_len = ray.direction().len();
+        let direction = ray.direction() / ray_len;
+
+        let mut closest: Option<(f64, Point3, Vec3, Vec3, f64)> = None;
+        for (i, ends) in self.points.windows(2).enumerate() {
+            let (a, b) = (ends[0], ends[1]);
+            let radius = self.radius((i as f64 + 0.5) / segments as f64);
+            let max_t = closest.map_or(*ray_t.end(), |(t, ..)| t);
+
+            let Some((t, axis_t)) = capsule(ray.origin(), direction, a, b, radius) else {
+                continue;
+            };
+            let t = t / ray_len;
+            if !ray_t.surrounds(t) || t >= max_t {
+                continue;
+            }
+
+            let point = ray.at(t);
+            let axis = b - a;
+            let normal = point - (a + axis * axis_t);
+            let u = (i as f64 + axis_t) / segments as f64;
+            closest = Some((t, point, normal, axis, u));
+        }
+
+        let (t, point, normal, axis, u) = closest?;
+        Some(
+            HitRecord::from_incoming_ray(
+                ray,
+                &point,
+                &normal.as_unit(),
+                t,
+                u,
+                0.0,
+                Rc::clone(&self.material),
+            )
+            .with_tangent(axis.as_unit()),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+}
+
+/// Intersects a ray (with a unit `direction`) with the capsule around the segment from `a` to
+/// `b`. Returns the distance to the first hit, and how far along the segment (`0.0..=1.0`)
+/// the closest point on the axis is.
+fn capsule(
+    origin: Point3,
+    direction: Vec3,
+    a: Point3,
+    b: Point3,
+    radius: f64,
+) -> Option<(f64, f64)> {
+    let ba = b - a;
+    let oa = origin - a;
+    let baba = ba.dot(&ba);
+    let bard = ba.dot(&direction);
+    let baoa = ba.dot(&oa);
+    let rdoa = direction.dot(&oa);
+    let oaoa = oa.dot(&oa);
+
+    // the side of the tube
+    let qa = baba - bard * bard;
+    let qb = baba * rdoa - baoa * bard;
+    let qc = baba * oaoa - baoa * baoa - radius * radius * baba;
+    let discriminant = qb * qb - qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // where along the axis the ray meets the tube
+    let y = if qa > 0.0 {
+        let t = (-qb - discriminant.sqrt()) / qa;
+        let y = baoa + t * bard;
+        if y > 0.0 && y < baba {
+            return Some((t, y / baba));
+        }
+        y
+    } else {
+        // parallel to the axis; the first cap reached is hit
+        -bard
+    };
+
+    // the rounded caps at either end
+    let (center, axis_t) = if y <= 0.0 { (a, 0.0) } else { (b, 1.0) };
+    let oc = origin - center;
+    let hb = direction.dot(&oc);
+    let hc = oc.dot(&oc) - radius * radius;
+    let discriminant = hb * hb - hc;
+    (discriminant > 0.0).then(|| (-hb - discriminant.sqrt(), axis_t))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    #[test]
+    fn straight_strand() {
+        // a straight strand along the x axis
+        let control_points = [0.0, 1.0, 2.0, 3.0].map(|x| Point3::new(x, 0.0, 0.0));
+        let material = Lambertian::solid(Color::white()).into_mat();
+        let curve = Curve::new(control_points, 0.1, 0.1, material);
+
+        let ray = Ray4::new(Point3::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0);
+        let hit = curve
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!((hit.point().z() - 0.1).abs() < 1e-9);
+        assert!((hit.t() - 2.45).abs() < 1e-9);
+        assert!((hit.u() - 0.5).abs() < 1e-9);
+        assert!((hit.tangent().unwrap().x() - 1.0).abs() < 1e-9);
+
+        let miss = Ray4::new(Point3::new(1.5, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(curve
+            .hit(&miss, Interval::new(0.001, f64::INFINITY))
+            .is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use super::{HittableVec, Translate};
+use crate::{rng::random_range, Hittable, Vec3};
+
+/// Places copies of an object on a regular 1D, 2D, or 3D lattice, such as a row of pillars
+/// or a city of blocks.
+///
+/// The first copy is placed where the object already is; copy `[x, y, z]` is offset from it
+/// by `spacing` multiplied by its index along each axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// The number of copies along each axis
+    counts: [u32; 3],
+    /// The distance between neighbouring copies along each axis
+    spacing: Vec3,
+    /// The largest random offset applied to each copy along each axis
+    jitter: Vec3,
+}
+
+impl Grid {
+    pub fn new(counts: [u32; 3], spacing: Vec3) -> Self {
+        assert!(
+            counts.iter().all(|&n| n > 0),
+            "Invalid grid size (expected at least one copy along each axis)"
+        );
+        Self {
+            counts,
+            spacing,
+            jitter: Vec3::empty(),
+        }
+    }
+
+    /// A grid of `columns` along the x axis and `rows` along the z axis, on the ground plane.
+    pub fn planar(columns: u32, rows: u32, spacing_x: f64, spacing_z: f64) -> Self {
+        Self::new([columns, 1, rows], Vec3::new(spacing_x, 0.0, spacing_z))
+    }
+
+    /// Moves each copy by a random amount, up to `jitter` along each axis in either direction,
+    /// so that the grid looks less artificial.
+    pub fn with_jitter(mut self, jitter: Vec3) -> Self {
+        assert!(
+            jitter.x() >= 0.0 && jitter.y() >= 0.0 && jitter.z() >= 0.0,
+            "Invalid jitter (expected >= 0.0)"
+        );
+        self.jitter = jitter;
+        self
+    }
+
+    /// The number of copies in the grid.
+    pub fn len(&self) -> usize {
+        self.counts.iter().map(|&n| n as usize).product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Places a copy of `object` at every point in the grid.
+    pub fn build(&self, object: Rc<dyn Hittable>) -> HittableVec {
+        self.build_with(object, |_, object| object)
+    }
+
+    /// Places a copy of `object` at every point in the grid, after passing it through
+    /// `transform` along with its index in the grid. `transform` may rotate or scale the copy,
+    /// or replace it entirely; the result is then moved into place.
+    pub fn build_with(
+        &self,
+        object: Rc<dyn Hittable>,
+        mut transform: impl FnMut([u32; 3], Rc<dyn Hittable>) -> Rc<dyn Hittable>,
+    ) -> HittableVec {
+        let mut copies = HittableVec::with_capacity(self.len());
+        let [nx, ny, nz] = self.counts;
+
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let jitter = Vec3::new(
+                        jitter(self.jitter.x()),
+                        jitter(self.jitter.y()),
+                        jitter(self.jitter.z()),
+                    );
+                    let offset = Vec3::new(
+                        f64::from(x) * self.spacing.x(),
+                        f64::from(y) * self.spacing.y(),
+                        f64::from(z) * self.spacing.z(),
+                    ) + jitter;
+
+                    let copy = transform([x, y, z], Rc::clone(&object));
+                    copies.add(Translate::new(copy, offset).hittable());
+                }
+            }
+        }
+        copies
+    }
+}
+
+fn jitter(max: f64) -> f64 {
+    if max > 0.0 {
+        random_range(-max..=max)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hittable::Sphere, material::Lambertian, Color, Material, Point3};
+
+    #[test]
+    fn copies_are_spaced() {
+        let sphere = Sphere::stationary(
+            Point3::origin(),
+            0.5,
+            Lambertian::solid(Color::white()).into_mat(),
+        )
+        .hittable();
+
+        let grid = Grid::new([3, 2, 1], Vec3::new(2.0, 3.0, 0.0));
+        let mut indices = Vec::new();
+        let copies = grid.build_with(sphere, |index, object| {
+            indices.push(index);
+            object
+        });
+
+        assert_eq!(copies.len(), 6);
+        assert_eq!(indices.last(), Some(&[2, 1, 0]));
+
+        // the last copy is centered at (4, 3, 0)
+        let bbox = copies.bounding_box().unwrap();
+        assert_eq!(*bbox.x().end(), 4.5);
+        assert_eq!(*bbox.y().end(), 3.5);
+    }
+}
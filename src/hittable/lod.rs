@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use crate::{camera::Camera, Hittable, Vec3};
+
+/// Several versions of the same object at decreasing levels of detail, such as a mesh
+/// decimated to fewer and fewer triangles.
+///
+/// One level is chosen per camera with [`Lod::select`] before rendering, based on how large
+/// the object appears in the image. Dropping the `Lod` afterwards frees the unused levels,
+/// so distant objects only cost as much memory and BVH traversal as their coarse version.
+#[derive(Debug, Clone)]
+pub struct Lod {
+    /// Each level, along with the size (in pixels) below which it may be used,
+    /// sorted from most to least detailed. The most detailed level is used at any size.
+    levels: Vec<(f64, Rc<dyn Hittable>)>,
+}
+
+impl Lod {
+    /// Creates a set of levels whose most detailed version is `object`.
+    pub fn new(object: Rc<dyn Hittable>) -> Self {
+        Self {
+            levels: vec![(0.0, object)],
+        }
+    }
+
+    /// Adds a less detailed version of the object, which is used when the object appears
+    /// smaller than `max_size` pixels across. Where levels overlap, the one with the
+    /// smallest `max_size` is used.
+    pub fn with_level(mut self, max_size: f64, object: Rc<dyn Hittable>) -> Self {
+        assert!(max_size > 0.0, "Invalid LOD size (expected > 0.0)");
+        self.levels.push((max_size, object));
+        // the most detailed level always stays first
+        self.levels[1..].sort_by(|a, b| b.0.total_cmp(&a.0));
+        self
+    }
+
+    /// The approximate size of the object in `camera`'s image, in pixels: the projected
+    /// diameter of the [bounding sphere](Hittable::bounding_sphere) of the most detailed
+    /// level. Infinite if the camera is inside that sphere, or if the object is unbounded.
+    pub fn projected_size(&self, camera: &Camera) -> f64 {
+        let Some(sphere) = self.levels[0].1.bounding_sphere() else {
+            return f64::INFINITY;
+        };
+        let radius = sphere.radius();
+
+        let offset: Vec3 = sphere.center() - camera.center();
+        let distance = offset.len();
+        if distance <= radius {
+            return f64::INFINITY;
+        }
+        let [_, [_, fy, _], _] = camera.intrinsics();
+        // the angle subtended by the sphere, as seen from the camera
+        let half_angle = (radius / distance).asin();
+        2.0 * fy * half_angle.tan()
+    }
+
+    /// Chooses the level of detail to render with `camera`: the least detailed level whose
+    /// `max_size` is larger than the object's [projected size](Self::projected_size).
+    pub fn select(&self, camera: &Camera) -> Rc<dyn Hittable> {
+        let size = self.projected_size(camera);
+        self.levels[1..]
+            .iter()
+            .rev()
+            .find(|(max_size, _)| size < *max_size)
+            .unwrap_or(&self.levels[0])
+            .1
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hittable::Sphere, material::Lambertian, test_support::BufferWriter, Angle, CameraBuilder,
+        Color, Material, Point3,
+    };
+
+    #[test]
+    fn distant_objects_are_coarse() {
+        let mut buf = Vec::new();
+        let camera = CameraBuilder::new()
+            .dimensions(100, 100)
+            .vfov(Angle::degrees(90.0))
+            .camera_center(Point3::origin())
+            .camera_target(Point3::new(0.0, 0.0, -1.0))
+            .writer(Box::new(BufferWriter(&mut buf)))
+            .build()
+            .unwrap();
+
+        let sphere = |z: f64, radius: f64| {
+            let mat = Lambertian::solid(Color::white()).into_mat();
+            Sphere::stationary(Point3::new(0.0, 0.0, z), radius, mat).hittable()
+        };
+        let lod = |z: f64| {
+            Lod::new(sphere(z, 1.0))
+                .with_level(10.0, sphere(z, 0.1))
+                .with_level(50.0, sphere(z, 0.5))
+        };
+        let radius = |object: Rc<dyn Hittable>| *object.bounding_box().unwrap().x().end();
+
+        // 100 pixels span 2 units at a distance of 1
+        assert!((lod(-50.0).projected_size(&camera) - 2.0).abs() < 0.01);
+        assert_eq!(radius(lod(-2.0).select(&camera)), 1.0);
+        assert_eq!(radius(lod(-5.0).select(&camera)), 0.5);
+        assert_eq!(radius(lod(-50.0).select(&camera)), 0.1);
+    }
+}
@@ -0,0 +1,449 @@
+//! Indexed triangle meshes, which share vertices between faces rather than storing a separate
+//! [`Triangle`](super::Triangle) for each one.
+
+use std::{collections::HashMap, rc::Rc};
+
+mod decimate;
+mod edit;
+mod save;
+
+use super::{
+    intersect_triangle, triangle_bvh::FlatNode, triangle_bvh::NodeKind, triangle_hit_record,
+    Triangle,
+};
+use crate::{
+    boundingbox::BoundingBox3, vec::Normalized, Angle, HitRecord, Hittable, Interval, Material,
+    Point2, Point3, Ray4, Vec3,
+};
+
+/// The largest number of faces in a leaf node.
+const LEAF_SIZE: usize = 2;
+
+/// A triangle mesh stored as a vertex buffer and an index buffer, with each face listing the
+/// positions of its three corners in the vertex buffer.
+///
+/// Faces are intersected directly from the buffers through a flat hierarchy built over them,
+/// so large meshes don't need a [`Triangle`](super::Triangle) allocated for every face.
+/// Hits report the position of the face in the index buffer as their
+/// [`primitive_index`](HitRecord::primitive_index).
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    positions: Vec<Point3>,
+    indices: Vec<[u32; 3]>,
+    // texture coordinates of each vertex; if missing, the barycentric coordinates are used
+    uvs: Option<Vec<Point2>>,
+    // the shading normal at each corner of each face; if missing, faces are shaded flat
+    normals: Option<Vec<[Vec3<Normalized>; 3]>>,
+    materials: Vec<Rc<dyn Material>>,
+    // the position in `materials` of each face's material; if missing, all faces use the first
+    face_materials: Option<Vec<u32>>,
+    nodes: Vec<FlatNode>,
+    // the faces in the order the leaves of the hierarchy refer to them
+    order: Vec<u32>,
+    bbox: BoundingBox3,
+}
+
+impl Mesh {
+    /// Creates a mesh from its vertex positions and faces.
+    ///
+    /// # Panics
+    /// Panics if a face refers to a vertex that doesn't exist.
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>, material: Rc<dyn Material>) -> Self {
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < positions.len()),
+            "Invalid mesh (a face refers to a vertex that doesn't exist)"
+        );
+
+        let bounds: Vec<BoundingBox3> = indices
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.map(|i| positions[i as usize]);
+                BoundingBox3::extending(
+                    &BoundingBox3::bounded_by(&a, &b),
+                    &BoundingBox3::bounded_by(&a, &c),
+                )
+            })
+            .collect();
+        let mut order: Vec<u32> = (0..indices.len() as u32).collect();
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            Self::build(&mut order, 0, &bounds, &mut nodes);
+        }
+        let bbox = nodes
+            .first()
+            .map_or_else(BoundingBox3::empty, |node| node.bbox.clone());
+
+        Self {
+            positions,
+            indices,
+            uvs: None,
+            normals: None,
+            materials: vec![material],
+            face_materials: None,
+            nodes,
+            order,
+            bbox,
+        }
+    }
+
+    /// Builds the subtree over the faces `order` (which begin at `offset` in the full list)
+    /// as [`TriangleBVH`](super::TriangleBVH) does, given the bounding box of every face.
+    fn build(order: &mut [u32], offset: usize, bounds: &[BoundingBox3], nodes: &mut Vec<FlatNode>) {
+        let bbox = order.iter().fold(BoundingBox3::empty(), |bbox, &face| {
+            BoundingBox3::extending(&bbox, &bounds[face as usize])
+        });
+
+        if order.len() <= LEAF_SIZE {
+            nodes.push(FlatNode {
+                bbox,
+                kind: NodeKind::Leaf {
+                    start: offset as u32,
+                    count: order.len() as u32,
+                },
+            });
+            return;
+        }
+
+        let axis = bbox.longest_axis();
+        order.sort_unstable_by(|&a, &b| {
+            bounds[a as usize][axis]
+                .start()
+                .partial_cmp(bounds[b as usize][axis].start())
+                .expect("Tried to cmp a NaN value")
+        });
+
+        let this = nodes.len();
+        nodes.push(FlatNode {
+            bbox,
+            kind: NodeKind::Interior { right: 0 },
+        });
+
+        let mid = order.len() / 2;
+        let (left, right) = order.split_at_mut(mid);
+        Self::build(left, offset, bounds, nodes);
+        let right_index = nodes.len() as u32;
+        Self::build(right, offset + mid, bounds, nodes);
+        nodes[this].kind = NodeKind::Interior { right: right_index };
+    }
+
+    /// Assigns texture coordinates to each vertex, which are interpolated across the faces.
+    ///
+    /// # Panics
+    /// Panics if there isn't exactly one pair of coordinates per vertex.
+    pub fn with_uvs(mut self, uvs: Vec<Point2>) -> Self {
+        assert_eq!(
+            uvs.len(),
+            self.positions.len(),
+            "Invalid mesh uvs (expected one per vertex)"
+        );
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Shades the mesh smoothly by interpolating normals across each face, for meshes which
+    /// approximate curved surfaces but don't come with normals of their own.
+    ///
+    /// The normal at each corner is the average of the normals of the faces meeting at that
+    /// point, weighted by the angle each face makes there. Faces are only averaged together if
+    /// their normals are within `crease_angle` of each other, so hard edges (such as
+    /// the edges of a box) stay sharp. Corners are matched by position rather than index, so
+    /// meshes which don't share vertices between faces, such as STL imports, are smoothed too.
+    pub fn with_smooth_normals(mut self, crease_angle: Angle) -> Self {
+        let min_cos = crease_angle.cos();
+        let faces: Vec<[Point3; 3]> = (0..self.len()).map(|i| self.face(i)).collect();
+        let face_normals: Vec<Vec3<Normalized>> = faces
+            .iter()
+            .map(|[a, b, c]| (*b - *a).cross(&(*c - *a)).as_unit())
+            .collect();
+        // the angle of each face at each of its corners
+        let angles: Vec<[f64; 3]> = faces
+            .iter()
+            .map(|face| {
+                std::array::from_fn(|i| {
+                    let p = face[i];
+                    let to_next = (face[(i + 1) % 3] - p).as_unit();
+                    let to_prev = (face[(i + 2) % 3] - p).as_unit();
+                    to_next.dot(&to_prev).clamp(-1.0, 1.0).acos()
+                })
+            })
+            .collect();
+
+        let mut corners: HashMap<[u64; 3], Vec<(usize, usize)>> = HashMap::new();
+        for (f, face) in faces.iter().enumerate() {
+            for (i, p) in face.iter().enumerate() {
+                let key = [p.x(), p.y(), p.z()].map(f64::to_bits);
+                corners.entry(key).or_default().push((f, i));
+            }
+        }
+
+        let normals = faces
+            .iter()
+            .enumerate()
+            .map(|(f, face)| {
+                std::array::from_fn(|i| {
+                    let p = face[i];
+                    let key = [p.x(), p.y(), p.z()].map(f64::to_bits);
+                    let sum = corners[&key]
+                        .iter()
+                        .filter(|&&(g, _)| face_normals[f].dot(&face_normals[g]) >= min_cos)
+                        .fold(Vec3::empty(), |sum, &(g, j)| {
+                            sum + face_normals[g] * angles[g][j]
+                        });
+                    if sum.near_zero() {
+                        face_normals[f]
+                    } else {
+                        sum.as_unit()
+                    }
+                })
+            })
+            .collect();
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Assigns each face one of `materials`, given by its position in the list, such as the
+    /// material groups of an imported model. Replaces the material the mesh was created with.
+    ///
+    /// # Panics
+    /// Panics if there isn't exactly one assignment per face, or one refers to a material
+    /// that doesn't exist.
+    pub fn with_face_materials(
+        mut self,
+        materials: Vec<Rc<dyn Material>>,
+        face_materials: Vec<u32>,
+    ) -> Self {
+        assert_eq!(
+            face_materials.len(),
+            self.indices.len(),
+            "Invalid face materials (expected one per face)"
+        );
+        assert!(
+            face_materials
+                .iter()
+                .all(|&i| (i as usize) < materials.len()),
+            "Invalid face materials (a face refers to a material that doesn't exist)"
+        );
+        self.materials = materials;
+        self.face_materials = Some(face_materials);
+        self
+    }
+
+    /// The material of the face at `index`.
+    pub fn material(&self, index: usize) -> &Rc<dyn Material> {
+        let material = self
+            .face_materials
+            .as_ref()
+            .map_or(0, |face_materials| face_materials[index] as usize);
+        &self.materials[material]
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    /// The number of faces in the mesh.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// The three corners of the face at `index`.
+    pub fn face(&self, index: usize) -> [Point3; 3] {
+        self.indices[index].map(|i| self.positions[i as usize])
+    }
+
+    /// Each face as a separate [`Triangle`], with its texture coordinates, material and
+    /// position in the mesh; for use where individual triangles are needed, such as baking.
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        (0..self.len()).map(|index| {
+            let [p0, p1, p2] = self.face(index);
+            let triangle = Triangle::from_points(p0, p1, p2, Rc::clone(self.material(index)))
+                .with_index(index);
+            match &self.uvs {
+                Some(uvs) => triangle.with_uvs(self.indices[index].map(|i| uvs[i as usize])),
+                None => triangle,
+            }
+        })
+    }
+
+    fn hit_face(&self, index: usize, ray: &Ray4, ray_t: &Interval) -> Option<HitRecord> {
+        let vertices = self.face(index);
+        let intersection = intersect_triangle(ray, &vertices, ray_t)?;
+
+        let edge_u: Vec3 = vertices[1] - vertices[0];
+        let edge_v: Vec3 = vertices[2] - vertices[0];
+        let normal = edge_u.cross(&edge_v).as_unit();
+        let uvs = self
+            .uvs
+            .as_ref()
+            .map(|uvs| self.indices[index].map(|i| uvs[i as usize]));
+        let hit = triangle_hit_record(
+            ray,
+            (edge_u, edge_v),
+            &normal,
+            uvs.as_ref(),
+            intersection,
+            Rc::clone(self.material(index)),
+        );
+        let hit = hit.with_primitive_index(index);
+        let Some(normals) = &self.normals else {
+            return Some(hit);
+        };
+        let [b0, b1, b2] = intersection.1;
+        let [n0, n1, n2] = normals[index];
+        let normal: Vec3 = n0 * b0 + n1 * b1 + n2 * b2;
+        Some(hit.with_shading_normal(normal.as_unit()))
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let ray3 = ray.ignore_time();
+        let start = *ray_t.start();
+        let mut closest = *ray_t.end();
+        let mut result = None;
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bbox.hit(&ray3, Interval::new(start, closest)) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Interior { right } => {
+                    stack.push(right as usize);
+                    stack.push(index + 1);
+                }
+                NodeKind::Leaf {
+                    start: first,
+                    count,
+                } => {
+                    let first = first as usize;
+                    for &face in &self.order[first..first + count as usize] {
+                        let ray_t = Interval::new(start, closest);
+                        if let Some(hit) = self.hit_face(face as usize, ray, &ray_t) {
+                            closest = hit.t();
+                            result = Some(hit);
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    #[test]
+    fn smooth_normals() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        // two faces folded 90 degrees along the y axis, like the corner of a box
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+        ];
+        let mesh = Mesh::new(positions, vec![[0, 2, 1], [0, 1, 3]], mat);
+        let ray = Ray4::new(Point3::new(0.01, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        // below the crease angle, the fold stays sharp
+        let sharp = mesh.clone().with_smooth_normals(Angle::degrees(80.0));
+        let hit = sharp.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.normal().z() - 1.0).abs() < 1e-9);
+
+        // above it, the normal along the fold points out between the faces
+        let smooth = mesh.with_smooth_normals(Angle::degrees(100.0));
+        let hit = smooth.hit(&ray, Interval::universe()).unwrap();
+        assert!((hit.normal().x() - hit.normal().z()).abs() < 0.05);
+        assert!(hit.front_face());
+    }
+
+    #[test]
+    fn face_materials() {
+        let white = Lambertian::solid(Color::white()).into_mat();
+        let red = Lambertian::solid(Color::red()).into_mat();
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let mesh = Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], Rc::clone(&white))
+            .with_face_materials(vec![white, Rc::clone(&red)], vec![0, 1]);
+
+        // the upper-left half of the square is the second face
+        let ray = Ray4::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, Interval::universe()).unwrap();
+        assert!(Rc::ptr_eq(&hit.material(), &red));
+    }
+
+    #[test]
+    fn matches_triangles() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+
+        // a strip of quads at increasing depth, sharing their vertical edges
+        let mut positions = Vec::new();
+        for i in 0..=20 {
+            let x = f64::from(i) * 0.5;
+            let z = -f64::from(i);
+            positions.push(Point3::new(x, 0.0, z));
+            positions.push(Point3::new(x, 1.0, z));
+        }
+        let indices: Vec<[u32; 3]> = (0..20)
+            .flat_map(|i| {
+                let [a, b, c, d] = [2 * i, 2 * i + 2, 2 * i + 3, 2 * i + 1];
+                [[a, b, c], [a, c, d]]
+            })
+            .collect();
+        let mesh = Mesh::new(positions, indices, Rc::clone(&mat));
+        assert_eq!(mesh.len(), 40);
+
+        let triangles: Vec<Triangle> = (0..mesh.len())
+            .map(|i| {
+                let [a, b, c] = mesh.face(i);
+                Triangle::from_points(a, b, c, Rc::clone(&mat)).with_index(i)
+            })
+            .collect();
+        for i in 0..100 {
+            let x = f64::from(i) * 0.1 - 0.05;
+            let ray = Ray4::new(Point3::new(x, 0.3, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let ray_t = Interval::new(0.001, f64::INFINITY);
+            let expected = triangles
+                .iter()
+                .filter_map(|tri| tri.hit(&ray, ray_t.clone()))
+                .min_by(|a, b| a.t().total_cmp(&b.t()));
+            let actual = mesh.hit(&ray, ray_t);
+            assert_eq!(expected.is_some(), actual.is_some());
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                assert_eq!(expected.t(), actual.t());
+                assert_eq!(expected.primitive_index(), actual.primitive_index());
+                assert_eq!(expected.u(), actual.u());
+            }
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Simplification of dense meshes by repeatedly collapsing the edge whose removal changes the
+//! surface least, following Garland & Heckbert's "Surface Simplification Using Quadric Error
+//! Metrics" (1997).
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    rc::Rc,
+};
+
+use super::Mesh;
+use crate::{Point3, Vec3};
+
+/// How much more moving the boundary of an open mesh costs than moving across its faces, so
+/// that the outlines of holes and edges are kept.
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// A symmetric 4x4 matrix summing the squared distances of a point from a set of planes,
+/// stored as its upper triangle.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// The quadric of the plane through `point` with the unit normal `normal`, scaled by
+    /// `weight`.
+    fn plane(normal: Vec3, point: Point3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x(), normal.y(), normal.z());
+        let d = -(a * point.x() + b * point.y() + c * point.z());
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+
+    fn error(&self, p: &Point3) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        aa * x * x
+            + bb * y * y
+            + cc * z * z
+            + 2.0 * (ab * x * y + ac * x * z + bc * y * z)
+            + 2.0 * (ad * x + bd * y + cd * z)
+            + dd
+    }
+}
+
+/// Merging the vertex `remove` into `keep`, which moves to `position`.
+#[derive(Debug)]
+struct Collapse {
+    cost: f64,
+    keep: u32,
+    remove: u32,
+    position: Point3,
+    /// The versions of `keep` and `remove` when the collapse was found; if either has changed
+    /// since, the collapse is out of date.
+    versions: (u32, u32),
+}
+
+// ordered so that the cheapest collapse is at the top of a `BinaryHeap`
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+/// The working state of a mesh being simplified.
+struct Decimation {
+    positions: Vec<Point3>,
+    faces: Vec<[u32; 3]>,
+    live: Vec<bool>,
+    /// The faces around each vertex, including some which have since been removed
+    vertex_faces: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+}
+
+impl Decimation {
+    /// The cheapest way of collapsing the edge from `a` to `b`: moving both to either end, or
+    /// to its midpoint.
+    fn collapse(&self, a: u32, b: u32) -> Collapse {
+        let quadric = self.quadrics[a as usize].add(&self.quadrics[b as usize]);
+        let (pa, pb) = (self.positions[a as usize], self.positions[b as usize]);
+        let (cost, position) = [pa, pb, pa + (pb - pa) / 2.0]
+            .into_iter()
+            .map(|p| (quadric.error(&p), p))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+        Collapse {
+            cost,
+            keep: a,
+            remove: b,
+            position,
+            versions: (self.versions[a as usize], self.versions[b as usize]),
+        }
+    }
+
+    /// Whether carrying out `collapse` would turn any remaining face over.
+    fn flips(&self, collapse: &Collapse) -> bool {
+        let (keep, remove) = (collapse.keep, collapse.remove);
+        [keep, remove]
+            .iter()
+            .flat_map(|&v| &self.vertex_faces[v as usize])
+            .filter(|&&f| self.live[f])
+            .any(|&f| {
+                let face = self.faces[f];
+                // faces along the edge are removed entirely
+                if face.contains(&keep) && face.contains(&remove) {
+                    return false;
+                }
+                let corners = face.map(|v| self.positions[v as usize]);
+                let moved = face.map(|v| {
+                    if v == keep || v == remove {
+                        collapse.position
+                    } else {
+                        self.positions[v as usize]
+                    }
+                });
+                let normal = |[a, b, c]: [Point3; 3]| (b - a).cross(&(c - a));
+                let (before, after) = (normal(corners), normal(moved));
+                after.near_zero() || before.dot(&after) <= 0.0
+            })
+    }
+
+    /// Merges `remove` into `keep`, returning the number of faces removed.
+    fn apply(&mut self, collapse: &Collapse) -> usize {
+        let (keep, remove) = (collapse.keep as usize, collapse.remove as usize);
+        self.positions[keep] = collapse.position;
+        self.quadrics[keep] = self.quadrics[keep].add(&self.quadrics[remove]);
+        self.versions[keep] += 1;
+        self.versions[remove] += 1;
+
+        let mut removed = 0;
+        for f in std::mem::take(&mut self.vertex_faces[remove]) {
+            if !self.live[f] {
+                continue;
+            }
+            let face = &mut self.faces[f];
+            if face.contains(&collapse.keep) {
+                self.live[f] = false;
+                removed += 1;
+            } else {
+                for v in face.iter_mut().filter(|v| **v == collapse.remove) {
+                    *v = collapse.keep;
+                }
+                self.vertex_faces[keep].push(f);
+            }
+        }
+        self.vertex_faces[keep].retain(|&f| self.live[f]);
+        removed
+    }
+
+    /// The vertices joined to `v` by an edge of a remaining face.
+    fn neighbours(&self, v: u32) -> Vec<u32> {
+        let mut neighbours: Vec<u32> = self.vertex_faces[v as usize]
+            .iter()
+            .flat_map(|&f| self.faces[f])
+            .filter(|&n| n != v)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+}
+
+impl Mesh {
+    /// Simplifies the mesh down to at most `target_faces` faces (where possible) by collapsing
+    /// edges, so that extremely dense meshes such as scans can be rendered with less memory.
+    /// Edges across flat areas are collapsed first, while edges along creases, and the
+    /// boundaries of open meshes, are kept for as long as possible.
+    ///
+    /// Vertices with the same position (and texture coordinates) are merged first, so meshes
+    /// which don't share vertices between faces, such as STL imports, are simplified too.
+    /// Texture coordinates and face materials are kept, but smooth normals are not; apply
+    /// [`Mesh::with_smooth_normals`] after simplifying.
+    pub fn decimated(self, target_faces: usize) -> Self {
+        if self.len() <= target_faces {
+            return self;
+        }
+
+        // merge coincident vertices, so that faces which only meet by position are joined
+        let mut merged: HashMap<[u64; 5], u32> = HashMap::new();
+        let canonical: Vec<u32> = (0..self.positions.len())
+            .map(|i| {
+                let p = self.positions[i];
+                let (u, v) = self
+                    .uvs
+                    .as_ref()
+                    .map_or((0.0, 0.0), |uvs| (uvs[i].x(), uvs[i].y()));
+                let key = [p.x(), p.y(), p.z(), u, v].map(f64::to_bits);
+                *merged.entry(key).or_insert(i as u32)
+            })
+            .collect();
+        let faces: Vec<[u32; 3]> = self
+            .indices
+            .iter()
+            .map(|face| face.map(|v| canonical[v as usize]))
+            .collect();
+
+        let vertex_count = self.positions.len();
+        let mut state = Decimation {
+            positions: self.positions.clone(),
+            live: vec![true; faces.len()],
+            vertex_faces: vec![Vec::new(); vertex_count],
+            quadrics: vec![Quadric::default(); vertex_count],
+            versions: vec![0; vertex_count],
+            faces,
+        };
+
+        // how many faces use each edge; edges used by only one face are on the boundary
+        let mut edges: HashMap<(u32, u32), (usize, usize)> = HashMap::new();
+        for (f, face) in state.faces.iter().enumerate() {
+            let [a, b, c] = face.map(|v| state.positions[v as usize]);
+            let cross = (b - a).cross(&(c - a));
+            for (i, &v) in face.iter().enumerate() {
+                state.vertex_faces[v as usize].push(f);
+                if !cross.near_zero() {
+                    // weighted by area, so that large faces hold their shape more strongly
+                    let quadric = Quadric::plane(cross.as_unit().into(), a, cross.len() / 2.0);
+                    state.quadrics[v as usize] = state.quadrics[v as usize].add(&quadric);
+                }
+                let w = face[(i + 1) % 3];
+                edges.entry((v.min(w), v.max(w))).or_insert((0, f)).0 += 1;
+            }
+        }
+        for (&(v, w), &(count, f)) in &edges {
+            let [a, b, c] = state.faces[f].map(|v| state.positions[v as usize]);
+            let normal = (b - a).cross(&(c - a));
+            let edge: Vec3 = state.positions[w as usize] - state.positions[v as usize];
+            let across = edge.cross(&normal);
+            if count != 1 || across.near_zero() {
+                continue;
+            }
+            // a plane through the boundary edge, perpendicular to its face
+            let quadric = Quadric::plane(
+                across.as_unit().into(),
+                state.positions[v as usize],
+                BOUNDARY_WEIGHT * edge.len_squared(),
+            );
+            for v in [v, w] {
+                state.quadrics[v as usize] = state.quadrics[v as usize].add(&quadric);
+            }
+        }
+
+        let mut heap: BinaryHeap<Collapse> =
+            edges.keys().map(|&(v, w)| state.collapse(v, w)).collect();
+        let mut face_count = state.faces.len();
+        while face_count > target_faces {
+            let Some(collapse) = heap.pop() else {
+                break;
+            };
+            let current = (
+                state.versions[collapse.keep as usize],
+                state.versions[collapse.remove as usize],
+            );
+            if collapse.versions != current || state.flips(&collapse) {
+                continue;
+            }
+            face_count -= state.apply(&collapse);
+            for neighbour in state.neighbours(collapse.keep) {
+                heap.push(state.collapse(collapse.keep, neighbour));
+            }
+        }
+
+        // gather up the vertices and faces which remain
+        let mut new_index = vec![u32::MAX; vertex_count];
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::with_capacity(face_count);
+        let mut face_materials = Vec::with_capacity(face_count);
+        for (f, face) in state.faces.iter().enumerate() {
+            if !state.live[f] {
+                continue;
+            }
+            indices.push(face.map(|v| {
+                if new_index[v as usize] == u32::MAX {
+                    new_index[v as usize] = positions.len() as u32;
+                    positions.push(state.positions[v as usize]);
+                    if let Some(old) = &self.uvs {
+                        uvs.push(old[v as usize]);
+                    }
+                }
+                new_index[v as usize]
+            }));
+            if let Some(old) = &self.face_materials {
+                face_materials.push(old[f]);
+            }
+        }
+
+        let mut mesh = Mesh::new(positions, indices, Rc::clone(&self.materials[0]));
+        if self.face_materials.is_some() {
+            mesh = mesh.with_face_materials(self.materials, face_materials);
+        }
+        if self.uvs.is_some() {
+            mesh = mesh.with_uvs(uvs);
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        material::{Lambertian, Material},
+        Axis, Color, Hittable, Interval, Ray4,
+    };
+
+    #[test]
+    fn flat_grid() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        // a square of 10 by 10 quads, each split in two, without shared vertices
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let corner = |di: i32, dj: i32| {
+                    Point3::new(f64::from(i + di) / 10.0, f64::from(j + dj) / 10.0, 0.0)
+                };
+                let start = positions.len() as u32;
+                positions.extend([corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)]);
+                let [a, b, c, d] = [start, start + 1, start + 2, start + 3];
+                indices.extend([[a, b, c], [a, c, d]]);
+            }
+        }
+        let mesh = Mesh::new(positions, indices, mat);
+        assert_eq!(mesh.len(), 200);
+
+        let decimated = mesh.clone().decimated(20);
+        assert!(decimated.len() <= 20);
+        // the outline of the square is kept
+        let (bbox, original) = (
+            decimated.bounding_box().unwrap(),
+            mesh.bounding_box().unwrap(),
+        );
+        for axis in [Axis::X, Axis::Y] {
+            assert!((bbox[axis].start() - original[axis].start()).abs() < 1e-3);
+            assert!((bbox[axis].end() - original[axis].end()).abs() < 1e-3);
+        }
+        for (x, y) in [(0.05, 0.05), (0.5, 0.5), (0.95, 0.3), (0.2, 0.99)] {
+            let ray = Ray4::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let hit = decimated.hit(&ray, Interval::universe()).unwrap();
+            assert!((hit.t() - 1.0).abs() < 1e-9);
+            assert!(hit.front_face());
+        }
+    }
+}
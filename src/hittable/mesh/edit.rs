@@ -0,0 +1,222 @@
+//! Clean-up and placement of meshes, done once when they're imported so that static scenes
+//! don't need [`Translate`](crate::hittable::Translate) or [`RotateY`](crate::hittable::RotateY)
+//! wrappers transforming every ray at render time.
+
+use std::{collections::HashMap, rc::Rc};
+
+use super::Mesh;
+use crate::{vec::Normalized, Angle, Point2, Point3, Vec3};
+
+impl Mesh {
+    /// Rebuilds the mesh from new vertex data, keeping the attributes of each face in `faces`,
+    /// which pairs the face's current position in the index buffer with its new corners.
+    fn rebuilt(
+        self,
+        positions: Vec<Point3>,
+        uvs: Option<Vec<Point2>>,
+        faces: Vec<(usize, [u32; 3])>,
+    ) -> Self {
+        let indices = faces.iter().map(|&(_, face)| face).collect();
+        let mut mesh = Mesh::new(positions, indices, Rc::clone(&self.materials[0]));
+        if let Some(uvs) = uvs {
+            mesh = mesh.with_uvs(uvs);
+        }
+        if let Some(face_materials) = self.face_materials {
+            let face_materials = faces.iter().map(|&(f, _)| face_materials[f]).collect();
+            mesh = mesh.with_face_materials(self.materials, face_materials);
+        }
+        mesh.normals = self
+            .normals
+            .map(|normals| faces.iter().map(|&(f, _)| normals[f]).collect());
+        mesh
+    }
+
+    /// Merges vertices within `tolerance` of each other (and with the same texture
+    /// coordinates), so that faces which meet only by position share their corners, and
+    /// removes the faces which collapse as a result. Welding a mesh which doesn't share
+    /// vertices, such as an STL import, makes it much smaller.
+    pub fn welded(self, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0, "Invalid weld tolerance (expected >= 0.0)");
+        // vertices are looked up in a grid of cells `tolerance` wide, so only the
+        // neighbouring cells need to be searched
+        let cell = |p: &Point3| -> [i64; 3] {
+            if tolerance == 0.0 {
+                [p.x(), p.y(), p.z()].map(|c| c.to_bits() as i64)
+            } else {
+                [p.x(), p.y(), p.z()].map(|c| (c / tolerance).floor() as i64)
+            }
+        };
+        let neighbours = if tolerance == 0.0 { 0 } else { 1 };
+
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut positions: Vec<Point3> = Vec::new();
+        let mut uvs: Option<Vec<Point2>> = self.uvs.as_ref().map(|_| Vec::new());
+        let mut remap = Vec::with_capacity(self.positions.len());
+        for (i, p) in self.positions.iter().enumerate() {
+            let uv = self.uvs.as_ref().map(|uvs| uvs[i]);
+            let [x, y, z] = cell(p);
+            let existing = (-neighbours..=neighbours)
+                .flat_map(|dx| (-neighbours..=neighbours).map(move |dy| (dx, dy)))
+                .flat_map(|(dx, dy)| (-neighbours..=neighbours).map(move |dz| [dx, dy, dz]))
+                .filter_map(|[dx, dy, dz]| cells.get(&[x + dx, y + dy, z + dz]))
+                .flatten()
+                .find(|&&j| {
+                    let offset: Vec3 = positions[j as usize] - *p;
+                    offset.len() <= tolerance && uvs.as_ref().map(|uvs| uvs[j as usize]) == uv
+                });
+            let index = match existing {
+                Some(&j) => j,
+                None => {
+                    let j = positions.len() as u32;
+                    positions.push(*p);
+                    if let (Some(uvs), Some(uv)) = (&mut uvs, uv) {
+                        uvs.push(uv);
+                    }
+                    cells.entry([x, y, z]).or_default().push(j);
+                    j
+                }
+            };
+            remap.push(index);
+        }
+
+        let faces = self
+            .indices
+            .iter()
+            .map(|face| face.map(|v| remap[v as usize]))
+            .enumerate()
+            .filter(|(_, [a, b, c])| a != b && b != c && a != c)
+            .collect();
+        self.rebuilt(positions, uvs, faces)
+    }
+
+    /// Reverses the winding of every face, turning the mesh inside out, for models exported
+    /// with their faces wound the other way.
+    pub fn flipped(mut self) -> Self {
+        for face in &mut self.indices {
+            face.swap(1, 2);
+        }
+        if let Some(normals) = &mut self.normals {
+            for corners in normals.iter_mut() {
+                corners.swap(1, 2);
+                *corners = corners.map(|n| -n);
+            }
+        }
+        // the faces haven't moved, so the hierarchy is still valid
+        self
+    }
+
+    /// Moves the mesh so that the centre of its bounding box is at the origin, as models are
+    /// often exported far from it.
+    pub fn recentered(self) -> Self {
+        let bbox = &self.bbox;
+        let center = Vec3::new(
+            (bbox.x().start() + bbox.x().end()) / 2.0,
+            (bbox.y().start() + bbox.y().end()) / 2.0,
+            (bbox.z().start() + bbox.z().end()) / 2.0,
+        );
+        self.translated(-center)
+    }
+
+    pub fn translated(self, offset: Vec3) -> Self {
+        self.transformed(|p| p + offset, |n| n, false)
+    }
+
+    /// Scales the mesh about the origin by a different factor along each axis. Scaling by a
+    /// negative factor along an odd number of axes mirrors the mesh, and its faces are
+    /// rewound so that they still face outwards.
+    pub fn scaled(self, factors: Vec3) -> Self {
+        let (x, y, z) = (factors.x(), factors.y(), factors.z());
+        assert!(
+            x != 0.0 && y != 0.0 && z != 0.0,
+            "Invalid scale (expected non-zero factors)"
+        );
+        self.transformed(
+            |p| Point3::new(p.x() * x, p.y() * y, p.z() * z),
+            // normals are scaled by the inverse, to stay perpendicular to the surface
+            |n| Vec3::new(n.x() / x, n.y() / y, n.z() / z),
+            x * y * z < 0.0,
+        )
+    }
+
+    /// Rotates the mesh by `angle` about the y axis, as [`RotateY`] would.
+    ///
+    /// [`RotateY`]: crate::hittable::RotateY
+    pub fn rotated_y(self, angle: Angle) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let rotate = move |x: f64, y: f64, z: f64| (cos * x + sin * z, y, -sin * x + cos * z);
+        self.transformed(
+            |p| {
+                let (x, y, z) = rotate(p.x(), p.y(), p.z());
+                Point3::new(x, y, z)
+            },
+            |n| {
+                let (x, y, z) = rotate(n.x(), n.y(), n.z());
+                Vec3::new(x, y, z)
+            },
+            false,
+        )
+    }
+
+    /// Moves every vertex with `position`, and every smooth normal with `normal`. If the
+    /// transform is `mirrored`, the faces are rewound so that they still face outwards.
+    fn transformed(
+        mut self,
+        position: impl Fn(Point3) -> Point3,
+        normal: impl Fn(Vec3) -> Vec3,
+        mirrored: bool,
+    ) -> Self {
+        let positions = self.positions.iter().map(|&p| position(p)).collect();
+        if let Some(normals) = &mut self.normals {
+            for corners in normals.iter_mut() {
+                *corners = corners.map(|n: Vec3<Normalized>| normal(n.into()).as_unit());
+            }
+        }
+        if mirrored {
+            self = self.flipped();
+        }
+        let uvs = self.uvs.take();
+        let faces = self.indices.iter().copied().enumerate().collect();
+        self.rebuilt(positions, uvs, faces)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        material::{Lambertian, Material},
+        Color, Hittable, Interval, Ray4,
+    };
+
+    #[test]
+    fn weld_and_place() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        // two faces of a square, without shared corners, plus a sliver between close points
+        let positions = vec![
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(11.0, 0.0, 0.0),
+            Point3::new(11.0, 1.0, 0.0),
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(11.0, 1.0 + 1e-9, 0.0),
+            Point3::new(10.0, 1.0, 0.0),
+        ];
+        let indices = vec![[0, 1, 2], [3, 4, 5], [2, 4, 5]];
+        let mesh = Mesh::new(positions, indices, mat).welded(1e-6);
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.len(), 2);
+
+        let mesh = mesh.recentered().scaled(Vec3::new(2.0, 2.0, -1.0));
+        let bbox = mesh.bounding_box().unwrap();
+        assert!((bbox.x().start() + 1.0).abs() < 1e-3 && (bbox.x().end() - 1.0).abs() < 1e-3);
+
+        // mirroring along z rewinds the faces, so that the side which faced +z faces -z
+        let ray = Ray4::new(Point3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        assert!(mesh.hit(&ray, Interval::universe()).unwrap().front_face());
+        let mesh = mesh.flipped();
+        assert!(!mesh.hit(&ray, Interval::universe()).unwrap().front_face());
+
+        let mesh = mesh.rotated_y(Angle::degrees(90.0));
+        let ray = Ray4::new(Point3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(mesh.hit(&ray, Interval::universe()).is_some());
+    }
+}
@@ -0,0 +1,233 @@
+//! Saving meshes, along with the hierarchy built over them, so that later renders of a large
+//! static scene can load them without rebuilding anything.
+
+use std::{
+    io::{self, Write},
+    rc::Rc,
+};
+
+use super::Mesh;
+use crate::{
+    boundingbox::BoundingBox3,
+    hittable::triangle_bvh::{
+        invalid, read_count, read_f64, read_header, read_nodes, read_point, read_u32, read_u8,
+        write_f64, write_nodes, write_point, write_u32,
+    },
+    Material, Point2, Vec3,
+};
+
+/// Identifies a saved [`Mesh`]; the version is bumped whenever the layout changes.
+const MAGIC: &[u8; 6] = b"RTMESH";
+const VERSION: u32 = 1;
+
+impl Mesh {
+    /// The materials of the mesh, in the order their positions are saved.
+    pub fn materials(&self) -> &[Rc<dyn Material>] {
+        &self.materials
+    }
+
+    /// Writes the mesh's buffers and hierarchy in a compact binary format.
+    ///
+    /// Materials can't be serialized, so faces are saved with the position of their material
+    /// in [`Mesh::materials`], and the materials are provided again when loading.
+    pub fn save(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_u32(writer, VERSION)?;
+
+        write_u32(writer, self.positions.len() as u32)?;
+        for position in &self.positions {
+            write_point(writer, position)?;
+        }
+        write_u32(writer, self.indices.len() as u32)?;
+        for &index in self.indices.iter().flatten() {
+            write_u32(writer, index)?;
+        }
+
+        writer.write_all(&[u8::from(self.uvs.is_some())])?;
+        for uv in self.uvs.iter().flatten() {
+            write_f64(writer, uv.x())?;
+            write_f64(writer, uv.y())?;
+        }
+        writer.write_all(&[u8::from(self.normals.is_some())])?;
+        for normal in self.normals.iter().flatten().flatten() {
+            write_f64(writer, normal.x())?;
+            write_f64(writer, normal.y())?;
+            write_f64(writer, normal.z())?;
+        }
+
+        write_u32(writer, self.materials.len() as u32)?;
+        writer.write_all(&[u8::from(self.face_materials.is_some())])?;
+        for &material in self.face_materials.iter().flatten() {
+            write_u32(writer, material)?;
+        }
+
+        write_nodes(writer, &self.nodes)?;
+        for &face in &self.order {
+            write_u32(writer, face)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a mesh written by [`Mesh::save`]. `materials` must hold the materials listed by
+    /// [`Mesh::materials`] when it was saved, in the same order.
+    pub fn load(bytes: &[u8], materials: &[Rc<dyn Material>]) -> io::Result<Self> {
+        let reader = &mut &bytes[..];
+        read_header(reader, MAGIC, VERSION, "mesh")?;
+
+        let count = read_count(reader, 24, "positions")?;
+        let positions = (0..count)
+            .map(|_| read_point(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let count = read_count(reader, 12, "faces")?;
+        let mut indices = Vec::with_capacity(count);
+        for face in 0..count {
+            let corners = [read_u32(reader)?, read_u32(reader)?, read_u32(reader)?];
+            if corners.iter().any(|&i| i as usize >= positions.len()) {
+                return Err(invalid(format!("face {face} refers to a missing vertex")));
+            }
+            indices.push(corners);
+        }
+
+        let uvs = match read_flag(reader, "uv")? {
+            true => Some(
+                (0..positions.len())
+                    .map(|_| Ok(Point2::new(read_f64(reader)?, read_f64(reader)?)))
+                    .collect::<io::Result<Vec<_>>>()?,
+            ),
+            false => None,
+        };
+        let normals = match read_flag(reader, "normal")? {
+            true => Some(
+                (0..indices.len())
+                    .map(|_| {
+                        let mut corners = [Vec3::new(0.0, 0.0, 1.0).as_unit(); 3];
+                        for corner in &mut corners {
+                            let (x, y, z) =
+                                (read_f64(reader)?, read_f64(reader)?, read_f64(reader)?);
+                            *corner = Vec3::new(x, y, z).as_unit();
+                        }
+                        Ok(corners)
+                    })
+                    .collect::<io::Result<Vec<_>>>()?,
+            ),
+            false => None,
+        };
+
+        let material_count = read_u32(reader)? as usize;
+        if materials.len() != material_count {
+            return Err(invalid(format!(
+                "expected {material_count} materials, but {} were provided",
+                materials.len()
+            )));
+        }
+        let face_materials = match read_flag(reader, "face material")? {
+            true => {
+                let mut face_materials = Vec::with_capacity(indices.len());
+                for face in 0..indices.len() {
+                    let material = read_u32(reader)?;
+                    if material as usize >= material_count {
+                        return Err(invalid(format!("face {face} refers to a missing material")));
+                    }
+                    face_materials.push(material);
+                }
+                Some(face_materials)
+            }
+            false => None,
+        };
+        if face_materials.is_none() && material_count != 1 {
+            return Err(invalid(
+                "a mesh without face materials must have one material",
+            ));
+        }
+
+        let nodes = read_nodes(reader, indices.len())?;
+        let mut order = Vec::with_capacity(indices.len());
+        for _ in 0..indices.len() {
+            let face = read_u32(reader)?;
+            if face as usize >= indices.len() {
+                return Err(invalid(format!(
+                    "the hierarchy refers to missing face {face}"
+                )));
+            }
+            order.push(face);
+        }
+
+        let bbox = nodes
+            .first()
+            .map_or_else(BoundingBox3::empty, |node| node.bbox.clone());
+        Ok(Self {
+            positions,
+            indices,
+            uvs,
+            normals,
+            materials: materials.to_vec(),
+            face_materials,
+            nodes,
+            order,
+            bbox,
+        })
+    }
+}
+
+fn read_flag(reader: &mut &[u8], what: &str) -> io::Result<bool> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        flag => Err(invalid(format!("invalid {what} flag {flag}"))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Angle, Color, Hittable, Interval, Point3, Ray4};
+
+    #[test]
+    fn save_and_load() {
+        let white = Lambertian::solid(Color::white()).into_mat();
+        let red = Lambertian::solid(Color::red()).into_mat();
+
+        // a strip of quads folding back and forth, alternating materials
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        for i in 0..=20 {
+            let x = f64::from(i) * 0.5;
+            let z = -f64::from(i % 2);
+            positions.extend([Point3::new(x, 0.0, z), Point3::new(x, 1.0, z)]);
+            uvs.extend([Point2::new(x, 0.0), Point2::new(x, 1.0)]);
+        }
+        let indices: Vec<[u32; 3]> = (0..20)
+            .flat_map(|i| [[2 * i, 2 * i + 2, 2 * i + 3], [2 * i, 2 * i + 3, 2 * i + 1]])
+            .collect();
+        let face_materials = (0..40).map(|face| (face / 2) % 2).collect();
+        let mesh = Mesh::new(positions, indices, Rc::clone(&white))
+            .with_uvs(uvs)
+            .with_smooth_normals(Angle::degrees(90.0))
+            .with_face_materials(vec![white, red], face_materials);
+
+        let mut buf = Vec::new();
+        mesh.save(&mut buf).unwrap();
+        let loaded = Mesh::load(&buf, mesh.materials()).unwrap();
+
+        for i in 0..100 {
+            let x = f64::from(i) * 0.1 - 0.05;
+            let ray = Ray4::new(Point3::new(x, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let expected = mesh.hit(&ray, Interval::new(0.001, f64::INFINITY));
+            let actual = loaded.hit(&ray, Interval::new(0.001, f64::INFINITY));
+            assert_eq!(expected.is_some(), actual.is_some());
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                assert_eq!(expected.t(), actual.t());
+                assert_eq!(expected.primitive_index(), actual.primitive_index());
+                assert_eq!((expected.u(), expected.v()), (actual.u(), actual.v()));
+                assert!((expected.normal().x() - actual.normal().x()).abs() < 1e-12);
+                assert!(Rc::ptr_eq(&expected.material(), &actual.material()));
+            }
+        }
+
+        assert!(Mesh::load(&buf[..buf.len() - 1], mesh.materials()).is_err());
+        assert!(Mesh::load(&buf, &mesh.materials()[..1]).is_err());
+        let mut huge = buf[..10].to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Mesh::load(&huge, mesh.materials()).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::rc::Rc;
+
+use super::{HitRecord, Hittable};
+use crate::{boundingbox::BoundingBox3, Interval, Material, Point3, Ray4, Vec3};
+
+/// The number of steps taken through each ball's radius while searching for the surface.
+/// Features smaller than this may be stepped over.
+const STEPS_PER_RADIUS: f64 = 24.0;
+/// The number of bisection steps used to refine a crossing of the surface.
+const REFINEMENT_STEPS: u32 = 32;
+
+/// A single point charge of a [`Metaballs`] field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Charge {
+    pub center: Point3,
+    /// The distance at which the charge's influence falls to zero
+    pub radius: f64,
+    /// The field at the charge's center. Negative charges carve into the surface.
+    pub strength: f64,
+}
+
+impl Charge {
+    /// The "soft object" falloff of Wyvill et al., `(1 - r²/R²)³`, which reaches zero (with a
+    /// zero gradient) at the charge's radius, so distant charges can be ignored.
+    fn field(&self, point: &Point3) -> f64 {
+        let r2 = (*point - self.center).len_squared() / (self.radius * self.radius);
+        if r2 >= 1.0 {
+            return 0.0;
+        }
+        let falloff = 1.0 - r2;
+        self.strength * falloff * falloff * falloff
+    }
+
+    fn gradient(&self, point: &Point3) -> Vec3 {
+        let offset = *point - self.center;
+        let r2 = offset.len_squared() / (self.radius * self.radius);
+        if r2 >= 1.0 {
+            return Vec3::empty();
+        }
+        let falloff = 1.0 - r2;
+        offset * (-6.0 * self.strength * falloff * falloff / (self.radius * self.radius))
+    }
+}
+
+/// An implicit surface enclosing the points where the summed field of a set of
+/// [`Charge`]s is above a threshold. Nearby charges blend smoothly into one another.
+#[derive(Debug)]
+pub struct Metaballs {
+    charges: Vec<Charge>,
+    /// The field value at the surface. Lower thresholds make a larger, blobbier surface,
+    /// where charges blend together from further apart.
+    threshold: f64,
+    material: Rc<dyn Material>,
+    bounding_box: BoundingBox3,
+}
+
+impl Metaballs {
+    pub fn new(threshold: f64, material: Rc<dyn Material>) -> Self {
+        assert!(threshold > 0.0, "Invalid threshold (expected > 0.0)");
+        Self {
+            charges: Vec::new(),
+            threshold,
+            material,
+            bounding_box: BoundingBox3::empty(),
+        }
+    }
+
+    /// Adds a charge to the field.
+    pub fn with_charge(mut self, center: Point3, radius: f64, strength: f64) -> Self {
+        assert!(radius > 0.0, "Invalid charge radius (expected > 0.0)");
+
+        let extent = Vec3::new(radius, radius, radius);
+        self.bounding_box = BoundingBox3::extending(
+            &self.bounding_box,
+            &BoundingBox3::bounded_by(&(center - extent), &(center + extent)),
+        );
+        self.charges.push(Charge {
+            center,
+            radius,
+            strength,
+        });
+        self
+    }
+
+    /// The summed field of every charge at `point`.
+    pub fn field(&self, point: &Point3) -> f64 {
+        self.charges.iter().map(|charge| charge.field(point)).sum()
+    }
+
+    fn gradient(&self, point: &Point3) -> Vec3 {
+        self.charges
+            .iter()
+            .fold(Vec3::empty(), |sum, charge| sum + charge.gradient(point))
+    }
+
+    /// The spans of the ray (in `ray_t`) which pass through the radius of at least one charge,
+    /// in order. The surface can only exist within these spans.
+    fn spans(&self, ray: &Ray4, ray_t: &Interval) -> Vec<(f64, f64)> {
+        let a = ray.direction().len_squared();
+        let mut spans: Vec<(f64, f64)> = self
+            .charges
+            .iter()
+            .filter_map(|charge| {
+                let oc = charge.center - ray.origin();
+                let h = Vec3::dot(&ray.direction(), &oc);
+                let c = oc.len_squared() - charge.radius * charge.radius;
+                let discriminant = h * h - a * c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let sqrtd = discriminant.sqrt();
+                let start = ((h - sqrtd) / a).max(*ray_t.start());
+                let end = ((h + sqrtd) / a).min(*ray_t.end());
+                (start < end).then_some((start, end))
+            })
+            .collect();
+
+        spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::with_capacity(spans.len());
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+}
+
+impl Hittable for Metaballs {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let min_radius = self
+            .charges
+            .iter()
+            .map(|charge| charge.radius)
+            .fold(f64::INFINITY, f64::min);
+        let step = min_radius / STEPS_PER_RADIUS / ray.direction().len();
+        let inside = |t: f64| self.field(&ray.at(t)) >= self.threshold;
+
+        for (start, end) in self.spans(ray, &ray_t) {
+            let mut t0 = start;
+            let was_inside = inside(t0);
+
+            while t0 < end {
+                let t1 = (t0 + step).min(end);
+                if inside(t1) == was_inside {
+                    t0 = t1;
+                    continue;
+                }
+
+                // the surface lies between t0 and t1
+                let (mut lo, mut hi) = (t0, t1);
+                for _ in 0..REFINEMENT_STEPS {
+                    let mid = (lo + hi) / 2.0;
+                    if inside(mid) == was_inside {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+
+                let point = ray.at(hi);
+                let gradient = self.gradient(&point);
+                if gradient.near_zero() {
+                    t0 = t1;
+                    continue;
+                }
+                // the field decreases outwards
+                let normal = (-gradient).as_unit();
+                return Some(HitRecord::from_incoming_ray(
+                    ray,
+                    &point,
+                    &normal,
+                    hi,
+                    0.0,
+                    0.0,
+                    Rc::clone(&self.material),
+                ));
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    #[test]
+    fn charges_blend() {
+        let material = Lambertian::solid(Color::white()).into_mat();
+        let blobs = Metaballs::new(0.25, material)
+            .with_charge(Point3::new(-0.6, 0.0, 0.0), 1.0, 1.0)
+            .with_charge(Point3::new(0.6, 0.0, 0.0), 1.0, 1.0);
+
+        // halfway between the charges, neither alone would reach the threshold
+        assert!(blobs.field(&Point3::origin()) > 0.25);
+
+        let ray = Ray4::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = blobs
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!((blobs.field(&hit.point()) - 0.25).abs() < 1e-6);
+        assert!(hit.front_face());
+        assert!((hit.normal().z() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,99 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Disc, HittableVec, Sphere};
+use crate::{
+    boundingbox::BVHNode, material::Lambertian, Color, Hittable, Material, OrthonormalBasis,
+    Point3, Vec3,
+};
+
+/// A single point of a [`PointCloud`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloudPoint {
+    pub position: Point3,
+    pub color: Color,
+    /// The direction the surface the point was sampled from faces, if known.
+    pub normal: Option<Vec3>,
+}
+
+/// A set of colored points, such as the output of a 3D scanner.
+///
+/// Point clouds have no surface of their own; they are rendered as "splats", small discs
+/// (facing along each point's normal) or spheres (for points without normals) which
+/// together cover the scanned surface.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+    points: Vec<CloudPoint>,
+}
+
+impl PointCloud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, point: CloudPoint) {
+        self.points.push(point);
+    }
+
+    pub fn points(&self) -> &[CloudPoint] {
+        &self.points
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Renders each point as a splat of `radius`, shaded as a [`Lambertian`] surface of the
+    /// point's color.
+    pub fn splats(&self, radius: f64) -> Rc<dyn Hittable> {
+        self.splats_with(radius, |color| Lambertian::solid(color).into_mat())
+    }
+
+    /// Renders each point as a splat of `radius`, with the material returned by `material`
+    /// for the point's color. Points of the same color share a material.
+    pub fn splats_with(
+        &self,
+        radius: f64,
+        mut material: impl FnMut(Color) -> Rc<dyn Material>,
+    ) -> Rc<dyn Hittable> {
+        assert!(radius > 0.0, "Invalid splat radius (expected > 0.0)");
+        if self.points.is_empty() {
+            return HittableVec::new().hittable();
+        }
+
+        let mut materials: HashMap<[u64; 3], Rc<dyn Material>> = HashMap::new();
+        let splats: Vec<Rc<dyn Hittable>> = self
+            .points
+            .iter()
+            .map(|point| {
+                let color = point.color;
+                let key = [color.r(), color.g(), color.b()].map(f64::to_bits);
+                let material = Rc::clone(materials.entry(key).or_insert_with(|| material(color)));
+
+                match point.normal.filter(|normal| !normal.near_zero()) {
+                    Some(normal) => {
+                        let basis = OrthonormalBasis::new(&normal);
+                        let u: Vec3 = basis.u().into();
+                        let v: Vec3 = basis.v().into();
+                        Disc::from_center(point.position, u * radius, v * radius, material)
+                            .hittable()
+                    }
+                    None => Sphere::stationary(point.position, radius, material).hittable(),
+                }
+            })
+            .collect();
+
+        BVHNode::new(splats).hittable()
+    }
+}
+
+impl FromIterator<CloudPoint> for PointCloud {
+    fn from_iter<T: IntoIterator<Item = CloudPoint>>(iter: T) -> Self {
+        Self {
+            points: iter.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::rc::Rc;
+
+use super::{HitRecord, HittableVec, RotateY, Transform, Translate};
+use crate::{
+    boundingbox::{BVHNode, BoundingBox3},
+    matrix::Matrix4,
+    Angle, Hittable, Interval, Point3, Ray4, Vec3,
+};
+
+/// Attaches a name to an object, which is reported by [`HitRecord::name`] for any hit on
+/// it. Where named objects are nested, the innermost name is reported.
+#[derive(Debug, Clone)]
+pub struct Named {
+    name: Rc<str>,
+    object: Rc<dyn Hittable>,
+}
+
+impl Named {
+    pub fn new(name: impl Into<Rc<str>>, object: Rc<dyn Hittable>) -> Self {
+        Self {
+            name: name.into(),
+            object,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Hittable for Named {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        let mut hit = self.object.hit(ray, ray_t)?;
+        if hit.name.is_none() {
+            hit.name = Some(Rc::clone(&self.name));
+        }
+        Some(hit)
+    }
+
+    fn hit_interval(&self, ray: &Ray4) -> Option<(f64, f64)> {
+        self.object.hit_interval(ray)
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        self.object.bounding_box()
+    }
+
+    fn motion_bounds(&self) -> Option<[BoundingBox3; 2]> {
+        self.object.motion_bounds()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+/// A named node in a [`SceneGraph`], with an optional object, child nodes, and a transform
+/// which applies to both. Nodes are transformed by their matrix (such as a node transform from
+/// an imported scene), then rotated about their own origin, then translated.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    name: String,
+    object: Option<Rc<dyn Hittable>>,
+    children: Vec<SceneNode>,
+    translation: Vec3,
+    /// Rotation about the y axis
+    rotation_y: Angle,
+    matrix: Matrix4,
+}
+
+impl SceneNode {
+    /// Creates an empty node. Names should not contain `/`, which separates the names in a
+    /// path.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        assert!(
+            !name.contains('/'),
+            "Invalid node name {name:?} (contains '/')"
+        );
+        Self {
+            name,
+            object: None,
+            children: Vec::new(),
+            translation: Vec3::empty(),
+            rotation_y: Angle::ZERO,
+            matrix: Matrix4::identity(),
+        }
+    }
+
+    pub fn with_object(mut self, object: Rc<dyn Hittable>) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    pub fn with_child(mut self, child: SceneNode) -> Self {
+        self.add_child(child);
+        self
+    }
+
+    pub fn add_child(&mut self, child: SceneNode) {
+        self.children.push(child);
+    }
+
+    pub fn translated(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Rotates the node by `angle` about the y axis.
+    pub fn rotated_y(mut self, angle: Angle) -> Self {
+        self.rotation_y = angle;
+        self
+    }
+
+    /// Applies an arbitrary affine `matrix` to the node, before its rotation and translation.
+    pub fn transformed(mut self, matrix: Matrix4) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.translation = translation;
+    }
+
+    pub fn set_rotation_y(&mut self, angle: Angle) {
+        self.rotation_y = angle;
+    }
+
+    pub fn set_matrix(&mut self, matrix: Matrix4) {
+        self.matrix = matrix;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn object(&self) -> Option<&Rc<dyn Hittable>> {
+        self.object.as_ref()
+    }
+
+    pub fn children(&self) -> &[SceneNode] {
+        &self.children
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    pub fn rotation_y(&self) -> Angle {
+        self.rotation_y
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    /// Builds the node and its children into a single object, with the node's transform
+    /// applied. Returns `None` if there is nothing to render.
+    fn build(&self, path: &str) -> Option<Rc<dyn Hittable>> {
+        let mut parts: Vec<Rc<dyn Hittable>> = Vec::new();
+        if let Some(object) = &self.object {
+            parts.push(Named::new(path, Rc::clone(object)).hittable());
+        }
+        for child in &self.children {
+            parts.extend(child.build(&format!("{path}/{}", child.name)));
+        }
+
+        let mut object = match parts.len() {
+            0 => return None,
+            1 => parts.pop().unwrap(),
+            _ => BVHNode::new(parts).hittable(),
+        };
+        if self.matrix != Matrix4::identity() {
+            object = Transform::new(object, self.matrix).hittable();
+        }
+        if self.rotation_y != Angle::ZERO {
+            object = RotateY::new(object, self.rotation_y).hittable();
+        }
+        if !self.translation.near_zero() {
+            object = Translate::new(object, self.translation).hittable();
+        }
+        Some(object)
+    }
+}
+
+/// A hierarchy of named objects, each with its own transform relative to its parent.
+///
+/// Nodes are found by their path, such as `"house/door"`, so that they can be moved between
+/// frames of an animation before the scene is [built](SceneGraph::build) into a world.
+/// Hits on an object report its path through [`HitRecord::name`].
+#[derive(Debug, Clone, Default)]
+pub struct SceneGraph {
+    roots: Vec<SceneNode>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, node: SceneNode) {
+        self.roots.push(node);
+    }
+
+    pub fn roots(&self) -> &[SceneNode] {
+        &self.roots
+    }
+
+    /// Finds the node at `path`, a list of node names separated by `/`.
+    pub fn get(&self, path: &str) -> Option<&SceneNode> {
+        let mut names = path.split('/');
+        let first = names.next()?;
+        let mut node = self.roots.iter().find(|n| n.name == first)?;
+        for name in names {
+            node = node.children.iter().find(|n| n.name == name)?;
+        }
+        Some(node)
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut SceneNode> {
+        let mut names = path.split('/');
+        let first = names.next()?;
+        let mut node = self.roots.iter_mut().find(|n| n.name == first)?;
+        for name in names {
+            node = node.children.iter_mut().find(|n| n.name == name)?;
+        }
+        Some(node)
+    }
+
+    /// Finds the first node named `name` anywhere in the graph, returning its path.
+    pub fn find(&self, name: &str) -> Option<(String, &SceneNode)> {
+        self.nodes().into_iter().find(|(_, node)| node.name == name)
+    }
+
+    /// Every node in the graph along with its path, parents before their children.
+    pub fn nodes(&self) -> Vec<(String, &SceneNode)> {
+        fn visit<'a>(node: &'a SceneNode, path: String, out: &mut Vec<(String, &'a SceneNode)>) {
+            out.push((path.clone(), node));
+            for child in &node.children {
+                visit(child, format!("{path}/{}", child.name), out);
+            }
+        }
+
+        let mut out = Vec::new();
+        for root in &self.roots {
+            visit(root, root.name.clone(), &mut out);
+        }
+        out
+    }
+
+    /// Builds the graph into a single object, ready to render.
+    pub fn build(&self) -> Rc<dyn Hittable> {
+        let parts: Vec<_> = self
+            .roots
+            .iter()
+            .filter_map(|root| root.build(&root.name))
+            .collect();
+        match parts.len() {
+            0 => HittableVec::new().hittable(),
+            _ => BVHNode::new(parts).hittable(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hittable::Sphere, material::Lambertian, Color, Material};
+
+    #[test]
+    fn nodes_are_found_and_transformed() {
+        let sphere = || {
+            let mat = Lambertian::solid(Color::white()).into_mat();
+            Sphere::stationary(Point3::origin(), 0.5, mat).hittable()
+        };
+
+        let mut graph = SceneGraph::new();
+        graph.add(
+            SceneNode::new("house")
+                .with_object(sphere())
+                .translated(Vec3::new(0.0, 0.0, -10.0))
+                .with_child(SceneNode::new("door").with_object(sphere())),
+        );
+        assert_eq!(graph.nodes().len(), 2);
+        assert_eq!(graph.find("door").unwrap().0, "house/door");
+
+        // move the door relative to the house
+        graph
+            .get_mut("house/door")
+            .unwrap()
+            .set_translation(Vec3::new(2.0, 0.0, 0.0));
+        let world = graph.build();
+
+        let ray = Ray4::new(Point3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = world.hit(&ray, Interval::universe()).unwrap();
+        assert_eq!(hit.name(), Some("house/door"));
+        assert!((hit.point() - Point3::new(2.0, 0.0, -9.5)).len() < 1e-9);
+
+        let ray = Ray4::new(Point3::origin(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = world.hit(&ray, Interval::universe()).unwrap();
+        assert_eq!(hit.name(), Some("house"));
+
+        // stretch the door along x, as an imported node transform might
+        graph
+            .get_mut("house/door")
+            .unwrap()
+            .set_matrix(Matrix4::scaling(Vec3::new(4.0, 1.0, 1.0)));
+        let world = graph.build();
+        let ray = Ray4::new(Point3::new(3.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = world.hit(&ray, Interval::universe()).unwrap();
+        assert_eq!(hit.name(), Some("house/door"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Smooth surfaces refined from a coarse polygon cage, following Catmull & Clark's
+//! "Recursively generated B-spline surfaces on arbitrary topological meshes" (1978).
+
+use std::{collections::HashMap, rc::Rc};
+
+use super::Mesh;
+use crate::{Angle, Material, Point3, Vec3};
+
+/// A control cage of polygons, which each step of Catmull–Clark subdivision refines into a
+/// smoother surface of quads, converging on a B-spline surface.
+///
+/// The cage can have faces of any number of sides, and may be open: boundary edges are
+/// refined as B-spline curves, so the edges of a sheet stay in place rather than shrinking
+/// away, and corners touching a single face stay fixed. Faces are wound counterclockwise
+/// when seen from the front, as in a [`Mesh`].
+#[derive(Debug, Clone)]
+pub struct SubdivisionSurface {
+    positions: Vec<Point3>,
+    faces: Vec<Vec<u32>>,
+}
+
+/// The faces either side of an edge, found by the edge's endpoints (in increasing order).
+type EdgeFaces = HashMap<(u32, u32), Vec<usize>>;
+
+impl SubdivisionSurface {
+    /// Creates a cage from its vertices and the polygons between them, as lists of indices
+    /// into `positions`.
+    ///
+    /// # Panics
+    /// Panics if a face has fewer than three corners, or refers to a vertex which doesn't
+    /// exist.
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<u32>>) -> Self {
+        assert!(
+            faces.iter().all(|face| face.len() >= 3),
+            "Invalid subdivision cage (expected faces of at least 3 vertices)"
+        );
+        assert!(
+            faces
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < positions.len()),
+            "Invalid subdivision cage (a face refers to a vertex which doesn't exist)"
+        );
+        Self { positions, faces }
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    pub fn faces(&self) -> &[Vec<u32>] {
+        &self.faces
+    }
+
+    /// The edges of each face, from each corner to the next.
+    fn edges(face: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let next = face.iter().cycle().skip(1);
+        face.iter().zip(next).map(|(&a, &b)| (a.min(b), a.max(b)))
+    }
+
+    /// Refines the cage by one step, splitting each face of `n` sides into `n` quads.
+    pub fn subdivided(&self) -> Self {
+        let vec = |p: &Point3| Vec3::from(*p);
+        let average = |points: &mut dyn Iterator<Item = Vec3>| {
+            let (sum, count) = points.fold((Vec3::empty(), 0.0), |(sum, n), p| (sum + p, n + 1.0));
+            sum / count
+        };
+
+        let face_points: Vec<Vec3> = self
+            .faces
+            .iter()
+            .map(|face| average(&mut face.iter().map(|&v| vec(&self.positions[v as usize]))))
+            .collect();
+
+        let mut edge_faces: EdgeFaces = HashMap::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            for edge in Self::edges(face) {
+                edge_faces.entry(edge).or_default().push(f);
+            }
+        }
+        // edges are numbered in a fixed order, so that the result doesn't depend on the
+        // order of the map
+        let mut edges: Vec<(u32, u32)> = edge_faces.keys().copied().collect();
+        edges.sort_unstable();
+        let edge_index: HashMap<(u32, u32), u32> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, &edge)| (edge, i as u32))
+            .collect();
+
+        let midpoint = |(a, b): (u32, u32)| {
+            (vec(&self.positions[a as usize]) + vec(&self.positions[b as usize])) / 2.0
+        };
+        let edge_points = edges.iter().map(|&edge| {
+            let faces = &edge_faces[&edge];
+            if faces.len() < 2 {
+                // boundary edges are split in the middle, as a curve
+                return midpoint(edge);
+            }
+            let (a, b) = edge;
+            let ends = [a, b].map(|v| vec(&self.positions[v as usize]));
+            average(
+                &mut ends
+                    .into_iter()
+                    .chain(faces.iter().map(|&f| face_points[f])),
+            )
+        });
+
+        // the edges and faces around each vertex
+        let mut vertex_edges: Vec<Vec<(u32, u32)>> = vec![Vec::new(); self.positions.len()];
+        let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); self.positions.len()];
+        for &(a, b) in &edges {
+            vertex_edges[a as usize].push((a, b));
+            vertex_edges[b as usize].push((a, b));
+        }
+        for (f, face) in self.faces.iter().enumerate() {
+            for &v in face {
+                vertex_faces[v as usize].push(f);
+            }
+        }
+
+        let vertex_points = self.positions.iter().enumerate().map(|(v, p)| {
+            let p = vec(p);
+            let edges = &vertex_edges[v];
+            let boundary: Vec<_> = edges
+                .iter()
+                .filter(|edge| edge_faces[edge].len() < 2)
+                .collect();
+            match boundary.len() {
+                // loose vertices stay put
+                _ if edges.is_empty() => p,
+                0 => {
+                    let n = edges.len() as f64;
+                    let faces = average(&mut vertex_faces[v].iter().map(|&f| face_points[f]));
+                    let edges = average(&mut edges.iter().map(|&edge| midpoint(edge)));
+                    (faces + edges * 2.0 + p * (n - 3.0)) / n
+                }
+                // the corner of a sheet touches a single face, and stays put like any other
+                2 if vertex_faces[v].len() > 1 => {
+                    let [a, b] = [boundary[0], boundary[1]].map(|&edge| midpoint(edge));
+                    (p * 2.0 + a + b) / 4.0
+                }
+                // where several open edges meet, the vertex is a corner, and stays put
+                _ => p,
+            }
+        });
+
+        let edge_offset = self.positions.len() as u32;
+        let face_offset = edge_offset + edges.len() as u32;
+        let positions = vertex_points
+            .chain(edge_points)
+            .chain(face_points.iter().copied())
+            .map(Point3::from)
+            .collect();
+
+        let mut faces = Vec::with_capacity(self.faces.iter().map(Vec::len).sum());
+        for (f, face) in self.faces.iter().enumerate() {
+            let edges: Vec<u32> = Self::edges(face)
+                .map(|edge| edge_offset + edge_index[&edge])
+                .collect();
+            for (i, &v) in face.iter().enumerate() {
+                let previous = edges[(i + face.len() - 1) % face.len()];
+                faces.push(vec![v, edges[i], face_offset + f as u32, previous]);
+            }
+        }
+
+        Self { positions, faces }
+    }
+
+    /// Refines the cage `levels` times and triangulates it into a smooth-shaded [`Mesh`].
+    /// Each level multiplies the number of faces by about four.
+    pub fn into_mesh(self, levels: u32, material: Rc<dyn Material>) -> Mesh {
+        let mut surface = self;
+        for _ in 0..levels {
+            surface = surface.subdivided();
+        }
+
+        let indices = surface
+            .faces
+            .iter()
+            .flat_map(|face| (1..face.len() - 1).map(|i| [face[0], face[i], face[i + 1]]))
+            .collect();
+        // the limit surface has no creases, so every face meeting at a corner is smoothed
+        Mesh::new(surface.positions, indices, material).with_smooth_normals(Angle::degrees(180.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color, Hittable, Interval, Ray4};
+
+    /// A cube from -1 to 1 along each axis, with its faces wound outwards.
+    fn cube() -> SubdivisionSurface {
+        let positions = (0..8)
+            .map(|i| {
+                let coord = |bit: u32| if i & bit == 0 { -1.0 } else { 1.0 };
+                Point3::new(coord(1), coord(2), coord(4))
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        SubdivisionSurface::new(positions, faces)
+    }
+
+    #[test]
+    fn catmull_clark() {
+        let once = cube().subdivided();
+        assert_eq!(once.positions().len(), 8 + 12 + 6);
+        assert_eq!(once.faces().len(), 24);
+        // the corners of a cube are pulled in to 5/9
+        let corner = once.positions()[7];
+        assert!((corner - Point3::new(5.0 / 9.0, 5.0 / 9.0, 5.0 / 9.0)).len() < 1e-12);
+
+        // the surface shrinks towards a sphere-like blob inside the cage
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let mesh = cube().into_mesh(3, mat);
+        assert_eq!(mesh.len(), 6 * 4usize.pow(3) * 2);
+        let ray = Ray4::new(Point3::new(5.0, 5.0, 5.0), Vec3::new(-1.0, -1.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, Interval::universe()).unwrap();
+        assert!(hit.front_face());
+        // the corners of the limit surface are at 1/2
+        assert!((hit.point().x() - 0.5).abs() < 0.01);
+
+        // open edges keep their place
+        let square = SubdivisionSurface::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 2, 3]],
+        )
+        .subdivided();
+        assert_eq!(square.positions()[4], Point3::new(0.5, 0.0, 0.0));
+        assert_eq!(
+            square.positions()[0..4],
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ]
+        );
+        assert!(square.positions().iter().all(|p| p.z() == 0.0));
+    }
+}
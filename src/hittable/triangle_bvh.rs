@@ -0,0 +1,457 @@
+//! A bounding volume hierarchy over a triangle mesh, stored as flat arrays so that it can be
+//! saved to disk once and loaded by later renders without being rebuilt.
+
+use std::{
+    io::{self, Read, Write},
+    rc::Rc,
+};
+
+use super::Triangle;
+use crate::{
+    boundingbox::BoundingBox3, Axis, HitRecord, Hittable, Interval, Material, Point2, Point3, Ray4,
+};
+
+/// Identifies a saved [`TriangleBVH`]; the version is bumped whenever the layout changes.
+const MAGIC: &[u8; 6] = b"RTBVH\0";
+const VERSION: u32 = 1;
+/// Stored in place of a triangle's index when it has none.
+const NO_INDEX: u64 = u64::MAX;
+/// The largest number of triangles in a leaf node.
+const LEAF_SIZE: usize = 2;
+/// The size of a saved triangle: its corners, uv flag, index and material.
+const TRIANGLE_BYTES: usize = 3 * 24 + 1 + 8 + 4;
+/// The size of a saved node: its bounds, kind and two fields.
+const NODE_BYTES: usize = 6 * 8 + 1 + 2 * 4;
+
+#[derive(Debug, Clone)]
+pub(super) enum NodeKind {
+    /// The left child immediately follows this node; `right` is the index of the right child.
+    Interior {
+        right: u32,
+    },
+    Leaf {
+        start: u32,
+        count: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct FlatNode {
+    pub(super) bbox: BoundingBox3,
+    pub(super) kind: NodeKind,
+}
+
+/// A BVH over a list of [`Triangle`]s, such as an imported mesh.
+///
+/// Unlike [`BVHNode`](crate::boundingbox::BVHNode), the tree is stored as a flat list of
+/// nodes, which can be written out with [`TriangleBVH::save`] and read back with
+/// [`TriangleBVH::load`]. Loading skips construction entirely, which saves time when a large
+/// static scene is rendered repeatedly.
+///
+/// Materials can't be serialized, so each triangle is saved with the position of its
+/// material in [`TriangleBVH::materials`], and the materials are provided again when loading.
+#[derive(Debug, Clone)]
+pub struct TriangleBVH {
+    nodes: Vec<FlatNode>,
+    triangles: Vec<Triangle>,
+    bbox: BoundingBox3,
+}
+
+impl TriangleBVH {
+    pub fn new(mut triangles: Vec<Triangle>) -> Self {
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            Self::build(&mut triangles, 0, &mut nodes);
+        }
+        let bbox = nodes
+            .first()
+            .map_or_else(BoundingBox3::empty, |node| node.bbox.clone());
+
+        Self {
+            nodes,
+            triangles,
+            bbox,
+        }
+    }
+
+    /// Builds the subtree over `triangles` (which begin at `offset` in the full list) by
+    /// splitting at the median along the longest axis, as [`BVHNode`](crate::boundingbox::BVHNode) does.
+    fn build(triangles: &mut [Triangle], offset: usize, nodes: &mut Vec<FlatNode>) {
+        let bbox = triangles.iter().fold(BoundingBox3::empty(), |bbox, tri| {
+            BoundingBox3::extending(&bbox, &tri.bounding_box)
+        });
+
+        if triangles.len() <= LEAF_SIZE {
+            nodes.push(FlatNode {
+                bbox,
+                kind: NodeKind::Leaf {
+                    start: offset as u32,
+                    count: triangles.len() as u32,
+                },
+            });
+            return;
+        }
+
+        let axis = bbox.longest_axis();
+        triangles.sort_unstable_by(|a, b| {
+            a.bounding_box[axis]
+                .start()
+                .partial_cmp(b.bounding_box[axis].start())
+                .expect("Tried to cmp a NaN value")
+        });
+
+        let this = nodes.len();
+        nodes.push(FlatNode {
+            bbox,
+            kind: NodeKind::Interior { right: 0 },
+        });
+
+        let mid = triangles.len() / 2;
+        let (left, right) = triangles.split_at_mut(mid);
+        Self::build(left, offset, nodes);
+        let right_index = nodes.len() as u32;
+        Self::build(right, offset + mid, nodes);
+        nodes[this].kind = NodeKind::Interior { right: right_index };
+    }
+
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    /// The distinct materials used by the triangles, in the order their positions are saved.
+    pub fn materials(&self) -> Vec<Rc<dyn Material>> {
+        let mut materials: Vec<Rc<dyn Material>> = Vec::new();
+        for tri in &self.triangles {
+            if !materials.iter().any(|mat| Rc::ptr_eq(mat, &tri.material)) {
+                materials.push(Rc::clone(&tri.material));
+            }
+        }
+        materials
+    }
+
+    /// Writes the hierarchy and its triangles in a compact binary format.
+    pub fn save(&self, writer: &mut impl Write) -> io::Result<()> {
+        let materials = self.materials();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        write_u32(writer, self.triangles.len() as u32)?;
+        for tri in &self.triangles {
+            for vertex in tri.vertices {
+                write_point(writer, &vertex)?;
+            }
+            match tri.uvs {
+                Some(uvs) => {
+                    writer.write_all(&[1])?;
+                    for uv in uvs {
+                        write_f64(writer, uv.x())?;
+                        write_f64(writer, uv.y())?;
+                    }
+                }
+                None => writer.write_all(&[0])?,
+            }
+            let index = tri.index.map_or(NO_INDEX, |index| index as u64);
+            writer.write_all(&index.to_le_bytes())?;
+            let material = materials
+                .iter()
+                .position(|mat| Rc::ptr_eq(mat, &tri.material))
+                .unwrap();
+            write_u32(writer, material as u32)?;
+        }
+
+        write_nodes(writer, &self.nodes)
+    }
+
+    /// Reads a hierarchy written by [`TriangleBVH::save`]. `materials` must hold the materials
+    /// listed by [`TriangleBVH::materials`] when it was saved, in the same order.
+    pub fn load(bytes: &[u8], materials: &[Rc<dyn Material>]) -> io::Result<Self> {
+        let reader = &mut &bytes[..];
+        read_header(reader, MAGIC, VERSION, "BVH")?;
+
+        let count = read_count(reader, TRIANGLE_BYTES, "triangles")?;
+        let mut triangles = Vec::with_capacity(count);
+        for _ in 0..count {
+            let [a, b, c] = [
+                read_point(reader)?,
+                read_point(reader)?,
+                read_point(reader)?,
+            ];
+            let uvs = match read_u8(reader)? {
+                0 => None,
+                1 => {
+                    let mut uvs = [Point2::new(0.0, 0.0); 3];
+                    for uv in &mut uvs {
+                        *uv = Point2::new(read_f64(reader)?, read_f64(reader)?);
+                    }
+                    Some(uvs)
+                }
+                flag => return Err(invalid(format!("invalid uv flag {flag}"))),
+            };
+            let mut index = [0; 8];
+            reader.read_exact(&mut index)?;
+            let index = u64::from_le_bytes(index);
+            let material = read_u32(reader)? as usize;
+            let Some(material) = materials.get(material) else {
+                return Err(invalid(format!(
+                    "material {material} is missing (only {} provided)",
+                    materials.len()
+                )));
+            };
+
+            let mut tri = Triangle::from_points(a, b, c, Rc::clone(material));
+            tri.uvs = uvs;
+            if index != NO_INDEX {
+                tri = tri.with_index(index as usize);
+            }
+            triangles.push(tri);
+        }
+
+        let nodes = read_nodes(reader, triangles.len())?;
+        let bbox = nodes
+            .first()
+            .map_or_else(BoundingBox3::empty, |node| node.bbox.clone());
+        Ok(Self {
+            nodes,
+            triangles,
+            bbox,
+        })
+    }
+}
+
+impl Hittable for TriangleBVH {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let ray3 = ray.ignore_time();
+        let start = *ray_t.start();
+        let mut closest = *ray_t.end();
+        let mut result = None;
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bbox.hit(&ray3, Interval::new(start, closest)) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Interior { right } => {
+                    stack.push(right as usize);
+                    stack.push(index + 1);
+                }
+                NodeKind::Leaf {
+                    start: first,
+                    count,
+                } => {
+                    let first = first as usize;
+                    for tri in &self.triangles[first..first + count as usize] {
+                        if let Some(hit) = tri.hit(ray, Interval::new(start, closest)) {
+                            closest = hit.t();
+                            result = Some(hit);
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bbox)
+    }
+}
+
+/// Writes the nodes of a flat hierarchy, as read by [`read_nodes`].
+pub(super) fn write_nodes(writer: &mut impl Write, nodes: &[FlatNode]) -> io::Result<()> {
+    write_u32(writer, nodes.len() as u32)?;
+    for node in nodes {
+        for axis in Axis::iter() {
+            write_f64(writer, *node.bbox[axis].start())?;
+            write_f64(writer, *node.bbox[axis].end())?;
+        }
+        let (kind, a, b) = match node.kind {
+            NodeKind::Interior { right } => (0, right, 0),
+            NodeKind::Leaf { start, count } => (1, start, count),
+        };
+        writer.write_all(&[kind])?;
+        write_u32(writer, a)?;
+        write_u32(writer, b)?;
+    }
+    Ok(())
+}
+
+/// Reads the nodes of a flat hierarchy over `primitives` primitives, checking that every
+/// node refers to nodes and primitives which exist.
+pub(super) fn read_nodes(reader: &mut &[u8], primitives: usize) -> io::Result<Vec<FlatNode>> {
+    let count = read_count(reader, NODE_BYTES, "nodes")?;
+    let mut nodes = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut bounds = [0.0; 6];
+        for bound in &mut bounds {
+            *bound = read_f64(reader)?;
+        }
+        let [x0, x1, y0, y1, z0, z1] = bounds;
+        let bbox = BoundingBox3::new(
+            Interval::new(x0, x1),
+            Interval::new(y0, y1),
+            Interval::new(z0, z1),
+        );
+
+        let kind = read_u8(reader)?;
+        let (a, b) = (read_u32(reader)?, read_u32(reader)?);
+        let kind = match kind {
+            0 if (a as usize) > i + 1 && (a as usize) < count => NodeKind::Interior { right: a },
+            1 if (a as usize + b as usize) <= primitives => NodeKind::Leaf { start: a, count: b },
+            _ => return Err(invalid(format!("node {i} is malformed"))),
+        };
+        nodes.push(FlatNode { bbox, kind });
+    }
+    if nodes.is_empty() != (primitives == 0) {
+        return Err(invalid("the hierarchy doesn't match the primitives"));
+    }
+    Ok(nodes)
+}
+
+/// Checks the magic bytes and version at the start of a saved `what`.
+pub(super) fn read_header(
+    reader: &mut impl Read,
+    magic: &[u8; 6],
+    version: u32,
+    what: &str,
+) -> io::Result<()> {
+    let mut found = [0; 6];
+    reader.read_exact(&mut found)?;
+    if &found != magic {
+        return Err(invalid(format!("not a saved {what}")));
+    }
+    let found = read_u32(reader)?;
+    if found != version {
+        return Err(invalid(format!(
+            "unsupported {what} version {found} (expected {version})"
+        )));
+    }
+    Ok(())
+}
+
+/// Reads the number of records which follow, each at least `size` bytes, rejecting counts
+/// which the rest of `reader` is too short to hold so that a corrupt file can't make loading
+/// allocate without bound.
+pub(super) fn read_count(reader: &mut &[u8], size: usize, what: &str) -> io::Result<usize> {
+    let count = read_u32(reader)? as usize;
+    if count > reader.len() / size {
+        return Err(invalid(format!(
+            "{count} {what} don't fit in the remaining {} bytes",
+            reader.len()
+        )));
+    }
+    Ok(count)
+}
+
+pub(super) fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+pub(super) fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(super) fn write_f64(writer: &mut impl Write, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(super) fn write_point(writer: &mut impl Write, point: &Point3) -> io::Result<()> {
+    write_f64(writer, point.x())?;
+    write_f64(writer, point.y())?;
+    write_f64(writer, point.z())
+}
+
+pub(super) fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(super) fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(super) fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+pub(super) fn read_point(reader: &mut impl Read) -> io::Result<Point3> {
+    Ok(Point3::new(
+        read_f64(reader)?,
+        read_f64(reader)?,
+        read_f64(reader)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color, Vec3};
+
+    #[test]
+    fn save_and_load() {
+        let red = Lambertian::solid(Color::new(1.0, 0.0, 0.0)).into_mat();
+        let blue = Lambertian::solid(Color::new(0.0, 0.0, 1.0)).into_mat();
+
+        // a strip of quads at increasing depth, alternating materials
+        let mut triangles = Vec::new();
+        for i in 0..20 {
+            let x = f64::from(i) * 0.5;
+            let z = -f64::from(i);
+            let mat = if i % 2 == 0 { &red } else { &blue };
+            let corners = [
+                Point3::new(x, 0.0, z),
+                Point3::new(x + 0.5, 0.0, z),
+                Point3::new(x + 0.5, 1.0, z),
+                Point3::new(x, 1.0, z),
+            ];
+            triangles.push(
+                Triangle::from_points(corners[0], corners[1], corners[2], Rc::clone(mat))
+                    .with_index(2 * i as usize),
+            );
+            triangles.push(
+                Triangle::from_points(corners[0], corners[2], corners[3], Rc::clone(mat))
+                    .with_index(2 * i as usize + 1),
+            );
+        }
+        let bvh = TriangleBVH::new(triangles);
+
+        let mut buf = Vec::new();
+        bvh.save(&mut buf).unwrap();
+        let loaded = TriangleBVH::load(&buf, &bvh.materials()).unwrap();
+        assert_eq!(loaded.triangles().len(), 40);
+
+        for i in 0..100 {
+            let x = f64::from(i) * 0.1 - 0.05;
+            let ray = Ray4::new(Point3::new(x, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let expected = bvh.hit(&ray, Interval::new(0.001, f64::INFINITY));
+            let actual = loaded.hit(&ray, Interval::new(0.001, f64::INFINITY));
+            assert_eq!(expected.is_some(), actual.is_some());
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                assert_eq!(expected.t(), actual.t());
+                assert_eq!(expected.primitive_index(), actual.primitive_index());
+            }
+        }
+
+        assert!(TriangleBVH::load(&buf[..10], &bvh.materials()).is_err());
+        assert!(TriangleBVH::load(&buf, &[]).is_err());
+
+        // a count larger than the file is rejected before anything is allocated for it
+        let mut huge = buf[..10].to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        let err = TriangleBVH::load(&huge, &bvh.materials()).unwrap_err();
+        assert!(err.to_string().contains("don't fit"), "{err}");
+    }
+}
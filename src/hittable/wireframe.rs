@@ -0,0 +1,182 @@
+use std::rc::Rc;
+
+use crate::{
+    boundingbox::BoundingBox3, material::DiffuseLight, Axis, Color, HitRecord, Hittable, Interval,
+    Material, Ray4,
+};
+
+/// The colors of the boxes at each level of a hierarchy, repeating for deeper levels.
+const LEVEL_COLORS: [Color; 6] = [
+    Color::new(1.0, 0.0, 0.0),
+    Color::new(1.0, 0.5, 0.0),
+    Color::new(1.0, 1.0, 0.0),
+    Color::new(0.0, 1.0, 0.0),
+    Color::new(0.0, 1.0, 1.0),
+    Color::new(0.0, 0.0, 1.0),
+];
+
+/// The edges of bounding boxes, drawn as glowing lines for diagnosing bad bounds and
+/// transform bugs. Add it to the world alongside the objects being inspected; edges are found
+/// by intersecting each ray with the boxes, so they're hidden by objects in front of them,
+/// and the far edges of each box show through its faces.
+#[derive(Debug)]
+pub struct BoundsWireframe {
+    boxes: Vec<(BoundingBox3, Rc<dyn Material>)>,
+    /// How close to an edge a ray must pass to hit it, in scene units
+    thickness: f64,
+    bbox: BoundingBox3,
+}
+
+impl BoundsWireframe {
+    /// Creates an empty wireframe, whose edges will be `thickness` units wide.
+    pub fn new(thickness: f64) -> Self {
+        assert!(thickness > 0.0, "Invalid thickness (expected > 0.0)");
+        Self {
+            boxes: Vec::new(),
+            thickness,
+            bbox: BoundingBox3::empty(),
+        }
+    }
+
+    /// Adds the bounding box of `object`, if it has one, in white.
+    pub fn object(self, object: &dyn Hittable) -> Self {
+        match object.bounding_box() {
+            Some(bbox) => self.with_box(bbox.clone(), Color::white()),
+            None => self,
+        }
+    }
+
+    /// Adds the box of every node of a bounding volume hierarchy, such as a
+    /// [`BVHNode`](crate::boundingbox::BVHNode), colored by depth from red at the root through to
+    /// blue, and the boxes of the objects at its leaves in white.
+    pub fn hierarchy(self, root: &dyn Hittable) -> Self {
+        self.node(root, 0)
+    }
+
+    fn node(mut self, node: &dyn Hittable, depth: usize) -> Self {
+        let Some([left, right]) = node.bvh_children() else {
+            return self.object(node);
+        };
+
+        if let Some(bbox) = node.bounding_box() {
+            let color = LEVEL_COLORS[depth % LEVEL_COLORS.len()];
+            self = self.with_box(bbox.clone(), color);
+        }
+        self = self.node(left.as_ref(), depth + 1);
+        // nodes with a single object hold it on both sides
+        if !Rc::ptr_eq(left, right) {
+            self = self.node(right.as_ref(), depth + 1);
+        }
+        self
+    }
+
+    fn with_box(mut self, bbox: BoundingBox3, color: Color) -> Self {
+        // boxes of unbounded objects have no edges to draw
+        let finite = Axis::iter().all(|axis| bbox[axis].size().is_finite());
+        if finite {
+            self.bbox = BoundingBox3::extending(&self.bbox, &bbox);
+            self.boxes
+                .push((bbox, DiffuseLight::solid(color).into_mat()));
+        }
+        self
+    }
+
+    /// Whether `t` along `ray` lies on an edge of `bbox`: close to its sides on two axes.
+    fn on_edge(&self, bbox: &BoundingBox3, ray: &Ray4, t: f64) -> bool {
+        let point = ray.at(t);
+        let near_sides = Axis::iter()
+            .filter(|&axis| {
+                let int = &bbox[axis];
+                let p = point[axis];
+                (p - int.start()).abs() < self.thickness || (p - int.end()).abs() < self.thickness
+            })
+            .count();
+        near_sides >= 2
+    }
+}
+
+/// The parameters at which `ray` enters and exits `bbox`, if it passes through it.
+fn box_interval(bbox: &BoundingBox3, ray: &Ray4) -> Option<(f64, f64)> {
+    let mut int = Interval::universe();
+    for axis in Axis::iter() {
+        let adinv = 1.0 / ray.direction()[axis];
+        let t0 = (bbox[axis].start() - ray.origin()[axis]) * adinv;
+        let t1 = (bbox[axis].end() - ray.origin()[axis]) * adinv;
+        int = Interval::positive(t0, t1).overlap(&int)?;
+    }
+    Some((*int.start(), *int.end()))
+}
+
+impl Hittable for BoundsWireframe {
+    fn hit(&self, ray: &Ray4, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(&ray.ignore_time(), ray_t.clone()) {
+            return None;
+        }
+
+        let mut closest: Option<(f64, &Rc<dyn Material>)> = None;
+        for (bbox, material) in &self.boxes {
+            let Some((enter, exit)) = box_interval(bbox, ray) else {
+                continue;
+            };
+            let t = [enter, exit].into_iter().find(|&t| {
+                ray_t.surrounds(t)
+                    && closest.is_none_or(|(closest, _)| t < closest)
+                    && self.on_edge(bbox, ray, t)
+            });
+            if let Some(t) = t {
+                closest = Some((t, material));
+            }
+        }
+
+        let (t, material) = closest?;
+        // the edges are lines, so they face whichever way they're seen from
+        let normal = (-ray.direction()).as_unit();
+        Some(HitRecord::from_incoming_ray(
+            ray,
+            &ray.at(t),
+            &normal,
+            t,
+            0.0,
+            0.0,
+            Rc::clone(material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<&BoundingBox3> {
+        Some(&self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{boundingbox::BVHNode, hittable::Sphere, material::Lambertian, Point3, Vec3};
+
+    #[test]
+    fn edges() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let spheres = (0..4)
+            .map(|i| {
+                let center = Point3::new(f64::from(i) * 3.0, 0.0, 0.0);
+                Sphere::stationary(center, 1.0, Rc::clone(&mat)).hittable()
+            })
+            .collect();
+        let bvh = BVHNode::new(spheres);
+        let wireframe = BoundsWireframe::new(0.05).hierarchy(&bvh);
+        // three nodes and four spheres
+        assert_eq!(wireframe.boxes.len(), 7);
+
+        let ray =
+            |x: f64, y: f64| Ray4::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        // the top-left edge of the first sphere's box, which is also the root's
+        let edge = wireframe.hit(&ray(-0.98, 0.98), ray_t.clone()).unwrap();
+        assert!((edge.t() - 4.0).abs() < 1e-9);
+        let color = edge
+            .material()
+            .emitted(&ray(-0.98, 0.98), &edge, 0.0, 0.0, &edge.point());
+        assert_eq!(color, LEVEL_COLORS[0]);
+        // the middle of a face
+        assert!(wireframe.hit(&ray(0.0, 0.0), ray_t).is_none());
+    }
+}
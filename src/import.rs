@@ -0,0 +1,8 @@
+//! Loaders for model and material files produced by other tools.
+
+pub mod bpt;
+pub mod mtl;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+pub mod xyz;
@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::{hittable::BezierPatch, Point3};
+
+/// Parses the contents of a Bézier patch (`.bpt`) file, the format the Utah teapot is usually
+/// distributed in: the number of patches, followed by each patch's degree in `u` and `v` and
+/// then its control points, one `x y z` point per line.
+///
+/// Only bicubic patches (of degree `3 3`) are supported.
+pub fn parse_bpt(source: &str) -> Result<Vec<BezierPatch>> {
+    // some copies separate coordinates with commas
+    let mut tokens = source
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty());
+    let mut next = |what: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| miette!("Unexpected end of file; expected {}.", what))
+    };
+
+    let count: usize = next("the number of patches")?
+        .parse()
+        .map_err(|_| miette!("The file must start with the number of patches."))?;
+
+    // the count isn't trusted to preallocate; a bogus one runs out of tokens instead
+    let mut patches = Vec::new();
+    for patch in 0..count {
+        let degree = (next("a patch degree")?, next("a patch degree")?);
+        if degree != ("3", "3") {
+            bail!(
+                "Patch {} has degree {} {}; only bicubic patches ({}) are supported.",
+                patch,
+                degree.0,
+                degree.1,
+                "3 3".green()
+            );
+        }
+
+        let mut control_points = [[Point3::origin(); 4]; 4];
+        for row in &mut control_points {
+            for point in row {
+                let mut coords = [0.0; 3];
+                for coord in &mut coords {
+                    let token = next("a control point")?;
+                    *coord = token.parse().map_err(|_| {
+                        miette!(
+                            "{} is not a valid coordinate (patch {}).",
+                            token.green(),
+                            patch
+                        )
+                    })?;
+                }
+                *point = Point3::from(coords);
+            }
+        }
+        patches.push(BezierPatch::new(control_points));
+    }
+
+    Ok(patches)
+}
+
+/// Loads a `.bpt` file; see [`parse_bpt`].
+pub fn load_bpt(path: &Path) -> Result<Vec<BezierPatch>> {
+    let source = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    parse_bpt(&source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_patch() -> Result<()> {
+        let mut source = String::from("1\n3 3\n");
+        for j in 0..4 {
+            for i in 0..4 {
+                source.push_str(&format!("{i}, {j}, 0\n"));
+            }
+        }
+
+        let patches = parse_bpt(&source)?;
+        assert_eq!(patches.len(), 1);
+        assert_eq!(
+            patches[0].control_points()[1][2],
+            Point3::new(2.0, 1.0, 0.0)
+        );
+
+        assert!(parse_bpt("1\n2 2\n").is_err());
+        // bogus counts run out of patches rather than memory
+        assert!(parse_bpt("18446744073709551615\n3 3\n").is_err());
+        assert!(parse_bpt("1000000000000\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::{
+    material::{BlinnPhong, Dielectric, DiffuseLight, Lambertian},
+    texture::{ImageTexture, SolidColor},
+    Color, Material, Texture,
+};
+
+/// A single material definition (`newmtl`) from a Wavefront `.mtl` file.
+/// Only the properties which map onto this crate's materials are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtlMaterial {
+    /// Diffuse color (`Kd`)
+    pub diffuse: Color,
+    /// Specular color (`Ks`)
+    pub specular: Color,
+    /// Specular exponent (`Ns`)
+    pub exponent: f64,
+    /// Opacity (`d`, or `1 - Tr`)
+    pub dissolve: f64,
+    /// Refractive index (`Ni`)
+    pub optical_density: f64,
+    /// Emitted color (`Ke`)
+    pub emission: Color,
+    /// Diffuse texture map (`map_Kd`), relative to the directory of the `.mtl` file
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::black(),
+            exponent: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.0,
+            emission: Color::black(),
+            diffuse_map: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps this definition onto the closest material available:
+    /// * an emissive `Ke` produces a [`DiffuseLight`],
+    /// * a transparent `d` produces a [`Dielectric`] with the `Ni` refractive index,
+    /// * a non-black `Ks` produces a [`BlinnPhong`] material,
+    /// * and anything else is [`Lambertian`].
+    pub fn into_material(self) -> Result<Rc<dyn Material>> {
+        if self.emission != Color::black() {
+            return Ok(DiffuseLight::solid(self.emission).into_mat());
+        }
+
+        if self.dissolve < 1.0 {
+            let index = if self.optical_density > 1.0 {
+                self.optical_density
+            } else {
+                // `Ni 1.0` is a common default, which would make the glass invisible
+                1.5
+            };
+            return Ok(Dielectric::new(index).into_mat());
+        }
+
+        let diffuse: Rc<dyn Texture> = match &self.diffuse_map {
+            Some(path) => {
+                let file = std::fs::File::open(path)
+                    .into_diagnostic()
+                    .map_err(|e| e.wrap_err(format!("Failed to open {}", path.display())))?;
+                ImageTexture::try_load(png::Decoder::new(file))
+                    .map_err(|e| miette!("Failed to load {}: {}", path.display(), e))?
+                    .into_texture()
+            }
+            None => SolidColor::new(self.diffuse).into_texture(),
+        };
+
+        if self.specular != Color::black() {
+            Ok(BlinnPhong::new(diffuse, self.specular, self.exponent).into_mat())
+        } else {
+            Ok(Lambertian::new(diffuse).into_mat())
+        }
+    }
+}
+
+/// Parses the contents of a `.mtl` file. Texture paths are resolved relative to `base_dir`.
+pub fn parse_mtl(source: &str, base_dir: &Path) -> Result<HashMap<String, MtlMaterial>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_no, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let location = format!("line {}", line_no + 1);
+
+        if keyword.is_empty() {
+            continue;
+        }
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((rest.to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = current.as_mut() else {
+            bail!(
+                "{} appears before any {} statement ({}).",
+                keyword.green(),
+                "newmtl".purple(),
+                location
+            );
+        };
+
+        match keyword {
+            "Kd" => material.diffuse = parse_color(rest, &location)?,
+            "Ks" => material.specular = parse_color(rest, &location)?,
+            "Ke" => material.emission = parse_color(rest, &location)?,
+            "Ns" => material.exponent = parse_float(rest, &location)?,
+            "Ni" => material.optical_density = parse_float(rest, &location)?,
+            "d" => material.dissolve = parse_float(rest, &location)?,
+            "Tr" => material.dissolve = 1.0 - parse_float(rest, &location)?,
+            // options such as `-bm 1.0` may precede the filename
+            "map_Kd" => match rest.split_whitespace().last() {
+                Some(file) => material.diffuse_map = Some(base_dir.join(file)),
+                None => bail!("{} requires a filename ({}).", "map_Kd".green(), location),
+            },
+            // unsupported statements (Ka, illum, bump maps, ...) are ignored
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+/// Loads a `.mtl` file and converts each of its definitions into a [`Material`].
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, Rc<dyn Material>>> {
+    let source = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    parse_mtl(&source, base_dir)?
+        .into_iter()
+        .map(|(name, material)| Ok((name, material.into_material()?)))
+        .collect()
+}
+
+fn parse_float(value: &str, location: &str) -> Result<f64> {
+    value
+        .split_whitespace()
+        .next()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| miette!("{} must be a decimal number ({}).", value.green(), location))
+}
+
+fn parse_color(value: &str, location: &str) -> Result<Color> {
+    let parts = value
+        .split_whitespace()
+        .map(|v| v.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| miette!("{} must be a color ({}).", value.green(), location))?;
+
+    match parts[..] {
+        // a single value is used for all three channels
+        [v] => Ok(Color::new(v, v, v)),
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => bail!(
+            "{} must be a color, represented as {} ({}).",
+            value.green(),
+            "r g b".purple(),
+            location
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+# Blender MTL File
+newmtl Paint
+Ns 250.0
+Kd 0.8 0.1 0.1
+Ks 0.5 0.5 0.5
+illum 2
+
+newmtl Glass
+Kd 1 1 1
+d 0.2
+Ni 1.45
+
+newmtl Lamp
+Ke 4 4 4
+map_Kd -bm 1.0 lamp.png
+"#;
+
+    #[test]
+    fn parse() -> Result<()> {
+        let materials = parse_mtl(SAMPLE, Path::new("models"))?;
+        assert_eq!(materials.len(), 3);
+
+        let paint = &materials["Paint"];
+        assert_eq!(paint.diffuse, Color::new(0.8, 0.1, 0.1));
+        assert_eq!(paint.specular, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(paint.exponent, 250.0);
+
+        assert_eq!(materials["Glass"].dissolve, 0.2);
+        assert_eq!(materials["Glass"].optical_density, 1.45);
+
+        let lamp = &materials["Lamp"];
+        assert_eq!(lamp.emission, Color::new(4.0, 4.0, 4.0));
+        assert_eq!(lamp.diffuse_map, Some(PathBuf::from("models/lamp.png")));
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, path::Path, rc::Rc};
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::{
+    hittable::{HittableVec, Mesh, SubdivisionSurface, Triangle},
+    import::mtl::load_mtl,
+    Hittable, Material, Point2, Point3,
+};
+
+/// Parses the contents of a Wavefront `.obj` file into a list of [`Triangle`]s.
+/// Polygons with more than three vertices are split into a fan of triangles.
+///
+/// Faces are assigned the material named by the most recent `usemtl` statement, looked up in
+/// `materials`; faces without a (known) material use `default_material`.
+pub fn parse_obj(
+    source: &str,
+    materials: &HashMap<String, Rc<dyn Material>>,
+    default_material: Rc<dyn Material>,
+) -> Result<HittableVec> {
+    let triangles = parse_obj_triangles(source, materials, default_material)?;
+    Ok(triangles.into_iter().map(Triangle::hittable).collect())
+}
+
+/// Like [`parse_obj`], but returns the [`Triangle`]s themselves, for uses which need access
+/// to the mesh's vertices (such as baking).
+pub fn parse_obj_triangles(
+    source: &str,
+    materials: &HashMap<String, Rc<dyn Material>>,
+    default_material: Rc<dyn Material>,
+) -> Result<Vec<Triangle>> {
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs: Vec<Point2> = Vec::new();
+    let mut triangles = Vec::new();
+    let mut material = Rc::clone(&default_material);
+
+    for (line_no, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let location = format!("line {}", line_no + 1);
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats(rest, &location)?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_floats(rest, &location)?;
+                uvs.push(Point2::new(u, v));
+            }
+            "usemtl" => {
+                material = match materials.get(rest.trim()) {
+                    Some(mat) => Rc::clone(mat),
+                    None => Rc::clone(&default_material),
+                };
+            }
+            "f" => {
+                let vertices = rest
+                    .split_whitespace()
+                    .map(|vertex| parse_vertex(vertex, positions.len(), uvs.len(), &location))
+                    .collect::<Result<Vec<_>>>()?;
+
+                if vertices.len() < 3 {
+                    bail!(
+                        "{} must have at least 3 vertices ({}).",
+                        "f".green(),
+                        location
+                    );
+                }
+
+                for i in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[i], vertices[i + 1]];
+                    let [p0, p1, p2] = corners.map(|(v, _)| positions[v]);
+
+                    // zero-area faces have no normal, and can never be hit anyways
+                    if (p1 - p0).cross(&(p2 - p0)).near_zero() {
+                        continue;
+                    }
+
+                    let mut triangle = Triangle::from_points(p0, p1, p2, Rc::clone(&material))
+                        .with_index(triangles.len());
+                    if let [(_, Some(t0)), (_, Some(t1)), (_, Some(t2))] = corners {
+                        triangle = triangle.with_uvs([uvs[t0], uvs[t1], uvs[t2]]);
+                    }
+                    triangles.push(triangle);
+                }
+            }
+            // normals, groups, smoothing groups, etc. are not supported
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+/// Like [`parse_obj`], but returns a single [`Mesh`] sharing the file's vertices between
+/// faces, with each material group assigned to its faces. Texture coordinates are kept only
+/// if every face has them.
+pub fn parse_obj_mesh(
+    source: &str,
+    materials: &HashMap<String, Rc<dyn Material>>,
+    default_material: Rc<dyn Material>,
+) -> Result<Mesh> {
+    let triangles = parse_obj_triangles(source, materials, Rc::clone(&default_material))?;
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs: Vec<Point2> = Vec::new();
+    // the corners seen so far, as their position and texture coordinates
+    let mut vertices: HashMap<[u64; 5], u32> = HashMap::new();
+    let mut indices = Vec::with_capacity(triangles.len());
+    let mut group_materials = vec![default_material];
+    let mut face_materials = Vec::with_capacity(triangles.len());
+    let has_uvs = triangles.iter().all(Triangle::has_uvs);
+
+    for tri in &triangles {
+        let face = std::array::from_fn(|i| {
+            let position = tri.vertices()[i];
+            // without texture coordinates, corners are shared by position alone
+            let uv = if has_uvs {
+                tri.uvs()[i]
+            } else {
+                Point2::new(0.0, 0.0)
+            };
+            let key = [position.x(), position.y(), position.z(), uv.x(), uv.y()].map(f64::to_bits);
+            *vertices.entry(key).or_insert_with(|| {
+                positions.push(position);
+                uvs.push(uv);
+                positions.len() as u32 - 1
+            })
+        });
+        indices.push(face);
+
+        let material = match group_materials
+            .iter()
+            .position(|mat| Rc::ptr_eq(mat, tri.material()))
+        {
+            Some(material) => material,
+            None => {
+                group_materials.push(Rc::clone(tri.material()));
+                group_materials.len() - 1
+            }
+        };
+        face_materials.push(material as u32);
+    }
+
+    let mut mesh = Mesh::new(positions, indices, Rc::clone(&group_materials[0]))
+        .with_face_materials(group_materials, face_materials);
+    if has_uvs {
+        mesh = mesh.with_uvs(uvs);
+    }
+    Ok(mesh)
+}
+
+/// Parses the vertices and polygons of a `.obj` file, as the control cage of a subdivision
+/// surface. Polygons are kept whole, and everything besides positions and faces (including
+/// materials and texture coordinates) is ignored.
+pub fn parse_obj_cage(source: &str) -> Result<SubdivisionSurface> {
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs = 0;
+    let mut faces = Vec::new();
+
+    for (line_no, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let location = format!("line {}", line_no + 1);
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats(rest, &location)?;
+                positions.push(Point3::new(x, y, z));
+            }
+            // counted so that face vertices which refer to them can be parsed
+            "vt" => uvs += 1,
+            "f" => {
+                let face = rest
+                    .split_whitespace()
+                    .map(|vertex| {
+                        parse_vertex(vertex, positions.len(), uvs, &location).map(|(v, _)| v as u32)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                if face.len() < 3 {
+                    bail!(
+                        "{} must have at least 3 vertices ({}).",
+                        "f".green(),
+                        location
+                    );
+                }
+                faces.push(face);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(SubdivisionSurface::new(positions, faces))
+}
+
+/// Loads a `.obj` file along with any `.mtl` files it references through `mtllib`,
+/// which are resolved relative to the `.obj` file.
+pub fn load_obj(path: &Path, default_material: Rc<dyn Material>) -> Result<HittableVec> {
+    let triangles = load_obj_triangles(path, default_material)?;
+    Ok(triangles.into_iter().map(Triangle::hittable).collect())
+}
+
+/// Like [`load_obj`], but returns the [`Triangle`]s themselves.
+pub fn load_obj_triangles(
+    path: &Path,
+    default_material: Rc<dyn Material>,
+) -> Result<Vec<Triangle>> {
+    let (source, materials) = read_obj(path)?;
+    parse_obj_triangles(&source, &materials, default_material)
+}
+
+/// Loads the control cage of a subdivision surface; see [`parse_obj_cage`].
+pub fn load_obj_cage(path: &Path) -> Result<SubdivisionSurface> {
+    let source = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    parse_obj_cage(&source)
+}
+
+/// Like [`load_obj`], but returns a single [`Mesh`]; see [`parse_obj_mesh`].
+pub fn load_obj_mesh(path: &Path, default_material: Rc<dyn Material>) -> Result<Mesh> {
+    let (source, materials) = read_obj(path)?;
+    parse_obj_mesh(&source, &materials, default_material)
+}
+
+/// Materials by the names used in `usemtl` statements.
+type Materials = HashMap<String, Rc<dyn Material>>;
+
+/// Reads a `.obj` file and the materials of the `.mtl` files it references.
+fn read_obj(path: &Path) -> Result<(String, Materials)> {
+    let source = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = HashMap::new();
+    for line in source.lines() {
+        if let Some(libraries) = line.trim().strip_prefix("mtllib") {
+            for library in libraries.split_whitespace() {
+                materials.extend(load_mtl(&base_dir.join(library))?);
+            }
+        }
+    }
+    Ok((source, materials))
+}
+
+fn parse_floats<const N: usize>(value: &str, location: &str) -> Result<[f64; N]> {
+    let mut res = [0.0; N];
+    let mut parts = value.split_whitespace();
+    for item in &mut res {
+        *item = parts
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| miette!("Expected {} decimal numbers ({}).", N, location))?;
+    }
+    Ok(res)
+}
+
+/// Parses a face vertex in the form `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based indices.
+fn parse_vertex(
+    vertex: &str,
+    positions: usize,
+    uvs: usize,
+    location: &str,
+) -> Result<(usize, Option<usize>)> {
+    let mut parts = vertex.split('/');
+    let position = resolve_index(parts.next().unwrap_or_default(), positions, location)?;
+    let uv = match parts.next() {
+        Some(uv) if !uv.is_empty() => Some(resolve_index(uv, uvs, location)?),
+        _ => None,
+    };
+    Ok((position, uv))
+}
+
+/// Resolves a one-based (or negative, relative to the end) OBJ index.
+fn resolve_index(index: &str, len: usize, location: &str) -> Result<usize> {
+    let index: i64 = index
+        .parse()
+        .map_err(|_| miette!("{} is not a valid index ({}).", index.green(), location))?;
+
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+
+    if !(0..len as i64).contains(&resolved) {
+        bail!("Index {} is out of bounds ({}).", index.green(), location);
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color, Interval, Ray4, Vec3};
+
+    #[test]
+    fn quad_is_triangulated() -> Result<()> {
+        let source = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+usemtl missing
+f 1/1 2/2 3/3 -1/-1
+"#;
+        let default = Lambertian::solid(Color::white()).into_mat();
+        let mesh = parse_obj(source, &HashMap::new(), Rc::clone(&default))?;
+        assert_eq!(mesh.len(), 2);
+
+        // the second triangle covers the upper-left half of the square
+        let triangles = parse_obj_triangles(source, &HashMap::new(), default)?;
+        let ray = Ray4::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = triangles[1].hit(&ray, Interval::universe()).unwrap();
+        assert_eq!(hit.primitive_index(), Some(1));
+        let [a, b, c] = hit.barycentrics().unwrap();
+        assert!((a + b + c - 1.0).abs() < 1e-9);
+        // the weights reproduce the hit point
+        let [p0, p1, p2] = triangles[1].vertices().map(Vec3::from);
+        let point = Point3::from(p0 * a + p1 * b + p2 * c);
+        assert!((point - hit.point()).len() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn mesh_material_groups() -> Result<()> {
+        let source = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+usemtl red
+f 1 2 3
+usemtl missing
+f 1 3 4
+"#;
+        let default = Lambertian::solid(Color::white()).into_mat();
+        let red = Lambertian::solid(Color::red()).into_mat();
+        let materials = HashMap::from([("red".to_string(), Rc::clone(&red))]);
+        let mesh = parse_obj_mesh(source, &materials, Rc::clone(&default))?;
+        // the shared corners are stored once
+        assert_eq!(mesh.positions().len(), 4);
+        assert!(Rc::ptr_eq(mesh.material(0), &red));
+        assert!(Rc::ptr_eq(mesh.material(1), &default));
+        Ok(())
+    }
+
+    #[test]
+    fn cage_keeps_polygons() -> Result<()> {
+        let source = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+f 1/1 2/1 3/1 4/1
+"#;
+        let cage = parse_obj_cage(source)?;
+        assert_eq!(cage.faces(), &[vec![0, 1, 2, 3]]);
+        assert!(parse_obj_cage("v 0 0 0\nf 1 2 3").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,575 @@
+use std::{path::Path, rc::Rc};
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::{
+    hittable::{CloudPoint, Mesh, PointCloud},
+    texture::VertexColors,
+    Color, Material, Point3, Vec3,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// The scalar types a PLY property may have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Reads a little-endian value from the start of `bytes`, which must be long enough.
+    fn read_le(self, bytes: &[u8]) -> f64 {
+        match self {
+            Scalar::I8 => f64::from(bytes[0] as i8),
+            Scalar::U8 => f64::from(bytes[0]),
+            Scalar::I16 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::U16 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::I32 => f64::from(i32::from_le_bytes(bytes[..4].try_into().unwrap())),
+            Scalar::U32 => f64::from(u32::from_le_bytes(bytes[..4].try_into().unwrap())),
+            Scalar::F32 => f64::from(f32::from_le_bytes(bytes[..4].try_into().unwrap())),
+            Scalar::F64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+
+    /// The value which represents full intensity for a color channel of this type.
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A property of an element: a single value, or a list of values preceded by their count.
+#[derive(Debug, Clone, Copy)]
+enum Property {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+/// A kind of element declared in the header, such as `vertex` or `face`, with the number
+/// of records of it in the body.
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<(String, Property)>,
+}
+
+impl Element {
+    fn find(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|(n, _)| n == name)
+    }
+}
+
+/// The header of a PLY file, along with the body it describes.
+struct Ply<'a> {
+    format: Format,
+    elements: Vec<Element>,
+    body: &'a [u8],
+}
+
+impl<'a> Ply<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self> {
+        const END_HEADER: &[u8] = b"end_header";
+        let header_end = bytes
+            .windows(END_HEADER.len())
+            .position(|window| window == END_HEADER)
+            .ok_or_else(|| miette!("The file has no {} line.", "end_header".green()))?;
+        let body_start = bytes[header_end..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| header_end + i + 1);
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .map_err(|_| miette!("The PLY header must be text."))?;
+
+        let mut lines = header.lines().map(str::trim);
+        if lines.next() != Some("ply") {
+            bail!("The file must start with {}.", "ply".green());
+        }
+
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let scalar = |kind: &str| {
+            Scalar::parse(kind).ok_or_else(|| miette!("Unknown property type {}.", kind.green()))
+        };
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let property = match words.as_slice() {
+                ["format", "ascii", _] => {
+                    format = Some(Format::Ascii);
+                    continue;
+                }
+                ["format", "binary_little_endian", _] => {
+                    format = Some(Format::BinaryLittleEndian);
+                    continue;
+                }
+                ["format", other, _] => {
+                    bail!("The {} PLY format is not supported.", other.green())
+                }
+                ["element", name, count] => {
+                    let count = count
+                        .parse::<usize>()
+                        .map_err(|_| miette!("Invalid {} count {}.", name, count.green()))?;
+                    elements.push(Element {
+                        name: name.to_string(),
+                        count,
+                        properties: Vec::new(),
+                    });
+                    continue;
+                }
+                ["property", "list", count, item, name] => (
+                    name,
+                    Property::List {
+                        count: scalar(count)?,
+                        item: scalar(item)?,
+                    },
+                ),
+                ["property", kind, name] => (name, Property::Scalar(scalar(kind)?)),
+                _ => continue,
+            };
+            let Some(element) = elements.last_mut() else {
+                bail!("Property {} is not part of an element.", property.0.green());
+            };
+            element
+                .properties
+                .push((property.0.to_string(), property.1));
+        }
+
+        let format = format.ok_or_else(|| miette!("The file has no {} line.", "format".green()))?;
+        Ok(Self {
+            format,
+            elements,
+            body: &bytes[body_start..],
+        })
+    }
+
+    fn element(&self, name: &str) -> Result<&Element> {
+        self.elements
+            .iter()
+            .find(|element| element.name == name)
+            .ok_or_else(|| miette!("The file has no {} element.", name.green()))
+    }
+
+    /// Reads every record in the body, in order, calling `visit` with each record's element
+    /// and the values of its properties. Scalar properties have a single value.
+    fn read(&self, mut visit: impl FnMut(&Element, &[Vec<f64>]) -> Result<()>) -> Result<()> {
+        let mut lines = match self.format {
+            Format::Ascii => Some(
+                std::str::from_utf8(self.body)
+                    .map_err(|_| miette!("ASCII PLY files must be text."))?
+                    .lines()
+                    .filter(|line| !line.trim().is_empty()),
+            ),
+            Format::BinaryLittleEndian => None,
+        };
+        let mut offset = 0;
+        let mut values: Vec<Vec<f64>> = Vec::new();
+
+        for element in &self.elements {
+            values.resize(element.properties.len(), Vec::new());
+            for i in 0..element.count {
+                let missing = || {
+                    miette!(
+                        "Expected {} {} elements, found {}.",
+                        element.count,
+                        element.name,
+                        i
+                    )
+                };
+                let invalid = || miette!("Invalid {} {}.", element.name, i);
+                match &mut lines {
+                    Some(lines) => {
+                        let mut words = lines.next().ok_or_else(missing)?.split_whitespace();
+                        let mut next = || -> Result<f64> {
+                            words
+                                .next()
+                                .and_then(|word| word.parse().ok())
+                                .ok_or_else(invalid)
+                        };
+                        for (value, (_, property)) in values.iter_mut().zip(&element.properties) {
+                            value.clear();
+                            let len = match property {
+                                Property::Scalar(_) => 1,
+                                Property::List { .. } => list_len(next()?).ok_or_else(invalid)?,
+                            };
+                            for _ in 0..len {
+                                value.push(next()?);
+                            }
+                        }
+                    }
+                    None => {
+                        let mut next = |scalar: Scalar| -> Result<f64> {
+                            let bytes = self
+                                .body
+                                .get(offset..offset + scalar.size())
+                                .ok_or_else(missing)?;
+                            offset += scalar.size();
+                            Ok(scalar.read_le(bytes))
+                        };
+                        for (value, (_, property)) in values.iter_mut().zip(&element.properties) {
+                            value.clear();
+                            match *property {
+                                Property::Scalar(scalar) => value.push(next(scalar)?),
+                                Property::List { count, item } => {
+                                    let len = list_len(next(count)?).ok_or_else(invalid)?;
+                                    for _ in 0..len {
+                                        value.push(next(item)?);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                visit(element, &values)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The length of a list, given its count property.
+fn list_len(count: f64) -> Option<usize> {
+    (count >= 0.0 && count.fract() == 0.0).then_some(count as usize)
+}
+
+/// Where the properties of each vertex are found among the properties of the `vertex` element.
+struct VertexLayout {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    /// The red, green and blue properties, along with the value of each at full intensity
+    color: Option<[(usize, f64); 3]>,
+}
+
+impl VertexLayout {
+    fn of(element: &Element) -> Result<Self> {
+        let find = |names: [&str; 3]| {
+            let [a, b, c] = names.map(|name| element.find(name));
+            Some([a?, b?, c?])
+        };
+        let Some(position) = find(["x", "y", "z"]) else {
+            bail!("Vertices must have x, y, and z properties.");
+        };
+        let color = find(["red", "green", "blue"]).map(|color| {
+            color.map(|i| match element.properties[i].1 {
+                Property::Scalar(scalar) => (i, scalar.color_scale()),
+                Property::List { .. } => (i, 1.0),
+            })
+        });
+        Ok(Self {
+            position,
+            normal: find(["nx", "ny", "nz"]),
+            color,
+        })
+    }
+
+    fn position(&self, values: &[Vec<f64>]) -> Result<Point3> {
+        let [x, y, z] = self.position.map(|i| values[i].first().copied());
+        match (x, y, z) {
+            (Some(x), Some(y), Some(z)) => Ok(Point3::new(x, y, z)),
+            _ => bail!("Vertex positions must be single values."),
+        }
+    }
+
+    /// The vertex's color, or white if the vertices have no colors.
+    fn color(&self, values: &[Vec<f64>]) -> Color {
+        match self.color {
+            Some(color) => {
+                let [r, g, b] =
+                    color.map(|(i, scale)| values[i].first().map_or(1.0, |v| v / scale));
+                Color::new(r, g, b)
+            }
+            None => Color::white(),
+        }
+    }
+
+    fn normal(&self, values: &[Vec<f64>]) -> Option<Vec3> {
+        let [x, y, z] = self.normal?.map(|i| values[i].first().copied());
+        Some(Vec3::new(x?, y?, z?))
+    }
+}
+
+/// Parses a PLY (Stanford polygon) file as a point cloud, reading the `x`, `y`, `z` position,
+/// `nx`, `ny`, `nz` normal, and `red`, `green`, `blue` color of each vertex.
+/// Faces and other elements are ignored, as are vertex properties not listed above.
+///
+/// ASCII and binary little-endian files are supported. Points without a color are white.
+pub fn parse_ply(bytes: &[u8]) -> Result<PointCloud> {
+    let ply = Ply::parse(bytes)?;
+    let layout = VertexLayout::of(ply.element("vertex")?)?;
+
+    let mut cloud = PointCloud::new();
+    ply.read(|element, values| {
+        if element.name == "vertex" {
+            cloud.push(CloudPoint {
+                position: layout.position(values)?,
+                color: layout.color(values),
+                normal: layout.normal(values),
+            });
+        }
+        Ok(())
+    })?;
+    Ok(cloud)
+}
+
+/// Parses a PLY (Stanford polygon) file as a [`Mesh`], from the `x`, `y`, `z` position of
+/// each vertex and the `vertex_indices` (or `vertex_index`) of each face. Polygons with more
+/// than three vertices are split into a fan of triangles.
+///
+/// If the vertices have `red`, `green`, `blue` colors, they're passed to `material` as
+/// [`VertexColors`], so that the mesh's material can be built from them; scanned models
+/// are often colored this way rather than with a texture.
+pub fn parse_ply_mesh(
+    bytes: &[u8],
+    material: impl FnOnce(Option<VertexColors>) -> Rc<dyn Material>,
+) -> Result<Mesh> {
+    let ply = Ply::parse(bytes)?;
+    let layout = VertexLayout::of(ply.element("vertex")?)?;
+    let face = ply.element("face")?;
+    let Some(corners) = face
+        .find("vertex_indices")
+        .or_else(|| face.find("vertex_index"))
+    else {
+        bail!("Faces must have a {} property.", "vertex_indices".green());
+    };
+
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices: Vec<[u32; 3]> = Vec::new();
+    let mut faces = 0;
+    ply.read(|element, values| {
+        match element.name.as_str() {
+            "vertex" => {
+                positions.push(layout.position(values)?);
+                colors.push(layout.color(values));
+            }
+            "face" => {
+                let face = faces;
+                faces += 1;
+                let polygon = &values[corners];
+                if polygon.len() < 3 {
+                    bail!("Face {} has fewer than 3 vertices.", face);
+                }
+                let Some(polygon) = polygon
+                    .iter()
+                    .map(|&v| u32::try_from(list_len(v)?).ok())
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    bail!("Invalid face {}.", face);
+                };
+                for k in 1..polygon.len() - 1 {
+                    indices.push([polygon[0], polygon[k], polygon[k + 1]]);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    // faces may come before the vertices they refer to, so they're checked afterwards
+    if let Some(i) = indices
+        .iter()
+        .flatten()
+        .find(|&&i| i as usize >= positions.len())
+    {
+        bail!(
+            "A face refers to vertex {}, but there are only {} vertices.",
+            i.purple(),
+            positions.len()
+        );
+    }
+
+    let colors = layout
+        .color
+        .map(|_| VertexColors::for_faces(&positions, &indices, colors));
+    Ok(Mesh::new(positions, indices, material(colors)))
+}
+
+/// Loads a `.ply` file; see [`parse_ply`].
+pub fn load_ply(path: &Path) -> Result<PointCloud> {
+    parse_ply(&read(path)?)
+}
+
+/// Loads a `.ply` file as a mesh; see [`parse_ply_mesh`].
+pub fn load_ply_mesh(
+    path: &Path,
+    material: impl FnOnce(Option<VertexColors>) -> Rc<dyn Material>,
+) -> Result<Mesh> {
+    parse_ply_mesh(&read(path)?, material)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, texture::Texture, Hittable, Interval, Ray4};
+
+    #[test]
+    fn ascii_and_binary() -> Result<()> {
+        let ascii = b"ply
+format ascii 1.0
+comment scanned
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 0
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 2 3 0 255 0
+";
+        let cloud = parse_ply(ascii)?;
+        assert_eq!(cloud.len(), 2);
+        assert_eq!(cloud.points()[1].position, Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(cloud.points()[1].color, Color::new(0.0, 1.0, 0.0));
+
+        // elements before the vertices are skipped
+        let faces_first = b"ply
+format ascii 1.0
+element face 1
+property list uchar int vertex_indices
+element vertex 1
+property float x
+property float y
+property float z
+end_header
+3 0 0 0
+4 5 6
+";
+        let cloud = parse_ply(faces_first)?;
+        assert_eq!(cloud.points()[0].position, Point3::new(4.0, 5.0, 6.0));
+
+        let mut binary = b"ply
+format binary_little_endian 1.0
+element vertex 1
+property double x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+end_header
+"
+        .to_vec();
+        binary.extend_from_slice(&1.5f64.to_le_bytes());
+        for value in [2.0f32, 3.0, 0.0, 0.0, 1.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        let cloud = parse_ply(&binary)?;
+        assert_eq!(cloud.points()[0].position, Point3::new(1.5, 2.0, 3.0));
+        assert_eq!(cloud.points()[0].normal, Some(Vec3::new(0.0, 0.0, 1.0)));
+        assert_eq!(cloud.points()[0].color, Color::white());
+        Ok(())
+    }
+
+    #[test]
+    fn meshes() -> Result<()> {
+        let quad = b"ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3
+";
+        let mut colors = None;
+        let mesh = parse_ply_mesh(quad, |vertex_colors| {
+            colors = vertex_colors;
+            Lambertian::solid(Color::white()).into_mat()
+        })?;
+        assert_eq!(mesh.len(), 2);
+        let ray = Ray4::new(Point3::new(0.5, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, Interval::universe()).unwrap();
+        let color = colors.unwrap().value_at(&hit);
+        assert!((color.r() - 0.25).abs() < 1e-9 && (color.b() - 0.75).abs() < 1e-9);
+
+        // in binary files, the faces may come first too
+        let mut binary = b"ply
+format binary_little_endian 1.0
+element face 1
+property list uchar uint vertex_indices
+element vertex 3
+property float x
+property float y
+property float z
+end_header
+"
+        .to_vec();
+        binary.push(3);
+        for index in [0u32, 1, 2] {
+            binary.extend_from_slice(&index.to_le_bytes());
+        }
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut colors = Some(VertexColors::new(Vec::new(), Vec::new()));
+        let mesh = parse_ply_mesh(&binary, |vertex_colors| {
+            colors = vertex_colors;
+            Lambertian::solid(Color::white()).into_mat()
+        })?;
+        assert_eq!(mesh.face(0)[1], Point3::new(1.0, 0.0, 0.0));
+        assert!(colors.is_none());
+        assert_eq!(parse_ply(&binary)?.len(), 3);
+
+        // faces must refer to existing vertices
+        let missing = String::from_utf8_lossy(quad).replace("4 0 1 2 3", "3 0 1 4");
+        let white = |_| Lambertian::solid(Color::white()).into_mat();
+        assert!(parse_ply_mesh(missing.as_bytes(), white).is_err());
+        Ok(())
+    }
+}
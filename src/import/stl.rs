@@ -0,0 +1,214 @@
+use std::{path::Path, rc::Rc};
+
+use miette::{bail, miette, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+
+use crate::{hittable::Mesh, Material, Point3, Vec3};
+
+/// The size of the header and triangle count which start a binary STL file.
+const BINARY_HEADER: usize = 84;
+/// The size of each triangle in a binary STL file: a normal, three corners, and a
+/// two-byte attribute count.
+const BINARY_TRIANGLE: usize = 50;
+
+/// Parses an STL (stereolithography) file, either ASCII or binary, into a [`Mesh`].
+/// STL files store each triangle separately, so the corners of adjacent faces aren't shared.
+///
+/// Each face's normal is taken from the winding of its corners. Unless `recompute_normals`
+/// is set, faces whose winding disagrees with the normal stored in the file are flipped to
+/// match it; many exporters write normals but wind faces inconsistently. Set
+/// `recompute_normals` for files with missing or unreliable stored normals.
+pub fn parse_stl(
+    bytes: &[u8],
+    material: Rc<dyn Material>,
+    recompute_normals: bool,
+) -> Result<Mesh> {
+    // binary files may start with "solid" too, but they always have exactly the size their
+    // triangle count implies
+    let binary_size = bytes
+        .get(80..BINARY_HEADER)
+        .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize)
+        .map(|count| BINARY_HEADER + count * BINARY_TRIANGLE);
+    let facets = if bytes.starts_with(b"solid") && binary_size != Some(bytes.len()) {
+        parse_ascii(bytes)?
+    } else {
+        parse_binary(bytes)?
+    };
+
+    let mut positions = Vec::with_capacity(facets.len() * 3);
+    let mut indices = Vec::with_capacity(facets.len());
+    for (normal, [p0, p1, p2]) in facets {
+        let winding = (p1 - p0).cross(&(p2 - p0));
+        // zero-area faces have no normal, and can never be hit anyways; tiny faces are kept,
+        // as a model in small units may be made up entirely of them
+        if winding.len_squared() == 0.0 {
+            continue;
+        }
+        let flip = !recompute_normals && winding.dot(&normal) < 0.0;
+        let start = positions.len() as u32;
+        if flip {
+            positions.extend([p0, p2, p1]);
+        } else {
+            positions.extend([p0, p1, p2]);
+        }
+        indices.push([start, start + 1, start + 2]);
+    }
+
+    Ok(Mesh::new(positions, indices, material))
+}
+
+/// Loads a `.stl` file; see [`parse_stl`].
+pub fn load_stl(path: &Path, material: Rc<dyn Material>, recompute_normals: bool) -> Result<Mesh> {
+    let bytes = std::fs::read(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    parse_stl(&bytes, material, recompute_normals)
+}
+
+type Facet = (Vec3, [Point3; 3]);
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Facet>> {
+    let Some(count) = bytes.get(80..BINARY_HEADER) else {
+        bail!("Binary STL files must have an 84 byte header.");
+    };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let body = &bytes[BINARY_HEADER..];
+    if body.len() < count * BINARY_TRIANGLE {
+        bail!(
+            "Expected {} triangles, found {}.",
+            count,
+            body.len() / BINARY_TRIANGLE
+        );
+    }
+
+    let read = |record: &[u8], i: usize| {
+        let value = |j: usize| {
+            let offset = (i * 3 + j) * 4;
+            f64::from(f32::from_le_bytes(
+                record[offset..offset + 4].try_into().unwrap(),
+            ))
+        };
+        (value(0), value(1), value(2))
+    };
+    Ok(body
+        .chunks_exact(BINARY_TRIANGLE)
+        .take(count)
+        .map(|record| {
+            let (nx, ny, nz) = read(record, 0);
+            let corners = [1, 2, 3].map(|i| {
+                let (x, y, z) = read(record, i);
+                Point3::new(x, y, z)
+            });
+            (Vec3::new(nx, ny, nz), corners)
+        })
+        .collect())
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Facet>> {
+    let source =
+        std::str::from_utf8(bytes).map_err(|_| miette!("ASCII STL files must be text."))?;
+
+    let mut facets = Vec::new();
+    let mut normal = Vec3::empty();
+    let mut corners = Vec::with_capacity(3);
+    for (line_no, line) in source.lines().enumerate() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let location = format!("line {}", line_no + 1);
+        let floats = |values: &[&str]| -> Result<(f64, f64, f64)> {
+            let &[x, y, z] = values else {
+                bail!(
+                    "Expected 3 decimal numbers, found {} ({}).",
+                    values.len().purple(),
+                    location
+                );
+            };
+            let parse = |value: &str| {
+                value.parse().map_err(|_| {
+                    miette!("Invalid decimal number {} ({}).", value.purple(), location)
+                })
+            };
+            Ok((parse(x)?, parse(y)?, parse(z)?))
+        };
+
+        match words.as_slice() {
+            ["facet", "normal", values @ ..] => {
+                let (x, y, z) = floats(values)?;
+                normal = Vec3::new(x, y, z);
+                corners.clear();
+            }
+            ["vertex", values @ ..] => {
+                let (x, y, z) = floats(values)?;
+                corners.push(Point3::new(x, y, z));
+            }
+            ["endfacet"] => {
+                let Ok(points) = <[Point3; 3]>::try_from(corners.as_slice()) else {
+                    bail!(
+                        "{} must have exactly 3 vertices ({}).",
+                        "facet".green(),
+                        location
+                    );
+                };
+                facets.push((normal, points));
+            }
+            // solid, outer loop, endloop and endsolid carry no data
+            _ => {}
+        }
+    }
+    Ok(facets)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{material::Lambertian, Color, Hittable, Interval, Ray4};
+
+    #[test]
+    fn ascii_and_binary() -> Result<()> {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        // wound clockwise when seen from +z, but its stored normal faces +z
+        let ascii = b"solid square
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 0 1 0
+    vertex 1 0 0
+  endloop
+endfacet
+endsolid square
+";
+        let mesh = parse_stl(ascii, Rc::clone(&mat), false)?;
+        assert_eq!(mesh.len(), 1);
+        let ray = Ray4::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, Interval::universe()).unwrap();
+        assert!(hit.front_face());
+        let recomputed = parse_stl(ascii, Rc::clone(&mat), true)?;
+        let hit = recomputed.hit(&ray, Interval::universe()).unwrap();
+        assert!(!hit.front_face());
+
+        // binary files may also begin with "solid"
+        let mut binary = b"solid".to_vec();
+        binary.resize(80, 0);
+        binary.extend_from_slice(&1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        binary.extend_from_slice(&[0, 0]);
+        let mesh = parse_stl(&binary, Rc::clone(&mat), false)?;
+        assert_eq!(mesh.face(0)[1], Point3::new(1.0, 0.0, 0.0));
+        assert!(mesh.hit(&ray, Interval::universe()).unwrap().front_face());
+
+        assert!(parse_stl(&binary[..100], Rc::clone(&mat), false).is_err());
+
+        // faces from a model in tiny units are kept
+        let tiny = String::from_utf8_lossy(ascii).replace(" 1", " 0.000001");
+        assert_eq!(parse_stl(tiny.as_bytes(), Rc::clone(&mat), false)?.len(), 1);
+
+        // malformed numbers are reported with their line
+        let malformed = String::from_utf8_lossy(ascii).replace("vertex 0 1 0", "vertex 0 1,5 0");
+        let error = parse_stl(malformed.as_bytes(), mat, false).unwrap_err();
+        assert!(error.to_string().contains("line 5"));
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use miette::{bail, IntoDiagnostic, Result};
+
+use crate::{
+    hittable::{CloudPoint, PointCloud},
+    Color, Point3, Vec3,
+};
+
+/// Parses the contents of an `.xyz` point cloud: one point per line, as `x y z`, optionally
+/// followed by an `r g b` color from 0 to 255 and then an `nx ny nz` normal.
+/// Values may be separated by spaces, tabs, or commas; lines starting with `#` are ignored.
+/// Points without a color are white.
+pub fn parse_xyz(source: &str) -> Result<PointCloud> {
+    let mut cloud = PointCloud::new();
+
+    for (line_no, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<_>, _>>();
+        let values = match values {
+            Ok(values) if matches!(values.len(), 3 | 6 | 9) => values,
+            _ => bail!(
+                "Expected 3, 6, or 9 decimal numbers (line {}).",
+                line_no + 1
+            ),
+        };
+
+        let color = match values.get(3..6) {
+            Some(&[r, g, b]) => Color::new(r / 255.0, g / 255.0, b / 255.0),
+            _ => Color::white(),
+        };
+        let normal = match values.get(6..9) {
+            Some(&[x, y, z]) => Some(Vec3::new(x, y, z)),
+            _ => None,
+        };
+
+        cloud.push(CloudPoint {
+            position: Point3::new(values[0], values[1], values[2]),
+            color,
+            normal,
+        });
+    }
+
+    Ok(cloud)
+}
+
+/// Loads an `.xyz` file; see [`parse_xyz`].
+pub fn load_xyz(path: &Path) -> Result<PointCloud> {
+    let source = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .map_err(|e| e.wrap_err(format!("Failed to read {}", path.display())))?;
+    parse_xyz(&source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn columns() -> Result<()> {
+        let cloud = parse_xyz("# scan\n0 0 0\n1,2,3,255,0,0\n0 0 1 0 0 255 0 1 0\n")?;
+        assert_eq!(cloud.len(), 3);
+        assert_eq!(cloud.points()[0].color, Color::white());
+        assert_eq!(cloud.points()[1].color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(cloud.points()[2].normal, Some(Vec3::new(0.0, 1.0, 0.0)));
+
+        assert!(parse_xyz("1 2\n").is_err());
+        Ok(())
+    }
+}
@@ -1,23 +1,41 @@
+pub mod analysis;
+pub mod archive;
 pub mod axis;
 pub mod boundingbox;
+pub mod bsdf;
 pub mod camera;
 pub mod config;
+pub mod dataset;
 pub mod export;
 pub mod hittable;
+pub mod import;
 pub mod material;
 pub mod math;
 pub mod onb;
 pub mod pdf;
+pub mod rng;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(test)]
+mod test_support;
 pub mod texture;
 
-pub use axis::Axis;
+pub use axis::{Axis, Direction};
 
-pub use camera::{AntialiasingType, Background, Camera, CameraBuilder};
+pub use bsdf::BSDF;
+
+pub use camera::{
+    Accumulator, AntialiasingType, Background, Camera, CameraBuilder, Integrator, Pick,
+    SampleDiagnostics,
+};
 
 pub use hittable::{HitRecord, Hittable};
 
 pub use material::Material;
 
+pub use math::angle;
+pub use math::angle::Angle;
+
 pub use math::point;
 pub use math::point::Point2;
 pub use math::point::Point3;
@@ -33,6 +51,8 @@ pub use math::interval::Interval;
 pub use math::color;
 pub use math::color::Color;
 
+pub use math::matrix;
+
 pub use math::ray;
 pub use math::ray::{Ray3, Ray4};
 
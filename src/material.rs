@@ -1,32 +1,40 @@
-use std::rc::Rc;
+use std::{cell::RefCell, f64::consts::PI, rc::Rc};
 
-use rand::random;
+use crate::rng::random;
 
-use crate::{texture::SolidColor, Color, HitRecord, OrthonormalBasis, Point3, Ray4, Texture, Vec3};
+pub mod library;
 
-#[derive(Debug)]
-pub struct MaterialResult {
-    pub attenuation: Color,
-    pub scattered: Ray4,
-    pub pdf: f64,
-}
+use crate::{
+    bsdf::{
+        conductor_reflectance, reflectance, BlinnPhongBSDF, DielectricBSDF, DiffuseBSDF, HairBSDF,
+        IsotropicBSDF, ReflectionBSDF, ThinDielectricBSDF,
+    },
+    texture::SolidColor,
+    Color, HitRecord, Point3, Ray4, Texture, BSDF,
+};
 
 pub trait Material: std::fmt::Debug {
-    fn scatter(&self, ray_in: &Ray4, record: &HitRecord) -> Option<MaterialResult>;
+    /// Produces the [`BSDF`] describing how light is scattered at the point hit.
+    /// Materials which do not scatter light (such as [`DiffuseLight`]) return `None`.
+    fn bsdf(&self, ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>>;
+
     fn emitted(&self, ray_in: &Ray4, record: &HitRecord, u: f64, v: f64, point: &Point3) -> Color {
+        let _ = (ray_in, record, u, v, point);
         Color::black()
     }
 
+    /// The name of this material's type, without its module path; used in diagnostics.
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
     fn into_mat(self) -> Rc<dyn Material>
     where
         Self: Sized + 'static,
     {
         Rc::new(self)
     }
-
-    fn scattering_pdf(&self, ray_in: &Ray4, record: &HitRecord, scattered: &Ray4) -> f64 {
-        unimplemented!();
-    }
 }
 
 #[derive(Debug)]
@@ -44,21 +52,9 @@ impl Lambertian {
 
 impl Material for Lambertian {
     // Lambertian materials are independant of the incoming ray due to Lambert's Cosine Law.
-    fn scatter(&self, ray_in: &Ray4, record: &HitRecord) -> Option<MaterialResult> {
-        let uvw = OrthonormalBasis::new(&record.normal().into());
-        let scatter_dir = uvw.transform(&Vec3::random_on_sphere_cosine());
-
-        let scattered = Ray4::new(record.point(), scatter_dir.as_unit().into(), ray_in.time());
-        Some(MaterialResult {
-            attenuation: self.0.value(record.u(), record.v(), &record.point()),
-            pdf: Vec3::dot(&uvw.w(), &scattered.direction()) / std::f64::consts::PI,
-            scattered,
-        })
-    }
-
-    fn scattering_pdf(&self, ray_in: &Ray4, record: &HitRecord, scattered: &Ray4) -> f64 {
-        let cos_theta = Vec3::dot(&record.normal(), &scattered.direction().as_unit());
-        return f64::max(0.0, cos_theta / std::f64::consts::PI);
+    fn bsdf(&self, _ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        let albedo = self.0.value_at(record);
+        Some(DiffuseBSDF::new(albedo, &record.normal()).into_bsdf())
     }
 }
 
@@ -73,11 +69,57 @@ impl DiffuseLight {
     pub fn solid(albedo: Color) -> Self {
         Self(Rc::new(SolidColor::new(albedo)))
     }
+
+    /// A light of the hue of `color`, emitting `power` from the front of a surface `area`
+    /// square scene units in size (such as a [`Parallelogram`](crate::hittable::Parallelogram)
+    /// of that area). The brightness of `color` is ignored; see [`LightPower::radiance`].
+    pub fn with_power(color: Color, power: LightPower, area: f64) -> Self {
+        let luminance = color.luminance();
+        assert!(
+            luminance > 0.0,
+            "Invalid light color (expected luminance > 0)"
+        );
+        let mut color = color;
+        color.set_brightness(power.radiance(area) / luminance);
+        Self::solid(color)
+    }
+}
+
+/// The brightness of a light in physical units, so that lights can be set from a bulb's
+/// packaging or a display's datasheet rather than by trial and error.
+///
+/// Radiance is rendered in watts per steradian per square metre, with scene units taken to
+/// be metres, so a radiance of 1.0 is written as a pixel value of 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightPower {
+    /// Radiant power, in watts, emitted by the whole light
+    Watts(f64),
+    /// Luminous flux, in lumens, emitted by the whole light
+    Lumens(f64),
+    /// Luminance, in candela per square metre (nits), of the light's surface
+    Nits(f64),
+}
+
+impl LightPower {
+    /// The lumens in each watt of light, at the wavelength the eye is most sensitive to.
+    pub const LUMENS_PER_WATT: f64 = 683.0;
+
+    /// The radiance of a light emitting this power evenly in every direction from the front
+    /// of a surface of `area` square scene units, as a [`DiffuseLight`] does.
+    /// The area is ignored for [`LightPower::Nits`], which doesn't depend on it.
+    pub fn radiance(self, area: f64) -> f64 {
+        // the power leaving a Lambertian emitter is pi times its radiance, per unit area
+        match self {
+            LightPower::Watts(watts) => watts / (PI * area),
+            LightPower::Lumens(lumens) => lumens / Self::LUMENS_PER_WATT / (PI * area),
+            LightPower::Nits(nits) => nits / Self::LUMENS_PER_WATT,
+        }
+    }
 }
 
 impl Material for DiffuseLight {
     // DiffuseLight does not scatter.
-    fn scatter(&self, _ray_in: &Ray4, _record: &HitRecord) -> Option<MaterialResult> {
+    fn bsdf(&self, _ray_in: &Ray4, _record: &HitRecord) -> Option<Rc<dyn BSDF>> {
         None
     }
 
@@ -95,39 +137,93 @@ impl Material for DiffuseLight {
 pub struct Metal {
     albedo: Color,
     fuzz: f64,
+    /// The complex refractive index `eta + ik` of each color channel.
+    /// If present, the reflectance is computed from the Fresnel equations instead of `albedo`.
+    complex_index: Option<(Color, Color)>,
 }
 
 impl Metal {
     pub fn new(albedo: Color) -> Self {
-        Self { albedo, fuzz: 0.0 }
+        Self {
+            albedo,
+            fuzz: 0.0,
+            complex_index: None,
+        }
     }
 
     pub fn with_fuzz(albedo: Color, fuzz: f64) -> Self {
+        Self::new(albedo).fuzzed(fuzz)
+    }
+
+    /// A metal whose reflectance is computed from its complex refractive index `eta + ik`,
+    /// given for the red, green, and blue channels. The metal's color shifts
+    /// towards white at grazing angles.
+    pub fn conductor(eta: Color, k: Color) -> Self {
+        Self {
+            albedo: Color::white(),
+            fuzz: 0.0,
+            complex_index: Some((eta, k)),
+        }
+    }
+
+    /// Sets the fuzz of this metal.
+    pub fn fuzzed(mut self, fuzz: f64) -> Self {
         assert!(
             (0.0..=1.0).contains(&fuzz),
             "Invalid fuzz value (expected 0.0..=1.0)",
         );
+        self.fuzz = fuzz;
+        self
+    }
+
+    // Measured refractive indices, averaged over the wavelengths of each channel.
+
+    pub fn gold() -> Self {
+        Self::conductor(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+        )
+    }
+
+    pub fn silver() -> Self {
+        Self::conductor(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+        )
+    }
+
+    pub fn copper() -> Self {
+        Self::conductor(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+        )
+    }
 
-        Self { albedo, fuzz }
+    pub fn aluminium() -> Self {
+        Self::conductor(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+        )
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray4, record: &HitRecord) -> Option<MaterialResult> {
-        let reflected = Vec3::reflect(&ray_in.direction(), &record.normal());
-        let reflected = reflected.as_unit() + (self.fuzz * Vec3::random_in_unit_sphere());
-        let scattered = Ray4::new(record.point(), reflected, ray_in.time());
-
-        if Vec3::dot(&reflected, &record.normal()) < 0.0 {
-            // if the ray has been scattered below the surface of the object
-            return None;
-        }
+    fn bsdf(&self, ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        let albedo = match self.complex_index {
+            Some((eta, k)) => {
+                let cos_theta = (-ray_in.direction().as_unit())
+                    .dot(&record.normal())
+                    .clamp(0.0, 1.0);
+                Color::new(
+                    conductor_reflectance(cos_theta, eta.r(), k.r()),
+                    conductor_reflectance(cos_theta, eta.g(), k.g()),
+                    conductor_reflectance(cos_theta, eta.b(), k.b()),
+                )
+            }
+            None => self.albedo,
+        };
 
-        Some(MaterialResult {
-            attenuation: self.albedo,
-            pdf: todo!(),
-            scattered,
-        })
+        Some(ReflectionBSDF::new(albedo, &record.normal(), self.fuzz).into_bsdf())
     }
 }
 
@@ -136,23 +232,59 @@ pub struct Dielectric {
     /// Refractive index in vacuum or air, or the ratio of the material's refractive index over
     /// the refractive index of the enclosing media
     refraction_index: f64,
+    /// Roughness of the surface, from `0.0..=1.0`; read from the texture's luminance.
+    /// Smooth glass if `None`.
+    roughness: Option<Rc<dyn Texture>>,
+    /// The Abbe number of the glass; lower values disperse light more strongly.
+    /// No dispersion if `None`.
+    abbe_number: Option<f64>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            roughness: None,
+            abbe_number: None,
+        }
+    }
+
+    /// Frosted or sandblasted glass, with a roughness that may vary across the surface.
+    pub fn frosted(refraction_index: f64, roughness: Rc<dyn Texture>) -> Self {
+        Self {
+            refraction_index,
+            roughness: Some(roughness),
+            abbe_number: None,
+        }
+    }
+
+    /// Splits light into a spectrum, with the strength given as an Abbe number: around 60
+    /// for crown glass, 30 for dense flint glass, and 55 for diamond. `refraction_index` is
+    /// taken to be the index at the yellow helium d-line (587.6nm).
+    pub fn with_abbe_number(mut self, abbe_number: f64) -> Self {
+        self.abbe_number = Some(abbe_number);
+        self
     }
 
-    fn reflectance(cos: f64, refraction_idx: f64) -> f64 {
-        // The likelihood of a ray to reflect, based on the Schlck approximation
-        let r0 = (1.0 - refraction_idx) / (1.0 + refraction_idx);
-        let r0 = r0 * r0;
-        r0 + (1.0 - r0) * (1.0 - cos).powf(5.0)
+    /// The refractive index at each of the red, green and blue channels, from Cauchy's
+    /// equation `n(λ) = A + B / λ²` fitted to the refractive index and Abbe number.
+    fn channel_indices(&self, abbe_number: f64) -> [f64; 3] {
+        // Fraunhofer lines used to define the Abbe number, in micrometres
+        const D_LINE: f64 = 0.5876;
+        const F_LINE: f64 = 0.4861;
+        const C_LINE: f64 = 0.6563;
+        // representative wavelengths of the red, green and blue channels
+        const CHANNELS: [f64; 3] = [0.61, 0.55, 0.465];
+
+        // V = (n_d - 1) / (n_F - n_C), and n_F - n_C = B (1/λ_F² - 1/λ_C²)
+        let b = (self.refraction_index - 1.0) / (abbe_number * (F_LINE.powi(-2) - C_LINE.powi(-2)));
+        let a = self.refraction_index - b / (D_LINE * D_LINE);
+        CHANNELS.map(|wavelength| a + b / (wavelength * wavelength))
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray4, record: &HitRecord) -> Option<MaterialResult> {
+    fn bsdf(&self, ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
         // exiting the material, the refraction index is reversed.
         // air has a refraction index of =~ 1.0
         let ri = if record.front_face() {
@@ -160,25 +292,40 @@ impl Material for Dielectric {
         } else {
             self.refraction_index
         };
-        let direction = ray_in.direction().as_unit();
-        let cos_theta = (-direction).dot(&record.normal()).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let cannot_refract = ri * sin_theta > 1.0;
+        let mut bsdf = DielectricBSDF::new(&record.normal(), ri);
+        if let Some(abbe_number) = self.abbe_number {
+            let indices = self.channel_indices(abbe_number);
+            let ratios = indices.map(|n| if record.front_face() { 1.0 / n } else { n });
+            bsdf = bsdf.with_dispersion(ratios).with_channel(ray_in.channel());
+        }
+        if let Some(roughness) = &self.roughness {
+            let roughness = roughness.value_at(record);
+            bsdf = bsdf.with_roughness(roughness.luminance());
+        }
 
-        let will_reflect = cannot_refract || (Dielectric::reflectance(cos_theta, ri) > random());
+        Some(bsdf.into_bsdf())
+    }
+}
 
-        let direction = if will_reflect {
-            Vec3::from(direction).reflect(&record.normal())
-        } else {
-            direction.refract(&record.normal(), ri)
-        };
+/// A single, infinitely thin pane of glass, such as a window or display case.
+/// Unlike [`Dielectric`], rays pass through without being refracted, so closed geometry is
+/// not required and no bounces are spent crossing the glass's thickness.
+#[derive(Debug)]
+pub struct ThinDielectric {
+    /// Refractive index of the glass, relative to the surrounding media
+    refraction_index: f64,
+}
 
-        Some(MaterialResult {
-            attenuation: Color::white(),
-            pdf: todo!(),
-            scattered: Ray4::new(record.point(), direction, ray_in.time()),
-        })
+impl ThinDielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        Self { refraction_index }
+    }
+}
+
+impl Material for ThinDielectric {
+    fn bsdf(&self, _ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        Some(ThinDielectricBSDF::new(&record.normal(), self.refraction_index).into_bsdf())
     }
 }
 
@@ -196,18 +343,180 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, ray_in: &Ray4, record: &HitRecord) -> Option<MaterialResult> {
-        let scattered = Ray4::new(record.point(), Vec3::random_in_unit_sphere(), ray_in.time());
-        let attenuation = self.0.value(record.u(), record.v(), &record.point());
+    fn bsdf(&self, _ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        let albedo = self.0.value_at(record);
+        Some(IsotropicBSDF::new(albedo).into_bsdf())
+    }
+}
+
+/// A smooth dielectric coating over an arbitrary base material, such as lacquer over wood or
+/// the clearcoat over car paint.
+///
+/// At each hit, the coating reflects with a probability given by its Fresnel reflectance;
+/// otherwise the light passes through to be scattered by the base material.
+#[derive(Debug)]
+pub struct Layered {
+    base: Rc<dyn Material>,
+    /// Refractive index of the coating
+    coat_index: f64,
+    /// Fuzziness of the coating's reflection, from `0.0..=1.0`
+    coat_fuzz: f64,
+}
 
-        Some(MaterialResult {
-            pdf: 1.0 / (4.0 * std::f64::consts::PI),
-            attenuation,
-            scattered,
-        })
+impl Layered {
+    pub fn new(base: Rc<dyn Material>, coat_index: f64, coat_fuzz: f64) -> Self {
+        assert!(coat_index > 0.0, "Invalid coat refractive index");
+        assert!(
+            (0.0..=1.0).contains(&coat_fuzz),
+            "Invalid fuzz value (expected 0.0..=1.0)",
+        );
+
+        Self {
+            base,
+            coat_index,
+            coat_fuzz,
+        }
+    }
+
+    /// A perfectly smooth coating with the refractive index of a typical lacquer (1.5).
+    pub fn clearcoat(base: Rc<dyn Material>) -> Self {
+        Self::new(base, 1.5, 0.0)
+    }
+}
+
+impl Material for Layered {
+    fn bsdf(&self, ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        // the coating is only visible from the outside of the object
+        if !record.front_face() {
+            return self.base.bsdf(ray_in, record);
+        }
+
+        let cos_theta = (-ray_in.direction().as_unit())
+            .dot(&record.normal())
+            .clamp(0.0, 1.0);
+
+        // choosing a layer with probability equal to its weight keeps the estimate unbiased
+        if reflectance(cos_theta, 1.0 / self.coat_index) > random() {
+            Some(ReflectionBSDF::new(Color::white(), &record.normal(), self.coat_fuzz).into_bsdf())
+        } else {
+            self.base.bsdf(ray_in, record)
+        }
+    }
+
+    fn emitted(&self, ray_in: &Ray4, record: &HitRecord, u: f64, v: f64, point: &Point3) -> Color {
+        self.base.emitted(ray_in, record, u, v, point)
+    }
+}
+
+/// The Blinn-Phong shading model, as used by OBJ/MTL files and older renderers.
+/// `diffuse` and `specular` correspond to the MTL `Kd` and `Ks` colors, and `exponent` to `Ns`.
+#[derive(Debug)]
+pub struct BlinnPhong {
+    diffuse: Rc<dyn Texture>,
+    specular: Color,
+    exponent: f64,
+}
+
+impl BlinnPhong {
+    pub fn new(diffuse: Rc<dyn Texture>, specular: Color, exponent: f64) -> Self {
+        assert!(
+            exponent >= 0.0,
+            "Invalid specular exponent (expected >= 0.0)"
+        );
+        Self {
+            diffuse,
+            specular,
+            exponent,
+        }
+    }
+
+    pub fn solid(diffuse: Color, specular: Color, exponent: f64) -> Self {
+        Self::new(SolidColor::new(diffuse).into_texture(), specular, exponent)
+    }
+}
+
+impl Material for BlinnPhong {
+    fn bsdf(&self, _ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        let diffuse = self.diffuse.value_at(record);
+        Some(
+            BlinnPhongBSDF::new(diffuse, self.specular, self.exponent, &record.normal())
+                .into_bsdf(),
+        )
+    }
+}
+
+/// A simple shading model for hair and fur; see [`HairBSDF`]. Intended for use with
+/// [`Curve`](crate::hittable::Curve)s, which record the direction along each strand.
+/// Surfaces without a tangent direction are shaded as [`Lambertian`].
+#[derive(Debug)]
+pub struct Hair {
+    color: Rc<dyn Texture>,
+    specular: Color,
+    exponent: f64,
+}
+
+impl Hair {
+    pub fn new(color: Rc<dyn Texture>, specular: Color, exponent: f64) -> Self {
+        assert!(
+            exponent >= 0.0,
+            "Invalid specular exponent (expected >= 0.0)"
+        );
+        Self {
+            color,
+            specular,
+            exponent,
+        }
+    }
+
+    pub fn solid(color: Color, specular: Color, exponent: f64) -> Self {
+        Self::new(SolidColor::new(color).into_texture(), specular, exponent)
+    }
+}
+
+impl Material for Hair {
+    fn bsdf(&self, _ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        let color = self.color.value_at(record);
+        let bsdf = match record.tangent() {
+            Some(tangent) => {
+                HairBSDF::new(color, self.specular, self.exponent, &tangent).into_bsdf()
+            }
+            None => DiffuseBSDF::new(color, &record.normal()).into_bsdf(),
+        };
+        Some(bsdf)
+    }
+}
+
+/// A material which can be replaced after the objects using it are built, so that a scene's
+/// look can be changed without rebuilding its geometry; see
+/// [`ConfigModel::as_live_world`](crate::config::ConfigModel::as_live_world).
+#[derive(Debug)]
+pub struct MaterialSlot(RefCell<Rc<dyn Material>>);
+
+impl MaterialSlot {
+    pub fn new(material: Rc<dyn Material>) -> Self {
+        Self(RefCell::new(material))
+    }
+
+    /// Replaces the material; objects using this slot are shaded with `material` from now on.
+    pub fn replace(&self, material: Rc<dyn Material>) {
+        *self.0.borrow_mut() = material;
+    }
+
+    pub fn get(&self) -> Rc<dyn Material> {
+        Rc::clone(&self.0.borrow())
+    }
+}
+
+impl Material for MaterialSlot {
+    fn bsdf(&self, ray_in: &Ray4, record: &HitRecord) -> Option<Rc<dyn BSDF>> {
+        self.0.borrow().bsdf(ray_in, record)
+    }
+
+    fn emitted(&self, ray_in: &Ray4, record: &HitRecord, u: f64, v: f64, point: &Point3) -> Color {
+        self.0.borrow().emitted(ray_in, record, u, v, point)
     }
 
-    fn scattering_pdf(&self, _: &Ray4, _: &HitRecord, _: &Ray4) -> f64 {
-        1.0 / (4.0 * std::f64::consts::PI)
+    fn type_name(&self) -> &'static str {
+        self.0.borrow().type_name()
     }
 }
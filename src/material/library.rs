@@ -0,0 +1,69 @@
+//! Ready-made materials with physically plausible parameters, for scenes which don't need
+//! anything more specific. In a config file they are referred to as `"@library/<name>"`.
+
+use std::rc::Rc;
+
+use super::{BlinnPhong, Dielectric, Lambertian, Layered, Metal};
+use crate::{texture::SolidColor, Color, Material, Texture};
+
+/// The names of every material in the library.
+pub const NAMES: &[&str] = &[
+    "glass",
+    "frosted_glass",
+    "water",
+    "diamond",
+    "gold",
+    "silver",
+    "copper",
+    "aluminium",
+    "brushed_aluminium",
+    "plastic",
+    "rubber",
+    "car_paint",
+];
+
+/// Creates the library material called `name`, or `None` if there is no such material.
+pub fn get(name: &str) -> Option<Rc<dyn Material>> {
+    let material = match name {
+        "glass" => Dielectric::new(1.5).into_mat(),
+        "frosted_glass" => {
+            Dielectric::frosted(1.5, SolidColor::new(Color::over_white(0.3)).into_texture())
+                .into_mat()
+        }
+        "water" => Dielectric::new(1.333).into_mat(),
+        "diamond" => Dielectric::new(2.418).into_mat(),
+        "gold" => Metal::gold().into_mat(),
+        "silver" => Metal::silver().into_mat(),
+        "copper" => Metal::copper().into_mat(),
+        "aluminium" => Metal::aluminium().into_mat(),
+        "brushed_aluminium" => Metal::aluminium().fuzzed(0.3).into_mat(),
+        // a white diffuse base under a glossy coat
+        "plastic" => {
+            Layered::clearcoat(Lambertian::solid(Color::over_white(0.8)).into_mat()).into_mat()
+        }
+        // dark and dull, with a broad, faint highlight
+        "rubber" => {
+            BlinnPhong::solid(Color::over_white(0.05), Color::over_white(0.1), 8.0).into_mat()
+        }
+        // a deep red base with a soft metallic sheen, under a smooth clearcoat
+        "car_paint" => Layered::clearcoat(
+            BlinnPhong::solid(Color::new(0.5, 0.02, 0.03), Color::over_white(0.2), 32.0).into_mat(),
+        )
+        .into_mat(),
+        _ => return None,
+    };
+    Some(material)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_name_resolves() {
+        for name in NAMES {
+            assert!(get(name).is_some(), "missing library material {name}");
+        }
+        assert!(get("unobtainium").is_none());
+    }
+}
@@ -1,6 +1,8 @@
+pub mod angle;
 pub mod color;
 pub mod interval;
 mod macros;
+pub mod matrix;
 pub mod point;
 pub mod ray;
 pub mod vec;
@@ -0,0 +1,105 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+/// An angle, which is given and read in an explicit unit so that degrees can't be passed
+/// where radians are expected, or the other way around.
+///
+/// # Examples
+/// ```
+/// use raytracing::Angle;
+///
+/// let right = Angle::degrees(90.0);
+/// assert_eq!(right, Angle::radians(std::f64::consts::FRAC_PI_2));
+/// assert_eq!((right / 2.0).as_degrees(), 45.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Angle(f64);
+
+impl Angle {
+    pub const ZERO: Self = Self(0.0);
+
+    pub const fn radians(radians: f64) -> Self {
+        Self(radians)
+    }
+
+    pub fn degrees(degrees: f64) -> Self {
+        Self(degrees.to_radians())
+    }
+
+    pub fn as_radians(self) -> f64 {
+        self.0
+    }
+
+    pub fn as_degrees(self) -> f64 {
+        self.0.to_degrees()
+    }
+
+    pub fn sin(self) -> f64 {
+        self.0.sin()
+    }
+
+    pub fn cos(self) -> f64 {
+        self.0.cos()
+    }
+
+    pub fn tan(self) -> f64 {
+        self.0.tan()
+    }
+
+    pub fn sin_cos(self) -> (f64, f64) {
+        self.0.sin_cos()
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.0.is_finite()
+    }
+}
+
+/// Displays the angle in degrees.
+impl Display for Angle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}°", self.as_degrees())
+    }
+}
+
+impl Neg for Angle {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Add for Angle {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Angle {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Mul<f64> for Angle {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Div<f64> for Angle {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self(self.0 / rhs)
+    }
+}
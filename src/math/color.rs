@@ -3,6 +3,9 @@ use std::{fmt::Display, ops::AddAssign};
 
 use super::vec::normal::NormalizationState;
 
+pub mod space;
+pub use space::ColorSpace;
+
 /// Represents an RGB color with 3 floats, ranging from `(0.0, 0.0, 0.0)` (black) to `(1.0, 1.0, 1.0)` (white).
 /// Invalid colors (colors outside the `0.0..=1.0` range) may be constructed; check [`Color::is_valid()`]
 /// if a color must be valid.
@@ -28,7 +31,7 @@ impl Color {
     /// Create a new Color with the given RGB values.
     /// ```
     /// use raytracing::Color;
-    /// let cyan = Color::new(0, 255, 255);
+    /// let cyan = Color::new_ints(0, 255, 255);
     /// ```
     pub fn new_ints(r: u8, g: u8, b: u8) -> Self {
         Self {
@@ -103,7 +106,6 @@ impl Color {
         Self::new(0.0, 0.0, 1.0)
     }
 
-    #[cfg(debug_assertions)]
     pub const fn debug_magenta() -> Self {
         Self::new(1.0, 0.0, 1.0)
     }
@@ -154,6 +156,19 @@ impl Color {
         [r, g, b]
     }
 
+    /// Returns the relative luminance of this (linear) color, using the Rec. 709 coefficients.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Returns whether this [`Color`] is a possible radiance value:
+    /// every channel is finite and non-negative, though it may be greater than 1.0.
+    pub fn is_physical(&self) -> bool {
+        [self.r, self.g, self.b]
+            .iter()
+            .all(|c| c.is_finite() && *c >= 0.0)
+    }
+
     /// Returns whether all properties of this [`Color`] are within the range [0.0, 1.0].
     pub fn is_valid(&self) -> bool {
         let inter: Interval = (0.0..=1.0).into();
@@ -0,0 +1,120 @@
+//! RGB color spaces, for passing renders to color-managed pipelines.
+
+use std::fmt::Display;
+
+use crate::Color;
+
+type Matrix = [[f64; 3]; 3];
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+const SRGB_TO_XYZ: Matrix = [
+    [0.4123907993, 0.3575843394, 0.1804807884],
+    [0.2126390059, 0.7151686788, 0.0721923154],
+    [0.0193308187, 0.1191947798, 0.9505321522],
+];
+const XYZ_TO_SRGB: Matrix = [
+    [3.2409699419, -1.5373831776, -0.4986107603],
+    [-0.9692436363, 1.8759675015, 0.0415550574],
+    [0.0556300797, -0.2039769589, 1.0569715142],
+];
+
+// including the Bradford adaptation between the D60 and D65 white points
+const SRGB_TO_ACESCG: Matrix = [
+    [0.6130974024, 0.3395231462, 0.0473794514],
+    [0.0701937225, 0.9163538791, 0.0134523985],
+    [0.0206155929, 0.1095697729, 0.8698146342],
+];
+const ACESCG_TO_SRGB: Matrix = [
+    [1.7050509927, -0.6217921207, -0.0832588720],
+    [-0.1302564175, 1.1408047366, -0.0105483191],
+    [-0.0240033568, -0.1289689761, 1.1529723329],
+];
+
+/// A space in which the channels of a [`Color`] are interpreted. All are linear; the gamma
+/// of image formats is applied separately, when writing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The primaries and D65 white point of sRGB and Rec. 709. Colors are in this space
+    /// unless stated otherwise.
+    #[default]
+    LinearSrgb,
+    /// The wide-gamut working space of the Academy Color Encoding System (AP1 primaries,
+    /// D60 white point), common in film and VFX pipelines.
+    AcesCg,
+    /// CIE 1931 XYZ, relative to the D65 white point.
+    Xyz,
+}
+
+impl ColorSpace {
+    fn matrix_to_srgb(self) -> &'static Matrix {
+        match self {
+            ColorSpace::LinearSrgb => &IDENTITY,
+            ColorSpace::AcesCg => &ACESCG_TO_SRGB,
+            ColorSpace::Xyz => &XYZ_TO_SRGB,
+        }
+    }
+
+    fn matrix_from_srgb(self) -> &'static Matrix {
+        match self {
+            ColorSpace::LinearSrgb => &IDENTITY,
+            ColorSpace::AcesCg => &SRGB_TO_ACESCG,
+            ColorSpace::Xyz => &SRGB_TO_XYZ,
+        }
+    }
+
+    /// Converts `color` from this space into `target`.
+    pub fn convert(self, color: Color, target: ColorSpace) -> Color {
+        if self == target {
+            return color;
+        }
+        apply(
+            target.matrix_from_srgb(),
+            apply(self.matrix_to_srgb(), color),
+        )
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorSpace::LinearSrgb => "Linear sRGB",
+            ColorSpace::AcesCg => "ACEScg",
+            ColorSpace::Xyz => "CIE XYZ (D65)",
+        };
+        write!(f, "{name}")
+    }
+}
+
+fn apply(matrix: &Matrix, color: Color) -> Color {
+    let [r, g, b] = matrix.map(|row| row[0] * color.r() + row[1] * color.g() + row[2] * color.b());
+    Color::new(r, g, b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let close = |a: Color, b: Color| {
+            (a.r() - b.r()).abs() < 1e-4
+                && (a.g() - b.g()).abs() < 1e-4
+                && (a.b() - b.b()).abs() < 1e-4
+        };
+        let spaces = [ColorSpace::LinearSrgb, ColorSpace::AcesCg, ColorSpace::Xyz];
+        let color = Color::new(0.8, 0.3, 0.1);
+        for from in spaces {
+            for to in spaces {
+                let there = from.convert(color, to);
+                assert!(close(to.convert(there, from), color), "{from} -> {to}");
+            }
+        }
+
+        // white keeps its luminance in XYZ, and stays neutral in ACEScg
+        let white = ColorSpace::LinearSrgb.convert(Color::white(), ColorSpace::Xyz);
+        assert!((white.g() - 1.0).abs() < 1e-4);
+        let white = ColorSpace::LinearSrgb.convert(Color::white(), ColorSpace::AcesCg);
+        assert!(close(white, Color::white()));
+    }
+}
@@ -61,7 +61,7 @@ impl Interval {
     ///
     /// let a = Interval::positive(10.0, 20.0);
     /// let b = Interval::positive(-2.0, 4.0);
-    /// assert_eq!(Interval::surrounding(&a, &b), Some(Interval::new(-2.0, 20.0)));
+    /// assert_eq!(Interval::surrounding(&a, &b), Interval::new(-2.0, 20.0));
     /// ```
     pub fn surrounding(a: &Self, b: &Self) -> Self {
         Self::positive(
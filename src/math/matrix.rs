@@ -0,0 +1,196 @@
+use std::ops::Mul;
+
+use crate::{Angle, Point3, Vec3};
+
+/// A 4×4 matrix, stored by row, acting on points and vectors in homogeneous coordinates.
+///
+/// Matrices are composed by multiplication, with the transform on the right applied first:
+/// `translation * rotation` rotates, then translates.
+///
+/// # Examples
+/// ```
+/// use raytracing::{matrix::Matrix4, Point3, Vec3};
+///
+/// let m = Matrix4::translation(Vec3::new(1.0, 2.0, 3.0)) * Matrix4::scaling(Vec3::new(2.0, 2.0, 2.0));
+/// assert_eq!(m.transform_point(&Point3::new(1.0, 1.0, 1.0)), Point3::new(3.0, 4.0, 5.0));
+/// // vectors aren't moved by translations
+/// assert_eq!(m.transform_vector(&Vec3::new(1.0, 0.0, 0.0)), Vec3::new(2.0, 0.0, 0.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4([[f64; 4]; 4]);
+
+impl Matrix4 {
+    pub const fn new(rows: [[f64; 4]; 4]) -> Self {
+        Self(rows)
+    }
+
+    pub const fn identity() -> Self {
+        Self([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut m = Self::identity();
+        m.0[0][3] = offset.x();
+        m.0[1][3] = offset.y();
+        m.0[2][3] = offset.z();
+        m
+    }
+
+    /// Scales by a different factor along each axis, about the origin.
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut m = Self::identity();
+        m.0[0][0] = factors.x();
+        m.0[1][1] = factors.y();
+        m.0[2][2] = factors.z();
+        m
+    }
+
+    /// Rotates by `angle` about `axis`, counterclockwise when looking back along it, as
+    /// [`Rotate`](crate::hittable::Rotate) does.
+    pub fn rotation(axis: Vec3, angle: Angle) -> Self {
+        let axis = axis.as_unit();
+        let (x, y, z) = (axis.x(), axis.y(), axis.z());
+        let (sin, cos) = angle.sin_cos();
+        let k = 1.0 - cos;
+        Self([
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rows(&self) -> &[[f64; 4]; 4] {
+        &self.0
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self(std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.0[j][i])
+        }))
+    }
+
+    /// Blends `a` into `b` component by component, as `t` goes from 0 to 1. This is exact for
+    /// translations and scalings, but a rotation is shortened and skewed partway through, by
+    /// more the further it turns.
+    pub fn lerp(a: &Self, b: &Self, t: f64) -> Self {
+        Self(std::array::from_fn(|i| {
+            std::array::from_fn(|j| a.0[i][j] + (b.0[i][j] - a.0[i][j]) * t)
+        }))
+    }
+
+    /// Whether the bottom row is `[0, 0, 0, 1]`, so that the matrix is a linear transform
+    /// followed by a translation, with no perspective.
+    pub fn is_affine(&self) -> bool {
+        self.0[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    /// Finds the inverse by Gauss-Jordan elimination, or `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut m = self.0;
+        let mut inv = Self::identity().0;
+        for col in 0..4 {
+            // swap in the row with the largest pivot, for stability
+            let pivot = (col..4).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+            if m[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            m.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / m[col][col];
+            for j in 0..4 {
+                m[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = m[row][col];
+                for j in 0..4 {
+                    m[row][j] -= factor * m[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+        Some(Self(inv))
+    }
+
+    /// Transforms a point, assuming the matrix [is affine](Matrix4::is_affine).
+    pub fn transform_point(&self, point: &Point3) -> Point3 {
+        let [x, y, z] = [0, 1, 2].map(|i| {
+            let row = self.0[i];
+            row[0] * point.x() + row[1] * point.y() + row[2] * point.z() + row[3]
+        });
+        Point3::new(x, y, z)
+    }
+
+    /// Transforms a vector, which unlike a point is unaffected by translation.
+    pub fn transform_vector(&self, vec: &Vec3) -> Vec3 {
+        let [x, y, z] = [0, 1, 2].map(|i| {
+            let row = self.0[i];
+            row[0] * vec.x() + row[1] * vec.y() + row[2] * vec.z()
+        });
+        Vec3::new(x, y, z)
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inverse() {
+        let m = Matrix4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Vec3::new(1.0, 1.0, 0.0), Angle::radians(0.8))
+            * Matrix4::scaling(Vec3::new(2.0, 0.5, -1.0));
+        let product = m * m.inverse().unwrap();
+        for (i, row) in product.rows().iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-9);
+            }
+        }
+
+        assert!(Matrix4::scaling(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+}
@@ -25,12 +25,26 @@ impl Ray3 {
     }
 }
 
+/// Rays offset from a camera ray by one pixel horizontally (`rx`) and vertically (`ry`).
+/// Where they hit a surface, relative to the main ray, shows how much of the surface
+/// one pixel covers.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RayDifferentials {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// Represents a 4-dimensional ray, starting at an origin and moving across a vector at a given time.
 pub struct Ray4 {
     origin: Point3,
     direction: Vec3,
     time: f64,
+    differentials: Option<RayDifferentials>,
+    /// The single color channel the path carries, once it has been split by dispersion.
+    channel: Option<usize>,
 }
 
 impl Ray4 {
@@ -39,6 +53,50 @@ impl Ray4 {
             origin,
             direction,
             time,
+            differentials: None,
+            channel: None,
+        }
+    }
+
+    pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    /// The rays through the neighbouring pixels, if this ray is tracking them.
+    pub fn differentials(&self) -> Option<RayDifferentials> {
+        self.differentials
+    }
+
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// The color channel (0, 1 or 2 for red, green or blue) the path carries, if it has been
+    /// split into channels by a dispersive material. Every other channel is already black.
+    pub fn channel(&self) -> Option<usize> {
+        self.channel
+    }
+
+    /// Transforms the ray, along with its differentials, by applying `point` to its origins
+    /// and `vector` to its directions.
+    pub fn transformed(
+        &self,
+        point: impl Fn(Point3) -> Point3,
+        vector: impl Fn(Vec3) -> Vec3,
+    ) -> Self {
+        Self {
+            origin: point(self.origin),
+            direction: vector(self.direction),
+            time: self.time,
+            differentials: self.differentials.map(|d| RayDifferentials {
+                rx_origin: point(d.rx_origin),
+                rx_direction: vector(d.rx_direction),
+                ry_origin: point(d.ry_origin),
+                ry_direction: vector(d.ry_direction),
+            }),
+            channel: self.channel,
         }
     }
 
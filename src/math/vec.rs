@@ -175,11 +175,10 @@ impl Vec3<Unknown> {
     /// Note that the distribution for each axis is uniform in the half-open interval of `[0.0, 1.0)`.
     /// See [`rand::distr::StandardUniform`] for more details.
     pub fn random() -> Vec3 {
-        let mut rng = rand::rng();
         let distr = rand::distr::StandardUniform;
-        let x = distr.sample(&mut rng);
-        let y = distr.sample(&mut rng);
-        let z = distr.sample(&mut rng);
+        let x = crate::rng::with_rng(|rng| distr.sample(rng));
+        let y = crate::rng::with_rng(|rng| distr.sample(rng));
+        let z = crate::rng::with_rng(|rng| distr.sample(rng));
 
         Vec3::new(x, y, z)
     }
@@ -192,7 +191,6 @@ impl Vec3<Unknown> {
     ///
     /// [`Uniform`]: rand::distr::Uniform
     pub fn random_range(range: impl TryInto<rand::distr::Uniform<f64>>) -> Vec3 {
-        let mut rng = rand::rng();
         // note: this isn't the cleanest way to make this generic over ranges but
         // other methods like `rand::distr::Uniform::try_from(range)` don't seem
         // to work due to vague errors about requiring `From<T>` impls.
@@ -200,9 +198,9 @@ impl Vec3<Unknown> {
             .try_into()
             .map_err(|_| "Invalid `range` provided to Vec3::random_range")
             .unwrap();
-        let x = distr.sample(&mut rng);
-        let y = distr.sample(&mut rng);
-        let z = distr.sample(&mut rng);
+        let x = crate::rng::with_rng(|rng| distr.sample(rng));
+        let y = crate::rng::with_rng(|rng| distr.sample(rng));
+        let z = crate::rng::with_rng(|rng| distr.sample(rng));
 
         Vec3::new(x, y, z)
     }
@@ -244,8 +242,8 @@ impl Vec3<Unknown> {
     /// weighted by cos(θ) where θ is the angle from the z-axis.
     #[inline]
     pub fn random_on_sphere_cosine() -> Vec3 {
-        let r1: f64 = rand::random();
-        let r2 = rand::random();
+        let r1: f64 = crate::rng::random();
+        let r2 = crate::rng::random();
 
         let phi = std::f64::consts::TAU * r1;
         let x = f64::cos(phi) * f64::sqrt(r2);
@@ -554,7 +552,7 @@ mod test {
     #[test]
     fn normalization() {
         let vec = Vec3::new(1.0, 2.0, 3.0);
-        assert!(!((vec.len() - 1.0).abs() < 0.001));
+        assert!((vec.len() - 1.0).abs() >= 0.001);
         let vec = vec.normalize();
         // length is =~ 1.00
         assert!((vec.len() - 1.0).abs() < 0.001);
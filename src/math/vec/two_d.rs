@@ -149,10 +149,9 @@ impl Vec2<Unknown> {
     /// Note that the distribution for each axis is uniform in the half-open interval of `[0.0, 1.0)`.
     /// See [`rand::distr::StandardUniform`] for more details.
     pub fn random() -> Vec2 {
-        let mut rng = rand::rng();
         let distr = rand::distr::StandardUniform;
-        let x = distr.sample(&mut rng);
-        let y = distr.sample(&mut rng);
+        let x = crate::rng::with_rng(|rng| distr.sample(rng));
+        let y = crate::rng::with_rng(|rng| distr.sample(rng));
 
         Vec2::new(x, y)
     }
@@ -165,7 +164,6 @@ impl Vec2<Unknown> {
     ///
     /// [`Uniform`]: rand::distr::Uniform
     pub fn random_range(range: impl TryInto<rand::distr::Uniform<f64>>) -> Vec2 {
-        let mut rng = rand::rng();
         // note: this isn't the cleanest way to make this generic over ranges but
         // other methods like `rand::distr::Uniform::try_from(range)` don't seem
         // to work due to vague errors about requiring `From<T>` impls.
@@ -173,8 +171,8 @@ impl Vec2<Unknown> {
             .try_into()
             .map_err(|_| "Invalid `range` provided to Vec2::random_range")
             .unwrap();
-        let x = distr.sample(&mut rng);
-        let y = distr.sample(&mut rng);
+        let x = crate::rng::with_rng(|rng| distr.sample(rng));
+        let y = crate::rng::with_rng(|rng| distr.sample(rng));
 
         Vec2::new(x, y)
     }
@@ -327,21 +325,21 @@ impl Display for Vec2 {
     }
 }
 
+impl From<Vec2> for Vec3 {
+    fn from(value: Vec2) -> Self {
+        Vec3::new(value.x(), value.y(), 0.0)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     #[test]
     fn normalization() {
         let vec = Vec2::new(1.0, 2.0);
-        assert!(!((vec.len() - 1.0).abs() < 0.001));
+        assert!((vec.len() - 1.0).abs() >= 0.001);
         let vec = vec.normalize();
         // length is =~ 1.00
         assert!((vec.len() - 1.0).abs() < 0.001);
     }
 }
-
-impl From<Vec2> for Vec3 {
-    fn from(value: Vec2) -> Self {
-        Vec3::new(value.x(), value.y(), 0.0)
-    }
-}
@@ -1,4 +1,4 @@
-use crate::{vec::Normalized, Vec3};
+use crate::{vec::Normalized, Axis, Vec3};
 
 #[derive(Debug)]
 pub struct OrthonormalBasis {
@@ -14,11 +14,10 @@ impl OrthonormalBasis {
         let w = vec.as_unit();
 
         let a = if w.x().abs() > 0.9 {
-            Vec3::new(0.0, 1.0, 0.0)
+            Axis::Y.unit()
         } else {
-            Vec3::new(1.0, 0.0, 0.0)
-        }
-        .as_unit();
+            Axis::X.unit()
+        };
 
         let v: Vec3 = Vec3::<Normalized>::cross(&w, &a).into();
         let v = v.as_unit();
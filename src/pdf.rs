@@ -1,4 +1,4 @@
-use crate::{Hittable, OrthonormalBasis, Point3, Vec3};
+use crate::{vec::Normalized, Hittable, OrthonormalBasis, Point3, Vec3, BSDF};
 use std::{f64::consts::PI, rc::Rc};
 
 pub trait PDF {
@@ -59,6 +59,32 @@ impl PDF for HittablePDF {
     }
 }
 
+/// Samples directions from a [`BSDF`], given the direction `wo` back along the incoming ray.
+pub struct BSDFPDF {
+    bsdf: Rc<dyn BSDF>,
+    wo: Vec3<Normalized>,
+}
+
+impl BSDFPDF {
+    pub fn new(bsdf: Rc<dyn BSDF>, wo: &Vec3<Normalized>) -> Self {
+        Self { bsdf, wo: *wo }
+    }
+}
+
+impl PDF for BSDFPDF {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.bsdf.pdf(&self.wo, direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        // an absorbed sample produces a direction with zero density
+        match self.bsdf.sample(&self.wo) {
+            Some(sample) => sample.direction,
+            None => Vec3::empty(),
+        }
+    }
+}
+
 pub struct MixedPDF {
     factors: Vec<(Rc<dyn PDF>, f64)>,
 }
@@ -90,14 +116,15 @@ impl PDF for MixedPDF {
     }
 
     fn generate(&self) -> Vec3 {
-        let rand: f64 = rand::random();
+        let rand: f64 = crate::rng::random();
         let mut sum = 0.0;
         for (pdf, weight) in &self.factors {
             sum += weight;
-            if sum < rand {
+            if rand < sum {
                 return pdf.generate();
             }
         }
-        unreachable!();
+        // floating point error may leave `sum` slightly below 1.0
+        self.factors.last().unwrap().0.generate()
     }
 }
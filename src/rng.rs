@@ -0,0 +1,58 @@
+//! The random numbers used while rendering, drawn from a generator local to each thread.
+//!
+//! The generator starts from a random seed, so renders differ from run to run. Seeding it
+//! (see [`seed`], or [`CameraBuilder::seed`](crate::CameraBuilder::seed)) makes a render
+//! reproducible, given the same scene, settings, build and platform.
+
+use std::cell::RefCell;
+
+use rand::{
+    distr::{uniform::SampleRange, uniform::SampleUniform, Distribution, StandardUniform},
+    rngs::SmallRng,
+    Rng, SeedableRng,
+};
+
+thread_local! {
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
+
+/// Restarts this thread's generator from `seed`.
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+/// A random value, as with [`rand::random`].
+pub fn random<T>() -> T
+where
+    StandardUniform: Distribution<T>,
+{
+    RNG.with(|rng| rng.borrow_mut().random())
+}
+
+/// A random value in `range`, as with [`rand::random_range`].
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    RNG.with(|rng| rng.borrow_mut().random_range(range))
+}
+
+/// Runs `f` with this thread's generator, for sampling from other distributions.
+pub fn with_rng<T>(f: impl FnOnce(&mut SmallRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeding_repeats_the_sequence() {
+        seed(7);
+        let first: [f64; 4] = std::array::from_fn(|_| random());
+        seed(7);
+        let second: [f64; 4] = std::array::from_fn(|_| random());
+        assert_eq!(first, second);
+    }
+}
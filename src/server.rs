@@ -0,0 +1,628 @@
+//! A small HTTP render service, enabled with the `server` feature.
+//!
+//! | Method | Path                 | Description                                             |
+//! |--------|----------------------|---------------------------------------------------------|
+//! | `POST` | `/render`            | Queue a scene config (TOML, in the body) for rendering  |
+//! | `GET`  | `/jobs/{id}`         | The status of a job, as JSON                            |
+//! | `GET`  | `/jobs/{id}/events`  | The status of a job, streamed as server-sent events     |
+//! | `GET`  | `/jobs/{id}/image`   | The rendered PNG, once the job is done                  |
+//!
+//! The camera is configured through the query string of `/render`:
+//! `width`, `height`, `spp`, `depth`, `vfov`, `center`, `target`, and `background`, where
+//! points and colors are given as `x,y,z`, `budget`, a time limit in seconds (see
+//! [`CameraBuilder::time_budget`]), `noise`, a relative error to render until (see
+//! [`CameraBuilder::noise_threshold`]), and `integrator`, either `path` or `preview` (see
+//! [`Integrator`]). Unset parameters use the [`CameraBuilder`] defaults.
+//!
+//! Jobs are rendered one at a time, in the order they are received. To keep any one client
+//! from exhausting the server, images may be at most [`MAX_PIXELS`] in size and
+//! [`MAX_SAMPLES_PER_PX`] samples deep, at most [`MAX_PENDING_JOBS`] may wait to be rendered,
+//! and finished jobs are forgotten after [`FINISHED_JOB_TTL`], or sooner once there are more
+//! than [`MAX_FINISHED_JOBS`]. Scenes may be at most [`MAX_SCENE_BYTES`] long.
+//!
+//! Scenes can't refer to files, such as image textures or meshes: the server's own files are
+//! never read on behalf of a client.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    io::{Read, Write},
+    net::ToSocketAddrs,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub mod live;
+
+use crate::{
+    config::{Assets, ConfigModel},
+    export::PngWriter,
+    hittable::HittableVec,
+    Angle, Background, CameraBuilder, Color, Hittable, Integrator, Point3,
+};
+
+/// The largest image, in pixels, a job may render.
+pub const MAX_PIXELS: u64 = 4096 * 4096;
+/// The most samples per pixel a job may take.
+pub const MAX_SAMPLES_PER_PX: u32 = 16_384;
+/// The greatest `depth` a job may set.
+pub const MAX_DEPTH: u32 = 1024;
+/// How many jobs may be queued or rendering at once; further jobs are refused.
+pub const MAX_PENDING_JOBS: usize = 64;
+/// How many finished jobs are kept for their results to be fetched.
+pub const MAX_FINISHED_JOBS: usize = 64;
+/// How long finished jobs are kept for their results to be fetched.
+pub const FINISHED_JOB_TTL: Duration = Duration::from_secs(15 * 60);
+/// The longest scene, in bytes, a job may post.
+pub const MAX_SCENE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+enum JobStatus {
+    Queued,
+    Rendering { rows_done: u32, rows_total: u32 },
+    Done(Arc<Vec<u8>>),
+    Failed(String),
+}
+
+impl JobStatus {
+    fn is_finished(&self) -> bool {
+        matches!(self, Self::Done(_) | Self::Failed(_))
+    }
+
+    fn to_json(&self, id: u64) -> String {
+        match self {
+            Self::Queued => format!(r#"{{"id":{id},"status":"queued"}}"#),
+            Self::Rendering {
+                rows_done,
+                rows_total,
+            } => format!(
+                r#"{{"id":{id},"status":"rendering","rows_done":{rows_done},"rows_total":{rows_total}}}"#
+            ),
+            Self::Done(_) => format!(r#"{{"id":{id},"status":"done","image":"/jobs/{id}/image"}}"#),
+            Self::Failed(err) => format!(
+                r#"{{"id":{id},"status":"failed","error":"{}"}}"#,
+                escape_json(err)
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    scene: String,
+    camera: CameraParams,
+}
+
+/// Camera settings parsed from the query string; `None` leaves the builder's default.
+#[derive(Debug, Default)]
+struct CameraParams {
+    dimensions: Option<(u32, u32)>,
+    samples_per_px: Option<u32>,
+    max_depth: Option<u32>,
+    vfov: Option<f64>,
+    center: Option<Point3>,
+    target: Option<Point3>,
+    background: Option<Color>,
+    time_budget: Option<Duration>,
+    noise_threshold: Option<f64>,
+    integrator: Option<Integrator>,
+}
+
+impl CameraParams {
+    fn parse(query: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        let mut width = None;
+        let mut height = None;
+
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let (key, value) = (percent_decode(key)?, percent_decode(value)?);
+            let key = key.as_str();
+            match key {
+                "width" => width = Some(parse_number(key, &value)?),
+                "height" => height = Some(parse_number(key, &value)?),
+                "spp" => params.samples_per_px = Some(parse_number(key, &value)?),
+                "depth" => params.max_depth = Some(parse_number(key, &value)?),
+                "vfov" => params.vfov = Some(parse_number(key, &value)?),
+                "center" => {
+                    let [x, y, z] = parse_triple(key, &value)?;
+                    params.center = Some(Point3::new(x, y, z));
+                }
+                "target" => {
+                    let [x, y, z] = parse_triple(key, &value)?;
+                    params.target = Some(Point3::new(x, y, z));
+                }
+                "background" => {
+                    let [r, g, b] = parse_triple(key, &value)?;
+                    params.background = Some(Color::new(r, g, b));
+                }
+                "budget" => {
+                    let seconds: f64 = parse_number(key, &value)?;
+                    let budget = Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| format!("`{key}` must be a positive number of seconds"))?;
+                    params.time_budget = Some(budget);
+                }
+                "noise" => params.noise_threshold = Some(parse_number(key, &value)?),
+                "integrator" => {
+                    params.integrator = Some(match value.as_str() {
+                        "path" => Integrator::PathTraced,
+                        "preview" => Integrator::Preview,
+                        _ => return Err(format!("`{key}` must be `path` or `preview`")),
+                    });
+                }
+                _ => return Err(format!("Unknown parameter `{key}`")),
+            }
+        }
+
+        params.dimensions = match (width, height) {
+            (Some(w), Some(h)) => Some((w, h)),
+            (None, None) => None,
+            _ => return Err("`width` and `height` must be provided together".to_string()),
+        };
+        if let Some(spp) = params.samples_per_px {
+            check_limit("spp", spp, MAX_SAMPLES_PER_PX)?;
+        }
+        if let Some(depth) = params.max_depth {
+            check_limit("depth", depth, MAX_DEPTH)?;
+        }
+        if let Some((width, height)) = params.dimensions {
+            check_limit(
+                "width * height",
+                u64::from(width) * u64::from(height),
+                MAX_PIXELS,
+            )?;
+        }
+        Ok(params)
+    }
+
+    /// Applies the parameters which were set to `builder`.
+    fn apply<'a>(&self, mut builder: CameraBuilder<'a>) -> CameraBuilder<'a> {
+        if let Some((width, height)) = self.dimensions {
+            builder = builder.dimensions(width, height);
+        }
+        if let Some(spp) = self.samples_per_px {
+            builder = builder.samples_per_px(spp);
+        }
+        if let Some(depth) = self.max_depth {
+            builder = builder.max_depth(depth);
+        }
+        if let Some(vfov) = self.vfov {
+            builder = builder.vfov(Angle::degrees(vfov));
+        }
+        if let Some(center) = self.center {
+            builder = builder.camera_center(center);
+        }
+        if let Some(target) = self.target {
+            builder = builder.camera_target(target);
+        }
+        if let Some(color) = self.background {
+            builder = builder.background(Background::Constant(color));
+        }
+        if let Some(budget) = self.time_budget {
+            builder = builder.time_budget(budget);
+        }
+        if let Some(threshold) = self.noise_threshold {
+            builder = builder.noise_threshold(threshold);
+        }
+        if let Some(integrator) = self.integrator {
+            builder = builder.integrator(integrator);
+        }
+        builder
+    }
+}
+
+fn check_limit<T: PartialOrd + std::fmt::Display>(
+    key: &str,
+    value: T,
+    max: T,
+) -> Result<(), String> {
+    if value > max {
+        return Err(format!("`{key}` must be at most {max}, found {value}"));
+    }
+    Ok(())
+}
+
+/// Decodes a query string component, where `+` is a space and `%XX` is a byte in hex.
+fn percent_decode(s: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = rest
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("Invalid percent-encoding in `{s}`"))?;
+                bytes.push(hex);
+                rest = &rest[2..];
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("`{s}` must decode to UTF-8"))
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{key}` must be a number, found `{value}`"))
+}
+
+fn parse_triple(key: &str, value: &str) -> Result<[f64; 3], String> {
+    let parts: Vec<f64> = value
+        .split(',')
+        .map(|part| parse_number(key, part))
+        .collect::<Result<_, _>>()?;
+    parts
+        .try_into()
+        .map_err(|_| format!("`{key}` must have three comma-separated components"))
+}
+
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The status of every job which hasn't been forgotten.
+#[derive(Debug, Default)]
+struct JobTable {
+    statuses: HashMap<u64, JobStatus>,
+    /// Finished jobs, oldest first, with when they finished
+    finished: VecDeque<(u64, Instant)>,
+}
+
+impl JobTable {
+    fn get(&self, id: u64) -> Option<&JobStatus> {
+        self.statuses.get(&id)
+    }
+
+    /// The number of jobs which are queued or rendering.
+    fn pending(&self) -> usize {
+        self.statuses.len() - self.finished.len()
+    }
+
+    fn update(&mut self, id: u64, status: JobStatus) {
+        if status.is_finished() {
+            self.finished.push_back((id, Instant::now()));
+        }
+        self.statuses.insert(id, status);
+        self.evict(Instant::now());
+    }
+
+    /// Forgets finished jobs which are older than [`FINISHED_JOB_TTL`], or beyond the newest
+    /// [`MAX_FINISHED_JOBS`].
+    fn evict(&mut self, now: Instant) {
+        while let Some(&(id, finished_at)) = self.finished.front() {
+            let expired = now.duration_since(finished_at) > FINISHED_JOB_TTL;
+            if !expired && self.finished.len() <= MAX_FINISHED_JOBS {
+                break;
+            }
+            self.finished.pop_front();
+            self.statuses.remove(&id);
+        }
+    }
+}
+
+type Jobs = Arc<Mutex<JobTable>>;
+
+/// Renders queued jobs one at a time. Scenes are built on this thread, as they are not [`Send`].
+fn worker(queue: Receiver<Job>, jobs: Jobs) {
+    for job in queue {
+        // a panic while rendering one scene shouldn't take down the queue
+        let result = panic::catch_unwind(AssertUnwindSafe(|| render(&job, &jobs)));
+        let status = match result {
+            Ok(Ok(png)) => JobStatus::Done(Arc::new(png)),
+            Ok(Err(err)) => JobStatus::Failed(err),
+            Err(_) => JobStatus::Failed("The renderer panicked".to_string()),
+        };
+        jobs.lock().unwrap().update(job.id, status);
+    }
+}
+
+/// Parses a posted scene. It is given no files to read, so any file it refers to is missing.
+fn parse_scene(scene: &str) -> Result<ConfigModel, String> {
+    let table = scene
+        .parse::<toml::Table>()
+        .map_err(|err| format!("Invalid TOML: {err}"))?;
+    ConfigModel::from_table_with_assets(&table, Assets::Bundled(Rc::default()))
+        .map_err(|err| format!("{err}"))
+}
+
+/// Reads a posted scene, refusing scenes longer than [`MAX_SCENE_BYTES`] without reading
+/// the rest of them.
+fn read_scene(body: impl Read) -> Result<String, (u16, &'static str)> {
+    let mut scene = String::new();
+    body.take(MAX_SCENE_BYTES + 1)
+        .read_to_string(&mut scene)
+        .map_err(|_| (400, "Body must be UTF-8 TOML"))?;
+    if scene.len() as u64 > MAX_SCENE_BYTES {
+        return Err((413, "The scene is too large"));
+    }
+    Ok(scene)
+}
+
+fn render(job: &Job, jobs: &Jobs) -> Result<Vec<u8>, String> {
+    let config = parse_scene(&job.scene)?;
+    // the query string takes precedence over the scene's own settings
+    let builder = job.camera.apply(CameraBuilder::from_config(&config));
+    let world = config.as_world();
+
+    let mut png = Vec::new();
+    let mut cam = builder
+        .writer(PngWriter::new(&mut png).into_box())
+        .on_progress(|rows_done, rows_total| {
+            jobs.lock().unwrap().update(
+                job.id,
+                JobStatus::Rendering {
+                    rows_done,
+                    rows_total,
+                },
+            );
+        })
+        .build()
+        .map_err(|errs| {
+            let errs: Vec<_> = errs.errors().iter().map(ToString::to_string).collect();
+            errs.join("\n")
+        })?;
+
+    // scenes can set their own camera, so the limits are checked again once it's built
+    let (width, height) = cam.dimensions();
+    check_limit(
+        "width * height",
+        u64::from(width) * u64::from(height),
+        MAX_PIXELS,
+    )?;
+    check_limit("spp", cam.samples_per_px(), MAX_SAMPLES_PER_PX)?;
+    check_limit("depth", cam.max_depth(), MAX_DEPTH)?;
+
+    // scene configs don't mark their lights, so light sampling is left to the BSDFs
+    cam.render(&world, HittableVec::new().hittable());
+    drop(cam);
+    Ok(png)
+}
+
+fn json_header() -> Header {
+    Header::from_bytes("Content-Type", "application/json").unwrap()
+}
+
+fn error_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(format!(r#"{{"error":"{}"}}"#, escape_json(message)))
+        .with_status_code(status)
+        .with_header(json_header())
+}
+
+/// Streams the status of a job as server-sent events until it finishes.
+///
+/// `tiny_http` buffers chunked responses, so the response is written (and chunked) by hand
+/// to send each event as soon as it happens.
+fn stream_events(request: Request, id: u64, jobs: &Jobs) -> std::io::Result<()> {
+    let mut writer = request.into_writer();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n\r\n"
+    )?;
+
+    let mut last = String::new();
+    loop {
+        let Some(status) = jobs.lock().unwrap().get(id).cloned() else {
+            break;
+        };
+
+        let json = status.to_json(id);
+        if json != last {
+            write_chunk(&mut writer, format!("data: {json}\n\n").as_bytes())?;
+            last = json;
+        }
+        if status.is_finished() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    write_chunk(&mut writer, &[])
+}
+
+/// Writes and flushes one chunk of a response using chunked transfer encoding.
+/// An empty chunk ends the response.
+fn write_chunk(writer: &mut dyn Write, bytes: &[u8]) -> std::io::Result<()> {
+    write!(writer, "{:x}\r\n", bytes.len())?;
+    writer.write_all(bytes)?;
+    write!(writer, "\r\n")?;
+    writer.flush()
+}
+
+fn handle(mut request: Request, jobs: &Jobs, queue: &mpsc::Sender<Job>, next_id: &Mutex<u64>) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let response = match (request.method(), segments.as_slice()) {
+        (Method::Post, ["render"]) => {
+            let camera = match CameraParams::parse(query) {
+                Ok(camera) => camera,
+                Err(err) => {
+                    let _ = request.respond(error_response(400, &err));
+                    return;
+                }
+            };
+
+            let scene = match read_scene(request.as_reader()) {
+                Ok(scene) => scene,
+                Err((status, err)) => {
+                    let _ = request.respond(error_response(status, err));
+                    return;
+                }
+            };
+
+            let id = {
+                let mut jobs = jobs.lock().unwrap();
+                if jobs.pending() >= MAX_PENDING_JOBS {
+                    drop(jobs);
+                    let _ = request.respond(error_response(503, "Too many jobs are queued"));
+                    return;
+                }
+                let mut next_id = next_id.lock().unwrap();
+                *next_id += 1;
+                jobs.update(*next_id, JobStatus::Queued);
+                *next_id
+            };
+            queue.send(Job { id, scene, camera }).unwrap();
+
+            Response::from_string(JobStatus::Queued.to_json(id))
+                .with_status_code(202)
+                .with_header(json_header())
+        }
+        (Method::Get, ["jobs", id, rest @ ..]) => {
+            let Ok(id) = id.parse::<u64>() else {
+                let _ = request.respond(error_response(404, "No such job"));
+                return;
+            };
+            let status = {
+                let mut jobs = jobs.lock().unwrap();
+                jobs.evict(Instant::now());
+                jobs.get(id).cloned()
+            };
+            let Some(status) = status else {
+                let _ = request.respond(error_response(404, "No such job"));
+                return;
+            };
+
+            match rest {
+                [] => Response::from_string(status.to_json(id)).with_header(json_header()),
+                ["events"] => {
+                    let _ = stream_events(request, id, jobs);
+                    return;
+                }
+                ["image"] => match status {
+                    JobStatus::Done(png) => Response::from_data(png.as_slice())
+                        .with_header(Header::from_bytes("Content-Type", "image/png").unwrap()),
+                    _ => error_response(409, "The job has not finished rendering"),
+                },
+                _ => error_response(404, "Not found"),
+            }
+        }
+        _ => error_response(404, "Not found"),
+    };
+
+    let _ = request.respond(response);
+}
+
+/// Runs the render server on `addr`, blocking forever.
+pub fn serve(addr: impl ToSocketAddrs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http(addr)?;
+    let jobs: Jobs = Arc::default();
+    let next_id = Arc::new(Mutex::new(0));
+
+    let (queue, receiver) = mpsc::channel();
+    {
+        let jobs = Arc::clone(&jobs);
+        thread::spawn(move || worker(receiver, jobs));
+    }
+
+    for request in server.incoming_requests() {
+        let jobs = Arc::clone(&jobs);
+        let queue = queue.clone();
+        let next_id = Arc::clone(&next_id);
+        thread::spawn(move || handle(request, &jobs, &queue, &next_id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_camera_params() {
+        let params = CameraParams::parse("width=64&height=32&center=0,1,2&spp=4").unwrap();
+        assert_eq!(params.dimensions, Some((64, 32)));
+        assert_eq!(params.center, Some(Point3::new(0.0, 1.0, 2.0)));
+        assert_eq!(params.samples_per_px, Some(4));
+
+        let params = CameraParams::parse("budget=0.5").unwrap();
+        assert_eq!(params.time_budget, Some(Duration::from_millis(500)));
+        assert!(CameraParams::parse("budget=-1").is_err());
+        let params = CameraParams::parse("noise=0.02").unwrap();
+        assert_eq!(params.noise_threshold, Some(0.02));
+
+        let params = CameraParams::parse("integrator=preview").unwrap();
+        assert_eq!(params.integrator, Some(Integrator::Preview));
+
+        let params = CameraParams::parse("center=0%2C1%2c2&background=%30.5,1,1").unwrap();
+        assert_eq!(params.center, Some(Point3::new(0.0, 1.0, 2.0)));
+        assert_eq!(params.background, Some(Color::new(0.5, 1.0, 1.0)));
+        assert!(CameraParams::parse("center=0%2").is_err());
+
+        assert!(CameraParams::parse("width=100000&height=100000").is_err());
+        assert!(CameraParams::parse("spp=1000000").is_err());
+        assert!(CameraParams::parse("width=64").is_err());
+        assert!(CameraParams::parse("center=1,2").is_err());
+        assert!(CameraParams::parse("zoom=2").is_err());
+    }
+
+    #[test]
+    fn scenes_cannot_read_server_files() {
+        let scene = |path: &str| {
+            format!(
+                "objects = []\n[materials]\n\
+                 [textures.stolen]\ntype = \"Image\"\npath = \"{path}\"\n"
+            )
+        };
+        let absolute = std::fs::canonicalize("assets/earth.png").unwrap();
+        let absolute = scene(&absolute.display().to_string());
+        // the same scene could be read from disk locally
+        assert!(absolute.parse::<ConfigModel>().is_ok());
+        assert!(parse_scene(&absolute).is_err());
+        assert!(parse_scene(&scene("/etc/passwd")).is_err());
+        assert!(parse_scene(&scene("assets/earth.png")).is_err());
+        assert!(parse_scene("objects = []\n[materials]\n[textures]\n").is_ok());
+    }
+
+    #[test]
+    fn scene_size_is_limited() {
+        let scene = "# a comment\n".repeat(100);
+        assert_eq!(read_scene(scene.as_bytes()).unwrap(), scene);
+        let oversized = vec![b'#'; MAX_SCENE_BYTES as usize * 2];
+        assert_eq!(read_scene(oversized.as_slice()).unwrap_err().0, 413);
+        assert_eq!(read_scene([0xff, 0xfe].as_slice()).unwrap_err().0, 400);
+    }
+
+    #[test]
+    fn finished_jobs_are_forgotten() {
+        let mut jobs = JobTable::default();
+        for id in 0..MAX_FINISHED_JOBS as u64 + 2 {
+            jobs.update(id, JobStatus::Queued);
+            jobs.update(id, JobStatus::Failed(String::new()));
+        }
+        jobs.update(1000, JobStatus::Queued);
+        // the oldest finished jobs went first, and pending jobs are kept
+        assert!(jobs.get(0).is_none() && jobs.get(1).is_none());
+        assert!(jobs.get(2).is_some());
+        assert_eq!(jobs.pending(), 1);
+
+        jobs.evict(Instant::now() + FINISHED_JOB_TTL * 2);
+        assert_eq!(jobs.statuses.len(), 1);
+        assert!(jobs.get(1000).is_some());
+    }
+}
@@ -0,0 +1,270 @@
+//! Serves an in-progress render over HTTP, so long renders can be watched from a browser.
+//!
+//! | Path         | Description                                                         |
+//! |--------------|---------------------------------------------------------------------|
+//! | `/`          | A page showing the live stream                                      |
+//! | `/stream`    | A `multipart/x-mixed-replace` stream of PNG frames, as with MJPEG   |
+//! | `/frame.png` | The most recent frame                                               |
+//!
+//! The server runs for as long as the [`LiveWriter`] is alive; dropping it closes open
+//! streams and frees the port.
+
+use std::{
+    error::Error,
+    io::Write,
+    net::ToSocketAddrs,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use tiny_http::{Header, Request, Response, Server};
+
+use super::write_chunk;
+use crate::{
+    export::{encode_png, ImageWriter, RenderMetadata},
+    Color,
+};
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Render preview</title></head>
+<body style="margin: 0; background: #222; display: grid; place-items: center; height: 100vh">
+<img src="/stream" style="max-width: 100%; max-height: 100%; image-rendering: pixelated">
+</body>
+</html>
+"#;
+
+#[derive(Debug, Default)]
+struct FrameState {
+    /// Incremented each time a new frame is published
+    generation: u64,
+    png: Option<Arc<Vec<u8>>>,
+    /// Set once the writer is dropped; open streams are then closed.
+    closed: bool,
+}
+
+/// The most recent frame, shared between the renderer and the HTTP server.
+#[derive(Debug, Default)]
+struct Frame {
+    state: Mutex<FrameState>,
+    updated: Condvar,
+}
+
+impl Frame {
+    fn publish(&self, png: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.png = Some(Arc::new(png));
+        self.updated.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.updated.notify_all();
+    }
+
+    /// Waits for a frame newer than `generation`.
+    /// Returns `None` once the stream is closed.
+    fn next(&self, generation: u64) -> Option<(u64, Arc<Vec<u8>>)> {
+        let state = self
+            .updated
+            .wait_while(self.state.lock().unwrap(), |s| {
+                !s.closed && (s.generation <= generation || s.png.is_none())
+            })
+            .unwrap();
+        if state.closed {
+            return None;
+        }
+        state.png.clone().map(|png| (state.generation, png))
+    }
+}
+
+/// An [`ImageWriter`] which serves the image over HTTP as it renders,
+/// and passes the finished image on to another writer.
+pub struct LiveWriter<'a> {
+    inner: Box<dyn ImageWriter + 'a>,
+    dimensions: (u32, u32),
+    frame: Arc<Frame>,
+    server: Arc<Server>,
+    /// The thread accepting connections, which ends once the server is unblocked
+    accept_thread: Option<JoinHandle<()>>,
+    /// The minimum time between preview frames
+    interval: Duration,
+    last_frame: Option<Instant>,
+}
+
+impl<'a> LiveWriter<'a> {
+    /// Starts serving on `addr`. The finished image is written to `inner`.
+    pub fn new(
+        addr: impl ToSocketAddrs,
+        inner: Box<dyn ImageWriter + 'a>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let server = Arc::new(Server::http(addr)?);
+        let frame = Arc::new(Frame::default());
+
+        let shared = Arc::clone(&frame);
+        let accepting = Arc::clone(&server);
+        let accept_thread = thread::spawn(move || {
+            for request in accepting.incoming_requests() {
+                let frame = Arc::clone(&shared);
+                thread::spawn(move || handle(request, &frame));
+            }
+        });
+
+        Ok(Self {
+            inner,
+            dimensions: (0, 0),
+            frame,
+            server,
+            accept_thread: Some(accept_thread),
+            interval: Duration::from_millis(250),
+            last_frame: None,
+        })
+    }
+
+    /// Sets the minimum time between preview frames; encoding frames slows the render slightly.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn into_box(self) -> Box<dyn ImageWriter + 'a> {
+        Box::new(self)
+    }
+
+    fn publish(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        let (width, height) = self.dimensions;
+        self.frame.publish(encode_png(width, height, colors)?);
+        self.last_frame = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for LiveWriter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveWriter")
+            .field("inner", &self.inner)
+            .field("dimensions", &self.dimensions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ImageWriter for LiveWriter<'_> {
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.dimensions = (width, height);
+        self.inner.write_header(width, height)
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.publish(colors)?;
+        self.inner.write(colors)
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        self.inner.write_metadata(metadata)
+    }
+
+    fn preview(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        if self
+            .last_frame
+            .is_none_or(|last| last.elapsed() >= self.interval)
+        {
+            self.publish(colors)?;
+        }
+        self.inner.preview(colors)
+    }
+}
+
+impl Drop for LiveWriter<'_> {
+    fn drop(&mut self) {
+        self.frame.close();
+        self.server.unblock();
+        if let Some(thread) = self.accept_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn handle(request: Request, frame: &Frame) {
+    let html = Header::from_bytes("Content-Type", "text/html").unwrap();
+    let png = Header::from_bytes("Content-Type", "image/png").unwrap();
+    let no_cache = Header::from_bytes("Cache-Control", "no-cache").unwrap();
+
+    let _ = match request.url() {
+        "/" => request.respond(Response::from_string(PAGE).with_header(html)),
+        "/stream" => stream(request, frame),
+        "/frame.png" => match frame.state.lock().unwrap().png.clone() {
+            Some(data) => request.respond(
+                Response::from_data(data.as_slice())
+                    .with_header(png)
+                    .with_header(no_cache),
+            ),
+            None => request.respond(Response::from_string("No frames yet").with_status_code(503)),
+        },
+        _ => request.respond(Response::from_string("Not found").with_status_code(404)),
+    };
+}
+
+/// Sends each new frame as a part of a `multipart/x-mixed-replace` response,
+/// until the writer is dropped or the client disconnects.
+fn stream(request: Request, frame: &Frame) -> std::io::Result<()> {
+    let mut writer = request.into_writer();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n\r\n"
+    )?;
+
+    let mut generation = 0;
+    while let Some((next, png)) = frame.next(generation) {
+        generation = next;
+
+        let mut part = format!(
+            "--frame\r\nContent-Type: image/png\r\nContent-Length: {}\r\n\r\n",
+            png.len()
+        )
+        .into_bytes();
+        part.extend_from_slice(&png);
+        part.extend_from_slice(b"\r\n");
+        write_chunk(&mut writer, &part)?;
+    }
+
+    write_chunk(&mut writer, b"--frame--\r\n")?;
+    write_chunk(&mut writer, &[])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::export::PpmWriter;
+
+    #[test]
+    fn frames_are_delivered_until_closed() {
+        let frame = Frame::default();
+        frame.publish(vec![1]);
+        frame.publish(vec![2]);
+
+        // only the most recent frame is kept
+        let (generation, png) = frame.next(0).unwrap();
+        assert_eq!(png.as_slice(), &[2]);
+
+        frame.close();
+        assert!(frame.next(generation).is_none());
+    }
+
+    #[test]
+    fn drop_frees_the_port() {
+        let mut buf = Vec::new();
+        let writer = LiveWriter::new("127.0.0.1:0", PpmWriter::new(&mut buf).into_box()).unwrap();
+        let addr = writer.server.server_addr().to_ip().unwrap();
+        drop(writer);
+
+        // the accept thread has been joined, so only tiny_http's own listening thread holds
+        // the port, and it closes shortly after the server is dropped
+        let rebound = (0..50).any(|_| {
+            thread::sleep(Duration::from_millis(20));
+            Server::http(addr).is_ok()
+        });
+        assert!(rebound);
+    }
+}
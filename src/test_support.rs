@@ -0,0 +1,46 @@
+//! Helpers shared by the tests of several modules.
+
+use std::error::Error;
+
+use crate::{
+    export::{ImageWriter, RenderMetadata},
+    Color,
+};
+
+/// Collects the rendered image in memory.
+#[derive(Debug)]
+pub(crate) struct BufferWriter<'a>(pub(crate) &'a mut Vec<Color>);
+
+impl ImageWriter for BufferWriter<'_> {
+    fn write_header(&mut self, _width: u32, _height: u32) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.0.extend_from_slice(colors);
+        Ok(())
+    }
+}
+
+/// Collects the rendered image in memory, along with its metadata.
+#[derive(Debug)]
+pub(crate) struct MetadataWriter<'a>(
+    pub(crate) &'a mut Vec<Color>,
+    pub(crate) &'a mut RenderMetadata,
+);
+
+impl ImageWriter for MetadataWriter<'_> {
+    fn write_header(&mut self, _width: u32, _height: u32) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write(&mut self, colors: &[Color]) -> Result<(), Box<dyn Error>> {
+        self.0.extend_from_slice(colors);
+        Ok(())
+    }
+
+    fn write_metadata(&mut self, metadata: &RenderMetadata) -> Result<(), Box<dyn Error>> {
+        self.1.clone_from(metadata);
+        Ok(())
+    }
+}
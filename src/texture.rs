@@ -1,11 +1,71 @@
-use std::{io::Read, rc::Rc};
+use std::{error::Error, io::Read, rc::Rc};
 
 use png::Decoder;
 
-use crate::{Color, Point3};
+use crate::{Color, HitRecord, Point3, Vec2, Vec3};
+
+pub mod lazy;
+pub mod node;
+pub mod vertex;
+pub use lazy::{LazyImageTexture, TextureCache};
+pub use node::{Invert, Mix, Multiply, Noise, Remap};
+pub use vertex::VertexColors;
+
+/// The area of a texture seen by one pixel, around the point being shaded:
+/// how far the hit point and its texture coordinates move between neighbouring pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    /// The change in the hit point to the next pixel across
+    pub dpdx: Vec3,
+    /// The change in the hit point to the next pixel down
+    pub dpdy: Vec3,
+    /// The change in `(u, v)` to the next pixel across
+    pub duvdx: Vec2,
+    /// The change in `(u, v)` to the next pixel down
+    pub duvdy: Vec2,
+}
+
+impl Footprint {
+    /// The width of the footprint in world space.
+    pub fn world_width(&self) -> f64 {
+        self.dpdx.len().max(self.dpdy.len())
+    }
+
+    /// The width of the footprint along each world axis.
+    fn axis_widths(&self) -> [f64; 3] {
+        [
+            self.dpdx.x().abs().max(self.dpdy.x().abs()),
+            self.dpdx.y().abs().max(self.dpdy.y().abs()),
+            self.dpdx.z().abs().max(self.dpdy.z().abs()),
+        ]
+    }
+
+    /// The width of the footprint in a `width`x`height` texel image.
+    fn texel_width(&self, width: u32, height: u32) -> f64 {
+        let scale = |d: Vec2| Vec2::new(d.x() * f64::from(width), d.y() * f64::from(height));
+        scale(self.duvdx).len().max(scale(self.duvdy).len())
+    }
+}
 
 pub trait Texture: std::fmt::Debug {
     fn value(&self, u: f64, v: f64, point: &Point3) -> Color;
+
+    /// The average value of the texture over `footprint`, so that detail finer than a pixel
+    /// is blurred instead of aliasing. Textures without fine detail need not implement this.
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let _ = footprint;
+        self.value(u, v, point)
+    }
+
+    /// The value of the texture at a hit, filtered over the hit's footprint if it has one.
+    fn value_at(&self, record: &HitRecord) -> Color {
+        let (u, v, point) = (record.u(), record.v(), record.point());
+        match record.footprint() {
+            Some(footprint) => self.filtered_value(u, v, &point, footprint),
+            None => self.value(u, v, &point),
+        }
+    }
+
     fn into_texture(self) -> Rc<dyn Texture>
     where
         Self: Sized + 'static,
@@ -35,40 +95,144 @@ impl From<Color> for SolidColor {
     }
 }
 
+/// The coordinates a [`Checkerboard`] is laid out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckerboardSpace {
+    /// Checks are cubes in world space; objects move through the pattern.
+    #[default]
+    World,
+    /// Checks are squares in texture space, so the pattern moves with the object's surface.
+    Uv,
+}
+
 #[derive(Debug)]
 pub struct Checkerboard {
-    scale: f64,
+    /// The size of each check along each axis; in UV space, only `x` (u) and `y` (v) are used.
+    scale: Vec3,
+    space: CheckerboardSpace,
     even: Rc<dyn Texture>,
     odd: Rc<dyn Texture>,
 }
 
 impl Checkerboard {
     pub fn new(scale: f64, even: Rc<dyn Texture>, odd: Rc<dyn Texture>) -> Self {
-        Self { scale, even, odd }
+        Self {
+            scale: Vec3::new(scale, scale, scale),
+            space: CheckerboardSpace::World,
+            even,
+            odd,
+        }
     }
 
     pub fn solid(scale: f64, even: SolidColor, odd: SolidColor) -> Self {
-        Self {
-            scale,
-            even: Rc::new(even),
-            odd: Rc::new(odd),
+        Self::new(scale, Rc::new(even), Rc::new(odd))
+    }
+
+    pub fn with_space(mut self, space: CheckerboardSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Sets a different size of check along each axis.
+    pub fn with_axis_scale(mut self, scale: Vec3) -> Self {
+        assert!(
+            scale.x() > 0.0 && scale.y() > 0.0 && scale.z() > 0.0,
+            "Invalid checkerboard scale (expected > 0.0)"
+        );
+        self.scale = scale;
+        self
+    }
+
+    /// The coordinates of the lookup, in units of checks. Only the first [`Self::axes`]
+    /// are used; the third is zero in UV space.
+    fn coordinates(&self, u: f64, v: f64, point: &Point3) -> [f64; 3] {
+        match self.space {
+            CheckerboardSpace::World => [
+                point.x() / self.scale.x(),
+                point.y() / self.scale.y(),
+                point.z() / self.scale.z(),
+            ],
+            CheckerboardSpace::Uv => [u / self.scale.x(), v / self.scale.y(), 0.0],
+        }
+    }
+
+    /// The number of axes the pattern alternates along.
+    fn axes(&self) -> usize {
+        match self.space {
+            CheckerboardSpace::World => 3,
+            CheckerboardSpace::Uv => 2,
         }
     }
 }
 
 impl Texture for Checkerboard {
     fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
-        let x = f64::floor((1.0 / self.scale) * point.x());
-        let y = f64::floor((1.0 / self.scale) * point.y());
-        let z = f64::floor((1.0 / self.scale) * point.z());
+        let sum: i64 = self
+            .coordinates(u, v, point)
+            .into_iter()
+            .take(self.axes())
+            .map(|c| c.floor() as i64)
+            .sum();
 
-        let is_even = (x as i32 + y as i32 + z as i32) % 2 == 0;
+        let is_even = sum.rem_euclid(2) == 0;
 
         match is_even {
             true => self.even.value(u, v, point),
             false => self.odd.value(u, v, point),
         }
     }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        /// The integral of a square wave which is `1` on `[0, 1)` and `-1` on `[1, 2)`.
+        fn integral(t: f64) -> f64 {
+            let t = t.rem_euclid(2.0);
+            if t < 1.0 {
+                t
+            } else {
+                2.0 - t
+            }
+        }
+
+        // the pattern is the product of a square wave along each axis; box filter each
+        let coords = self.coordinates(u, v, point);
+        let widths = match self.space {
+            CheckerboardSpace::World => {
+                let [x, y, z] = footprint.axis_widths();
+                [x / self.scale.x(), y / self.scale.y(), z / self.scale.z()]
+            }
+            CheckerboardSpace::Uv => {
+                let (dx, dy) = (footprint.duvdx, footprint.duvdy);
+                [
+                    dx.x().abs().max(dy.x().abs()) / self.scale.x(),
+                    dx.y().abs().max(dy.y().abs()) / self.scale.y(),
+                    0.0,
+                ]
+            }
+        };
+        let mut product = 1.0;
+        for (c, w) in coords.into_iter().zip(widths).take(self.axes()) {
+            product *= if w < 1e-8 {
+                // no width along this axis (such as the normal of an axis-aligned plane), so
+                // point sample the square wave: sign(sin(πc)), matching `value` on the edges
+                if c.rem_euclid(2.0) < 1.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            } else {
+                (integral(c + w / 2.0) - integral(c - w / 2.0)) / w
+            };
+        }
+
+        let even = self.even.filtered_value(u, v, point, footprint);
+        let odd = self.odd.filtered_value(u, v, point, footprint);
+        let t = 0.5 + 0.5 * product;
+        Color::new(
+            odd.r() + (even.r() - odd.r()) * t,
+            odd.g() + (even.g() - odd.g()) * t,
+            odd.b() + (even.b() - odd.b()) * t,
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -76,6 +240,8 @@ pub struct ImageTexture {
     image_data: Vec<u8>,
     width: u32,
     height: u32,
+    /// Successively half-sized copies of the image, used when filtering
+    mip_levels: Vec<(u32, u32, Vec<u8>)>,
 }
 
 impl ImageTexture {
@@ -85,40 +251,95 @@ impl ImageTexture {
             (dimensions.0 * dimensions.1 * 3) as usize,
             "sanity check; image_data.len() = # of pixels * 3 channels per pixel"
         );
-        Self {
+        let mut texture = Self {
             image_data,
             width: dimensions.0,
             height: dimensions.1,
+            mip_levels: Vec::new(),
+        };
+        texture.build_mip_levels();
+        texture
+    }
+
+    fn build_mip_levels(&mut self) {
+        let (mut width, mut height) = (self.width, self.height);
+        while width > 1 || height > 1 {
+            let data = match self.mip_levels.last() {
+                Some((_, _, data)) => data,
+                None => &self.image_data,
+            };
+            let (half_w, half_h) = ((width / 2).max(1), (height / 2).max(1));
+
+            let mut half = Vec::with_capacity((half_w * half_h * 3) as usize);
+            for j in 0..half_h {
+                for i in 0..half_w {
+                    for channel in 0..3 {
+                        // average the (up to) four texels covered by this one
+                        let mut sum = 0;
+                        for (di, dj) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                            let x = (i * 2 + di).min(width - 1);
+                            let y = (j * 2 + dj).min(height - 1);
+                            sum += u32::from(data[((y * width + x) * 3 + channel) as usize]);
+                        }
+                        half.push(((sum + 2) / 4) as u8);
+                    }
+                }
+            }
+
+            self.mip_levels.push((half_w, half_h, half));
+            (width, height) = (half_w, half_h);
         }
     }
 
-    pub fn load<R: Read>(mut decoder: Decoder<R>) -> Self {
+    /// Looks up the nearest texel at `(u, v)` in a level of the image; level 0 is the
+    /// full-size image.
+    fn texel(&self, level: usize, u: f64, v: f64) -> Color {
+        let (width, height, data) = match level {
+            0 => (self.width, self.height, &self.image_data),
+            n => {
+                let (w, h, data) = &self.mip_levels[n - 1];
+                (*w, *h, data)
+            }
+        };
+        // Flip v to image coordinates
+        let v = 1.0 - v;
+
+        let i = ((u * f64::from(width)) as u32).min(width - 1);
+        let j = ((v * f64::from(height)) as u32).min(height - 1);
+        let ind = ((j * width + i) * 3) as usize;
+        Color::new_ints(data[ind], data[ind + 1], data[ind + 2])
+    }
+
+    /// Decodes an 8-bit RGB PNG.
+    ///
+    /// # Panics
+    /// Panics if the image can't be decoded; see [`ImageTexture::try_load`].
+    pub fn load<R: Read>(decoder: Decoder<R>) -> Self {
+        Self::try_load(decoder).unwrap()
+    }
+
+    /// Like [`ImageTexture::load`], but returns an error if the image is corrupt, animated,
+    /// or isn't 8-bit RGB.
+    pub fn try_load<R: Read>(mut decoder: Decoder<R>) -> Result<Self, Box<dyn Error>> {
         decoder.set_transformations(png::Transformations::normalize_to_color8());
-        let mut reader = decoder.read_info().unwrap();
+        let mut reader = decoder.read_info()?;
 
-        assert!(
-            reader.info().frame_control.is_none(),
-            "Cannot accept APNGs."
-        );
-        assert!(
-            matches!(reader.info().color_type, png::ColorType::Rgb),
-            "Must be 8-bit PNG."
-        );
+        if reader.info().frame_control.is_some() {
+            return Err("Cannot accept APNGs.".into());
+        }
+        if !matches!(reader.info().color_type, png::ColorType::Rgb) {
+            return Err("Must be 8-bit RGB PNG.".into());
+        }
 
         let mut buf = vec![0; reader.output_buffer_size()];
-        reader.next_frame(&mut buf).unwrap();
+        reader.next_frame(&mut buf)?;
         let info = reader.info();
 
-        assert_eq!(
-            buf.len(),
-            usize::try_from(info.width * info.height * 3).unwrap()
-        );
-
-        Self {
-            image_data: buf,
-            width: info.width,
-            height: info.height,
+        if buf.len() != (info.width * info.height * 3) as usize {
+            return Err("Image data doesn't match its dimensions.".into());
         }
+
+        Ok(Self::new(buf, (info.width, info.height)))
     }
 }
 
@@ -128,8 +349,9 @@ impl Texture for ImageTexture {
         // Flip v to image coordinates
         let v = 1.0 - v;
 
-        let i = (u * f64::from(self.width)) as u32;
-        let j = (v * f64::from(self.height)) as u32;
+        // the far edges, at 1.0, belong to the last texel
+        let i = ((u * f64::from(self.width)) as u32).min(self.width - 1);
+        let j = ((v * f64::from(self.height)) as u32).min(self.height - 1);
         let ind = ((j * self.width + i) * 3) as usize;
         let [r, g, b] = &self.image_data[ind..ind + 3] else {
             panic!("Failed to deserialize texture")
@@ -137,4 +359,90 @@ impl Texture for ImageTexture {
 
         Color::new_ints(*r, *g, *b)
     }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let level = footprint.texel_width(self.width, self.height).log2();
+        if level.is_nan() || level <= 0.0 {
+            return self.value(u, v, point);
+        }
+
+        // blend between the two nearest levels
+        let level = level.min(self.mip_levels.len() as f64);
+        let (lower, t) = (level.floor() as usize, level.fract());
+        let a = self.texel(lower, u, v);
+        if lower == self.mip_levels.len() {
+            return a;
+        }
+        let b = self.texel(lower + 1, u, v);
+        Color::new(
+            a.r() + (b.r() - a.r()) * t,
+            a.g() + (b.g() - a.g()) * t,
+            a.b() + (b.b() - a.b()) * t,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn footprint(world: f64, uv: f64) -> Footprint {
+        Footprint {
+            dpdx: Vec3::new(world, 0.0, 0.0),
+            dpdy: Vec3::new(0.0, world, world),
+            duvdx: Vec2::new(uv, 0.0),
+            duvdy: Vec2::new(0.0, uv),
+        }
+    }
+
+    #[test]
+    fn axis_aligned_planes_keep_their_checks() {
+        let checker = Checkerboard::solid(1.0, Color::white().into(), Color::black().into());
+        // a footprint on a plane of constant y has no width along y
+        let footprint = Footprint {
+            dpdx: Vec3::new(1e-3, 0.0, 0.0),
+            dpdy: Vec3::new(0.0, 0.0, 1e-3),
+            duvdx: Vec2::new(0.0, 0.0),
+            duvdy: Vec2::new(0.0, 0.0),
+        };
+        for (x, y) in [(0.25, 0.5), (1.25, 0.5), (0.25, -0.5), (-0.75, 2.5)] {
+            let p = Point3::new(x, y, 0.25);
+            let filtered = checker.filtered_value(0.0, 0.0, &p, &footprint);
+            assert!((filtered.r() - checker.value(0.0, 0.0, &p).r()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn wide_footprints_average() {
+        let p = Point3::new(0.25, 0.25, 0.25);
+        let checker = Checkerboard::solid(1.0, Color::white().into(), Color::black().into());
+        assert_eq!(checker.value(0.0, 0.0, &p), Color::white());
+        // a tiny footprint doesn't change the result
+        let sharp = checker.filtered_value(0.0, 0.0, &p, &footprint(1e-4, 0.0));
+        assert!((sharp.r() - 1.0).abs() < 1e-3);
+        // a footprint covering many checks is grey
+        let blurred = checker.filtered_value(0.0, 0.0, &p, &footprint(20.0, 0.0));
+        assert!((blurred.r() - 0.5).abs() < 0.05);
+
+        let uv_checker = Checkerboard::solid(1.0, Color::white().into(), Color::black().into())
+            .with_space(CheckerboardSpace::Uv)
+            .with_axis_scale(Vec3::new(0.5, 0.25, 1.0));
+        // world space is ignored
+        assert_eq!(uv_checker.value(0.1, 0.1, &p), Color::white());
+        assert_eq!(uv_checker.value(0.6, 0.3, &p), Color::white());
+        assert_eq!(uv_checker.value(0.6, 0.1, &p), Color::black());
+        let blurred = uv_checker.filtered_value(0.6, 0.1, &p, &footprint(0.0, 10.0));
+        assert!((blurred.r() - 0.5).abs() < 0.05);
+        // with a small footprint, filtered UV checks still alternate
+        for (u, v, expected) in [(0.1, 0.1, 1.0), (0.6, 0.1, 0.0), (0.6, 0.3, 1.0)] {
+            let sharp = uv_checker.filtered_value(u, v, &p, &footprint(0.0, 1e-4));
+            assert!((sharp.r() - expected).abs() < 1e-3, "{u}, {v}: {sharp:?}");
+        }
+
+        // a 2x2 image of black and white texels
+        let image = ImageTexture::new([[0; 3], [255; 3], [255; 3], [0; 3]].concat(), (2, 2));
+        assert_eq!(image.value(0.25, 0.25, &p), Color::white());
+        let blurred = image.filtered_value(0.25, 0.25, &p, &footprint(0.0, 1.0));
+        assert_eq!(blurred, Color::new_ints(128, 128, 128));
+    }
 }
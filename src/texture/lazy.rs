@@ -0,0 +1,354 @@
+//! Image textures which are decoded into tiles on disk and only loaded into memory
+//! when they are sampled, so scenes can reference more texture data than fits in RAM.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use miette::{bail, IntoDiagnostic, Result};
+use png::{ColorType, Decoder, Transformations};
+
+use super::Texture;
+use crate::{Color, Point3};
+
+/// The width and height, in pixels, of each tile.
+const TILE_SIZE: u32 = 64;
+const TILE_BYTES: usize = (TILE_SIZE * TILE_SIZE * 3) as usize;
+
+/// Numbers the temporary tile files of every texture in this process, so that no two
+/// textures (even in different caches or threads) write to the same file.
+static NEXT_TILE_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Identifies a tile: the texture it belongs to, and its index within that texture.
+type TileKey = (usize, u32);
+
+#[derive(Debug, Default)]
+struct CacheState {
+    tiles: HashMap<TileKey, (Rc<[u8]>, u64)>,
+    /// Resident tiles, ordered from least to most recently used
+    recency: BTreeMap<u64, TileKey>,
+    clock: u64,
+    misses: u64,
+    read_errors: u64,
+}
+
+/// Keeps recently used tiles of [`LazyImageTexture`]s in memory, evicting the least
+/// recently used tiles once their total size exceeds a budget.
+///
+/// One cache is usually shared between every lazy texture in a scene.
+#[derive(Debug)]
+pub struct TextureCache {
+    /// The maximum number of bytes of tile data kept in memory
+    budget: usize,
+    state: RefCell<CacheState>,
+    next_id: Cell<usize>,
+}
+
+impl TextureCache {
+    /// Creates a cache holding up to `budget` bytes of tiles. At least one tile is always
+    /// kept, however small the budget.
+    pub fn new(budget: usize) -> Rc<Self> {
+        Rc::new(Self {
+            budget,
+            state: RefCell::default(),
+            next_id: Cell::new(0),
+        })
+    }
+
+    /// The number of bytes of tile data currently in memory.
+    pub fn resident_bytes(&self) -> usize {
+        self.state.borrow().tiles.len() * TILE_BYTES
+    }
+
+    /// The number of times a tile had to be read from disk.
+    pub fn misses(&self) -> u64 {
+        self.state.borrow().misses
+    }
+
+    /// The number of times a tile couldn't be read from disk. Those samples were shown in
+    /// [magenta](Color::debug_magenta); see [`LazyImageTexture::try_value`].
+    pub fn read_errors(&self) -> u64 {
+        self.state.borrow().read_errors
+    }
+
+    fn register(&self) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    /// Returns the tile for `key`, loading it with `load` if it isn't resident.
+    /// Tiles which fail to load aren't kept, so they are retried on the next access.
+    fn tile(
+        &self,
+        key: TileKey,
+        load: impl FnOnce() -> io::Result<Rc<[u8]>>,
+    ) -> io::Result<Rc<[u8]>> {
+        let mut state = self.state.borrow_mut();
+        state.clock += 1;
+        let now = state.clock;
+
+        if let Some((tile, last_used)) = state.tiles.get_mut(&key) {
+            let (tile, previous) = (Rc::clone(tile), *last_used);
+            *last_used = now;
+            state.recency.remove(&previous);
+            state.recency.insert(now, key);
+            return Ok(tile);
+        }
+
+        state.misses += 1;
+        while !state.tiles.is_empty() && (state.tiles.len() + 1) * TILE_BYTES > self.budget {
+            let (_, oldest) = state.recency.pop_first().unwrap();
+            state.tiles.remove(&oldest);
+        }
+
+        let tile = load().inspect_err(|_| state.read_errors += 1)?;
+        state.tiles.insert(key, (Rc::clone(&tile), now));
+        state.recency.insert(now, key);
+        Ok(tile)
+    }
+
+    /// Drops every tile belonging to the texture `id`.
+    fn evict(&self, id: usize) {
+        let mut state = self.state.borrow_mut();
+        let CacheState { tiles, recency, .. } = &mut *state;
+        tiles.retain(|(texture, _), (_, last_used)| {
+            let keep = *texture != id;
+            if !keep {
+                recency.remove(last_used);
+            }
+            keep
+        });
+    }
+}
+
+/// A PNG image texture whose pixels are only kept in memory while they are in use.
+///
+/// Opening the texture decodes the image once, a band of rows at a time, into a temporary
+/// file of uncompressed tiles. Sampling the texture then reads the tiles it needs from that
+/// file through a shared [`TextureCache`]. The temporary file is deleted when the texture
+/// is dropped.
+#[derive(Debug)]
+pub struct LazyImageTexture {
+    cache: Rc<TextureCache>,
+    id: usize,
+    width: u32,
+    height: u32,
+    /// The number of tiles in each row of tiles
+    tiles_x: u32,
+    tile_path: PathBuf,
+    tile_file: RefCell<File>,
+}
+
+impl LazyImageTexture {
+    /// Opens the PNG image at `path`. 8-bit and 16-bit, greyscale and color images are
+    /// accepted; alpha is ignored.
+    pub fn open(path: &Path, cache: &Rc<TextureCache>) -> Result<Self> {
+        let file = File::open(path)
+            .into_diagnostic()
+            .map_err(|e| e.wrap_err(format!("Failed to open {}", path.display())))?;
+
+        let id = cache.register();
+        let tile_path = std::env::temp_dir().join(format!(
+            "raytracing-{}-{}.tiles",
+            std::process::id(),
+            NEXT_TILE_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut tile_file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tile_path)
+            .into_diagnostic()
+            .map_err(|e| e.wrap_err(format!("Failed to create {}", tile_path.display())))?;
+
+        match write_tiles(Decoder::new(file), &mut tile_file) {
+            Ok((width, height)) => Ok(Self {
+                cache: Rc::clone(cache),
+                id,
+                width,
+                height,
+                tiles_x: width.div_ceil(TILE_SIZE),
+                tile_path,
+                tile_file: RefCell::new(tile_file),
+            }),
+            Err(e) => {
+                let _ = std::fs::remove_file(&tile_path);
+                Err(e.wrap_err(format!("Failed to decode {}", path.display())))
+            }
+        }
+    }
+
+    /// The width and height of the image, in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The color at `(u, v)`, or the error from reading its tile from disk.
+    pub fn try_value(&self, u: f64, v: f64) -> io::Result<Color> {
+        // Flip v to image coordinates
+        let v = 1.0 - v;
+
+        let i = ((u * f64::from(self.width)) as u32).min(self.width - 1);
+        let j = ((v * f64::from(self.height)) as u32).min(self.height - 1);
+        let index = (j / TILE_SIZE) * self.tiles_x + i / TILE_SIZE;
+        let tile = self
+            .cache
+            .tile((self.id, index), || self.read_tile(index))?;
+
+        let ind = (((j % TILE_SIZE) * TILE_SIZE + i % TILE_SIZE) * 3) as usize;
+        Ok(Color::new_ints(tile[ind], tile[ind + 1], tile[ind + 2]))
+    }
+
+    fn read_tile(&self, index: u32) -> io::Result<Rc<[u8]>> {
+        let mut tile = vec![0; TILE_BYTES];
+        let mut file = self.tile_file.borrow_mut();
+        file.seek(SeekFrom::Start(u64::from(index) * TILE_BYTES as u64))?;
+        file.read_exact(&mut tile)?;
+        Ok(tile.into())
+    }
+}
+
+impl Texture for LazyImageTexture {
+    fn value(&self, u: f64, v: f64, _point: &Point3) -> Color {
+        // the failure is counted by the cache; a render can't stop part way through a sample
+        self.try_value(u, v).unwrap_or(Color::debug_magenta())
+    }
+}
+
+impl Drop for LazyImageTexture {
+    fn drop(&mut self) {
+        self.cache.evict(self.id);
+        let _ = std::fs::remove_file(&self.tile_path);
+    }
+}
+
+/// Decodes the image from `decoder` into `out` as a sequence of RGB tiles, each
+/// [`TILE_SIZE`] pixels square; tiles on the right and bottom edges are padded with black.
+/// Returns the dimensions of the image.
+fn write_tiles<R: Read>(mut decoder: Decoder<R>, out: &mut impl Write) -> Result<(u32, u32)> {
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().into_diagnostic()?;
+    if reader.info().frame_control.is_some() {
+        bail!("Cannot accept APNGs.");
+    }
+
+    let (width, height) = (reader.info().width, reader.info().height);
+    let channels = match reader.output_color_type().0 {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+        ColorType::Indexed => bail!("Indexed images could not be expanded."),
+    };
+    let to_rgb = |pixel: &[u8]| match pixel.len() {
+        1 | 2 => [pixel[0]; 3],
+        _ => [pixel[0], pixel[1], pixel[2]],
+    };
+
+    let rows: Box<dyn Iterator<Item = Result<Vec<u8>>>> = if reader.info().interlaced {
+        // interlaced rows arrive out of order, so the whole image is decoded at once
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let line_size = reader.next_frame(&mut buf).into_diagnostic()?.line_size;
+        let rows: Vec<_> = buf.chunks(line_size).map(|row| Ok(row.to_vec())).collect();
+        Box::new(rows.into_iter())
+    } else {
+        Box::new(std::iter::from_fn(move || {
+            reader
+                .next_row()
+                .into_diagnostic()
+                .map(|row| row.map(|row| row.data().to_vec()))
+                .transpose()
+        }))
+    };
+
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    // one row of tiles
+    let mut band = vec![0; tiles_x as usize * TILE_BYTES];
+    let mut rows = rows.take(height as usize).enumerate().peekable();
+    while rows.peek().is_some() {
+        band.fill(0);
+        for (y, row) in rows.by_ref().take(TILE_SIZE as usize) {
+            let row = row?;
+            let y = y as u32 % TILE_SIZE;
+            for (x, pixel) in row.chunks(channels).enumerate() {
+                let x = x as u32;
+                let tile = (x / TILE_SIZE) as usize * TILE_BYTES;
+                let ind = tile + ((y * TILE_SIZE + x % TILE_SIZE) * 3) as usize;
+                band[ind..ind + 3].copy_from_slice(&to_rgb(pixel));
+            }
+        }
+        out.write_all(&band).into_diagnostic()?;
+    }
+
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Encodes a `width`x`height` RGB image whose red channel is the column and green channel
+    /// is the row of each pixel.
+    fn gradient_png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        let data: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [x as u8, y as u8, 0]))
+            .collect();
+        writer.write_image_data(&data).unwrap();
+        writer.finish().unwrap();
+        png
+    }
+
+    #[test]
+    fn tiles_are_loaded_on_demand() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("lazy-texture-{}.png", std::process::id()));
+        std::fs::write(&path, gradient_png(150, 100)).into_diagnostic()?;
+
+        // room for two tiles
+        let cache = TextureCache::new(2 * TILE_BYTES);
+        let texture = LazyImageTexture::open(&path, &cache)?;
+        std::fs::remove_file(&path).into_diagnostic()?;
+        assert_eq!(cache.resident_bytes(), 0);
+
+        let pixel = |x: u32, y: u32| {
+            let u = (f64::from(x) + 0.5) / 150.0;
+            let v = 1.0 - (f64::from(y) + 0.5) / 100.0;
+            texture.value(u, v, &Point3::origin())
+        };
+        assert_eq!(pixel(10, 20), Color::new_ints(10, 20, 0));
+        assert_eq!(pixel(149, 99), Color::new_ints(149, 99, 0));
+        assert_eq!(pixel(70, 5), Color::new_ints(70, 5, 0));
+        assert_eq!(cache.misses(), 3);
+        assert_eq!(cache.resident_bytes(), 2 * TILE_BYTES);
+
+        // the first tile was evicted
+        assert_eq!(pixel(11, 21), Color::new_ints(11, 21, 0));
+        assert_eq!(cache.misses(), 4);
+
+        // a second texture gets its own tile file
+        std::fs::write(&path, gradient_png(4, 4)).into_diagnostic()?;
+        let other = LazyImageTexture::open(&path, &TextureCache::new(0))?;
+        std::fs::remove_file(&path).into_diagnostic()?;
+        assert_ne!(other.tile_path, texture.tile_path);
+
+        // a tile file which was cut short is reported rather than panicking
+        texture.tile_file.borrow().set_len(0).into_diagnostic()?;
+        assert!(texture.try_value(0.99, 0.01).is_err());
+        assert_eq!(
+            texture.value(0.99, 0.01, &Point3::origin()),
+            Color::debug_magenta()
+        );
+        assert_eq!(cache.read_errors(), 2);
+        Ok(())
+    }
+}
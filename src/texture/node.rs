@@ -0,0 +1,304 @@
+//! Textures which combine or transform other textures, so that complex surfaces can be
+//! built up from simple parts. Each node takes its inputs as `Rc<dyn Texture>`s, and
+//! constant inputs can be given as a [`SolidColor`](super::SolidColor).
+
+use std::rc::Rc;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::{Footprint, Texture};
+use crate::{Color, Point3, Vec3};
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    Color::new(
+        a.r() + (b.r() - a.r()) * t,
+        a.g() + (b.g() - a.g()) * t,
+        a.b() + (b.b() - a.b()) * t,
+    )
+}
+
+/// Blends between two textures, using the luminance of a third as the weight:
+/// where `factor` is black, `a` is shown, and where it is white, `b` is shown.
+/// With a black-and-white `factor`, this masks one texture with another.
+#[derive(Debug)]
+pub struct Mix {
+    a: Rc<dyn Texture>,
+    b: Rc<dyn Texture>,
+    factor: Rc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(a: Rc<dyn Texture>, b: Rc<dyn Texture>, factor: Rc<dyn Texture>) -> Self {
+        Self { a, b, factor }
+    }
+}
+
+impl Texture for Mix {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let t = self.factor.value(u, v, point).luminance().clamp(0.0, 1.0);
+        lerp(self.a.value(u, v, point), self.b.value(u, v, point), t)
+    }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let factor = self.factor.filtered_value(u, v, point, footprint);
+        let t = factor.luminance().clamp(0.0, 1.0);
+        lerp(
+            self.a.filtered_value(u, v, point, footprint),
+            self.b.filtered_value(u, v, point, footprint),
+            t,
+        )
+    }
+}
+
+/// Multiplies two textures together, channel by channel.
+#[derive(Debug)]
+pub struct Multiply {
+    a: Rc<dyn Texture>,
+    b: Rc<dyn Texture>,
+}
+
+impl Multiply {
+    pub fn new(a: Rc<dyn Texture>, b: Rc<dyn Texture>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Texture for Multiply {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        self.a.value(u, v, point).mul(&self.b.value(u, v, point))
+    }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let a = self.a.filtered_value(u, v, point, footprint);
+        a.mul(&self.b.filtered_value(u, v, point, footprint))
+    }
+}
+
+/// Inverts a texture, subtracting each channel from 1.0.
+#[derive(Debug)]
+pub struct Invert(Rc<dyn Texture>);
+
+impl Invert {
+    pub fn new(input: Rc<dyn Texture>) -> Self {
+        Self(input)
+    }
+}
+
+impl Texture for Invert {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let c = self.0.value(u, v, point);
+        Color::new(1.0 - c.r(), 1.0 - c.g(), 1.0 - c.b())
+    }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let c = self.0.filtered_value(u, v, point, footprint);
+        Color::new(1.0 - c.r(), 1.0 - c.g(), 1.0 - c.b())
+    }
+}
+
+/// Maps the luminance of a texture onto a gradient between two colors, like a color ramp.
+/// Luminances below `from.0` show `low`, luminances above `from.1` show `high`, and those in
+/// between are interpolated.
+#[derive(Debug)]
+pub struct Remap {
+    input: Rc<dyn Texture>,
+    from: (f64, f64),
+    low: Color,
+    high: Color,
+}
+
+impl Remap {
+    pub fn new(input: Rc<dyn Texture>, from: (f64, f64), low: Color, high: Color) -> Self {
+        assert!(
+            from.0 < from.1,
+            "Invalid remap range (expected from.0 < from.1)"
+        );
+        Self {
+            input,
+            from,
+            low,
+            high,
+        }
+    }
+}
+
+impl Texture for Remap {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let luminance = self.input.value(u, v, point).luminance();
+        let t = ((luminance - self.from.0) / (self.from.1 - self.from.0)).clamp(0.0, 1.0);
+        lerp(self.low, self.high, t)
+    }
+
+    fn filtered_value(&self, u: f64, v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        let luminance = self
+            .input
+            .filtered_value(u, v, point, footprint)
+            .luminance();
+        let t = ((luminance - self.from.0) / (self.from.1 - self.from.0)).clamp(0.0, 1.0);
+        lerp(self.low, self.high, t)
+    }
+}
+
+const POINT_COUNT: usize = 256;
+
+/// Ken Perlin's gradient noise, on a lattice of random unit vectors.
+#[derive(Debug)]
+struct Perlin {
+    gradients: Vec<Vec3>,
+    perm: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let v = Vec3::new(
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                );
+                if (1e-3..=1.0).contains(&v.len_squared()) {
+                    break v / v.len();
+                }
+            })
+            .collect();
+        let mut perm = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm = [perm(), perm(), perm()];
+        Self { gradients, perm }
+    }
+
+    /// Noise in the range `-1.0..=1.0`.
+    fn noise(&self, p: &Point3) -> f64 {
+        let floor = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let frac = [p.x() - floor[0], p.y() - floor[1], p.z() - floor[2]];
+        // Hermite smoothing hides the lattice
+        let smooth = frac.map(|f| f * f * (3.0 - 2.0 * f));
+        let cell = floor.map(|f| f as i64);
+
+        let mut sum = 0.0;
+        for corner in 0..8 {
+            let d = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let index = (0..3).fold(0, |acc, axis| {
+                let lattice = (cell[axis] + d[axis] as i64).rem_euclid(POINT_COUNT as i64);
+                acc ^ self.perm[axis][lattice as usize]
+            });
+            let offset = Vec3::new(
+                frac[0] - d[0] as f64,
+                frac[1] - d[1] as f64,
+                frac[2] - d[2] as f64,
+            );
+            let weight: f64 = (0..3)
+                .map(|axis| match d[axis] {
+                    1 => smooth[axis],
+                    _ => 1.0 - smooth[axis],
+                })
+                .product();
+            sum += weight * self.gradients[index].dot(&offset);
+        }
+        sum
+    }
+}
+
+/// Greyscale Perlin noise, summed over several octaves of increasing frequency.
+/// Values range from black to white, averaging mid-grey.
+#[derive(Debug)]
+pub struct Noise {
+    perlin: Perlin,
+    /// The size of the noise's features, in world units
+    scale: f64,
+    octaves: u32,
+}
+
+impl Noise {
+    pub fn new(scale: f64) -> Self {
+        assert!(scale > 0.0, "Invalid noise scale (expected > 0.0)");
+        Self {
+            perlin: Perlin::new(0),
+            scale,
+            octaves: 1,
+        }
+    }
+
+    /// Adds finer detail; each octave has twice the frequency and half the strength of the
+    /// last.
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        assert!(octaves > 0, "Invalid octave count (expected at least 1)");
+        self.octaves = octaves;
+        self
+    }
+
+    /// Chooses a different pattern of noise. The same seed always gives the same pattern.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+}
+
+impl Noise {
+    /// Sums octaves of noise until their features would be smaller than `min_feature`.
+    fn fbm(&self, point: &Point3, min_feature: f64) -> Color {
+        let mut p = Point3::from(Vec3::from(*point) / self.scale);
+        let (mut sum, mut weight, mut total) = (0.0, 1.0, 0.0);
+        let mut feature = self.scale;
+        for _ in 0..self.octaves {
+            if feature < min_feature {
+                // finer octaves would alias; they average to zero anyway
+                break;
+            }
+            sum += weight * self.perlin.noise(&p);
+            total += weight;
+            weight /= 2.0;
+            feature /= 2.0;
+            p = Point3::from(Vec3::from(p) * 2.0);
+        }
+        if total == 0.0 {
+            return Color::over_white(0.5);
+        }
+        Color::over_white((0.5 + 0.5 * sum / total).clamp(0.0, 1.0))
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        self.fbm(point, 0.0)
+    }
+
+    fn filtered_value(&self, _u: f64, _v: f64, point: &Point3, footprint: &Footprint) -> Color {
+        self.fbm(point, footprint.world_width())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn nodes_combine() {
+        let p = Point3::origin();
+        let red = SolidColor::new(Color::new(1.0, 0.0, 0.0)).into_texture();
+        let grey = SolidColor::new(Color::over_white(0.5)).into_texture();
+
+        let inverted = Invert::new(Rc::clone(&red));
+        assert_eq!(inverted.value(0.0, 0.0, &p), Color::new(0.0, 1.0, 1.0));
+
+        let mix = Mix::new(Rc::clone(&red), inverted.into_texture(), Rc::clone(&grey));
+        assert_eq!(mix.value(0.0, 0.0, &p), Color::over_white(0.5));
+
+        let product = Multiply::new(red, Rc::clone(&grey));
+        assert_eq!(product.value(0.0, 0.0, &p), Color::new(0.5, 0.0, 0.0));
+
+        let ramp = Remap::new(grey, (0.0, 0.25), Color::black(), Color::white());
+        assert_eq!(ramp.value(0.0, 0.0, &p), Color::white());
+
+        let noise = Noise::new(1.0).with_octaves(4);
+        let value = noise.value(0.0, 0.0, &Point3::new(0.3, 1.7, -2.2));
+        assert!((0.0..=1.0).contains(&value.r()));
+        assert_eq!(value, noise.value(0.0, 0.0, &Point3::new(0.3, 1.7, -2.2)));
+    }
+}
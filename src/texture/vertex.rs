@@ -0,0 +1,109 @@
+//! Colors stored on the vertices of a mesh, as captured by scanners and photogrammetry.
+
+use super::Texture;
+use crate::{Color, HitRecord, Point3};
+
+/// Colors assigned to each vertex of a mesh, interpolated across each face.
+///
+/// Faces are found through [`HitRecord::primitive_index`] and the colors of their corners
+/// blended with [`HitRecord::barycentrics`], so this texture works on a [`Mesh`](crate::hittable::Mesh) or on
+/// [`Triangle`](crate::hittable::Triangle)s given their [index](crate::hittable::Triangle::with_index)
+/// in the same index buffer. Hits on anything else are colored
+/// [magenta](Color::debug_magenta).
+#[derive(Debug)]
+pub struct VertexColors {
+    colors: Vec<Color>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl VertexColors {
+    /// Creates the texture from a color for each vertex and the index buffer listing the
+    /// vertices at the corners of each face.
+    ///
+    /// # Panics
+    /// Panics if a face refers to a vertex without a color.
+    pub fn new(colors: Vec<Color>, indices: Vec<[u32; 3]>) -> Self {
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < colors.len()),
+            "Invalid vertex colors (a face refers to a vertex without a color)"
+        );
+        Self { colors, indices }
+    }
+
+    /// Creates the texture for the mesh with the vertex `positions` and face `indices` given,
+    /// from a color for each of its vertices. This is built before the mesh itself, whose
+    /// material needs the texture.
+    ///
+    /// # Panics
+    /// Panics if there isn't exactly one color per vertex.
+    pub fn for_faces(positions: &[Point3], indices: &[[u32; 3]], colors: Vec<Color>) -> Self {
+        assert_eq!(
+            colors.len(),
+            positions.len(),
+            "Invalid vertex colors (expected one per vertex)"
+        );
+        Self::new(colors, indices.to_vec())
+    }
+}
+
+impl Texture for VertexColors {
+    fn value(&self, _u: f64, _v: f64, _point: &Point3) -> Color {
+        // without the hit, there's no face to look up
+        Color::debug_magenta()
+    }
+
+    fn value_at(&self, record: &HitRecord) -> Color {
+        let (Some(index), Some(weights)) = (record.primitive_index(), record.barycentrics()) else {
+            return Color::debug_magenta();
+        };
+        let Some(face) = self.indices.get(index) else {
+            return Color::debug_magenta();
+        };
+
+        let [r, g, b] = [Color::r, Color::g, Color::b].map(|channel| {
+            face.iter()
+                .zip(weights)
+                .map(|(&v, weight)| channel(&self.colors[v as usize]) * weight)
+                .sum()
+        });
+        Color::new(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hittable::Mesh, material::Lambertian, Hittable, Interval, Material, Ray4, Vec3};
+
+    #[test]
+    fn interpolated() {
+        let mat = Lambertian::solid(Color::white()).into_mat();
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let indices = vec![[0, 1, 2]];
+        let colors = VertexColors::for_faces(
+            &positions,
+            &indices,
+            vec![Color::red(), Color::green(), Color::blue()],
+        );
+        let mesh = Mesh::new(positions, indices, mat);
+
+        let ray = Ray4::new(Point3::new(0.5, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, Interval::universe()).unwrap();
+        let color = colors.value_at(&hit);
+        let expected = Color::new(0.25, 0.5, 0.25);
+        assert!((color.r() - expected.r()).abs() < 1e-9);
+        assert!((color.g() - expected.g()).abs() < 1e-9);
+        assert!((color.b() - expected.b()).abs() < 1e-9);
+        assert_eq!(
+            colors.value(0.0, 0.0, &Point3::origin()),
+            Color::debug_magenta()
+        );
+    }
+}